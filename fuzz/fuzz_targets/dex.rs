@@ -0,0 +1,40 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+#![no_main]
+
+use exofmt::dex::{self, IoReader};
+use exofmt::Limits;
+use libfuzzer_sys::fuzz_target;
+use std::io::{BufReader, Cursor};
+
+const LIMITS: Limits = Limits {
+    max_allocation: 1 << 24,
+    max_items: 1 << 20,
+    max_recursion_depth: 64,
+};
+
+fuzz_target!(|data: &[u8]| {
+    let mut reader = BufReader::new(Cursor::new(data));
+
+    if let Ok(mut dex_reader) = IoReader::with_limits(&mut reader, 0, LIMITS) {
+        if data.starts_with(&dex::cdex::MAGIC) {
+            let _ = dex_reader.read_cdex();
+        } else {
+            let _ = dex_reader.read_dex();
+        }
+    }
+});
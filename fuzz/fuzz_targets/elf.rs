@@ -0,0 +1,125 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+#![no_main]
+
+use exofmt::elf::{self, ElfClass, Reader};
+use exofmt::Limits;
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+// Small enough that libFuzzer's default `-rss_limit_mb` is never hit by a file that's within the limits
+const LIMITS: Limits = Limits {
+    max_allocation: 1 << 24,
+    max_items: 1 << 20,
+    max_recursion_depth: 64,
+};
+
+fn read_everything<'a, TReader: Reader<'a>>(reader: &mut TReader) {
+    let header = match reader.read_header() {
+        Ok(header) => header,
+        Err(_) => return,
+    };
+
+    if let Ok(program_headers) =
+        reader.read_program_headers(header.e_phoff, header.e_phentsize, header.e_phnum)
+    {
+        for program_header in &program_headers {
+            let _ = reader.get_program_bytes(program_header);
+
+            if program_header.p_type == elf::PT_DYNAMIC {
+                let _ = reader.read_dynamic_program(program_header);
+            }
+        }
+    }
+
+    let section_headers =
+        match reader.read_section_headers(header.e_shoff, header.e_shentsize, header.e_shnum) {
+            Ok(section_headers) => section_headers,
+            Err(_) => return,
+        };
+    let mut dynsym_len = 0;
+
+    for section_header in &section_headers {
+        let _ = reader.get_decompressed_section_bytes(section_header);
+
+        match section_header.sh_type {
+            elf::SHT_STRTAB => {
+                let _ = reader.read_str_table_section(section_header);
+            }
+            elf::SHT_SYMTAB => {
+                let _ = reader.read_sym_table_section(section_header);
+            }
+            elf::SHT_DYNSYM => {
+                if let Ok(symbols) =
+                    reader.read_sym_table(section_header.sh_offset, section_header.sh_size)
+                {
+                    dynsym_len = symbols.len();
+                }
+            }
+            elf::SHT_NOTE => {
+                let _ = reader.read_note_section(section_header);
+            }
+            elf::SHT_DYNAMIC => {
+                let _ = reader.read_dynamic_section(section_header);
+            }
+            elf::SHT_HASH => {
+                let _ = reader.read_hash_section(section_header);
+            }
+            elf::SHT_REL => {
+                let _ = reader.read_rel_section(section_header);
+            }
+            elf::SHT_RELA => {
+                let _ = reader.read_rela_section(section_header);
+            }
+            elf::SHT_RELR => {
+                let _ = reader.read_relr_section(section_header);
+            }
+            elf::gnu::SHT_GNU_HASH => {
+                let _ = reader.read_gnu_hash_table_section(section_header, dynsym_len);
+            }
+            _ => {}
+        }
+    }
+
+    let _ = elf::validate(reader);
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut cursor = Cursor::new(data);
+
+    let ident = match elf::get_elf_ident(&mut cursor) {
+        Ok(ident) => ident,
+        Err(_) => return,
+    };
+
+    match ident.class {
+        ElfClass::Elf32 => {
+            if let Ok(mut reader) =
+                elf::elf32::IoReader::with_limits(&mut cursor, ident.endianness, LIMITS)
+            {
+                read_everything(&mut reader);
+            }
+        }
+        ElfClass::Elf64 => {
+            if let Ok(mut reader) =
+                elf::elf64::IoReader::with_limits(&mut cursor, ident.endianness, LIMITS)
+            {
+                read_everything(&mut reader);
+            }
+        }
+    }
+});
@@ -0,0 +1,129 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+#![no_main]
+
+use exofmt::vdex::{vdex006, vdex010, vdex019, vdex021, vdex027};
+use exofmt::Limits;
+use libfuzzer_sys::fuzz_target;
+use std::io::{BufReader, Cursor};
+
+const LIMITS: Limits = Limits {
+    max_allocation: 1 << 24,
+    max_items: 1 << 20,
+    max_recursion_depth: 64,
+};
+
+fuzz_target!(|data: &[u8]| {
+    let mut reader = BufReader::new(Cursor::new(data));
+
+    // The version is right after the `vdex` magic in every version
+    match data.get(4..8) {
+        Some(version) if version == vdex006::VERSION => {
+            if let Ok(mut vdex_reader) = vdex006::IoReader::with_limits(&mut reader, LIMITS) {
+                if let Ok(header) = vdex_reader.read_header() {
+                    let _ = vdex_reader.read_dex_sections_checksums(&header);
+                    let _ = vdex_reader.read_dex_sections(&header);
+                    let _ = vdex_reader.read_verifier_deps(&header);
+                    let _ = vdex_reader.read_quickening_info(&header, &Vec::new());
+                }
+            }
+        }
+        Some(version) if version == vdex010::VERSION => {
+            if let Ok(mut vdex_reader) = vdex010::IoReader::with_limits(&mut reader, LIMITS) {
+                if let Ok(header) = vdex_reader.read_header() {
+                    let _ = vdex_reader.read_dex_sections_checksums(&header);
+                    let _ = vdex_reader.read_dex_sections(&header);
+                    let _ = vdex_reader.read_verifier_deps(&header);
+                    let _ = vdex_reader.read_quickening_info(&header, &Vec::new());
+                }
+            }
+        }
+        Some(version) if version == vdex019::VERIFIER_DEPS_VERSION => {
+            if let Ok(mut vdex_reader) = vdex019::IoReader::with_limits(&mut reader, LIMITS) {
+                if let Ok(header) = vdex_reader.read_verifier_deps_header() {
+                    let _ = vdex_reader.read_dex_sections_checksums(&header);
+                    let dex_section_header = vdex_reader.read_dex_section_header(&header).ok();
+                    let dex_files = vdex_reader
+                        .read_dex_files_with_quickening_offsets(&header)
+                        .unwrap_or_default();
+                    let _ = vdex_reader.read_verifier_deps(&header, dex_section_header.as_ref());
+
+                    if let Some(dex_section_header) = &dex_section_header {
+                        let _ = vdex_reader.read_quickening_info(
+                            &header,
+                            dex_section_header,
+                            &dex_files,
+                        );
+                    }
+                }
+            }
+        }
+        Some(version) if version == vdex021::VERIFIER_DEPS_VERSION => {
+            if let Ok(mut vdex_reader) = vdex021::IoReader::with_limits(&mut reader, LIMITS) {
+                if let Ok(header) = vdex_reader.read_verifier_deps_header() {
+                    let _ = vdex_reader.read_dex_sections_checksums(&header);
+                    let dex_section_header = vdex_reader.read_dex_section_header(&header).ok();
+                    let dex_files = vdex_reader
+                        .read_dex_files_with_quickening_offsets(&header)
+                        .unwrap_or_default();
+
+                    if let Some(dex_section_header) = &dex_section_header {
+                        let _ =
+                            vdex_reader.read_verifier_deps(&header, dex_section_header, &dex_files);
+                        let _ = vdex_reader.read_quickening_info(
+                            &header,
+                            dex_section_header,
+                            &dex_files,
+                        );
+                    }
+
+                    let _ = vdex_reader
+                        .read_bootclasspath_checksum(&header, dex_section_header.as_ref());
+                    let _ =
+                        vdex_reader.read_class_loader_context(&header, dex_section_header.as_ref());
+                }
+            }
+        }
+        Some(version) if version == vdex027::VERIFIER_DEPS_VERSION => {
+            if let Ok(mut vdex_reader) = vdex027::IoReader::with_limits(&mut reader, LIMITS) {
+                if let Ok(header) = vdex_reader.read_vdex_file_header() {
+                    for index in 0..header.number_of_sections {
+                        let section_header =
+                            match vdex_reader.read_vdex_section_header(&header, index) {
+                                Ok(section_header) => section_header,
+                                Err(_) => break,
+                            };
+
+                        let _ = match section_header.section_kind {
+                            vdex027::VDEX_SECTION_CHECKSUM => {
+                                vdex_reader.read_checksum_section(&section_header).map(drop)
+                            }
+                            vdex027::VDEX_SECTION_DEX_FILE => vdex_reader
+                                .read_dex_files_section(&section_header)
+                                .map(drop),
+                            vdex027::VDEX_SECTION_VERIFIER_DEPS => {
+                                vdex_reader.read_verifier_deps(&section_header).map(drop)
+                            }
+                            _ => Ok(()),
+                        };
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+});
@@ -18,7 +18,7 @@
 // This is a port from: https://cs.android.com/android/platform/superproject/+/master:art/libdexfile/dex/compact_offset_table.cc
 
 use crate::leb128;
-use crate::Error;
+use crate::{Details, Error};
 use scroll::Endian;
 use scroll::IOread;
 use std::io::BufReader;
@@ -106,9 +106,12 @@ impl<'a, TRead: IOread<Endian> + Seek> CompactOffsetTableReader<'a, TRead> {
             .count_ones();
 
         if count == 0 {
-            return Err(Error::InvalidArguments(format!(
-                "CompactOffsetTable was provided an invalid index which resulted in a count of `0`"
-            )));
+            return Err(Error::InvalidArguments(
+                Details::new(
+                    "CompactOffsetTable was provided an invalid index which resulted in a count of `0`",
+                )
+                .structure("compact_offset_table::CompactOffsetTableReader"),
+            ));
         }
 
         let mut current_offset: u32 = self.minimum_offset;
@@ -116,13 +119,11 @@ impl<'a, TRead: IOread<Endian> + Seek> CompactOffsetTableReader<'a, TRead> {
         self.reader.seek(SeekFrom::Start(block_index as u64))?;
 
         loop {
-            current_offset +=
-                match leb128::decode_uleb128::<u32, BufReader<TRead>>(&mut self.reader) {
-                    Ok(value) => value,
-                    Err(error) => {
-                        panic!("{}", error);
-                    }
-                };
+            // Same as ART, the offsets are `uint32_t` and wrap
+            current_offset = current_offset
+                .wrapping_add(leb128::decode_uleb128::<u32, BufReader<TRead>>(
+                    self.reader,
+                )?);
             count -= 1;
 
             if count == 0 {
@@ -0,0 +1,376 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+// Format detection for every container the crate supports, see `detect` and `open`
+
+use crate::dex::{CDex, Dex};
+use crate::elf::{ElfClass, Header, ProgramHeader, SectionHeader};
+use crate::vdex::{vdex006, vdex010, vdex019, vdex021, vdex027};
+use crate::{Details, Error, Limits};
+use scroll::Endian;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VDexVersion {
+    V006,
+    V010,
+    V019,
+    V021,
+    V027,
+}
+
+/// The container format of a file, found from its magic and version
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Elf {
+        class: ElfClass,
+        endianness: Endian,
+    },
+    /// `version` is the number after the magic, e.g. `35` for `dex\n035\0`
+    Dex {
+        version: u32,
+    },
+    CDex {
+        version: u32,
+    },
+    VDex(VDexVersion),
+}
+
+/// The headers of an ELF file, use `elf32::IoReader` or `elf64::IoReader` (see `ElfObject::class`) for the rest
+pub struct ElfObject {
+    pub class: ElfClass,
+    pub endianness: Endian,
+    pub header: Header,
+    pub program_headers: Vec<ProgramHeader>,
+    pub section_headers: Vec<SectionHeader>,
+}
+
+/// A parsed file of any supported format, see `open`
+///
+/// VDex files are parsed up to their headers and dex checksums, the version's `IoReader` reads the rest.
+pub enum Object {
+    Elf(ElfObject),
+    Dex(Dex<'static>),
+    CDex(CDex<'static>),
+    VDex006 {
+        header: vdex006::Header,
+        dex_checksums: Vec<u32>,
+    },
+    VDex010 {
+        header: vdex010::Header,
+        dex_checksums: Vec<u32>,
+    },
+    VDex019 {
+        header: vdex019::VerifierDepsHeader,
+        dex_checksums: Vec<u32>,
+    },
+    VDex021 {
+        header: vdex021::VerifierDepsHeader,
+        dex_checksums: Vec<u32>,
+    },
+    VDex027 {
+        header: vdex027::VDexFileHeader,
+        section_headers: Vec<vdex027::VDexSectionHeader>,
+    },
+}
+
+impl Object {
+    pub fn format(&self) -> Format {
+        match self {
+            Object::Elf(elf) => Format::Elf {
+                class: elf.class,
+                endianness: elf.endianness,
+            },
+            Object::Dex(dex) => Format::Dex {
+                version: parse_version(&dex.header.magic[4..8]).unwrap_or(0),
+            },
+            Object::CDex(cdex) => Format::CDex {
+                version: parse_version(&cdex.header.header.magic[4..8]).unwrap_or(0),
+            },
+            Object::VDex006 { .. } => Format::VDex(VDexVersion::V006),
+            Object::VDex010 { .. } => Format::VDex(VDexVersion::V010),
+            Object::VDex019 { .. } => Format::VDex(VDexVersion::V019),
+            Object::VDex021 { .. } => Format::VDex(VDexVersion::V021),
+            Object::VDex027 { .. } => Format::VDex(VDexVersion::V027),
+        }
+    }
+}
+
+/// Finds the format of `reader` from its first bytes, the position of `reader` is left unchanged
+pub fn detect<TRead: Read + Seek>(reader: &mut TRead) -> Result<Format> {
+    let reset_position = reader.stream_position()?;
+
+    reader.seek(SeekFrom::Start(0))?;
+    let mut ident: Vec<u8> = Vec::with_capacity(8);
+    reader.by_ref().take(8).read_to_end(&mut ident)?;
+    reader.seek(SeekFrom::Start(reset_position))?;
+
+    let (magic, version) = ident.split_at(ident.len().min(4));
+
+    match magic {
+        [0x7f, b'E', b'L', b'F'] => {
+            let elf_ident = crate::elf::get_elf_ident(reader)?;
+            Ok(Format::Elf {
+                class: elf_ident.class,
+                endianness: elf_ident.endianness,
+            })
+        }
+        _ if magic == crate::dex::MAGIC => Ok(Format::Dex {
+            version: dex_version(version)?,
+        }),
+        _ if magic == crate::dex::cdex::MAGIC => Ok(Format::CDex {
+            version: dex_version(version)?,
+        }),
+        _ if magic == vdex027::MAGIC => {
+            let version =
+                match version {
+                    _ if version == vdex006::VERSION => VDexVersion::V006,
+                    _ if version == vdex010::VERSION => VDexVersion::V010,
+                    _ if version == vdex019::VERIFIER_DEPS_VERSION => VDexVersion::V019,
+                    _ if version == vdex021::VERIFIER_DEPS_VERSION => VDexVersion::V021,
+                    _ if version == vdex027::VERIFIER_DEPS_VERSION => VDexVersion::V027,
+                    _ => return Err(Error::InvalidVersionNumber(
+                        Details::new(format!(
+                            "Expected one of the supported VDex versions `006`, `010`, `019`, `021` or `027`, found `{:02x?}`",
+                            version
+                        ))
+                        .field("version")
+                        .offset(4),
+                    )),
+                };
+            Ok(Format::VDex(version))
+        }
+        _ => Err(Error::InvalidMagicNumber(
+            Details::new(format!(
+                "Expected an ELF, Dex, Compact Dex or VDex magic number, found `{:02x?}`",
+                magic
+            ))
+            .field("magic")
+            .offset(0),
+        )),
+    }
+}
+
+/// Detects the format of `reader` and parses it
+pub fn open<TRead: Read + Seek>(reader: &mut TRead) -> Result<Object> {
+    open_with_limits(reader, Limits::default())
+}
+
+/// Same as `open` but with `limits` instead of the default limits
+pub fn open_with_limits<TRead: Read + Seek>(reader: &mut TRead, limits: Limits) -> Result<Object> {
+    let format = detect(reader)?;
+    let mut buf_reader = BufReader::new(reader);
+
+    Ok(match format {
+        Format::Elf { class, endianness } => {
+            let (header, program_headers, section_headers) = match class {
+                ElfClass::Elf32 => {
+                    let mut elf_reader = crate::elf::elf32::IoReader::with_limits(
+                        &mut buf_reader,
+                        endianness,
+                        limits,
+                    )?;
+                    let header = elf_reader.read_header()?;
+                    let program_headers = elf_reader.read_program_headers(
+                        header.e_phoff,
+                        header.e_phentsize,
+                        header.e_phnum,
+                    )?;
+                    let section_headers = elf_reader.read_section_headers(
+                        header.e_shoff,
+                        header.e_shentsize,
+                        header.e_shnum,
+                    )?;
+                    (header, program_headers, section_headers)
+                }
+                ElfClass::Elf64 => {
+                    let mut elf_reader = crate::elf::elf64::IoReader::with_limits(
+                        &mut buf_reader,
+                        endianness,
+                        limits,
+                    )?;
+                    let header = elf_reader.read_header()?;
+                    let program_headers = elf_reader.read_program_headers(
+                        header.e_phoff,
+                        header.e_phentsize,
+                        header.e_phnum,
+                    )?;
+                    let section_headers = elf_reader.read_section_headers(
+                        header.e_shoff,
+                        header.e_shentsize,
+                        header.e_shnum,
+                    )?;
+                    (header, program_headers, section_headers)
+                }
+            };
+
+            Object::Elf(ElfObject {
+                class,
+                endianness,
+                header,
+                program_headers,
+                section_headers,
+            })
+        }
+        Format::Dex { .. } => {
+            Object::Dex(crate::dex::IoReader::with_limits(&mut buf_reader, 0, limits)?.read_dex()?)
+        }
+        Format::CDex { .. } => Object::CDex(
+            crate::dex::IoReader::with_limits(&mut buf_reader, 0, limits)?.read_cdex()?,
+        ),
+        Format::VDex(VDexVersion::V006) => {
+            let mut vdex_reader = vdex006::IoReader::with_limits(&mut buf_reader, limits)?;
+            let header = vdex_reader.read_header()?;
+            let dex_checksums = vdex_reader.read_dex_sections_checksums(&header)?;
+            Object::VDex006 {
+                header,
+                dex_checksums,
+            }
+        }
+        Format::VDex(VDexVersion::V010) => {
+            let mut vdex_reader = vdex010::IoReader::with_limits(&mut buf_reader, limits)?;
+            let header = vdex_reader.read_header()?;
+            let dex_checksums = vdex_reader.read_dex_sections_checksums(&header)?;
+            Object::VDex010 {
+                header,
+                dex_checksums,
+            }
+        }
+        Format::VDex(VDexVersion::V019) => {
+            let mut vdex_reader = vdex019::IoReader::with_limits(&mut buf_reader, limits)?;
+            let header = vdex_reader.read_verifier_deps_header()?;
+            let dex_checksums = vdex_reader.read_dex_sections_checksums(&header)?;
+            Object::VDex019 {
+                header,
+                dex_checksums,
+            }
+        }
+        Format::VDex(VDexVersion::V021) => {
+            let mut vdex_reader = vdex021::IoReader::with_limits(&mut buf_reader, limits)?;
+            let header = vdex_reader.read_verifier_deps_header()?;
+            let dex_checksums = vdex_reader.read_dex_sections_checksums(&header)?;
+            Object::VDex021 {
+                header,
+                dex_checksums,
+            }
+        }
+        Format::VDex(VDexVersion::V027) => {
+            let mut vdex_reader = vdex027::IoReader::with_limits(&mut buf_reader, limits)?;
+            let header = vdex_reader.read_vdex_file_header()?;
+            let mut section_headers: Vec<vdex027::VDexSectionHeader> = Vec::new();
+            for index in 0..header.number_of_sections {
+                section_headers.push(vdex_reader.read_vdex_section_header(&header, index)?);
+            }
+            Object::VDex027 {
+                header,
+                section_headers,
+            }
+        }
+    })
+}
+
+/// Parses the `035\0` after a Dex or CDex magic
+fn dex_version(version: &[u8]) -> Result<u32> {
+    parse_version(version).ok_or_else(|| {
+        Error::InvalidVersionNumber(
+            Details::new(format!(
+                "Expected a three digit Dex version followed by a NUL, found `{:02x?}`",
+                version
+            ))
+            .structure("dex::Header")
+            .field("magic")
+            .offset(4),
+        )
+    })
+}
+
+fn parse_version(version: &[u8]) -> Option<u32> {
+    match version {
+        [digits @ .., 0] if digits.len() == 3 && digits.iter().all(u8::is_ascii_digit) => {
+            std::str::from_utf8(digits).ok()?.parse().ok()
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn detect_and_open_formats() {
+        let mut elf = vec![0u8; 0x80];
+        elf[..8].copy_from_slice(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0]);
+        // `e_type`, `e_machine`, `e_version`, then `e_ehsize` and the table entry sizes
+        elf[16..24].copy_from_slice(&[3, 0, 0xb7, 0, 1, 0, 0, 0]);
+        elf[52..64].copy_from_slice(&[0x40, 0, 0x38, 0, 0, 0, 0x40, 0, 0, 0, 0, 0]);
+
+        let mut cursor = Cursor::new(elf);
+        cursor.set_position(5);
+        assert_eq!(
+            detect(&mut cursor).unwrap(),
+            Format::Elf {
+                class: ElfClass::Elf64,
+                endianness: scroll::LE
+            }
+        );
+        assert_eq!(cursor.position(), 5);
+
+        let Object::Elf(object) = open(&mut cursor).unwrap() else {
+            panic!("Expected an ELF object");
+        };
+        assert_eq!(object.header.e_machine, crate::elf::EM_AARCH64);
+        assert!(object.section_headers.is_empty());
+
+        assert_eq!(
+            detect(&mut Cursor::new(b"dex\n039\0")).unwrap(),
+            Format::Dex { version: 39 }
+        );
+        assert_eq!(
+            detect(&mut Cursor::new(b"cdex001\0")).unwrap(),
+            Format::CDex { version: 1 }
+        );
+        assert!(matches!(
+            detect(&mut Cursor::new(b"dex\nabc\0")),
+            Err(Error::InvalidVersionNumber(_))
+        ));
+        assert!(matches!(
+            detect(&mut Cursor::new(b"PK\x03\x04")),
+            Err(Error::InvalidMagicNumber(_))
+        ));
+        let error = detect(&mut Cursor::new(b"vdex099\0")).unwrap_err();
+        assert!(matches!(error, Error::InvalidVersionNumber(_)));
+        assert_eq!(
+            error.details().and_then(|details| details.field),
+            Some("version")
+        );
+        assert_eq!(error.offset(), Some(4));
+
+        let mut vdex = Cursor::new(b"vdex027\0\0\0\0\0".to_vec());
+        assert_eq!(detect(&mut vdex).unwrap(), Format::VDex(VDexVersion::V027));
+        let object = open(&mut vdex).unwrap();
+        assert_eq!(object.format(), Format::VDex(VDexVersion::V027));
+        assert!(matches!(
+            object,
+            Object::VDex027 {
+                section_headers,
+                ..
+            } if section_headers.is_empty()
+        ));
+    }
+}
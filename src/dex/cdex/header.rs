@@ -14,7 +14,7 @@
  * limitations under the License.
  */
 
-use crate::Error;
+use crate::{Details, Error};
 use scroll::Endian;
 use scroll::IOread;
 use std::io::BufReader;
@@ -54,10 +54,10 @@ impl Header {
         } else if header.endian_tag == crate::dex::ENDIAN_CONSTANT_BYTES {
             Endian::Big
         } else {
-            return Err(Error::Malformed(format!(
+            return Err(Error::Malformed(Details::new(format!(
                 "Dex file `endian_tag` field malformed - found `{:02x?}` but expected `{:02x?}` or `{:02x?}`",
                 header.endian_tag, crate::dex::ENDIAN_CONSTANT_BYTES, crate::dex::REVERSE_ENDIAN_CONSTANT_BYTES,
-            )));
+            )).structure("dex::Header").field("endian_tag")));
         };
 
         let feature_flags = reader.ioread_with::<u32>(endianness)?;
@@ -14,7 +14,7 @@
  * limitations under the License.
  */
 
-use crate::Error;
+use crate::{Details, Error};
 use scroll::Endian;
 use scroll::IOread;
 use std::io::BufReader;
@@ -87,6 +87,31 @@ mod tests {
     fn header_size_check() {
         assert_eq!(::std::mem::size_of::<Header>(), 0x70);
     }
+
+    #[test]
+    fn header_error_offsets() {
+        // The Dex file starts `8` bytes into the stream
+        let mut bytes = vec![0u8; 8 + 0x70];
+        bytes[8..16].copy_from_slice(b"dex\n039\0");
+        let mut cursor = std::io::Cursor::new(bytes.clone());
+        cursor.set_position(8);
+        let error = match Header::read_from_buf_reader(&mut BufReader::new(cursor)) {
+            Ok(_) => panic!("Expected an error"),
+            Err(error) => error,
+        };
+        assert!(matches!(error, Error::Malformed(_)));
+        assert_eq!(error.offset(), Some(8 + 40));
+
+        bytes[8..12].copy_from_slice(b"PK\x03\x04");
+        let mut cursor = std::io::Cursor::new(bytes);
+        cursor.set_position(8);
+        let error = match Header::read_from_buf_reader(&mut BufReader::new(cursor)) {
+            Ok(_) => panic!("Expected an error"),
+            Err(error) => error,
+        };
+        assert!(matches!(error, Error::InvalidMagicNumber(_)));
+        assert_eq!(error.offset(), Some(8));
+    }
 }
 
 pub const MAGIC: [u8; 4] = [0x64, 0x65, 0x78, 0x0a];
@@ -108,12 +133,21 @@ impl Header {
         let mut header_size = [0u8; 4];
         let mut endian_tag = [0u8; 4];
 
+        let magic_offset = reader.stream_position()?;
         reader.read_exact(&mut magic)?;
 
         // I don't bother to validate the Dex version number but maybe we should... TBD
         // We allow `dex` or `cdex`, the caller should double check this...
         if (magic[0..4] != MAGIC && magic[0..4] != crate::dex::cdex::MAGIC) || magic[7] != 0 {
-            return Err(Error::InvalidMagicNumber(magic.to_vec()));
+            return Err(Error::InvalidMagicNumber(
+                Details::new(format!(
+                    "Expected a `dex` or `cdex` magic number followed by a NUL terminated version, found `{:02x?}`",
+                    magic
+                ))
+                .structure("dex::Header")
+                .field("magic")
+                .offset(magic_offset),
+            ));
         }
 
         reader.read_exact(&mut checksum)?;
@@ -127,10 +161,15 @@ impl Header {
         } else if endian_tag == ENDIAN_CONSTANT_BYTES {
             Endian::Big
         } else {
-            return Err(Error::Malformed(format!(
-                "Dex file `endian_tag` field malformed - found `{:02x?}` but expected `{:02x?}` or `{:02x?}`",
-                endian_tag, ENDIAN_CONSTANT_BYTES, REVERSE_ENDIAN_CONSTANT_BYTES,
-            )));
+            return Err(Error::Malformed(
+                Details::new(format!(
+                    "Dex file `endian_tag` field malformed - found `{:02x?}` but expected `{:02x?}` or `{:02x?}`",
+                    endian_tag, ENDIAN_CONSTANT_BYTES, REVERSE_ENDIAN_CONSTANT_BYTES,
+                ))
+                .structure("dex::Header")
+                .field("endian_tag")
+                .offset(magic_offset + 40),
+            ));
         };
 
         let checksum = if endianness.is_little() {
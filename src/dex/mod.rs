@@ -81,9 +81,10 @@ mod string_data_item;
 pub use string_data_item::*;
 
 use crate::compact_offset_table::CompactOffsetTableReader;
+use crate::error::ResultExt;
 use crate::leb128;
 use crate::mutf8::MUTF8;
-use crate::Error;
+use crate::{ContextFrame, Details, Error, Limits};
 use scroll::Endian;
 use scroll::IOread;
 use std::borrow::Cow;
@@ -296,20 +297,25 @@ pub struct IoReader<'a, TRead: IOread<Endian> + Seek> {
     // Compact Dex: start of the shared data section
     pub data_start_offset: u64,
     pub data_size: u64,
+    pub limits: Limits,
+    // How many encoded arrays and annotations we're currently nested in
+    value_depth: u32,
 }
 
 macro_rules! io_read_section_as_array_at {
     ($io_reader:expr, $reader:expr, $endianness:expr, $alignment:expr, $section_size:expr, $file_start_offset:expr, $section_offset:expr, $index_type:ty) => {{
         if $section_size > 0 {
-            let section_size = $section_size as usize;
+            let section_size = $section_size as u64;
             let section_offset = u64::from($section_offset);
 
+            let mut section_items: Vec<$index_type> = $io_reader
+                .limits
+                .vec_with_capacity(section_size, "a Dex section")?;
+
             let current_offset = $reader.seek(SeekFrom::Current(0))?;
 
             $reader.seek(SeekFrom::Start($file_start_offset + section_offset))?;
 
-            let mut section_items: Vec<$index_type> = Vec::with_capacity(section_size);
-
             for _ in 0..section_size {
                 $io_reader.seek_round_up_alignment($alignment)?;
 
@@ -326,7 +332,7 @@ macro_rules! io_read_section_as_array_at {
 }
 
 macro_rules! io_read_section_as_list_at {
-    ($reader:expr, $endianness:expr, $file_start_offset:expr, $section_offset:expr, $index_type:ty) => {{
+    ($reader:expr, $endianness:expr, $limits:expr, $file_start_offset:expr, $section_offset:expr, $index_type:ty) => {{
         if $section_offset > 0 {
             let section_offset = u64::from($section_offset);
 
@@ -334,8 +340,9 @@ macro_rules! io_read_section_as_list_at {
 
             $reader.seek(SeekFrom::Start($file_start_offset + section_offset))?;
 
-            let size = $reader.ioread_with::<u32>($endianness)? as usize;
-            let mut result_list: Vec<$index_type> = Vec::with_capacity(size);
+            let size = $reader.ioread_with::<u32>($endianness)?;
+            let mut result_list: Vec<$index_type> =
+                $limits.vec_with_capacity(u64::from(size), "a Dex list")?;
 
             for _ in 0..size {
                 result_list.push($reader.ioread_with::<$index_type>($endianness)?);
@@ -352,6 +359,14 @@ macro_rules! io_read_section_as_list_at {
 
 impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
     pub fn new(reader: &'a mut BufReader<TRead>, file_start_offset: u64) -> Result<Self> {
+        Self::with_limits(reader, file_start_offset, Limits::default())
+    }
+
+    pub fn with_limits(
+        reader: &'a mut BufReader<TRead>,
+        file_start_offset: u64,
+        limits: Limits,
+    ) -> Result<Self> {
         Ok(Self {
             reader,
             // Start with `Little` at first, `read_header` will correct this
@@ -359,6 +374,8 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
             file_start_offset,
             data_start_offset: file_start_offset,
             data_size: 0,
+            limits,
+            value_depth: 0,
         })
     }
 
@@ -389,18 +406,27 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
             self.read_class_defs_at(header.class_defs_size, header.class_defs_offset, false)?;
         let link_section = self.read_link_section_at(header.link_size, header.link_offset)?;
         let data_section = self.read_data_section_at(header.data_size, header.data_offset)?;
-        let map_list = self.read_map_list_at(header.map_offset)?;
+        let map_list = self
+            .read_map_list_at(header.map_offset)
+            .context(|| ContextFrame::new("map_list"))?;
 
         let mut call_site_ids: Option<Vec<CallSiteIdItem>> = None;
         let mut method_handles: Option<Vec<MethodHandleItem>> = None;
 
         for map_item in &map_list {
             if map_item.type_code == TYPE_CALL_SITE_ID_ITEM {
-                call_site_ids = Some(self.read_call_site_ids_at(map_item.size, map_item.offset)?);
+                call_site_ids = Some(
+                    self.read_call_site_ids_at(map_item.size, map_item.offset)
+                        .context(|| ContextFrame::new("call_site_ids"))?,
+                );
             } else if map_item.type_code == TYPE_METHOD_HANDLE_ITEM {
-                method_handles = Some(self.read_method_handles_at(map_item.size, map_item.offset)?);
+                method_handles = Some(
+                    self.read_method_handles_at(map_item.size, map_item.offset)
+                        .context(|| ContextFrame::new("method_handles"))?,
+                );
             } else if map_item.type_code == TYPE_HIDDENAPI_CLASS_DATA_ITEM {
-                self.read_hiddenapi_data_at(map_item.size, map_item.offset, &mut class_defs)?;
+                self.read_hiddenapi_data_at(map_item.size, map_item.offset, &mut class_defs)
+                    .context(|| ContextFrame::new("hiddenapi_class_data"))?;
             }
         }
 
@@ -456,18 +482,27 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
         )?;
         let link_section =
             self.read_link_section_at(header.header.link_size, header.header.link_offset)?;
-        let map_list = self.read_map_list_at(header.header.map_offset)?;
+        let map_list = self
+            .read_map_list_at(header.header.map_offset)
+            .context(|| ContextFrame::new("map_list"))?;
 
         let mut call_site_ids: Option<Vec<CallSiteIdItem>> = None;
         let mut method_handles: Option<Vec<MethodHandleItem>> = None;
 
         for map_item in &map_list {
             if map_item.type_code == TYPE_CALL_SITE_ID_ITEM {
-                call_site_ids = Some(self.read_call_site_ids_at(map_item.size, map_item.offset)?);
+                call_site_ids = Some(
+                    self.read_call_site_ids_at(map_item.size, map_item.offset)
+                        .context(|| ContextFrame::new("call_site_ids"))?,
+                );
             } else if map_item.type_code == TYPE_METHOD_HANDLE_ITEM {
-                method_handles = Some(self.read_method_handles_at(map_item.size, map_item.offset)?);
+                method_handles = Some(
+                    self.read_method_handles_at(map_item.size, map_item.offset)
+                        .context(|| ContextFrame::new("method_handles"))?,
+                );
             } else if map_item.type_code == TYPE_HIDDENAPI_CLASS_DATA_ITEM {
-                self.read_hiddenapi_data_at(map_item.size, map_item.offset, &mut class_defs)?;
+                self.read_hiddenapi_data_at(map_item.size, map_item.offset, &mut class_defs)
+                    .context(|| ContextFrame::new("hiddenapi_class_data"))?;
             }
         }
 
@@ -483,7 +518,21 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
         };
 
         let owned_data_offset = header.header.data_offset + header.owned_data_begin;
-        let owned_data_size = header.owned_data_end - header.owned_data_begin;
+        let owned_data_size = header
+            .owned_data_end
+            .checked_sub(header.owned_data_begin)
+            .ok_or_else(|| {
+                Error::Malformed(
+                    Details::new(format!(
+                        "Compact Dex `owned_data_end` (`{}`) is before `owned_data_begin` (`{}`)",
+                        header.owned_data_end, header.owned_data_begin
+                    ))
+                    .structure("dex::cdex::Header")
+                    .field("owned_data_end")
+                    // After the `0x70` byte Dex header and five other `u32` fields
+                    .offset(self.file_start_offset + 0x84),
+                )
+            })?;
         let owned_data_section = self.read_data_section_at(owned_data_size, owned_data_offset)?;
 
         for class_def in &mut class_defs {
@@ -553,7 +602,16 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
         // magic is `cdex`
         // TODO: We need a function similar to `get_elf_ident` or something ig.
         if result.magic[0..4] != MAGIC {
-            return Err(Error::InvalidMagicNumber(result.magic.to_vec()));
+            return Err(Error::InvalidMagicNumber(
+                Details::new(format!(
+                    "Expected `{:02x?}` for the Dex magic number, found `{:02x?}`",
+                    MAGIC,
+                    &result.magic[0..4]
+                ))
+                .structure("dex::Header")
+                .field("magic")
+                .offset(self.file_start_offset),
+            ));
         }
 
         self.endianness = if result.endian_tag == REVERSE_ENDIAN_CONSTANT_BYTES {
@@ -561,10 +619,15 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
         } else if result.endian_tag == ENDIAN_CONSTANT_BYTES {
             Endian::Big
         } else {
-            return Err(Error::Malformed(format!(
-                "Dex file `endian_tag` field malformed - found `{:02x?}` but expected `{:02x?}` or `{:02x?}`",
-                result.endian_tag, ENDIAN_CONSTANT_BYTES, REVERSE_ENDIAN_CONSTANT_BYTES,
-            )));
+            return Err(Error::Malformed(
+                Details::new(format!(
+                    "Dex file `endian_tag` field malformed - found `{:02x?}` but expected `{:02x?}` or `{:02x?}`",
+                    result.endian_tag, ENDIAN_CONSTANT_BYTES, REVERSE_ENDIAN_CONSTANT_BYTES,
+                ))
+                .structure("dex::Header")
+                .field("endian_tag")
+                .offset(self.file_start_offset + 40),
+            ));
         };
 
         self.data_size = u64::from(result.data_size);
@@ -580,7 +643,16 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
         // TODO: We need a function similar to `get_elf_ident` or something ig.
         if result.header.magic[0..4] != cdex::MAGIC {
-            return Err(Error::InvalidMagicNumber(result.header.magic.to_vec()));
+            return Err(Error::InvalidMagicNumber(
+                Details::new(format!(
+                    "Expected `{:02x?}` for the Compact Dex magic number, found `{:02x?}`",
+                    cdex::MAGIC,
+                    &result.header.magic[0..4]
+                ))
+                .structure("dex::cdex::Header")
+                .field("magic")
+                .offset(self.file_start_offset),
+            ));
         }
 
         self.endianness = if result.header.endian_tag == REVERSE_ENDIAN_CONSTANT_BYTES {
@@ -588,10 +660,15 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
         } else if result.header.endian_tag == ENDIAN_CONSTANT_BYTES {
             Endian::Big
         } else {
-            return Err(Error::Malformed(format!(
-                "Dex file `endian_tag` field malformed - found `{:02x?}` but expected `{:02x?}` or `{:02x?}`",
-                result.header.endian_tag, ENDIAN_CONSTANT_BYTES, REVERSE_ENDIAN_CONSTANT_BYTES,
-            )));
+            return Err(Error::Malformed(
+                Details::new(format!(
+                    "Dex file `endian_tag` field malformed - found `{:02x?}` but expected `{:02x?}` or `{:02x?}`",
+                    result.header.endian_tag, ENDIAN_CONSTANT_BYTES, REVERSE_ENDIAN_CONSTANT_BYTES,
+                ))
+                .structure("dex::cdex::Header")
+                .field("endian_tag")
+                .offset(self.file_start_offset + 40),
+            ));
         };
 
         self.data_start_offset += u64::from(result.header.data_offset);
@@ -606,7 +683,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
         link_offset: u32,
     ) -> Result<Cow<'static, [u8]>> {
         if link_size > 0 {
-            let link_size = link_size as usize;
+            let link_size = self
+                .limits
+                .check_allocation(u64::from(link_size), "the link section")?;
             let link_offset = u64::from(link_offset);
 
             let mut result: Vec<u8> = Vec::with_capacity(link_size);
@@ -633,7 +712,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
         data_offset: u32,
     ) -> Result<Cow<'static, [u8]>> {
         if data_size > 0 {
-            let data_size = data_size as usize;
+            let data_size = self
+                .limits
+                .check_allocation(u64::from(data_size), "the data section")?;
             let data_offset = u64::from(data_offset);
 
             let mut result: Vec<u8> = Vec::with_capacity(data_size);
@@ -659,6 +740,7 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
             // self,
             self.reader,
             self.endianness,
+            self.limits,
             // DEX_MAP_LIST_ALIGNMENT,
             self.data_start_offset,
             map_offset,
@@ -672,25 +754,34 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
         string_ids_offset: u32,
     ) -> Result<Vec<StringIdItem<'static>>> {
         if string_ids_size > 0 {
-            let string_ids_size = string_ids_size as usize;
             let string_ids_offset = u64::from(string_ids_offset);
 
+            let mut string_id_items: Vec<StringIdItem> = self
+                .limits
+                .vec_with_capacity(u64::from(string_ids_size), "string ids")?;
+
             let current_offset = self.reader.seek(SeekFrom::Current(0))?;
 
             self.reader
                 .seek(SeekFrom::Start(self.file_start_offset + string_ids_offset))?;
 
-            let mut string_id_items: Vec<StringIdItem> = Vec::with_capacity(string_ids_size);
-
-            for _ in 0..string_ids_size {
+            for index in 0..string_ids_size {
                 self.seek_round_up_alignment(DEX_STRING_ID_ITEM_ALIGNMENT)?;
 
                 let raw_string_id_item = self
                     .reader
                     .ioread_with::<raw::StringIdItem>(self.endianness)?;
 
-                let string_data_item =
-                    self.read_string_data_item_at(raw_string_id_item.string_data_offset)?;
+                let string_data_item = self
+                    .read_string_data_item_at(raw_string_id_item.string_data_offset)
+                    .context(|| {
+                        ContextFrame::new("string_ids")
+                            .index(index as usize)
+                            .offset(
+                                self.file_start_offset
+                                    + u64::from(raw_string_id_item.string_data_offset),
+                            )
+                    })?;
 
                 string_id_items.push(StringIdItem {
                     string_data: string_data_item,
@@ -728,24 +819,27 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
         proto_ids_offset: u32,
     ) -> Result<Vec<ProtoIdItem>> {
         if proto_ids_size > 0 {
-            let proto_ids_size = proto_ids_size as usize;
             let proto_ids_offset = u64::from(proto_ids_offset);
 
+            let mut proto_id_items: Vec<ProtoIdItem> = self
+                .limits
+                .vec_with_capacity(u64::from(proto_ids_size), "proto ids")?;
+
             let current_offset = self.reader.seek(SeekFrom::Current(0))?;
 
             self.reader
                 .seek(SeekFrom::Start(self.file_start_offset + proto_ids_offset))?;
 
-            let mut proto_id_items: Vec<ProtoIdItem> = Vec::with_capacity(proto_ids_size);
-
-            for _ in 0..proto_ids_size {
+            for index in 0..proto_ids_size {
                 self.seek_round_up_alignment(DEX_PROTO_ID_ITEM_ALIGNMENT)?;
 
                 let raw_proto_id_item = self
                     .reader
                     .ioread_with::<raw::ProtoIdItem>(self.endianness)?;
 
-                let type_list = self.read_type_list_at(raw_proto_id_item.parameters_offset)?;
+                let type_list = self
+                    .read_type_list_at(raw_proto_id_item.parameters_offset)
+                    .context(|| ContextFrame::new("proto_ids").index(index as usize))?;
 
                 proto_id_items.push(ProtoIdItem {
                     shorty_index: raw_proto_id_item.shorty_index,
@@ -767,6 +861,7 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
             // self,
             self.reader,
             self.endianness,
+            self.limits,
             // DEX_TYPE_LIST_ALIGNMENT,
             self.data_start_offset,
             type_list_offset,
@@ -855,10 +950,14 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
         macro_rules! read_integer {
             ($type:ty, $type_size:literal, $fill:literal, $value_type_name:literal) => {
                 if value_arg > ($type_size - 1) {
-                    Err(Error::Malformed(format!(
-                        "Malformed encoded value with type `{}` has invalid size `{}`",
-                        $value_type_name, value_arg,
-                    )))
+                    Err(Error::Malformed(
+                        Details::new(format!(
+                            "Malformed encoded value with type `{}` has invalid size `{}`",
+                            $value_type_name, value_arg,
+                        ))
+                        .structure("dex::EncodedValue")
+                        .offset(self.reader.stream_position()? - 1),
+                    ))
                 } else {
                     let mut buffer: [u8; $type_size] = [$fill; $type_size];
 
@@ -880,10 +979,14 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
         macro_rules! read_float {
             ($type:ty, $type_size:literal, $fill:literal, $value_type_name:literal) => {
                 if value_arg > ($type_size - 1) {
-                    Err(Error::Malformed(format!(
-                        "Malformed encoded value with type `{}` has invalid size `{}`",
-                        $value_type_name, value_arg,
-                    )))
+                    Err(Error::Malformed(
+                        Details::new(format!(
+                            "Malformed encoded value with type `{}` has invalid size `{}`",
+                            $value_type_name, value_arg,
+                        ))
+                        .structure("dex::EncodedValue")
+                        .offset(self.reader.stream_position()? - 1),
+                    ))
                 } else {
                     let mut buffer: [u8; $type_size] = [$fill; $type_size];
 
@@ -959,23 +1062,52 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
                 field_id_index: read_integer!(u32, 4, 0x00, "VALUE_ENUM")?,
             }),
             // TODO: Should we confirm `value_arg` is `0`?
-            raw::VALUE_ARRAY => Ok(EncodedValue::Array(self.read_encoded_array()?)),
+            raw::VALUE_ARRAY => Ok(EncodedValue::Array(
+                self.read_nested_value(Self::read_encoded_array)?,
+            )),
             // TODO: Should we confirm `value_arg` is `0`?
-            raw::VALUE_ANNOTATION => Ok(EncodedValue::Annotation(self.read_encoded_annotation()?)),
+            raw::VALUE_ANNOTATION => Ok(EncodedValue::Annotation(
+                self.read_nested_value(Self::read_encoded_annotation)?,
+            )),
             // TODO: Should we confirm `value_arg` is `0`?
             raw::VALUE_NULL => Ok(EncodedValue::Null),
             // TODO: Should we confirm `value_arg` is either `0` or `1`? Right now we ignore incorrect values
             raw::VALUE_BOOLEAN => Ok(EncodedValue::Boolean(value_arg != 0)),
-            unknown => Err(Error::Malformed(format!(
-                "Unknown `value_type` value `{}` found in `read_encoded_value`",
-                unknown,
-            ))),
+            unknown => Err(Error::Malformed(
+                Details::new(format!(
+                    "Unknown `value_type` value `{}` found in `read_encoded_value`",
+                    unknown,
+                ))
+                .structure("dex::EncodedValue")
+                .field("value_type")
+                .offset(self.reader.stream_position()? - 1),
+            )),
         }
     }
 
+    // Reads an array or annotation nested inside an encoded value, making sure hostile files can't nest them deep
+    // enough to overflow the stack
+    fn read_nested_value<T>(&mut self, read: fn(&mut Self) -> Result<T>) -> Result<T> {
+        self.value_depth += 1;
+
+        let result = match self
+            .limits
+            .check_recursion(self.value_depth, "Encoded arrays and annotations")
+        {
+            Ok(()) => read(self),
+            Err(error) => Err(error),
+        };
+
+        self.value_depth -= 1;
+
+        result
+    }
+
     fn read_encoded_array(&mut self) -> Result<Vec<EncodedValue>> {
         let size: u32 = leb128::decode_uleb128(&mut self.reader)?;
-        let mut result: Vec<EncodedValue> = Vec::with_capacity(size as usize);
+        let mut result: Vec<EncodedValue> = self
+            .limits
+            .vec_with_capacity(u64::from(size), "an encoded array")?;
 
         for _ in 0..size {
             self.seek_round_up_alignment(DEX_ENCODED_ARRAY_ITEM_ALIGNMENT)?;
@@ -989,7 +1121,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
     fn read_encoded_annotation(&mut self) -> Result<EncodedAnnotation> {
         let type_index: u32 = leb128::decode_uleb128(&mut self.reader)?;
         let size: u32 = leb128::decode_uleb128(&mut self.reader)?;
-        let mut elements: Vec<AnnotationElement> = Vec::with_capacity(size as usize);
+        let mut elements: Vec<AnnotationElement> = self
+            .limits
+            .vec_with_capacity(u64::from(size), "annotation elements")?;
 
         for _ in 0..size {
             elements.push(self.read_annotation_element()?);
@@ -1020,7 +1154,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
             ))?;
 
             let size = self.reader.ioread_with::<u32>(self.endianness)?;
-            let mut result: Vec<Vec<AnnotationItem>> = Vec::with_capacity(size as usize);
+            let mut result: Vec<Vec<AnnotationItem>> = self
+                .limits
+                .vec_with_capacity(u64::from(size), "an annotation set ref list")?;
 
             for _ in 0..size {
                 self.seek_round_up_alignment(DEX_ANNOTATION_SET_REF_LIST_ALIGNMENT)?;
@@ -1055,7 +1191,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
             ))?;
 
             let size = self.reader.ioread_with::<u32>(self.endianness)?;
-            let mut result: Vec<AnnotationItem> = Vec::with_capacity(size as usize);
+            let mut result: Vec<AnnotationItem> = self
+                .limits
+                .vec_with_capacity(u64::from(size), "an annotation set")?;
 
             for _ in 0..size {
                 let annotation_off_item = self
@@ -1109,12 +1247,18 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
                 .reader
                 .ioread_with::<raw::AnnotationsDirectoryItemHeader>(self.endianness)?;
             // We will come back to `class_annotations_offset`, I just don't want to deal with the offset resetting stuff...
-            let mut field_annotations: Vec<FieldAnnotation> =
-                Vec::with_capacity(header.fields_size as usize);
-            let mut method_annotations: Vec<MethodAnnotation> =
-                Vec::with_capacity(header.annotated_methods_size as usize);
+            let mut field_annotations: Vec<FieldAnnotation> = self
+                .limits
+                .vec_with_capacity(u64::from(header.fields_size), "field annotations")?;
+            let mut method_annotations: Vec<MethodAnnotation> = self.limits.vec_with_capacity(
+                u64::from(header.annotated_methods_size),
+                "method annotations",
+            )?;
             let mut parameter_annotations: Vec<ParameterAnnotation> =
-                Vec::with_capacity(header.annotated_parameters_size as usize);
+                self.limits.vec_with_capacity(
+                    u64::from(header.annotated_parameters_size),
+                    "parameter annotations",
+                )?;
 
             for _ in 0..header.fields_size {
                 let raw_field_annotation = self
@@ -1180,7 +1324,14 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
     }
 
     pub fn read_code_item_at(&mut self, code_item_offset: u32) -> Result<CodeItem> {
-        assert!(code_item_offset != 0);
+        if code_item_offset == 0 {
+            return Err(Error::InvalidArguments(
+                Details::new(
+                    "Invalid `code_item_offset` passed to `read_code_item_at`, it must not be `0`",
+                )
+                .structure("dex::CodeItem"),
+            ));
+        }
 
         let current_offset = self.reader.seek(SeekFrom::Current(0))?;
 
@@ -1191,7 +1342,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
         let code_item_header = self
             .reader
             .ioread_with::<raw::CodeItemHeader>(self.endianness)?;
-        let mut instructions: Vec<u16> = Vec::with_capacity(code_item_header.insns_size as usize);
+        let mut instructions: Vec<u16> = self
+            .limits
+            .vec_with_capacity(u64::from(code_item_header.insns_size), "instructions")?;
 
         for _ in 0..code_item_header.insns_size {
             instructions.push(self.reader.ioread_with::<u16>(self.endianness)?);
@@ -1220,9 +1373,12 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
         } else {
             0
         };
-        let mut handlers: Vec<EncodedCatchHandler> = Vec::with_capacity(handlers_size as usize);
-        let mut handlers_offset_translations: Vec<(u64, u16)> =
-            Vec::with_capacity(handlers_size as usize);
+        let mut handlers: Vec<EncodedCatchHandler> = self
+            .limits
+            .vec_with_capacity(u64::from(handlers_size), "catch handlers")?;
+        let mut handlers_offset_translations: Vec<(u64, u16)> = self
+            .limits
+            .vec_with_capacity(u64::from(handlers_size), "catch handlers")?;
 
         for handlers_index in 0..handlers_size {
             let handlers_offset = self.reader.seek(SeekFrom::Current(0))? - handlers_offset_start;
@@ -1268,7 +1424,14 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
         //       the instructions contain `FILL_ARRAY_DATA`, `PACKED_SWITCH`, or `SPARSE_SWITCH`
         //       We don't have to consider this for reading due to use starting parsing at
         //       `code_item_offset` but it must be taken into consideration for writing.
-        assert!(code_item_offset != 0);
+        if code_item_offset == 0 {
+            return Err(Error::InvalidArguments(
+                Details::new(
+                    "Invalid `code_item_offset` passed to `read_compact_code_item_at`, it must not be `0`",
+                )
+                .structure("dex::CodeItem"),
+            ));
+        }
 
         let current_offset = self.reader.seek(SeekFrom::Current(0))?;
 
@@ -1319,28 +1482,38 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
             let instructions_start_offset: u64 = self.reader.seek(SeekFrom::Current(0))?;
 
             // Seek backwards once instead of attempting to parse backwards
-            self.reader.seek(SeekFrom::Start(
-                (self.data_start_offset + u64::from(code_item_offset)) - preheader_size,
-            ))?;
+            let preheader_offset = (self.data_start_offset + u64::from(code_item_offset))
+                .checked_sub(preheader_size)
+                .ok_or_else(|| {
+                    Error::Malformed(
+                        Details::new(format!(
+                            "Compact code item at `{}` has a preheader of `{}` bytes that starts before the file",
+                            code_item_offset, preheader_size
+                        ))
+                        .structure("dex::CodeItem")
+                        .offset(self.data_start_offset + u64::from(code_item_offset)),
+                    )
+                })?;
+            self.reader.seek(SeekFrom::Start(preheader_offset))?;
 
             if (insns_count_and_flags & cdex::CODE_ITEM_FLAG_PREHEADER_TRIES_SIZE) != 0 {
                 let value: u16 = self.reader.ioread_with(self.endianness)?;
-                tries_size += value;
+                tries_size = tries_size.wrapping_add(value);
             }
 
             if (insns_count_and_flags & cdex::CODE_ITEM_FLAG_PREHEADER_OUTS_SIZE) != 0 {
                 let value: u16 = self.reader.ioread_with(self.endianness)?;
-                outs_size += value;
+                outs_size = outs_size.wrapping_add(value);
             }
 
             if (insns_count_and_flags & cdex::CODE_ITEM_FLAG_PREHEADER_INS_SIZE) != 0 {
                 let value: u16 = self.reader.ioread_with(self.endianness)?;
-                ins_size += value;
+                ins_size = ins_size.wrapping_add(value);
             }
 
             if (insns_count_and_flags & cdex::CODE_ITEM_FLAG_PREHEADER_REGISTER_SIZE) != 0 {
                 let value: u16 = self.reader.ioread_with(self.endianness)?;
-                registers_size += value;
+                registers_size = registers_size.wrapping_add(value);
             }
 
             if (insns_count_and_flags & cdex::CODE_ITEM_FLAG_PREHEADER_INSNS_SIZE) != 0 {
@@ -1355,7 +1528,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
                 .seek(SeekFrom::Start(instructions_start_offset))?;
         }
 
-        let mut instructions: Vec<u16> = Vec::with_capacity(insns_count as usize);
+        let mut instructions: Vec<u16> = self
+            .limits
+            .vec_with_capacity(u64::from(insns_count), "instructions")?;
 
         for _ in 0..insns_count {
             instructions.push(self.reader.ioread_with::<u16>(self.endianness)?);
@@ -1380,9 +1555,12 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
         } else {
             0
         };
-        let mut handlers: Vec<EncodedCatchHandler> = Vec::with_capacity(handlers_size as usize);
-        let mut handlers_offset_translations: Vec<(u64, u16)> =
-            Vec::with_capacity(handlers_size as usize);
+        let mut handlers: Vec<EncodedCatchHandler> = self
+            .limits
+            .vec_with_capacity(u64::from(handlers_size), "catch handlers")?;
+        let mut handlers_offset_translations: Vec<(u64, u16)> = self
+            .limits
+            .vec_with_capacity(u64::from(handlers_size), "catch handlers")?;
 
         for handlers_index in 0..handlers_size {
             let handlers_offset = self.reader.seek(SeekFrom::Current(0))? - handlers_offset_start;
@@ -1420,8 +1598,10 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
     fn read_encoded_catch_handler(&mut self) -> Result<EncodedCatchHandler> {
         let size = leb128::decode_sleb128::<i32, BufReader<TRead>>(&mut self.reader)?;
-        let abs_size = size.abs() as usize;
-        let mut handlers: Vec<EncodedTypeAddressPair> = Vec::with_capacity(abs_size);
+        let abs_size = size.unsigned_abs();
+        let mut handlers: Vec<EncodedTypeAddressPair> = self
+            .limits
+            .vec_with_capacity(u64::from(abs_size), "catch handler type address pairs")?;
 
         for _ in 0..abs_size {
             handlers.push(self.read_encoded_type_addr_pair()?);
@@ -1464,7 +1644,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
             let line_start: u32 = leb128::decode_uleb128(&mut self.reader)?;
             let parameters_size: u32 = leb128::decode_uleb128(&mut self.reader)?;
-            let mut parameters: Vec<u32> = Vec::with_capacity(parameters_size as usize);
+            let mut parameters: Vec<u32> = self
+                .limits
+                .vec_with_capacity(u64::from(parameters_size), "debug info parameters")?;
 
             for _ in 0..parameters_size {
                 parameters.push(uleb128p1::uleb128p1::decode(&mut self.reader)?.to_u32());
@@ -1489,7 +1671,14 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
         class_data_offset: u32,
         read_compact_code_item: bool,
     ) -> Result<ClassDataItem> {
-        assert!(class_data_offset != 0);
+        if class_data_offset == 0 {
+            return Err(Error::InvalidArguments(
+                Details::new(
+                    "Invalid `class_data_offset` passed to `read_class_data_item_at`, it must not be `0`",
+                )
+                .structure("dex::ClassDataItem"),
+            ));
+        }
 
         let current_offset = self.reader.seek(SeekFrom::Current(0))?;
 
@@ -1502,82 +1691,57 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
         let direct_methods_size: u32 = leb128::decode_uleb128(&mut self.reader)?;
         let virtual_methods_size: u32 = leb128::decode_uleb128(&mut self.reader)?;
 
-        let mut static_fields: Vec<EncodedField> = Vec::with_capacity(static_fields_size as usize);
-        let mut instance_fields: Vec<EncodedField> =
-            Vec::with_capacity(instance_fields_size as usize);
-        let mut direct_methods: Vec<EncodedMethod> =
-            Vec::with_capacity(direct_methods_size as usize);
-        let mut virtual_methods: Vec<EncodedMethod> =
-            Vec::with_capacity(virtual_methods_size as usize);
-
-        macro_rules! read_encoded_field {
-            ($result_list:ident, $last_field_index:ident) => {
-                let field_index_diff: u32 = leb128::decode_uleb128(&mut self.reader)?;
-                let field_index = $last_field_index + field_index_diff;
-                let access_flags: u32 = leb128::decode_uleb128(&mut self.reader)?;
-
-                $result_list.push(EncodedField {
-                    field_index,
-                    access_flags,
-                    hiddenapi_flag: None,
-                });
-
-                $last_field_index = field_index;
-            };
-        }
-
-        macro_rules! read_encoded_method {
-            ($result_list:ident, $last_method_index:ident) => {
-                let method_index_diff: u32 = leb128::decode_uleb128(&mut self.reader)?;
-                let method_index = $last_method_index + method_index_diff;
-                let access_flags: u32 = leb128::decode_uleb128(&mut self.reader)?;
-                let code_offset: u32 = leb128::decode_uleb128(&mut self.reader)?;
-
-                let code_item = if code_offset > 0 {
-                    let code_item = if read_compact_code_item {
-                        self.read_compact_code_item_at(code_offset)?
-                    } else {
-                        self.read_code_item_at(code_offset)?
-                    };
-
-                    Some(code_item)
-                } else {
-                    None
-                };
-
-                $result_list.push(EncodedMethod {
-                    method_index,
-                    access_flags,
-                    code: code_item,
-                    hiddenapi_flag: None,
-                });
-
-                $last_method_index = method_index;
-            };
-        }
+        let mut static_fields: Vec<EncodedField> = self
+            .limits
+            .vec_with_capacity(u64::from(static_fields_size), "static fields")?;
+        let mut instance_fields: Vec<EncodedField> = self
+            .limits
+            .vec_with_capacity(u64::from(instance_fields_size), "instance fields")?;
+        let mut direct_methods: Vec<EncodedMethod> = self
+            .limits
+            .vec_with_capacity(u64::from(direct_methods_size), "direct methods")?;
+        let mut virtual_methods: Vec<EncodedMethod> = self
+            .limits
+            .vec_with_capacity(u64::from(virtual_methods_size), "virtual methods")?;
 
         let mut last_static_field_index: u32 = 0;
 
-        for _ in 0..static_fields_size {
-            read_encoded_field!(static_fields, last_static_field_index);
+        for index in 0..static_fields_size {
+            let encoded_field = self
+                .read_encoded_field(last_static_field_index)
+                .context(|| ContextFrame::new("static_fields").index(index as usize))?;
+            last_static_field_index = encoded_field.field_index;
+            static_fields.push(encoded_field);
         }
 
         let mut last_instance_field_index: u32 = 0;
 
-        for _ in 0..instance_fields_size {
-            read_encoded_field!(instance_fields, last_instance_field_index);
+        for index in 0..instance_fields_size {
+            let encoded_field = self
+                .read_encoded_field(last_instance_field_index)
+                .context(|| ContextFrame::new("instance_fields").index(index as usize))?;
+            last_instance_field_index = encoded_field.field_index;
+            instance_fields.push(encoded_field);
         }
 
         let mut last_direct_method_index: u32 = 0;
 
-        for _ in 0..direct_methods_size {
-            read_encoded_method!(direct_methods, last_direct_method_index);
+        for index in 0..direct_methods_size {
+            let encoded_method = self
+                .read_encoded_method(last_direct_method_index, read_compact_code_item)
+                .context(|| ContextFrame::new("direct_methods").index(index as usize))?;
+            last_direct_method_index = encoded_method.method_index;
+            direct_methods.push(encoded_method);
         }
 
         let mut last_virtual_method_index: u32 = 0;
 
-        for _ in 0..virtual_methods_size {
-            read_encoded_method!(virtual_methods, last_virtual_method_index);
+        for index in 0..virtual_methods_size {
+            let encoded_method = self
+                .read_encoded_method(last_virtual_method_index, read_compact_code_item)
+                .context(|| ContextFrame::new("virtual_methods").index(index as usize))?;
+            last_virtual_method_index = encoded_method.method_index;
+            virtual_methods.push(encoded_method);
         }
 
         self.reader.seek(SeekFrom::Start(current_offset))?;
@@ -1590,6 +1754,101 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
         })
     }
 
+    fn read_encoded_field(&mut self, last_field_index: u32) -> Result<EncodedField> {
+        let field_index_diff: u32 = leb128::decode_uleb128(&mut self.reader)?;
+        let access_flags: u32 = leb128::decode_uleb128(&mut self.reader)?;
+
+        Ok(EncodedField {
+            field_index: last_field_index.wrapping_add(field_index_diff),
+            access_flags,
+            hiddenapi_flag: None,
+        })
+    }
+
+    fn read_encoded_method(
+        &mut self,
+        last_method_index: u32,
+        read_compact_code_item: bool,
+    ) -> Result<EncodedMethod> {
+        let method_index_diff: u32 = leb128::decode_uleb128(&mut self.reader)?;
+        let access_flags: u32 = leb128::decode_uleb128(&mut self.reader)?;
+        let code_offset: u32 = leb128::decode_uleb128(&mut self.reader)?;
+
+        let code_item = if code_offset > 0 {
+            let code_item = if read_compact_code_item {
+                self.read_compact_code_item_at(code_offset)
+            } else {
+                self.read_code_item_at(code_offset)
+            };
+
+            Some(code_item.context(|| {
+                ContextFrame::new("code_item")
+                    .offset(self.data_start_offset + u64::from(code_offset))
+            })?)
+        } else {
+            None
+        };
+
+        Ok(EncodedMethod {
+            method_index: last_method_index.wrapping_add(method_index_diff),
+            access_flags,
+            code: code_item,
+            hiddenapi_flag: None,
+        })
+    }
+
+    fn read_class_def_item(&mut self, read_compact_code_item: bool) -> Result<ClassDefItem> {
+        let raw_class_def_item = self
+            .reader
+            .ioread_with::<raw::ClassDefItem>(self.endianness)?;
+
+        let interfaces = self
+            .read_type_list_at(raw_class_def_item.interfaces_offset)
+            .context(|| ContextFrame::new("interfaces"))?;
+        let annotations = if raw_class_def_item.annotations_offset > 0 {
+            Some(
+                self.read_annotations_directory_item_at(raw_class_def_item.annotations_offset)
+                    .context(|| {
+                        ContextFrame::new("annotations").offset(
+                            self.data_start_offset
+                                + u64::from(raw_class_def_item.annotations_offset),
+                        )
+                    })?,
+            )
+        } else {
+            None
+        };
+        let class_data = if raw_class_def_item.class_data_offset > 0 {
+            Some(
+                self.read_class_data_item_at(
+                    raw_class_def_item.class_data_offset,
+                    read_compact_code_item,
+                )
+                .context(|| {
+                    ContextFrame::new("class_data").offset(
+                        self.data_start_offset + u64::from(raw_class_def_item.class_data_offset),
+                    )
+                })?,
+            )
+        } else {
+            None
+        };
+        let static_values = self
+            .read_encoded_array_item_at(raw_class_def_item.static_values_offset)
+            .context(|| ContextFrame::new("static_values"))?;
+
+        Ok(ClassDefItem {
+            class_index: raw_class_def_item.class_index,
+            access_flags: raw_class_def_item.access_flags,
+            superclass_index: raw_class_def_item.superclass_index,
+            interfaces,
+            source_file_index: raw_class_def_item.source_file_index,
+            annotations,
+            class_data,
+            static_values,
+        })
+    }
+
     pub fn read_class_defs_at(
         &mut self,
         class_defs_size: u32,
@@ -1603,44 +1862,23 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
                 self.file_start_offset + u64::from(class_defs_offset),
             ))?;
 
-            let mut result: Vec<ClassDefItem> = Vec::with_capacity(class_defs_size as usize);
+            let mut result: Vec<ClassDefItem> = self
+                .limits
+                .vec_with_capacity(u64::from(class_defs_size), "class defs")?;
 
-            for _ in 0..class_defs_size {
+            for index in 0..class_defs_size {
                 self.seek_round_up_alignment(DEX_CLASS_DEF_ITEM_ALIGNMENT)?;
 
-                let raw_class_def_item = self
-                    .reader
-                    .ioread_with::<raw::ClassDefItem>(self.endianness)?;
+                let item_offset = self.reader.stream_position()?;
+                let class_def_item =
+                    self.read_class_def_item(read_compact_code_item)
+                        .context(|| {
+                            ContextFrame::new("class_defs")
+                                .index(index as usize)
+                                .offset(item_offset)
+                        })?;
 
-                let interfaces = self.read_type_list_at(raw_class_def_item.interfaces_offset)?;
-                let annotations = if raw_class_def_item.annotations_offset > 0 {
-                    Some(self.read_annotations_directory_item_at(
-                        raw_class_def_item.annotations_offset,
-                    )?)
-                } else {
-                    None
-                };
-                let class_data = if raw_class_def_item.class_data_offset > 0 {
-                    Some(self.read_class_data_item_at(
-                        raw_class_def_item.class_data_offset,
-                        read_compact_code_item,
-                    )?)
-                } else {
-                    None
-                };
-                let static_values =
-                    self.read_encoded_array_item_at(raw_class_def_item.static_values_offset)?;
-
-                result.push(ClassDefItem {
-                    class_index: raw_class_def_item.class_index,
-                    access_flags: raw_class_def_item.access_flags,
-                    superclass_index: raw_class_def_item.superclass_index,
-                    interfaces,
-                    source_file_index: raw_class_def_item.source_file_index,
-                    annotations,
-                    class_data,
-                    static_values,
-                })
+                result.push(class_def_item);
             }
 
             self.reader.seek(SeekFrom::Start(current_offset))?;
@@ -1681,7 +1919,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
                 self.file_start_offset + u64::from(call_site_ids_offset),
             ))?;
 
-            let mut result: Vec<CallSiteIdItem> = Vec::with_capacity(call_site_ids_size as usize);
+            let mut result: Vec<CallSiteIdItem> = self
+                .limits
+                .vec_with_capacity(u64::from(call_site_ids_size), "call site ids")?;
 
             for _ in 0..call_site_ids_size {
                 self.seek_round_up_alignment(DEX_CALL_SITE_ID_ITEM_ALIGNMENT)?;
@@ -1738,10 +1978,14 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
     ) -> Result<()> {
         if hiddenapi_offset > 0 {
             if hiddenapi_size != 1 {
-                return Err(Error::Malformed(format!(
-                    "Map list items of `TYPE_HIDDENAPI_CLASS_DATA_ITEM` should only have a size value of `1`, found `{}`",
-                    hiddenapi_size
-                )));
+                return Err(Error::Malformed(
+                    Details::new(format!(
+                        "Map list items of `TYPE_HIDDENAPI_CLASS_DATA_ITEM` should only have a size value of `1`, found `{}`",
+                        hiddenapi_size
+                    ))
+                    .structure("dex::MapItem")
+                    .field("size"),
+                ));
             }
 
             let current_offset = self.reader.seek(SeekFrom::Current(0))?;
@@ -0,0 +1,163 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::elf::aarch64::R_AARCH64_NONE;
+use crate::elf::RelA;
+use crate::leb128::decode_uleb128;
+use crate::{Details, Error, Limits};
+use std::io::Cursor;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// MTE tags memory in granules of 16 bytes
+pub const MEMTAG_GRANULE_SIZE: u64 = 16;
+/// Number of low bits of a descriptor holding a small size, a size of `0` means the size follows as its own ULEB128
+const MEMTAG_STEP_SIZE_BITS: u32 = 3;
+
+/// A tagged global from a `SHT_AARCH64_MEMTAG_GLOBALS_DYNAMIC` section
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemtagGlobal {
+    /// Virtual address of the global, always granule aligned
+    pub address: u64,
+    /// Size of the global in bytes, rounded up to a whole number of granules
+    pub size: u64,
+}
+
+/// The tagged globals of an executable or shared object, from the `SHT_AARCH64_MEMTAG_GLOBALS_DYNAMIC` section (or
+/// `DT_AARCH64_MEMTAG_GLOBALS`/`DT_AARCH64_MEMTAG_GLOBALSSZ`)
+///
+/// Each global is encoded as a ULEB128 holding the distance in granules from the end of the previous global shifted
+/// left by `3`, with the size in granules in the low bits. Globals of `8` granules or more have `0` in the low bits
+/// and their size minus one as a second ULEB128.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemtagGlobals {
+    pub globals: Vec<MemtagGlobal>,
+}
+
+impl MemtagGlobals {
+    pub fn parse(bytes: &[u8]) -> Result<MemtagGlobals> {
+        Self::parse_with_limits(bytes, &Limits::default())
+    }
+
+    /// Same as `parse` but checks the number of globals against `limits` instead of the default limits
+    pub fn parse_with_limits(bytes: &[u8], limits: &Limits) -> Result<MemtagGlobals> {
+        // Every descriptor is at least one byte
+        limits.check_items::<MemtagGlobal>(bytes.len() as u64, "MTE global descriptors")?;
+
+        let mut globals: Vec<MemtagGlobal> = Vec::new();
+        let mut cursor = Cursor::new(bytes);
+        let mut last_global_end: u64 = 0;
+
+        while (cursor.position() as usize) < bytes.len() {
+            let offset = cursor.position();
+            let value: u64 = decode_uleb128(&mut cursor)?;
+            let step = value >> MEMTAG_STEP_SIZE_BITS;
+            let granules = match value & ((1 << MEMTAG_STEP_SIZE_BITS) - 1) {
+                0 => decode_uleb128::<u64, _>(&mut cursor)?.checked_add(1),
+                small => Some(small),
+            };
+
+            let global = granules
+                .and_then(|granules| granules.checked_mul(MEMTAG_GRANULE_SIZE))
+                .and_then(|size| {
+                    let address = step
+                        .checked_mul(MEMTAG_GRANULE_SIZE)?
+                        .checked_add(last_global_end)?;
+                    last_global_end = address.checked_add(size)?;
+                    Some(MemtagGlobal { address, size })
+                })
+                .ok_or_else(|| {
+                    Error::Malformed(
+                        Details::new(format!(
+                            "MTE global descriptor `{}` overflows the address space",
+                            globals.len()
+                        ))
+                        .structure("elf::aarch64::MemtagGlobals")
+                        .offset(offset),
+                    )
+                })?;
+
+            globals.push(global);
+        }
+
+        Ok(MemtagGlobals { globals })
+    }
+
+    /// Returns the global containing `address`
+    pub fn find(&self, address: u64) -> Option<&MemtagGlobal> {
+        let index = self
+            .globals
+            .partition_point(|global| global.address <= address)
+            .checked_sub(1)?;
+        let global = &self.globals[index];
+
+        (address - global.address < global.size).then_some(global)
+    }
+
+    /// Total size in bytes of every tagged global
+    pub fn total_size(&self) -> u64 {
+        self.globals
+            .iter()
+            .fold(0u64, |total, global| total.saturating_add(global.size))
+    }
+
+    /// Returns the symbol indices of the globals to tag in a relocatable object
+    ///
+    /// `SHT_AARCH64_MEMTAG_GLOBALS_STATIC` sections are empty, each tagged global is instead marked with an
+    /// `R_AARCH64_NONE` relocation against it in the section's relocation section.
+    pub fn static_symbols(relocations: &[RelA]) -> Vec<u32> {
+        relocations
+            .iter()
+            .filter(|relocation| relocation.r_type() == R_AARCH64_NONE)
+            .map(|relocation| relocation.r_sym())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_dynamic_descriptors() {
+        // 0x10..0x30, then 0x40..0xc0 which is too big for the low bits, then 0xc0..0xd0
+        let bytes = [(1 << 3) | 2, 1 << 3, 7, 1];
+        let globals = MemtagGlobals::parse(&bytes).unwrap();
+
+        assert_eq!(
+            globals.globals,
+            vec![
+                MemtagGlobal {
+                    address: 0x10,
+                    size: 0x20
+                },
+                MemtagGlobal {
+                    address: 0x40,
+                    size: 0x80
+                },
+                MemtagGlobal {
+                    address: 0xc0,
+                    size: 0x10
+                },
+            ]
+        );
+        assert_eq!(globals.find(0x7f).unwrap().address, 0x40);
+        assert!(globals.find(0x30).is_none());
+        assert_eq!(globals.total_size(), 0xb0);
+
+        // A truncated second ULEB128
+        assert!(MemtagGlobals::parse(&[1 << 3]).is_err());
+    }
+}
@@ -16,6 +16,9 @@
 
 use crate::stringable_consts_blocks::option_stringable_consts_block;
 
+mod memtag;
+pub use memtag::*;
+
 // st_other
 option_stringable_consts_block! {
     const stringable: u8 {
@@ -0,0 +1,124 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::Error;
+use bitflags::bitflags;
+use scroll::{Endian, Pread};
+use scroll_derive::{IOread, IOwrite, Pread, Pwrite, SizeWith};
+
+type Result<T> = std::result::Result<T, Error>;
+
+// kernel_code_properties
+pub const KERNEL_CODE_PROPERTY_ENABLE_SGPR_PRIVATE_SEGMENT_BUFFER: u16 = 1 << 0;
+pub const KERNEL_CODE_PROPERTY_ENABLE_SGPR_DISPATCH_PTR: u16 = 1 << 1;
+pub const KERNEL_CODE_PROPERTY_ENABLE_SGPR_QUEUE_PTR: u16 = 1 << 2;
+pub const KERNEL_CODE_PROPERTY_ENABLE_SGPR_KERNARG_SEGMENT_PTR: u16 = 1 << 3;
+pub const KERNEL_CODE_PROPERTY_ENABLE_SGPR_DISPATCH_ID: u16 = 1 << 4;
+pub const KERNEL_CODE_PROPERTY_ENABLE_SGPR_FLAT_SCRATCH_INIT: u16 = 1 << 5;
+pub const KERNEL_CODE_PROPERTY_ENABLE_SGPR_PRIVATE_SEGMENT_SIZE: u16 = 1 << 6;
+/// GFX10+, the kernel runs in wave32 mode
+pub const KERNEL_CODE_PROPERTY_ENABLE_WAVEFRONT_SIZE32: u16 = 1 << 10;
+pub const KERNEL_CODE_PROPERTY_USES_DYNAMIC_STACK: u16 = 1 << 11;
+
+bitflags! {
+    #[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct KernelCodeProperties: u16 {
+        const ENABLE_SGPR_PRIVATE_SEGMENT_BUFFER = KERNEL_CODE_PROPERTY_ENABLE_SGPR_PRIVATE_SEGMENT_BUFFER;
+        const ENABLE_SGPR_DISPATCH_PTR = KERNEL_CODE_PROPERTY_ENABLE_SGPR_DISPATCH_PTR;
+        const ENABLE_SGPR_QUEUE_PTR = KERNEL_CODE_PROPERTY_ENABLE_SGPR_QUEUE_PTR;
+        const ENABLE_SGPR_KERNARG_SEGMENT_PTR = KERNEL_CODE_PROPERTY_ENABLE_SGPR_KERNARG_SEGMENT_PTR;
+        const ENABLE_SGPR_DISPATCH_ID = KERNEL_CODE_PROPERTY_ENABLE_SGPR_DISPATCH_ID;
+        const ENABLE_SGPR_FLAT_SCRATCH_INIT = KERNEL_CODE_PROPERTY_ENABLE_SGPR_FLAT_SCRATCH_INIT;
+        const ENABLE_SGPR_PRIVATE_SEGMENT_SIZE = KERNEL_CODE_PROPERTY_ENABLE_SGPR_PRIVATE_SEGMENT_SIZE;
+        const ENABLE_WAVEFRONT_SIZE32 = KERNEL_CODE_PROPERTY_ENABLE_WAVEFRONT_SIZE32;
+        const USES_DYNAMIC_STACK = KERNEL_CODE_PROPERTY_USES_DYNAMIC_STACK;
+    }
+}
+
+/// `kernel_descriptor_t`, the 64 bytes a `<kernel>.kd` symbol points at in code object v3 and later
+#[repr(C)]
+#[derive(Debug, PartialEq, Pread, Pwrite, IOread, IOwrite, SizeWith)]
+pub struct KernelDescriptor {
+    /// LDS bytes the kernel needs, not counting dynamically allocated LDS
+    pub group_segment_fixed_size: u32,
+    /// Scratch bytes per work-item, not counting the dynamic stack
+    pub private_segment_fixed_size: u32,
+    /// Size of the kernarg segment in bytes
+    pub kernarg_size: u32,
+    pub reserved0: [u8; 4],
+    /// Byte offset from the start of this descriptor to the kernel's first instruction
+    pub kernel_code_entry_byte_offset: i64,
+    pub reserved1: [u8; 20],
+    /// GFX10+ `COMPUTE_PGM_RSRC3`, GFX90A+ uses it for the accumulation register offset
+    pub compute_pgm_rsrc3: u32,
+    pub compute_pgm_rsrc1: u32,
+    pub compute_pgm_rsrc2: u32,
+    /// `KERNEL_CODE_PROPERTY_*` bits
+    pub kernel_code_properties: u16,
+    /// Code object v5, number of kernarg dwords preloaded into SGPRs (bits 0-6) and their offset (bits 7-15)
+    pub kernarg_preload: u16,
+    pub reserved3: [u8; 4],
+}
+
+impl KernelDescriptor {
+    /// Parses the descriptor at the start of `bytes`
+    pub fn parse(bytes: &[u8], endianness: Endian) -> Result<KernelDescriptor> {
+        Ok(bytes.pread_with::<KernelDescriptor>(0, endianness)?)
+    }
+
+    /// Kernel descriptor symbols are named after the kernel's `.symbol` with a `.kd` suffix
+    pub fn is_descriptor_symbol(name: &str) -> bool {
+        name.ends_with(".kd")
+    }
+
+    /// Address of the kernel's first instruction given the address the descriptor was read from
+    pub fn kernel_code_entry(&self, descriptor_address: u64) -> u64 {
+        descriptor_address.wrapping_add_signed(self.kernel_code_entry_byte_offset)
+    }
+
+    pub fn properties(&self) -> KernelCodeProperties {
+        KernelCodeProperties::from_bits_retain(self.kernel_code_properties)
+    }
+
+    /// `COMPUTE_PGM_RSRC1.GRANULATED_WORKITEM_VGPR_COUNT`, the VGPR count in allocation granules minus one
+    pub fn granulated_workitem_vgpr_count(&self) -> u32 {
+        self.compute_pgm_rsrc1 & 0x3f
+    }
+
+    /// `COMPUTE_PGM_RSRC1.GRANULATED_WAVEFRONT_SGPR_COUNT`, the SGPR count in allocation granules minus one
+    pub fn granulated_wavefront_sgpr_count(&self) -> u32 {
+        (self.compute_pgm_rsrc1 >> 6) & 0xf
+    }
+
+    /// `COMPUTE_PGM_RSRC2.USER_SGPR_COUNT`
+    pub fn user_sgpr_count(&self) -> u32 {
+        (self.compute_pgm_rsrc2 >> 1) & 0x1f
+    }
+
+    pub fn uses_wavefront_size32(&self) -> bool {
+        self.properties()
+            .contains(KernelCodeProperties::ENABLE_WAVEFRONT_SIZE32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kernel_descriptor_size_check() {
+        assert_eq!(::std::mem::size_of::<KernelDescriptor>(), 0x40);
+    }
+}
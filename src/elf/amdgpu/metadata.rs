@@ -0,0 +1,349 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::elf::amdgpu::{MsgPackValue, NT_AMDGPU_METADATA};
+use crate::elf::Note;
+use crate::error::ResultExt;
+use crate::{ContextFrame, Details, Error, Limits};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// One entry of a kernel's `.args`
+#[derive(Clone, Debug, PartialEq)]
+pub struct KernelArgMetadata {
+    /// `.name`, the source name of the argument
+    pub name: Option<String>,
+    /// `.type_name`
+    pub type_name: Option<String>,
+    /// `.size` in bytes
+    pub size: u64,
+    /// `.offset` into the kernarg segment
+    pub offset: u64,
+    /// `.value_kind`, e.g. `by_value`, `global_buffer` or `hidden_global_offset_x`
+    pub value_kind: String,
+    /// `.address_space` of pointer arguments, e.g. `global` or `local`
+    pub address_space: Option<String>,
+    /// `.access`, e.g. `read_only`
+    pub access: Option<String>,
+    /// `.pointee_align` of dynamic shared pointers
+    pub pointee_align: Option<u64>,
+}
+
+/// One entry of `amdhsa.kernels`
+#[derive(Clone, Debug, PartialEq)]
+pub struct KernelMetadata {
+    /// `.name`, the source name of the kernel
+    pub name: String,
+    /// `.symbol`, the name of the kernel descriptor symbol (ending in `.kd`)
+    pub symbol: String,
+    /// `.language`, e.g. `OpenCL C` or `HIP`
+    pub language: Option<String>,
+    pub args: Vec<KernelArgMetadata>,
+    /// `.kernarg_segment_size` in bytes
+    pub kernarg_segment_size: u64,
+    /// `.kernarg_segment_align` in bytes
+    pub kernarg_segment_align: u64,
+    /// `.group_segment_fixed_size`, the LDS size in bytes
+    pub group_segment_fixed_size: u64,
+    /// `.private_segment_fixed_size`, the scratch size per work-item in bytes
+    pub private_segment_fixed_size: u64,
+    /// `.wavefront_size`, `32` or `64`
+    pub wavefront_size: u64,
+    /// `.sgpr_count`
+    pub sgpr_count: u64,
+    /// `.vgpr_count`
+    pub vgpr_count: u64,
+    /// `.agpr_count`, only for GPUs with accumulation registers
+    pub agpr_count: Option<u64>,
+    /// `.max_flat_workgroup_size`
+    pub max_flat_workgroup_size: u64,
+    /// `.sgpr_spill_count`
+    pub sgpr_spill_count: Option<u64>,
+    /// `.vgpr_spill_count`
+    pub vgpr_spill_count: Option<u64>,
+    /// `.uses_dynamic_stack`, added in code object v5
+    pub uses_dynamic_stack: Option<bool>,
+}
+
+/// The contents of an `NT_AMDGPU_METADATA` note, used by code object v3 and later
+///
+/// Only the commonly needed fields are typed, everything else can be read from `document`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CodeObjectMetadata {
+    /// `amdhsa.version`, `[1, 0]` for code object v3, `[1, 1]` for v4 and `[1, 2]` for v5
+    pub version: (u64, u64),
+    /// `amdhsa.target`, e.g. `amdgcn-amd-amdhsa--gfx90a:xnack+`, added in code object v4
+    pub target: Option<String>,
+    /// `amdhsa.printf`, the format strings used by `printf` calls
+    pub printf: Vec<String>,
+    /// `amdhsa.kernels`
+    pub kernels: Vec<KernelMetadata>,
+    /// The whole decoded document
+    pub document: MsgPackValue,
+}
+
+impl CodeObjectMetadata {
+    /// Parses the descriptor of an `AMDGPU` note of type `NT_AMDGPU_METADATA`
+    pub fn from_note(note: &Note) -> Result<CodeObjectMetadata> {
+        if note.n_name != "AMDGPU" || note.n_type != NT_AMDGPU_METADATA {
+            return Err(Error::InvalidArguments(
+                Details::new(format!(
+                    "Expected an `AMDGPU` note of type `NT_AMDGPU_METADATA` but got a `{}` note of type `{}`",
+                    note.n_name, note.n_type
+                ))
+                .structure("elf::Note")
+                .field("n_type"),
+            ));
+        }
+
+        Self::parse(&note.n_desc)
+    }
+
+    /// Parses a MessagePack encoded metadata document
+    pub fn parse(bytes: &[u8]) -> Result<CodeObjectMetadata> {
+        Self::parse_with_limits(bytes, &Limits::default())
+    }
+
+    /// Same as `parse` but checks the document against `limits` instead of the default limits
+    pub fn parse_with_limits(bytes: &[u8], limits: &Limits) -> Result<CodeObjectMetadata> {
+        let document = MsgPackValue::parse_with_limits(bytes, limits)?;
+
+        let version = match document
+            .get("amdhsa.version")
+            .and_then(|version| version.as_array())
+        {
+            Some([major, minor, ..]) => (
+                required_u64(major, "amdgpu::CodeObjectMetadata", "amdhsa.version")?,
+                required_u64(minor, "amdgpu::CodeObjectMetadata", "amdhsa.version")?,
+            ),
+            _ => return Err(missing("amdgpu::CodeObjectMetadata", "amdhsa.version")),
+        };
+        let target = optional_string(&document, "amdhsa.target");
+        let printf = document
+            .get("amdhsa.printf")
+            .and_then(|printf| printf.as_array())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|format| format.as_str().map(str::to_string))
+            .collect();
+        let kernels = document
+            .get("amdhsa.kernels")
+            .and_then(|kernels| kernels.as_array())
+            .unwrap_or_default()
+            .iter()
+            .enumerate()
+            .map(|(index, kernel)| {
+                KernelMetadata::from_value(kernel)
+                    .context(|| ContextFrame::new("amdhsa.kernels").index(index))
+            })
+            .collect::<Result<Vec<KernelMetadata>>>()?;
+
+        Ok(CodeObjectMetadata {
+            version,
+            target,
+            printf,
+            kernels,
+            document,
+        })
+    }
+}
+
+impl KernelMetadata {
+    fn from_value(value: &MsgPackValue) -> Result<KernelMetadata> {
+        const STRUCTURE: &str = "amdgpu::KernelMetadata";
+
+        let args = value
+            .get(".args")
+            .and_then(|args| args.as_array())
+            .unwrap_or_default()
+            .iter()
+            .enumerate()
+            .map(|(index, arg)| {
+                KernelArgMetadata::from_value(arg)
+                    .context(|| ContextFrame::new(".args").index(index))
+            })
+            .collect::<Result<Vec<KernelArgMetadata>>>()?;
+
+        Ok(KernelMetadata {
+            name: required_string(value, STRUCTURE, ".name")?,
+            symbol: required_string(value, STRUCTURE, ".symbol")?,
+            language: optional_string(value, ".language"),
+            args,
+            kernarg_segment_size: required_field_u64(value, STRUCTURE, ".kernarg_segment_size")?,
+            kernarg_segment_align: required_field_u64(value, STRUCTURE, ".kernarg_segment_align")?,
+            group_segment_fixed_size: required_field_u64(
+                value,
+                STRUCTURE,
+                ".group_segment_fixed_size",
+            )?,
+            private_segment_fixed_size: required_field_u64(
+                value,
+                STRUCTURE,
+                ".private_segment_fixed_size",
+            )?,
+            wavefront_size: required_field_u64(value, STRUCTURE, ".wavefront_size")?,
+            sgpr_count: required_field_u64(value, STRUCTURE, ".sgpr_count")?,
+            vgpr_count: required_field_u64(value, STRUCTURE, ".vgpr_count")?,
+            agpr_count: value.get(".agpr_count").and_then(|count| count.as_u64()),
+            max_flat_workgroup_size: required_field_u64(
+                value,
+                STRUCTURE,
+                ".max_flat_workgroup_size",
+            )?,
+            sgpr_spill_count: value
+                .get(".sgpr_spill_count")
+                .and_then(|count| count.as_u64()),
+            vgpr_spill_count: value
+                .get(".vgpr_spill_count")
+                .and_then(|count| count.as_u64()),
+            uses_dynamic_stack: value
+                .get(".uses_dynamic_stack")
+                .and_then(|uses| uses.as_bool()),
+        })
+    }
+}
+
+impl KernelArgMetadata {
+    fn from_value(value: &MsgPackValue) -> Result<KernelArgMetadata> {
+        const STRUCTURE: &str = "amdgpu::KernelArgMetadata";
+
+        Ok(KernelArgMetadata {
+            name: optional_string(value, ".name"),
+            type_name: optional_string(value, ".type_name"),
+            size: required_field_u64(value, STRUCTURE, ".size")?,
+            offset: required_field_u64(value, STRUCTURE, ".offset")?,
+            value_kind: required_string(value, STRUCTURE, ".value_kind")?,
+            address_space: optional_string(value, ".address_space"),
+            access: optional_string(value, ".access"),
+            pointee_align: value.get(".pointee_align").and_then(|align| align.as_u64()),
+        })
+    }
+}
+
+fn missing(structure: &'static str, field: &'static str) -> Error {
+    Error::Malformed(
+        Details::new(format!("AMDGPU metadata is missing `{}`", field))
+            .structure(structure)
+            .field(field),
+    )
+}
+
+fn required_u64(value: &MsgPackValue, structure: &'static str, field: &'static str) -> Result<u64> {
+    value.as_u64().ok_or_else(|| {
+        Error::Malformed(
+            Details::new(format!(
+                "AMDGPU metadata `{}` must be an unsigned integer but is `{:?}`",
+                field, value
+            ))
+            .structure(structure)
+            .field(field),
+        )
+    })
+}
+
+fn required_field_u64(
+    map: &MsgPackValue,
+    structure: &'static str,
+    field: &'static str,
+) -> Result<u64> {
+    match map.get(field) {
+        Some(value) => required_u64(value, structure, field),
+        None => Err(missing(structure, field)),
+    }
+}
+
+fn required_string(
+    map: &MsgPackValue,
+    structure: &'static str,
+    field: &'static str,
+) -> Result<String> {
+    optional_string(map, field).ok_or_else(|| missing(structure, field))
+}
+
+fn optional_string(map: &MsgPackValue, field: &str) -> Option<String> {
+    map.get(field)
+        .and_then(|value| value.as_str())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(bytes: &mut Vec<u8>, value: &str) {
+        bytes.push(0xa0 | value.len() as u8);
+        bytes.extend_from_slice(value.as_bytes());
+    }
+
+    fn entry(bytes: &mut Vec<u8>, key: &str, value: u8) {
+        string(bytes, key);
+        bytes.push(value);
+    }
+
+    #[test]
+    fn parse_kernels() {
+        let mut bytes: Vec<u8> = vec![0x82];
+        string(&mut bytes, "amdhsa.version");
+        bytes.extend_from_slice(&[0x92, 0x01, 0x02]);
+        string(&mut bytes, "amdhsa.kernels");
+        bytes.push(0x91);
+        let kernel_map = bytes.len();
+        bytes.push(0x8c);
+        string(&mut bytes, ".name");
+        string(&mut bytes, "add");
+        string(&mut bytes, ".symbol");
+        string(&mut bytes, "add.kd");
+        string(&mut bytes, ".args");
+        bytes.extend_from_slice(&[0x91, 0x83]);
+        entry(&mut bytes, ".size", 8);
+        entry(&mut bytes, ".offset", 0);
+        string(&mut bytes, ".value_kind");
+        string(&mut bytes, "global_buffer");
+        entry(&mut bytes, ".kernarg_segment_size", 8);
+        entry(&mut bytes, ".kernarg_segment_align", 8);
+        entry(&mut bytes, ".group_segment_fixed_size", 64);
+        entry(&mut bytes, ".private_segment_fixed_size", 0);
+        entry(&mut bytes, ".wavefront_size", 64);
+        entry(&mut bytes, ".sgpr_count", 10);
+        entry(&mut bytes, ".vgpr_count", 4);
+        string(&mut bytes, ".max_flat_workgroup_size");
+        bytes.extend_from_slice(&[0xcd, 0x04, 0x00]);
+        string(&mut bytes, ".uses_dynamic_stack");
+        bytes.push(0xc2);
+
+        let metadata = CodeObjectMetadata::parse(&bytes).unwrap();
+        assert_eq!(metadata.version, (1, 2));
+        assert_eq!(metadata.kernels.len(), 1);
+
+        let kernel = &metadata.kernels[0];
+        assert_eq!(
+            (kernel.name.as_str(), kernel.symbol.as_str()),
+            ("add", "add.kd")
+        );
+        assert_eq!(kernel.group_segment_fixed_size, 64);
+        assert_eq!(kernel.max_flat_workgroup_size, 1024);
+        assert_eq!(kernel.uses_dynamic_stack, Some(false));
+        assert_eq!(kernel.args[0].value_kind, "global_buffer");
+
+        // Dropping `.uses_dynamic_stack` and `.max_flat_workgroup_size` leaves a required field missing
+        bytes[kernel_map] = 0x8a;
+        let error = CodeObjectMetadata::parse(&bytes).unwrap_err();
+        assert_eq!(
+            error.details().and_then(|details| details.field),
+            Some(".max_flat_workgroup_size")
+        );
+        assert_eq!(error.context_chain()[0].name, "amdhsa.kernels");
+    }
+}
@@ -16,6 +16,13 @@
 
 use crate::stringable_consts_blocks::option_stringable_consts_block;
 
+mod kernel_descriptor;
+pub use kernel_descriptor::*;
+mod metadata;
+pub use metadata::*;
+mod msgpack;
+pub use msgpack::*;
+
 // ABI versions
 option_stringable_consts_block! {
     const stringable: u32 {
@@ -32,89 +39,96 @@ option_stringable_consts_block! {
 }
 
 // e_flags
-/// Not specified processor.
-pub const EF_AMDGPU_MACH_NONE: u32 = 0x000;
-
-/// R600-based processors.
-/// Radeon HD 2000/3000 Series (R600).
-pub const EF_AMDGPU_MACH_R600_R600: u32 = 0x001;
-pub const EF_AMDGPU_MACH_R600_R630: u32 = 0x002;
-pub const EF_AMDGPU_MACH_R600_RS880: u32 = 0x003;
-pub const EF_AMDGPU_MACH_R600_RV670: u32 = 0x004;
-/// Radeon HD 4000 Series (R700).
-pub const EF_AMDGPU_MACH_R600_RV710: u32 = 0x005;
-pub const EF_AMDGPU_MACH_R600_RV730: u32 = 0x006;
-pub const EF_AMDGPU_MACH_R600_RV770: u32 = 0x007;
-/// Radeon HD 5000 Series (Evergreen).
-pub const EF_AMDGPU_MACH_R600_CEDAR: u32 = 0x008;
-pub const EF_AMDGPU_MACH_R600_CYPRESS: u32 = 0x009;
-pub const EF_AMDGPU_MACH_R600_JUNIPER: u32 = 0x00a;
-pub const EF_AMDGPU_MACH_R600_REDWOOD: u32 = 0x00b;
-pub const EF_AMDGPU_MACH_R600_SUMO: u32 = 0x00c;
-/// Radeon HD 6000 Series (Northern Islands).
-pub const EF_AMDGPU_MACH_R600_BARTS: u32 = 0x00d;
-pub const EF_AMDGPU_MACH_R600_CAICOS: u32 = 0x00e;
-pub const EF_AMDGPU_MACH_R600_CAYMAN: u32 = 0x00f;
-pub const EF_AMDGPU_MACH_R600_TURKS: u32 = 0x010;
-
-/// Reserved for R600-based processors.
-pub const EF_AMDGPU_MACH_R600_RESERVED_FIRST: u32 = 0x011;
-pub const EF_AMDGPU_MACH_R600_RESERVED_LAST: u32 = 0x01f;
-
-/// First/last R600-based processors.
-pub const EF_AMDGPU_MACH_R600_FIRST: u32 = EF_AMDGPU_MACH_R600_R600;
-pub const EF_AMDGPU_MACH_R600_LAST: u32 = EF_AMDGPU_MACH_R600_TURKS;
-
-/// AMDGCN-based processors.
-pub const EF_AMDGPU_MACH_AMDGCN_GFX600: u32 = 0x020;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX601: u32 = 0x021;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX700: u32 = 0x022;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX701: u32 = 0x023;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX702: u32 = 0x024;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX703: u32 = 0x025;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX704: u32 = 0x026;
-pub const EF_AMDGPU_MACH_AMDGCN_RESERVED_0X27: u32 = 0x027;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX801: u32 = 0x028;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX802: u32 = 0x029;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX803: u32 = 0x02a;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX810: u32 = 0x02b;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX900: u32 = 0x02c;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX902: u32 = 0x02d;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX904: u32 = 0x02e;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX906: u32 = 0x02f;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX908: u32 = 0x030;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX909: u32 = 0x031;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX90C: u32 = 0x032;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX1010: u32 = 0x033;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX1011: u32 = 0x034;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX1012: u32 = 0x035;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX1030: u32 = 0x036;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX1031: u32 = 0x037;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX1032: u32 = 0x038;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX1033: u32 = 0x039;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX602: u32 = 0x03a;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX705: u32 = 0x03b;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX805: u32 = 0x03c;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX1035: u32 = 0x03d;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX1034: u32 = 0x03e;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX90A: u32 = 0x03f;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX940: u32 = 0x040;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX1100: u32 = 0x041;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX1013: u32 = 0x042;
-pub const EF_AMDGPU_MACH_AMDGCN_RESERVED_0X43: u32 = 0x043;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX1103: u32 = 0x044;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX1036: u32 = 0x045;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX1101: u32 = 0x046;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX1102: u32 = 0x047;
-pub const EF_AMDGPU_MACH_AMDGCN_RESERVED_0X48: u32 = 0x048;
-pub const EF_AMDGPU_MACH_AMDGCN_RESERVED_0X49: u32 = 0x049;
-pub const EF_AMDGPU_MACH_AMDGCN_RESERVED_0X4A: u32 = 0x04a;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX941: u32 = 0x04b;
-pub const EF_AMDGPU_MACH_AMDGCN_GFX942: u32 = 0x04c;
-
-/// First/last AMDGCN-based processors.
-pub const EF_AMDGPU_MACH_AMDGCN_FIRST: u32 = EF_AMDGPU_MACH_AMDGCN_GFX600;
-pub const EF_AMDGPU_MACH_AMDGCN_LAST: u32 = EF_AMDGPU_MACH_AMDGCN_GFX942;
+/// Selects the target GPU, see `mach_to_str`
+pub const EF_AMDGPU_MACH: u32 = 0x0ff;
+
+option_stringable_consts_block! {
+    const stringable: u32 {
+        /// R600-based processors.
+        /// Radeon HD 2000/3000 Series (R600).
+        pub EF_AMDGPU_MACH_R600_R600 = 0x001;
+        pub EF_AMDGPU_MACH_R600_R630 = 0x002;
+        pub EF_AMDGPU_MACH_R600_RS880 = 0x003;
+        pub EF_AMDGPU_MACH_R600_RV670 = 0x004;
+        /// Radeon HD 4000 Series (R700).
+        pub EF_AMDGPU_MACH_R600_RV710 = 0x005;
+        pub EF_AMDGPU_MACH_R600_RV730 = 0x006;
+        pub EF_AMDGPU_MACH_R600_RV770 = 0x007;
+        /// Radeon HD 5000 Series (Evergreen).
+        pub EF_AMDGPU_MACH_R600_CEDAR = 0x008;
+        pub EF_AMDGPU_MACH_R600_CYPRESS = 0x009;
+        pub EF_AMDGPU_MACH_R600_JUNIPER = 0x00a;
+        pub EF_AMDGPU_MACH_R600_REDWOOD = 0x00b;
+        pub EF_AMDGPU_MACH_R600_SUMO = 0x00c;
+        /// Radeon HD 6000 Series (Northern Islands).
+        pub EF_AMDGPU_MACH_R600_BARTS = 0x00d;
+        pub EF_AMDGPU_MACH_R600_CAICOS = 0x00e;
+        pub EF_AMDGPU_MACH_R600_CAYMAN = 0x00f;
+        pub EF_AMDGPU_MACH_R600_TURKS = 0x010;
+        /// AMDGCN-based processors.
+        pub EF_AMDGPU_MACH_AMDGCN_GFX600 = 0x020;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX601 = 0x021;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX700 = 0x022;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX701 = 0x023;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX702 = 0x024;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX703 = 0x025;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX704 = 0x026;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX801 = 0x028;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX802 = 0x029;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX803 = 0x02a;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX810 = 0x02b;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX900 = 0x02c;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX902 = 0x02d;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX904 = 0x02e;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX906 = 0x02f;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX908 = 0x030;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX909 = 0x031;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX90C = 0x032;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX1010 = 0x033;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX1011 = 0x034;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX1012 = 0x035;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX1030 = 0x036;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX1031 = 0x037;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX1032 = 0x038;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX1033 = 0x039;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX602 = 0x03a;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX705 = 0x03b;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX805 = 0x03c;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX1035 = 0x03d;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX1034 = 0x03e;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX90A = 0x03f;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX940 = 0x040;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX1100 = 0x041;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX1013 = 0x042;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX1103 = 0x044;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX1036 = 0x045;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX1101 = 0x046;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX1102 = 0x047;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX941 = 0x04b;
+        pub EF_AMDGPU_MACH_AMDGCN_GFX942 = 0x04c;
+    }
+
+    const ignore: u32 {
+        /// Not specified processor.
+        pub EF_AMDGPU_MACH_NONE = 0x000;
+        /// Reserved for R600-based processors.
+        pub EF_AMDGPU_MACH_R600_RESERVED_FIRST = 0x011;
+        pub EF_AMDGPU_MACH_R600_RESERVED_LAST = 0x01f;
+        /// First/last R600-based processors.
+        pub EF_AMDGPU_MACH_R600_FIRST = EF_AMDGPU_MACH_R600_R600;
+        pub EF_AMDGPU_MACH_R600_LAST = EF_AMDGPU_MACH_R600_TURKS;
+        pub EF_AMDGPU_MACH_AMDGCN_RESERVED_0X27 = 0x027;
+        pub EF_AMDGPU_MACH_AMDGCN_RESERVED_0X43 = 0x043;
+        pub EF_AMDGPU_MACH_AMDGCN_RESERVED_0X48 = 0x048;
+        pub EF_AMDGPU_MACH_AMDGCN_RESERVED_0X49 = 0x049;
+        pub EF_AMDGPU_MACH_AMDGCN_RESERVED_0X4A = 0x04a;
+        /// First/last AMDGCN-based processors.
+        pub EF_AMDGPU_MACH_AMDGCN_FIRST = EF_AMDGPU_MACH_AMDGCN_GFX600;
+        pub EF_AMDGPU_MACH_AMDGCN_LAST = EF_AMDGPU_MACH_AMDGCN_GFX942;
+    }
+
+    pub fn mach_to_str(value: u32) -> Option<&'static str>;
+}
 
 /// Indicates if the "xnack" target feature is enabled for all code contained
 /// in the object.
@@ -216,7 +230,7 @@ option_stringable_consts_block! {
 
     }
 
-    pub fn _to_str(value: u8) -> Option<&'static str>;
+    pub fn stt_to_str(value: u8) -> Option<&'static str>;
 }
 
 // Relocations
@@ -0,0 +1,330 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::{Details, Error, Limits};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// A decoded MessagePack value, as used by `NT_AMDGPU_METADATA` notes
+#[derive(Clone, Debug, PartialEq)]
+pub enum MsgPackValue {
+    Nil,
+    Boolean(bool),
+    /// Every non-negative integer, regardless of how it was encoded
+    Unsigned(u64),
+    /// Negative integers
+    Signed(i64),
+    Float(f64),
+    String(String),
+    Binary(Vec<u8>),
+    Array(Vec<MsgPackValue>),
+    /// Keys are kept in document order, lookups with `get` are linear
+    Map(Vec<(MsgPackValue, MsgPackValue)>),
+    Extension(i8, Vec<u8>),
+}
+
+impl MsgPackValue {
+    /// Decodes one MessagePack value from the start of `bytes`, trailing bytes are ignored
+    pub fn parse(bytes: &[u8]) -> Result<MsgPackValue> {
+        Self::parse_with_limits(bytes, &Limits::default())
+    }
+
+    /// Same as `parse` but checks nesting and sizes against `limits` instead of the default limits
+    pub fn parse_with_limits(bytes: &[u8], limits: &Limits) -> Result<MsgPackValue> {
+        MsgPackDecoder {
+            bytes,
+            offset: 0,
+            depth: 0,
+            limits,
+        }
+        .read_value()
+    }
+
+    /// Looks up a string key in a map, `None` for other values
+    pub fn get(&self, key: &str) -> Option<&MsgPackValue> {
+        match self {
+            MsgPackValue::Map(entries) => entries
+                .iter()
+                .find(|(entry_key, _)| entry_key.as_str() == Some(key))
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            MsgPackValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            MsgPackValue::Unsigned(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            MsgPackValue::Unsigned(value) => i64::try_from(*value).ok(),
+            MsgPackValue::Signed(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            MsgPackValue::Boolean(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[MsgPackValue]> {
+        match self {
+            MsgPackValue::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+struct MsgPackDecoder<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    depth: u32,
+    limits: &'a Limits,
+}
+
+impl<'a> MsgPackDecoder<'a> {
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| {
+                Error::Malformed(
+                    Details::new(format!(
+                        "MessagePack value needs `{}` bytes but only `{}` are left",
+                        len,
+                        self.bytes.len() - self.offset
+                    ))
+                    .structure("elf::amdgpu::MsgPackValue")
+                    .offset(self.offset as u64),
+                )
+            })?;
+        let result = &self.bytes[self.offset..end];
+
+        self.offset = end;
+
+        Ok(result)
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_be<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut result = [0u8; N];
+        result.copy_from_slice(self.read_bytes(N)?);
+        Ok(result)
+    }
+
+    fn read_length(&mut self, length_size: usize) -> Result<u64> {
+        Ok(match length_size {
+            1 => u64::from(self.read_u8()?),
+            2 => u64::from(u16::from_be_bytes(self.read_be()?)),
+            _ => u64::from(u32::from_be_bytes(self.read_be()?)),
+        })
+    }
+
+    fn read_string(&mut self, len: u64) -> Result<MsgPackValue> {
+        let offset = self.offset;
+        let len = self.limits.check_allocation(len, "a MessagePack string")?;
+        let bytes = self.read_bytes(len)?;
+
+        match std::str::from_utf8(bytes) {
+            Ok(string) => Ok(MsgPackValue::String(string.to_string())),
+            Err(utf8_error) => Err(Error::Malformed(
+                Details::new(format!("Invalid MessagePack string, {}", utf8_error))
+                    .structure("elf::amdgpu::MsgPackValue")
+                    .offset(offset as u64),
+            )),
+        }
+    }
+
+    fn read_binary(&mut self, len: u64) -> Result<Vec<u8>> {
+        let len = self
+            .limits
+            .check_allocation(len, "MessagePack binary data")?;
+        Ok(self.read_bytes(len)?.to_vec())
+    }
+
+    fn read_extension(&mut self, len: u64) -> Result<MsgPackValue> {
+        let extension_type = self.read_u8()? as i8;
+        Ok(MsgPackValue::Extension(
+            extension_type,
+            self.read_binary(len)?,
+        ))
+    }
+
+    fn read_nested<T>(&mut self, read: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.depth += 1;
+        self.limits
+            .check_recursion(self.depth, "MessagePack arrays and maps")?;
+        let result = read(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn read_array(&mut self, len: u64) -> Result<MsgPackValue> {
+        // Every element takes at least one byte
+        let mut values: Vec<MsgPackValue> = self
+            .limits
+            .vec_with_capacity(len.min(self.remaining()), "MessagePack array items")?;
+
+        self.read_nested(|decoder| {
+            for _ in 0..len {
+                values.push(decoder.read_value()?);
+            }
+
+            Ok(MsgPackValue::Array(values))
+        })
+    }
+
+    fn read_map(&mut self, len: u64) -> Result<MsgPackValue> {
+        let mut entries: Vec<(MsgPackValue, MsgPackValue)> = self
+            .limits
+            .vec_with_capacity(len.min(self.remaining()), "MessagePack map entries")?;
+
+        self.read_nested(|decoder| {
+            for _ in 0..len {
+                let key = decoder.read_value()?;
+                let value = decoder.read_value()?;
+                entries.push((key, value));
+            }
+
+            Ok(MsgPackValue::Map(entries))
+        })
+    }
+
+    fn remaining(&self) -> u64 {
+        (self.bytes.len() - self.offset) as u64
+    }
+
+    fn read_value(&mut self) -> Result<MsgPackValue> {
+        let offset = self.offset;
+        let marker = self.read_u8()?;
+
+        match marker {
+            0x00..=0x7f => Ok(MsgPackValue::Unsigned(u64::from(marker))),
+            0x80..=0x8f => self.read_map(u64::from(marker & 0x0f)),
+            0x90..=0x9f => self.read_array(u64::from(marker & 0x0f)),
+            0xa0..=0xbf => self.read_string(u64::from(marker & 0x1f)),
+            0xc0 => Ok(MsgPackValue::Nil),
+            0xc2 => Ok(MsgPackValue::Boolean(false)),
+            0xc3 => Ok(MsgPackValue::Boolean(true)),
+            0xc4..=0xc6 => {
+                let len = self.read_length(1 << (marker - 0xc4))?;
+                Ok(MsgPackValue::Binary(self.read_binary(len)?))
+            }
+            0xc7..=0xc9 => {
+                let len = self.read_length(1 << (marker - 0xc7))?;
+                self.read_extension(len)
+            }
+            0xca => Ok(MsgPackValue::Float(f64::from(f32::from_be_bytes(
+                self.read_be()?,
+            )))),
+            0xcb => Ok(MsgPackValue::Float(f64::from_be_bytes(self.read_be()?))),
+            0xcc => Ok(MsgPackValue::Unsigned(u64::from(self.read_u8()?))),
+            0xcd => Ok(MsgPackValue::Unsigned(u64::from(u16::from_be_bytes(
+                self.read_be()?,
+            )))),
+            0xce => Ok(MsgPackValue::Unsigned(u64::from(u32::from_be_bytes(
+                self.read_be()?,
+            )))),
+            0xcf => Ok(MsgPackValue::Unsigned(u64::from_be_bytes(self.read_be()?))),
+            0xd0 => Ok(signed(i64::from(self.read_u8()? as i8))),
+            0xd1 => Ok(signed(i64::from(i16::from_be_bytes(self.read_be()?)))),
+            0xd2 => Ok(signed(i64::from(i32::from_be_bytes(self.read_be()?)))),
+            0xd3 => Ok(signed(i64::from_be_bytes(self.read_be()?))),
+            0xd4..=0xd8 => self.read_extension(1 << (marker - 0xd4)),
+            0xd9..=0xdb => {
+                let len = self.read_length(1 << (marker - 0xd9))?;
+                self.read_string(len)
+            }
+            0xdc | 0xdd => {
+                let len = self.read_length(2 << (marker - 0xdc))?;
+                self.read_array(len)
+            }
+            0xde | 0xdf => {
+                let len = self.read_length(2 << (marker - 0xde))?;
+                self.read_map(len)
+            }
+            0xe0..=0xff => Ok(MsgPackValue::Signed(i64::from(marker as i8))),
+            0xc1 => Err(Error::Malformed(
+                Details::new("Invalid MessagePack marker `0xc1`")
+                    .structure("elf::amdgpu::MsgPackValue")
+                    .offset(offset as u64),
+            )),
+        }
+    }
+}
+
+/// MessagePack encoders may use signed types for positive values, those are normalized to `Unsigned`
+fn signed(value: i64) -> MsgPackValue {
+    if value >= 0 {
+        MsgPackValue::Unsigned(value as u64)
+    } else {
+        MsgPackValue::Signed(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_values() {
+        let bytes = [
+            0x86, // fixmap with 6 entries
+            0xa1, b'a', 0xcd, 0x01, 0x00, // "a": 256
+            0xa1, b'b', 0xff, // "b": -1
+            0xa1, b'c', 0x92, 0xc3, 0xc0, // "c": [true, nil]
+            0xa1, b'd', 0xd9, 0x02, b'h', b'i', // "d": "hi" as str8
+            0xa1, b'e', 0xd0, 0x05, // "e": 5 as int8
+            0xa1, b'f', 0xc4, 0x01, 0xaa, // "f": bin8 [0xaa]
+        ];
+        let value = MsgPackValue::parse(&bytes).unwrap();
+
+        assert_eq!(value.get("a").and_then(|a| a.as_u64()), Some(256));
+        assert_eq!(value.get("b").and_then(|b| b.as_i64()), Some(-1));
+        assert_eq!(
+            value.get("c").and_then(|c| c.as_array()),
+            Some(&[MsgPackValue::Boolean(true), MsgPackValue::Nil][..])
+        );
+        assert_eq!(value.get("d").and_then(|d| d.as_str()), Some("hi"));
+        assert_eq!(value.get("e"), Some(&MsgPackValue::Unsigned(5)));
+        assert_eq!(value.get("f"), Some(&MsgPackValue::Binary(vec![0xaa])));
+
+        assert!(matches!(
+            MsgPackValue::parse(&[0x92, 0x01]),
+            Err(Error::Malformed(_))
+        ));
+        assert!(matches!(
+            MsgPackValue::parse(&[0x91; 100]),
+            Err(Error::Malformed(_))
+        ));
+    }
+}
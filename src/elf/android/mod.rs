@@ -16,6 +16,9 @@
 
 use crate::stringable_consts_blocks::option_stringable_consts_block;
 
+mod note;
+pub use note::*;
+
 // Section types
 option_stringable_consts_block! {
     const stringable: u32 {
@@ -41,9 +44,8 @@ option_stringable_consts_block! {
     }
 
     const ignore: u32 {
-        // Memory tagging values used in NT_ANDROID_TYPE_MEMTAG notes
-        // TODO: I'm 90% sure these were supposed to be flags but I can't remember... double check to see...
-        //       Just off a guess though, the `_mask` mixed with none of the values besides `_mask` using more than one bit, I think yes these are flags...
+        // Memory tagging values used in NT_ANDROID_TYPE_MEMTAG notes, the level is stored in the `NT_MEMTAG_LEVEL_MASK`
+        // bits and `NT_MEMTAG_HEAP`/`NT_MEMTAG_STACK` are flags on top of it (see `AndroidMemtag`)
         pub NT_MEMTAG_LEVEL_NONE = 0;
         /// Use the kernels auto-upgrade feature to allow for MTE Asynchronous, Asymmetric,
         /// or Synchronous mode. This allows silicon vendors to specify, on a per-cpu basis
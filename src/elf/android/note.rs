@@ -0,0 +1,211 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::elf::android::{
+    NT_ANDROID_TYPE_IDENT, NT_ANDROID_TYPE_MEMTAG, NT_MEMTAG_HEAP, NT_MEMTAG_LEVEL_ASYNC,
+    NT_MEMTAG_LEVEL_MASK, NT_MEMTAG_LEVEL_NONE, NT_MEMTAG_LEVEL_SYNC, NT_MEMTAG_STACK,
+};
+use crate::elf::Note;
+use crate::{Details, Error};
+use scroll::{Endian, Pread};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Size of the NUL padded `ndk_version` and `ndk_build_number` fields added in NDK r21
+const NDK_STRING_SIZE: usize = 64;
+
+/// The contents of an `Android` note of type `NT_ANDROID_TYPE_IDENT` (`.note.android.ident`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AndroidIdent {
+    /// `__ANDROID_API__` the binary was built against, i.e. its minimum supported API level
+    pub api_level: u32,
+    /// NDK version, e.g. `r25c`, only written by NDK r21 and later
+    pub ndk_version: Option<String>,
+    /// NDK build number, e.g. `9519653`, only written by NDK r21 and later
+    pub ndk_build_number: Option<String>,
+}
+
+impl AndroidIdent {
+    /// Parses the descriptor of an `Android` note of type `NT_ANDROID_TYPE_IDENT`
+    pub fn from_note(note: &Note, endianness: Endian) -> Result<AndroidIdent> {
+        check_note(note, NT_ANDROID_TYPE_IDENT, "NT_ANDROID_TYPE_IDENT")?;
+        Self::parse(&note.n_desc, endianness)
+    }
+
+    /// Parses an ident descriptor, older NDKs only write the API level
+    pub fn parse(bytes: &[u8], endianness: Endian) -> Result<AndroidIdent> {
+        let api_level = read_u32(bytes, endianness, "android::AndroidIdent", "android_api")?;
+
+        let (ndk_version, ndk_build_number) = match bytes.get(4..4 + 2 * NDK_STRING_SIZE) {
+            Some(strings) => (
+                Some(ndk_string(&strings[..NDK_STRING_SIZE])),
+                Some(ndk_string(&strings[NDK_STRING_SIZE..])),
+            ),
+            None => (None, None),
+        };
+
+        Ok(AndroidIdent {
+            api_level,
+            ndk_version,
+            ndk_build_number,
+        })
+    }
+}
+
+/// The MTE mode requested by an `NT_ANDROID_TYPE_MEMTAG` note
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemtagLevel {
+    None,
+    Async,
+    Sync,
+    /// `NT_MEMTAG_LEVEL_MASK` itself, which isn't a defined level
+    Unknown(u32),
+}
+
+impl MemtagLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MemtagLevel::None => "none",
+            MemtagLevel::Async => "async",
+            MemtagLevel::Sync => "sync",
+            MemtagLevel::Unknown(_) => "unknown",
+        }
+    }
+}
+
+/// The contents of an `Android` note of type `NT_ANDROID_TYPE_MEMTAG` (`.note.android.memtag`)
+///
+/// The descriptor is a single word, the level is stored in the `NT_MEMTAG_LEVEL_MASK` bits and `NT_MEMTAG_HEAP` and
+/// `NT_MEMTAG_STACK` are flags on top of it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AndroidMemtag {
+    pub level: MemtagLevel,
+    /// Heap allocations are tagged
+    pub heap: bool,
+    /// Stack allocations are tagged, the loader has to map the stacks with `PROT_MTE`
+    pub stack: bool,
+}
+
+impl AndroidMemtag {
+    /// Parses the descriptor of an `Android` note of type `NT_ANDROID_TYPE_MEMTAG`
+    pub fn from_note(note: &Note, endianness: Endian) -> Result<AndroidMemtag> {
+        check_note(note, NT_ANDROID_TYPE_MEMTAG, "NT_ANDROID_TYPE_MEMTAG")?;
+        Self::parse(&note.n_desc, endianness)
+    }
+
+    pub fn parse(bytes: &[u8], endianness: Endian) -> Result<AndroidMemtag> {
+        Ok(Self::from_value(read_u32(
+            bytes,
+            endianness,
+            "android::AndroidMemtag",
+            "memtag_mode",
+        )?))
+    }
+
+    pub fn from_value(value: u32) -> AndroidMemtag {
+        let level = match value & NT_MEMTAG_LEVEL_MASK {
+            NT_MEMTAG_LEVEL_NONE => MemtagLevel::None,
+            NT_MEMTAG_LEVEL_ASYNC => MemtagLevel::Async,
+            NT_MEMTAG_LEVEL_SYNC => MemtagLevel::Sync,
+            unknown => MemtagLevel::Unknown(unknown),
+        };
+
+        AndroidMemtag {
+            level,
+            heap: value & NT_MEMTAG_HEAP != 0,
+            stack: value & NT_MEMTAG_STACK != 0,
+        }
+    }
+
+    /// MTE is enabled for at least one of the heap or the stack
+    pub fn is_enabled(&self) -> bool {
+        self.level != MemtagLevel::None && (self.heap || self.stack)
+    }
+}
+
+fn check_note(note: &Note, n_type: u32, type_name: &str) -> Result<()> {
+    if note.n_name != "Android" || note.n_type != n_type {
+        return Err(Error::InvalidArguments(
+            Details::new(format!(
+                "Expected an `Android` note of type `{}` but got a `{}` note of type `{}`",
+                type_name, note.n_name, note.n_type
+            ))
+            .structure("elf::Note")
+            .field("n_type"),
+        ));
+    }
+
+    Ok(())
+}
+
+fn read_u32(
+    bytes: &[u8],
+    endianness: Endian,
+    structure: &'static str,
+    field: &'static str,
+) -> Result<u32> {
+    bytes.pread_with::<u32>(0, endianness).map_err(|_| {
+        Error::Malformed(
+            Details::new(format!(
+                "Android note descriptor is `{}` bytes, expected at least `4`",
+                bytes.len()
+            ))
+            .structure(structure)
+            .field(field),
+        )
+    })
+}
+
+fn ndk_string(bytes: &[u8]) -> String {
+    let end = bytes
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    #[test]
+    fn parse_ident_and_memtag() {
+        let mut desc = 29u32.to_le_bytes().to_vec();
+        let old_ident = AndroidIdent::parse(&desc, scroll::LE).unwrap();
+        assert_eq!((old_ident.api_level, old_ident.ndk_version), (29, None));
+
+        let mut version = [0u8; NDK_STRING_SIZE];
+        version[..4].copy_from_slice(b"r25c");
+        let mut build_number = [0u8; NDK_STRING_SIZE];
+        build_number[..7].copy_from_slice(b"9519653");
+        desc.extend_from_slice(&version);
+        desc.extend_from_slice(&build_number);
+        let note = Note {
+            n_type: NT_ANDROID_TYPE_IDENT,
+            n_name: Cow::Borrowed("Android"),
+            n_desc: Cow::Owned(desc),
+        };
+        let ident = AndroidIdent::from_note(&note, scroll::LE).unwrap();
+        assert_eq!(ident.ndk_version.as_deref(), Some("r25c"));
+        assert_eq!(ident.ndk_build_number.as_deref(), Some("9519653"));
+
+        let memtag = AndroidMemtag::from_value(NT_MEMTAG_LEVEL_SYNC | NT_MEMTAG_HEAP);
+        assert_eq!(memtag.level, MemtagLevel::Sync);
+        assert!(memtag.heap && !memtag.stack && memtag.is_enabled());
+        assert!(AndroidMemtag::from_note(&note, scroll::LE).is_err());
+        assert!(AndroidMemtag::parse(&[1, 0], scroll::LE).is_err());
+    }
+}
@@ -0,0 +1,128 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// `aeabi` build attribute tags, the names match what `readelf -A` prints
+// Source: https://github.com/ARM-software/abi-aa/blob/main/addenda32/addenda32.rst#public-aeabi-attribute-tags
+#![allow(non_upper_case_globals)]
+
+use crate::stringable_consts_blocks::option_stringable_consts_block;
+
+option_stringable_consts_block! {
+    const stringable: u64 {
+        /// NTBS, the raw name of the CPU as given by the user
+        pub Tag_CPU_raw_name = 4;
+        /// NTBS, the canonical name of the CPU
+        pub Tag_CPU_name = 5;
+        pub Tag_CPU_arch = 6;
+        pub Tag_CPU_arch_profile = 7;
+        pub Tag_ARM_ISA_use = 8;
+        pub Tag_THUMB_ISA_use = 9;
+        pub Tag_FP_arch = 10;
+        pub Tag_WMMX_arch = 11;
+        pub Tag_Advanced_SIMD_arch = 12;
+        pub Tag_PCS_config = 13;
+        pub Tag_ABI_PCS_R9_use = 14;
+        pub Tag_ABI_PCS_RW_data = 15;
+        pub Tag_ABI_PCS_RO_data = 16;
+        pub Tag_ABI_PCS_GOT_use = 17;
+        pub Tag_ABI_PCS_wchar_t = 18;
+        pub Tag_ABI_FP_rounding = 19;
+        pub Tag_ABI_FP_denormal = 20;
+        pub Tag_ABI_FP_exceptions = 21;
+        pub Tag_ABI_FP_user_exceptions = 22;
+        pub Tag_ABI_FP_number_model = 23;
+        pub Tag_ABI_align_needed = 24;
+        pub Tag_ABI_align_preserved = 25;
+        pub Tag_ABI_enum_size = 26;
+        pub Tag_ABI_HardFP_use = 27;
+        /// Whether floating point arguments are passed in VFP registers (hard-float) or core registers (soft-float)
+        pub Tag_ABI_VFP_args = 28;
+        pub Tag_ABI_WMMX_args = 29;
+        pub Tag_ABI_optimization_goals = 30;
+        pub Tag_ABI_FP_optimization_goals = 31;
+        /// ULEB128 flag followed by a NTBS vendor name
+        pub Tag_compatibility = 32;
+        pub Tag_CPU_unaligned_access = 34;
+        pub Tag_FP_HP_extension = 36;
+        pub Tag_ABI_FP_16bit_format = 38;
+        pub Tag_MPextension_use = 42;
+        pub Tag_DIV_use = 44;
+        pub Tag_DSP_extension = 46;
+        pub Tag_MVE_arch = 48;
+        pub Tag_PAC_extension = 50;
+        pub Tag_BTI_extension = 52;
+        pub Tag_nodefaults = 64;
+        pub Tag_also_compatible_with = 65;
+        pub Tag_T2EE_use = 66;
+        /// NTBS, the version of the ABI the file conforms to
+        pub Tag_conformance = 67;
+        pub Tag_Virtualization_use = 68;
+        pub Tag_BTI_use = 74;
+        pub Tag_PACRET_use = 76;
+    }
+
+    const ignore: u64 {}
+
+    pub fn attribute_tag_to_str(value: u64) -> Option<&'static str>;
+}
+
+// Tag_CPU_arch values
+option_stringable_consts_block! {
+    const stringable: u64 {
+        pub CPU_ARCH_PRE_V4 = 0;
+        pub CPU_ARCH_V4 = 1;
+        pub CPU_ARCH_V4T = 2;
+        pub CPU_ARCH_V5T = 3;
+        pub CPU_ARCH_V5TE = 4;
+        pub CPU_ARCH_V5TEJ = 5;
+        pub CPU_ARCH_V6 = 6;
+        pub CPU_ARCH_V6KZ = 7;
+        pub CPU_ARCH_V6T2 = 8;
+        pub CPU_ARCH_V6K = 9;
+        pub CPU_ARCH_V7 = 10;
+        pub CPU_ARCH_V6_M = 11;
+        pub CPU_ARCH_V6S_M = 12;
+        pub CPU_ARCH_V7E_M = 13;
+        pub CPU_ARCH_V8_A = 14;
+        pub CPU_ARCH_V8_R = 15;
+        pub CPU_ARCH_V8_M_BASE = 16;
+        pub CPU_ARCH_V8_M_MAIN = 17;
+        pub CPU_ARCH_V8_1_M_MAIN = 21;
+        pub CPU_ARCH_V9_A = 22;
+    }
+
+    const ignore: u64 {}
+
+    pub fn cpu_arch_to_str(value: u64) -> Option<&'static str>;
+}
+
+// Tag_ABI_VFP_args values
+option_stringable_consts_block! {
+    const stringable: u64 {
+        /// Floating point arguments are passed in core registers
+        pub VFP_ARGS_BASE = 0;
+        /// Floating point arguments are passed in VFP registers
+        pub VFP_ARGS_VFP = 1;
+        /// Toolchain specific conventions
+        pub VFP_ARGS_TOOLCHAIN = 2;
+        /// Compatible with both the base and VFP conventions
+        pub VFP_ARGS_COMPATIBLE = 3;
+    }
+
+    const ignore: u64 {}
+
+    pub fn vfp_args_to_str(value: u64) -> Option<&'static str>;
+}
@@ -0,0 +1,522 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// ARM exception handling ABI (EHABI) unwind tables, `.ARM.exidx` (`SHT_ARM_EXIDX`/`PT_ARM_EXIDX`) and `.ARM.extab`
+//
+// Source: https://github.com/ARM-software/abi-aa/blob/main/ehabi32/ehabi32.rst
+//
+// `.ARM.exidx` is a table of two word entries sorted by function address:
+//  - Word 0: prel31 offset to the start of the function, bit 31 is always `0`
+//  - Word 1 is one of:
+//    - `EXIDX_CANTUNWIND`, the function can't be unwound
+//    - Bit 31 set, a compact model personality routine (index `0` only) with its unwind opcodes stored in-line
+//    - Bit 31 clear, a prel31 offset to the function's `.ARM.extab` entry
+use crate::{Details, Error};
+use scroll::{Endian, Pread};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// The value of the second `.ARM.exidx` word for functions which can't be unwound
+pub const EXIDX_CANTUNWIND: u32 = 0x1;
+
+// Compact model personality routines
+/// `__aeabi_unwind_cpp_pr0`, short frame with up to 3 opcodes
+pub const PERSONALITY_SU16: u8 = 0;
+/// `__aeabi_unwind_cpp_pr1`, long frame with 16-bit scope descriptors
+pub const PERSONALITY_LU16: u8 = 1;
+/// `__aeabi_unwind_cpp_pr2`, long frame with 32-bit scope descriptors
+pub const PERSONALITY_LU32: u8 = 2;
+
+#[derive(Clone)]
+pub enum UnwindInstruction {
+    /// `vsp = vsp + value`
+    VspAdd(u32),
+    /// `vsp = vsp - value`
+    VspSub(u32),
+    /// Pop the core registers in the mask, bit `n` is `r[n]`
+    PopCore(u16),
+    /// `vsp = r[n]`
+    SetVsp(u8),
+    /// Pop the VFP double registers `d[first]..=d[first + count - 1]`
+    PopVfp {
+        first: u8,
+        count: u8,
+        /// The registers were saved by `FSTMFDX` rather than `VPUSH`, which leaves an extra padding word
+        fstmfdx: bool,
+    },
+    /// Pop the iWMMXt data registers `wR[first]..=wR[first + count - 1]`
+    PopWmmxData { first: u8, count: u8 },
+    /// Pop the iWMMXt control registers in the mask, bit `n` is `wCGR[n]`
+    PopWmmxControl(u8),
+    /// The function can't be unwound
+    RefuseToUnwind,
+    /// Unwinding is done, restore `pc` from `lr` if it hasn't been popped
+    Finish,
+    /// A spare or reserved opcode, the unwinder will refuse to unwind
+    Spare(Vec<u8>),
+}
+
+#[derive(Clone)]
+pub enum Personality {
+    /// One of the ARM defined `__aeabi_unwind_cpp_pr[0-2]` routines
+    Compact(u8),
+    /// Address of a generic model personality routine, e.g. `__gxx_personality_v0`
+    Generic(u64),
+}
+
+#[derive(Clone)]
+pub struct UnwindProgram {
+    pub personality: Personality,
+    pub instructions: Vec<UnwindInstruction>,
+    /// Address of the language specific data following the unwind opcodes within `.ARM.extab`, `None` for in-line entries
+    pub lsda_address: Option<u64>,
+}
+
+pub enum ExIdxValue {
+    CantUnwind,
+    Inline(UnwindProgram),
+    /// Address of the function's `.ARM.extab` entry
+    ExTab(u64),
+}
+
+pub struct ExIdxEntry {
+    pub function_address: u64,
+    pub value: ExIdxValue,
+}
+
+/// Resolves a prel31 value stored at `place`
+fn prel31(place: u64, value: u32) -> u64 {
+    // Sign extend from bit 30
+    let offset = ((value << 1) as i32) >> 1;
+    place.wrapping_add(offset as i64 as u64) & 0xffffffff
+}
+
+impl ExIdxEntry {
+    /// Parses every entry within `.ARM.exidx`, `address` is the `sh_addr` of the section
+    pub fn parse_all(bytes: &[u8], address: u64, endianness: Endian) -> Result<Vec<ExIdxEntry>> {
+        if bytes.len() % 8 != 0 {
+            return Err(Error::Malformed(
+                Details::new(format!(
+                    "`.ARM.exidx` size of `{}` is not a multiple of the entry size `8`",
+                    bytes.len()
+                ))
+                .structure("elf::arm::ExIdxEntry"),
+            ));
+        }
+
+        let mut result: Vec<ExIdxEntry> = Vec::with_capacity(bytes.len() / 8);
+
+        for offset in (0..bytes.len()).step_by(8) {
+            let function_word = bytes.pread_with::<u32>(offset, endianness)?;
+            let value_word = bytes.pread_with::<u32>(offset + 4, endianness)?;
+            let entry_address = address.wrapping_add(offset as u64);
+
+            if function_word & 0x80000000 != 0 {
+                return Err(Error::Malformed(
+                    Details::new(format!(
+                        "`.ARM.exidx` entry at offset `{}` has bit 31 set in its function offset",
+                        offset
+                    ))
+                    .structure("elf::arm::ExIdxEntry")
+                    .field("function_address"),
+                ));
+            }
+
+            let value = if value_word == EXIDX_CANTUNWIND {
+                ExIdxValue::CantUnwind
+            } else if value_word & 0x80000000 != 0 {
+                let personality = ((value_word >> 24) & 0xf) as u8;
+
+                if personality != PERSONALITY_SU16 {
+                    return Err(Error::Malformed(
+                        Details::new(format!(
+                            "`.ARM.exidx` entry at offset `{}` uses personality routine `{}` in-line, only `0` is allowed",
+                            offset, personality
+                        ))
+                        .structure("elf::arm::ExIdxEntry")
+                        .field("value"),
+                    ));
+                }
+
+                ExIdxValue::Inline(UnwindProgram {
+                    personality: Personality::Compact(personality),
+                    instructions: decode_instructions(&value_word.to_be_bytes()[1..])?,
+                    lsda_address: None,
+                })
+            } else {
+                ExIdxValue::ExTab(prel31(entry_address.wrapping_add(4), value_word))
+            };
+
+            result.push(ExIdxEntry {
+                function_address: prel31(entry_address, function_word),
+                value,
+            });
+        }
+
+        Ok(result)
+    }
+
+    /// Finds the entry for the function containing `address`, `entries` must be sorted as they are in `.ARM.exidx`
+    pub fn find(entries: &[ExIdxEntry], address: u64) -> Option<&ExIdxEntry> {
+        match entries.partition_point(|entry| entry.function_address <= address) {
+            0 => None,
+            index => Some(&entries[index - 1]),
+        }
+    }
+
+    /// Gets the unwind program of this entry, `extab_bytes` and `extab_address` are only used for `ExIdxValue::ExTab`
+    ///
+    /// Returns `None` for `ExIdxValue::CantUnwind`.
+    pub fn unwind_program(
+        &self,
+        extab_bytes: &[u8],
+        extab_address: u64,
+        endianness: Endian,
+    ) -> Result<Option<UnwindProgram>> {
+        match &self.value {
+            ExIdxValue::CantUnwind => Ok(None),
+            ExIdxValue::Inline(program) => Ok(Some(program.clone())),
+            ExIdxValue::ExTab(address) => Ok(Some(UnwindProgram::parse_extab(
+                extab_bytes,
+                extab_address,
+                *address,
+                endianness,
+            )?)),
+        }
+    }
+}
+
+impl UnwindProgram {
+    /// Parses the `.ARM.extab` entry at `entry_address`, `extab_address` is the `sh_addr` of `.ARM.extab`
+    pub fn parse_extab(
+        extab_bytes: &[u8],
+        extab_address: u64,
+        entry_address: u64,
+        endianness: Endian,
+    ) -> Result<UnwindProgram> {
+        let offset = entry_address
+            .checked_sub(extab_address)
+            .and_then(|offset| usize::try_from(offset).ok())
+            .filter(|offset| *offset < extab_bytes.len())
+            .ok_or_else(|| {
+                Error::Malformed(
+                    Details::new(format!(
+                        "`.ARM.extab` entry address `0x{:x}` is outside of `.ARM.extab`",
+                        entry_address
+                    ))
+                    .structure("elf::arm::ExIdxEntry")
+                    .field("value"),
+                )
+            })?;
+
+        let first_word = extab_bytes.pread_with::<u32>(offset, endianness)?;
+
+        // The generic model is followed by the personality specific data, for the GCC and LLVM personality routines this
+        // starts with a word holding the count of additional opcode words in its top byte followed by 3 opcode bytes.
+        let (personality, opcodes_offset, first_word) = if first_word & 0x80000000 != 0 {
+            let index = ((first_word >> 24) & 0xf) as u8;
+            (Personality::Compact(index), offset, first_word)
+        } else {
+            (
+                Personality::Generic(prel31(entry_address, first_word)),
+                offset + 4,
+                extab_bytes.pread_with::<u32>(offset + 4, endianness)?,
+            )
+        };
+
+        let (mut opcodes, additional_words) = match personality {
+            Personality::Compact(PERSONALITY_SU16) => (first_word.to_be_bytes()[1..].to_vec(), 0),
+            Personality::Compact(PERSONALITY_LU16) | Personality::Compact(PERSONALITY_LU32) => {
+                (first_word.to_be_bytes()[2..].to_vec(), (first_word >> 16) & 0xff)
+            }
+            Personality::Generic(_) => (first_word.to_be_bytes()[1..].to_vec(), (first_word >> 24) & 0xff),
+            Personality::Compact(unknown) => {
+                return Err(Error::Malformed(
+                    Details::new(format!(
+                        "`.ARM.extab` entry at address `0x{:x}` uses unknown compact personality routine `{}`",
+                        entry_address, unknown
+                    ))
+                    .structure("elf::arm::UnwindProgram")
+                    .field("personality"),
+                ))
+            }
+        };
+
+        let mut word_offset = opcodes_offset + 4;
+
+        for _ in 0..additional_words {
+            opcodes.extend_from_slice(
+                &extab_bytes
+                    .pread_with::<u32>(word_offset, endianness)?
+                    .to_be_bytes(),
+            );
+            word_offset += 4;
+        }
+
+        let lsda_address = extab_address
+            .checked_add(word_offset as u64)
+            .ok_or_else(|| {
+                Error::Malformed(
+                    Details::new(format!(
+                        "`.ARM.extab` entry at address `0x{:x}` has an out of range language specific data address",
+                        entry_address
+                    ))
+                    .structure("elf::arm::UnwindProgram")
+                    .field("lsda_address"),
+                )
+            })?;
+
+        Ok(UnwindProgram {
+            personality,
+            instructions: decode_instructions(&opcodes)?,
+            lsda_address: Some(lsda_address),
+        })
+    }
+}
+
+fn truncated_opcode(opcode: u8) -> Error {
+    Error::Malformed(
+        Details::new(format!(
+            "Unwind opcode `0x{:02x}` is missing its operand",
+            opcode
+        ))
+        .structure("elf::arm::UnwindInstruction"),
+    )
+}
+
+/// Decodes unwind opcode bytes into instructions, decoding stops at the first `Finish`
+pub fn decode_instructions(bytes: &[u8]) -> Result<Vec<UnwindInstruction>> {
+    let mut result: Vec<UnwindInstruction> = Vec::new();
+    let mut index: usize = 0;
+
+    while index < bytes.len() {
+        let opcode = bytes[index];
+        index += 1;
+
+        let mut next_byte = || -> Result<u8> {
+            let byte = *bytes.get(index).ok_or_else(|| truncated_opcode(opcode))?;
+            index += 1;
+            Ok(byte)
+        };
+
+        let instruction = match opcode {
+            0x00..=0x3f => UnwindInstruction::VspAdd((u32::from(opcode & 0x3f) << 2) + 4),
+            0x40..=0x7f => UnwindInstruction::VspSub((u32::from(opcode & 0x3f) << 2) + 4),
+            0x80..=0x8f => {
+                let operand = next_byte()?;
+                let mask = (u16::from(opcode & 0xf) << 8) | u16::from(operand);
+
+                if mask == 0 {
+                    UnwindInstruction::RefuseToUnwind
+                } else {
+                    UnwindInstruction::PopCore(mask << 4)
+                }
+            }
+            0x9d | 0x9f => UnwindInstruction::Spare(vec![opcode]),
+            0x90..=0x9f => UnwindInstruction::SetVsp(opcode & 0xf),
+            0xa0..=0xa7 => UnwindInstruction::PopCore(((1u16 << ((opcode & 0x7) + 1)) - 1) << 4),
+            0xa8..=0xaf => {
+                UnwindInstruction::PopCore((((1u16 << ((opcode & 0x7) + 1)) - 1) << 4) | (1 << 14))
+            }
+            0xb0 => UnwindInstruction::Finish,
+            0xb1 => {
+                let operand = next_byte()?;
+
+                if operand == 0 || operand & 0xf0 != 0 {
+                    UnwindInstruction::Spare(vec![opcode, operand])
+                } else {
+                    UnwindInstruction::PopCore(u16::from(operand))
+                }
+            }
+            0xb2 => {
+                let mut value: u64 = 0;
+                let mut shift = 0;
+
+                loop {
+                    let byte = next_byte()?;
+
+                    if shift < 64 {
+                        value |= u64::from(byte & 0x7f) << shift;
+                    }
+
+                    shift += 7;
+
+                    if byte & 0x80 == 0 {
+                        break;
+                    }
+                }
+
+                let value = value
+                    .checked_mul(4)
+                    .and_then(|value| value.checked_add(0x204))
+                    .and_then(|value| u32::try_from(value).ok())
+                    .ok_or_else(|| {
+                        Error::Malformed(
+                            Details::new(format!(
+                                "Unwind opcode `0xb2` has an out of range operand `{}`",
+                                value
+                            ))
+                            .structure("elf::arm::UnwindInstruction"),
+                        )
+                    })?;
+
+                UnwindInstruction::VspAdd(value)
+            }
+            0xb3 => {
+                let operand = next_byte()?;
+                UnwindInstruction::PopVfp {
+                    first: operand >> 4,
+                    count: (operand & 0xf) + 1,
+                    fstmfdx: true,
+                }
+            }
+            0xb8..=0xbf => UnwindInstruction::PopVfp {
+                first: 8,
+                count: (opcode & 0x7) + 1,
+                fstmfdx: true,
+            },
+            0xc0..=0xc5 => UnwindInstruction::PopWmmxData {
+                first: 10,
+                count: (opcode & 0x7) + 1,
+            },
+            0xc6 => {
+                let operand = next_byte()?;
+                UnwindInstruction::PopWmmxData {
+                    first: operand >> 4,
+                    count: (operand & 0xf) + 1,
+                }
+            }
+            0xc7 => {
+                let operand = next_byte()?;
+
+                if operand == 0 || operand & 0xf0 != 0 {
+                    UnwindInstruction::Spare(vec![opcode, operand])
+                } else {
+                    UnwindInstruction::PopWmmxControl(operand)
+                }
+            }
+            0xc8 | 0xc9 => {
+                let operand = next_byte()?;
+                UnwindInstruction::PopVfp {
+                    first: (operand >> 4) + if opcode == 0xc8 { 16 } else { 0 },
+                    count: (operand & 0xf) + 1,
+                    fstmfdx: false,
+                }
+            }
+            0xd0..=0xd7 => UnwindInstruction::PopVfp {
+                first: 8,
+                count: (opcode & 0x7) + 1,
+                fstmfdx: false,
+            },
+            // 0xb4-0xb7, 0xca-0xcf and 0xd8-0xff
+            _ => UnwindInstruction::Spare(vec![opcode]),
+        };
+
+        let is_finish = matches!(instruction, UnwindInstruction::Finish);
+        result.push(instruction);
+
+        if is_finish {
+            break;
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_exidx_and_extab() {
+        let mut exidx: Vec<u8> = Vec::new();
+        // Function at `0x800`, in-line `pop {r4, r14}` then finish
+        exidx.extend_from_slice(&((0x800u32.wrapping_sub(0x1000)) & 0x7fffffff).to_le_bytes());
+        exidx.extend_from_slice(&0x80a8b0b0u32.to_le_bytes());
+        // Function at `0x900`, `.ARM.extab` entry at `0x2000`
+        exidx.extend_from_slice(&((0x900u32.wrapping_sub(0x1008)) & 0x7fffffff).to_le_bytes());
+        exidx.extend_from_slice(&(0x2000u32 - 0x100c).to_le_bytes());
+
+        let mut extab: Vec<u8> = Vec::new();
+        // `lu16` with one extra word, `vpop {d8-d12}`, `mov sp, r7`, finish
+        extab.extend_from_slice(&0x8101c984u32.to_le_bytes());
+        extab.extend_from_slice(&0x97b0b0b0u32.to_le_bytes());
+
+        let entries = ExIdxEntry::parse_all(&exidx, 0x1000, scroll::LE).unwrap();
+        assert_eq!(entries[0].function_address, 0x800);
+        assert_eq!(entries[1].function_address, 0x900);
+        assert!(ExIdxEntry::find(&entries, 0x7ff).is_none());
+        assert_eq!(
+            ExIdxEntry::find(&entries, 0x8ff).unwrap().function_address,
+            0x800
+        );
+
+        let program = entries[0]
+            .unwind_program(&extab, 0x2000, scroll::LE)
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            program.instructions[..],
+            [
+                UnwindInstruction::PopCore(0x4010),
+                UnwindInstruction::Finish
+            ]
+        ));
+
+        let program = entries[1]
+            .unwind_program(&extab, 0x2000, scroll::LE)
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            program.personality,
+            Personality::Compact(PERSONALITY_LU16)
+        ));
+        assert!(matches!(
+            program.instructions[..],
+            [
+                UnwindInstruction::PopVfp {
+                    first: 8,
+                    count: 5,
+                    fstmfdx: false
+                },
+                UnwindInstruction::SetVsp(7),
+                UnwindInstruction::Finish
+            ]
+        ));
+        assert_eq!(program.lsda_address, Some(0x2008));
+    }
+
+    #[test]
+    fn decode_generic_personality() {
+        let mut extab: Vec<u8> = vec![0; 0x10];
+        // Personality routine at `0x1800`, one extra word, `vsp = vsp + 8`, `pop {r4}`, finish
+        extab.extend_from_slice(&((0x1800u32.wrapping_sub(0x2010)) & 0x7fffffff).to_le_bytes());
+        extab.extend_from_slice(&0x01018001u32.to_le_bytes());
+        extab.extend_from_slice(&0xb0b0b000u32.to_le_bytes());
+
+        let program = UnwindProgram::parse_extab(&extab, 0x2000, 0x2010, scroll::LE).unwrap();
+        assert!(matches!(program.personality, Personality::Generic(0x1800)));
+        assert!(matches!(
+            program.instructions[..],
+            [
+                UnwindInstruction::VspAdd(8),
+                UnwindInstruction::PopCore(0x0010),
+                UnwindInstruction::Finish
+            ]
+        ));
+        assert_eq!(program.lsda_address, Some(0x201c));
+    }
+}
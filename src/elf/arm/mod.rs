@@ -16,6 +16,11 @@
 
 use crate::stringable_consts_blocks::option_stringable_consts_block;
 
+mod attributes;
+pub use attributes::*;
+mod ehabi;
+pub use ehabi::*;
+
 // e_flags
 /// Legacy pre EABI_VER5
 pub const EF_ARM_SOFT_FLOAT: u32 = 0x00000200;
@@ -26,6 +31,7 @@ pub const EF_ARM_VFP_FLOAT: u32 = 0x00000400;
 /// EABI_VER5
 pub const EF_ARM_ABI_FLOAT_HARD: u32 = 0x00000400;
 pub const EF_ARM_BE8: u32 = 0x00800000;
+pub const EF_ARM_LE8: u32 = 0x00400000;
 pub const EF_ARM_EABI_UNKNOWN: u32 = 0x00000000;
 pub const EF_ARM_EABI_VER1: u32 = 0x01000000;
 pub const EF_ARM_EABI_VER2: u32 = 0x02000000;
@@ -0,0 +1,392 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Build attributes (`SHT_ARM_ATTRIBUTES`, `SHT_RISCV_ATTRIBUTES`, `SHT_GNU_ATTRIBUTES`, etc.)
+//
+// Sources:
+//  - https://github.com/ARM-software/abi-aa/blob/main/addenda32/addenda32.rst#build-attributes
+//  - https://github.com/riscv-non-isa/riscv-elf-psabi-doc/blob/master/riscv-elf.adoc#rv-section-attributes
+//
+// Layout of the section:
+//  - `format_version: u8` (`A`)
+//  - Vendor sections until the end of the section, each made of:
+//    - `size: u32` (including the size itself)
+//    - `vendor_name: NTBS`
+//    - Sub-sections until the end of the vendor section, each made of:
+//      - `tag: uleb128` (`Tag_File`, `Tag_Section` or `Tag_Symbol`)
+//      - `size: u32` (including the tag and size)
+//      - For `Tag_Section` and `Tag_Symbol`, a list of `uleb128` indices terminated by `0`
+//      - Attributes until the end of the sub-section, each made of a `uleb128` tag and either a `uleb128` or `NTBS` value
+#![allow(non_upper_case_globals)]
+
+use crate::leb128;
+use crate::stringable_consts_blocks::option_stringable_consts_block;
+use crate::utils::read_cstring;
+use crate::{Details, Error};
+use scroll::{Endian, Pread};
+use std::io::Cursor;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// The only supported format version, the ASCII character `A`
+pub const ATTRIBUTES_FORMAT_VERSION: u8 = b'A';
+
+// Sub-section tags
+option_stringable_consts_block! {
+    const stringable: u64 {
+        /// The attributes apply to the entire file
+        pub Tag_File = 1;
+        /// The attributes apply to the listed sections
+        pub Tag_Section = 2;
+        /// The attributes apply to the listed symbols
+        pub Tag_Symbol = 3;
+    }
+
+    const ignore: u64 {}
+
+    pub fn attributes_scope_tag_to_str(value: u64) -> Option<&'static str>;
+}
+
+pub struct BuildAttributes {
+    pub format_version: u8,
+    pub vendors: Vec<AttributesVendor>,
+}
+
+pub struct AttributesVendor {
+    /// The vendor name, e.g. `aeabi` for ARM, `riscv` for RISC-V or `gnu` for GNU
+    pub name: String,
+    pub subsections: Vec<AttributesSubsection>,
+}
+
+pub enum AttributesScope {
+    File,
+    /// Section header indices the attributes apply to
+    Section(Vec<u32>),
+    /// Symbol indices the attributes apply to
+    Symbol(Vec<u32>),
+}
+
+pub struct AttributesSubsection {
+    pub scope: AttributesScope,
+    pub attributes: Vec<Attribute>,
+}
+
+pub struct Attribute {
+    pub tag: u64,
+    pub value: AttributeValue,
+}
+
+pub enum AttributeValue {
+    Integer(u64),
+    String(String),
+    /// `Tag_compatibility` is the only attribute with two values, a flag followed by a vendor name
+    Compatibility {
+        flag: u64,
+        vendor: String,
+    },
+}
+
+impl AttributesVendor {
+    /// Gets the first file scoped attribute with the tag `tag`
+    pub fn get_file_attribute(&self, tag: u64) -> Option<&AttributeValue> {
+        self.subsections
+            .iter()
+            .filter(|subsection| matches!(subsection.scope, AttributesScope::File))
+            .flat_map(|subsection| subsection.attributes.iter())
+            .find(|attribute| attribute.tag == tag)
+            .map(|attribute| &attribute.value)
+    }
+}
+
+impl BuildAttributes {
+    pub fn get_vendor(&self, name: &str) -> Option<&AttributesVendor> {
+        self.vendors.iter().find(|vendor| vendor.name == name)
+    }
+
+    /// Parses the bytes of a build attributes section, e.g. from `Reader::get_section_bytes`
+    pub fn parse(bytes: &[u8], endianness: Endian) -> Result<BuildAttributes> {
+        let format_version = match bytes.first() {
+            Some(&ATTRIBUTES_FORMAT_VERSION) => ATTRIBUTES_FORMAT_VERSION,
+            Some(format_version) => {
+                return Err(Error::InvalidVersionNumber(
+                    Details::new(format!(
+                        "Expected `{}` for build attributes format version, found `{}`",
+                        ATTRIBUTES_FORMAT_VERSION as char, *format_version as char
+                    ))
+                    .structure("elf::BuildAttributes")
+                    .field("format_version"),
+                ))
+            }
+            None => {
+                return Err(Error::Malformed(
+                    Details::new(
+                        "Build attributes section is empty, expected at least a format version",
+                    )
+                    .structure("elf::BuildAttributes"),
+                ))
+            }
+        };
+
+        let mut vendors: Vec<AttributesVendor> = Vec::new();
+        let mut offset: usize = 1;
+
+        while offset < bytes.len() {
+            let vendor_bytes = read_vendor_block(bytes, offset, endianness)?;
+            offset += vendor_bytes.len() + 4;
+
+            let mut name_cursor = Cursor::new(vendor_bytes);
+            let name = read_cstring(&mut name_cursor)?;
+            let name_len = name_cursor.position() as usize;
+            let mut subsections: Vec<AttributesSubsection> = Vec::new();
+            let mut subsection_offset = name_len;
+
+            while subsection_offset < vendor_bytes.len() {
+                let mut cursor = Cursor::new(&vendor_bytes[subsection_offset..]);
+                let scope_tag: u64 = leb128::decode_uleb128(&mut cursor)?;
+                let tag_len = cursor.position() as usize;
+                let size = vendor_bytes
+                    .pread_with::<u32>(subsection_offset + tag_len, endianness)?
+                    as usize;
+                let header_len = tag_len + 4;
+
+                // The size includes the tag and size
+                if size < header_len || size > vendor_bytes.len() - subsection_offset {
+                    return Err(Error::Malformed(
+                        Details::new(format!(
+                            "Build attributes sub-section at offset `{}` has an invalid size of `{}`, only `{}` bytes are available",
+                            subsection_offset,
+                            size,
+                            vendor_bytes.len() - subsection_offset
+                        ))
+                        .structure("elf::AttributesSubsection"),
+                    ));
+                }
+
+                let subsection_bytes =
+                    &vendor_bytes[subsection_offset + header_len..subsection_offset + size];
+                subsection_offset += size;
+                subsections.push(parse_subsection(&name, scope_tag, subsection_bytes)?);
+            }
+
+            vendors.push(AttributesVendor { name, subsections });
+        }
+
+        Ok(BuildAttributes {
+            format_version,
+            vendors,
+        })
+    }
+}
+
+/// Gets the name of the attribute `tag` for the vendor `vendor`
+pub fn attribute_tag_to_str(vendor: &str, tag: u64) -> Option<&'static str> {
+    match vendor {
+        "aeabi" => crate::elf::arm::attribute_tag_to_str(tag),
+        "riscv" => crate::elf::riscv::attribute_tag_to_str(tag),
+        _ => None,
+    }
+}
+
+/// Reads a `u32` size at `offset` that includes itself and returns the bytes it covers, minus the size itself
+fn read_vendor_block(bytes: &[u8], offset: usize, endianness: Endian) -> Result<&[u8]> {
+    let size = bytes.pread_with::<u32>(offset, endianness)? as usize;
+
+    if size < 4 || size > bytes.len() - offset {
+        Err(Error::Malformed(
+            Details::new(format!(
+                "Build attributes vendor section at offset `{}` has an invalid size of `{}`, only `{}` bytes are available",
+                offset,
+                size,
+                bytes.len() - offset
+            ))
+            .structure("elf::AttributesVendor"),
+        ))
+    } else {
+        Ok(&bytes[offset + 4..offset + size])
+    }
+}
+
+/// Whether the value of `tag` is a `NTBS` rather than a `uleb128`
+///
+/// Both ARM and RISC-V (and GNU) use odd tags for strings and even tags for integers, ARM has a few exceptions below `32`.
+fn is_string_tag(vendor: &str, tag: u64) -> bool {
+    if vendor == "aeabi" && tag < 32 {
+        tag == crate::elf::arm::Tag_CPU_raw_name || tag == crate::elf::arm::Tag_CPU_name
+    } else {
+        tag % 2 == 1
+    }
+}
+
+fn parse_subsection(vendor: &str, scope_tag: u64, bytes: &[u8]) -> Result<AttributesSubsection> {
+    let mut cursor = Cursor::new(bytes);
+
+    let scope = match scope_tag {
+        Tag_File => AttributesScope::File,
+        Tag_Section | Tag_Symbol => {
+            let mut indices: Vec<u32> = Vec::new();
+
+            loop {
+                let index: u32 = leb128::decode_uleb128(&mut cursor)?;
+
+                if index == 0 {
+                    break;
+                }
+
+                indices.push(index);
+            }
+
+            if scope_tag == Tag_Section {
+                AttributesScope::Section(indices)
+            } else {
+                AttributesScope::Symbol(indices)
+            }
+        }
+        unknown => {
+            return Err(Error::Malformed(
+                Details::new(format!(
+                    "Unknown build attributes sub-section tag `{}` for vendor `{}`",
+                    unknown, vendor
+                ))
+                .structure("elf::AttributesSubsection")
+                .field("scope"),
+            ))
+        }
+    };
+
+    let mut attributes: Vec<Attribute> = Vec::new();
+
+    while (cursor.position() as usize) < bytes.len() {
+        let tag: u64 = leb128::decode_uleb128(&mut cursor)?;
+
+        let value = if vendor == "aeabi" && tag == crate::elf::arm::Tag_compatibility {
+            let flag: u64 = leb128::decode_uleb128(&mut cursor)?;
+            let vendor = read_cstring(&mut cursor)?;
+            AttributeValue::Compatibility { flag, vendor }
+        } else if is_string_tag(vendor, tag) {
+            AttributeValue::String(read_cstring(&mut cursor)?)
+        } else {
+            AttributeValue::Integer(leb128::decode_uleb128(&mut cursor)?)
+        };
+
+        attributes.push(Attribute { tag, value });
+    }
+
+    Ok(AttributesSubsection { scope, attributes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_riscv_attributes() {
+        let mut bytes: Vec<u8> = vec![b'A'];
+        let arch = b"rv64i2p1_m2p0_a2p1_c2p0\0";
+        let vendor_size = 4 + b"riscv\0".len() + 1 + 4 + 2 + 1 + arch.len();
+        bytes.extend_from_slice(&(vendor_size as u32).to_le_bytes());
+        bytes.extend_from_slice(b"riscv\0");
+        bytes.push(Tag_File as u8);
+        bytes.extend_from_slice(&((1 + 4 + 2 + 1 + arch.len()) as u32).to_le_bytes());
+        bytes.extend_from_slice(&[crate::elf::riscv::Tag_RISCV_stack_align as u8, 16]);
+        bytes.push(crate::elf::riscv::Tag_RISCV_arch as u8);
+        bytes.extend_from_slice(arch);
+
+        let attributes = BuildAttributes::parse(&bytes, scroll::LE).unwrap();
+        let riscv = attributes.get_vendor("riscv").unwrap();
+
+        assert!(matches!(
+            riscv.get_file_attribute(crate::elf::riscv::Tag_RISCV_stack_align),
+            Some(AttributeValue::Integer(16))
+        ));
+        assert!(matches!(
+            riscv.get_file_attribute(crate::elf::riscv::Tag_RISCV_arch),
+            Some(AttributeValue::String(arch)) if arch == "rv64i2p1_m2p0_a2p1_c2p0"
+        ));
+        assert_eq!(attribute_tag_to_str("riscv", 5), Some("Tag_RISCV_arch"));
+    }
+
+    #[test]
+    fn parse_arm_attributes() {
+        use crate::elf::arm::{
+            Tag_ARM_ISA_use, Tag_CPU_arch, Tag_CPU_name, Tag_THUMB_ISA_use, Tag_compatibility,
+        };
+
+        fn subsection(scope_tag: u64, body: &[u8]) -> Vec<u8> {
+            let mut result = vec![scope_tag as u8];
+            result.extend_from_slice(&((1 + 4 + body.len()) as u32).to_be_bytes());
+            result.extend_from_slice(body);
+            result
+        }
+
+        let mut file = vec![Tag_CPU_name as u8];
+        file.extend_from_slice(b"Cortex-A9\0");
+        file.extend_from_slice(&[Tag_compatibility as u8, 1]);
+        file.extend_from_slice(b"gnu\0");
+        file.extend_from_slice(&[Tag_ARM_ISA_use as u8, 1]);
+
+        // Section `3` and section `200`, which takes two bytes as a `uleb128`, then the terminating `0`
+        let section = [3, 0xc8, 0x01, 0, Tag_THUMB_ISA_use as u8, 2];
+        let symbol = [7, 0, Tag_CPU_arch as u8, 10];
+
+        let mut vendor = b"aeabi\0".to_vec();
+        vendor.extend(subsection(Tag_File, &file));
+        vendor.extend(subsection(Tag_Section, &section));
+        vendor.extend(subsection(Tag_Symbol, &symbol));
+
+        let mut bytes: Vec<u8> = vec![b'A'];
+        bytes.extend_from_slice(&((4 + vendor.len()) as u32).to_be_bytes());
+        bytes.extend(vendor);
+
+        let attributes = BuildAttributes::parse(&bytes, scroll::BE).unwrap();
+        let aeabi = attributes.get_vendor("aeabi").unwrap();
+        assert_eq!(aeabi.subsections.len(), 3);
+
+        assert!(matches!(
+            aeabi.get_file_attribute(Tag_CPU_name),
+            Some(AttributeValue::String(name)) if name == "Cortex-A9"
+        ));
+        assert!(matches!(
+            aeabi.get_file_attribute(Tag_compatibility),
+            Some(AttributeValue::Compatibility { flag: 1, vendor }) if vendor == "gnu"
+        ));
+        assert!(matches!(
+            aeabi.get_file_attribute(Tag_ARM_ISA_use),
+            Some(AttributeValue::Integer(1))
+        ));
+        // Attributes of the other scopes aren't file attributes
+        assert!(aeabi.get_file_attribute(Tag_THUMB_ISA_use).is_none());
+
+        let section = &aeabi.subsections[1];
+        assert!(
+            matches!(&section.scope, AttributesScope::Section(indices) if indices == &[3, 200])
+        );
+        assert!(matches!(
+            section.attributes.as_slice(),
+            [Attribute { tag, value: AttributeValue::Integer(2) }] if *tag == Tag_THUMB_ISA_use
+        ));
+
+        let symbol = &aeabi.subsections[2];
+        assert!(matches!(&symbol.scope, AttributesScope::Symbol(indices) if indices == &[7]));
+        assert!(matches!(
+            symbol.attributes.as_slice(),
+            [Attribute { tag, value: AttributeValue::Integer(10) }] if *tag == Tag_CPU_arch
+        ));
+        assert_eq!(
+            attribute_tag_to_str("aeabi", Tag_compatibility),
+            Some("Tag_compatibility")
+        );
+    }
+}
@@ -0,0 +1,628 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::elf::bpf::*;
+use crate::elf::StrTab;
+use crate::error::ResultExt;
+use crate::{ContextFrame, Details, Error, Limits};
+use scroll::{Endian, Pread};
+use std::borrow::Cow;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// libbpf gives up following typedefs and modifiers after this many steps
+const MAX_RESOLVE_DEPTH: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BtfHeader {
+    pub magic: u16,
+    pub version: u8,
+    pub flags: u8,
+    pub hdr_len: u32,
+    /// Offset of the types from the end of the header
+    pub type_off: u32,
+    pub type_len: u32,
+    /// Offset of the strings from the end of the header
+    pub str_off: u32,
+    pub str_len: u32,
+}
+
+impl BtfHeader {
+    /// Reads the header at the start of `bytes`, the byte order is detected from `magic`
+    pub fn parse(bytes: &[u8]) -> Result<(BtfHeader, Endian)> {
+        let endianness = btf_endianness(bytes, "bpf::BtfHeader")?;
+        let offset = &mut 0;
+
+        Ok((
+            BtfHeader {
+                magic: bytes.gread_with(offset, endianness)?,
+                version: bytes.gread_with(offset, endianness)?,
+                flags: bytes.gread_with(offset, endianness)?,
+                hdr_len: bytes.gread_with(offset, endianness)?,
+                type_off: bytes.gread_with(offset, endianness)?,
+                type_len: bytes.gread_with(offset, endianness)?,
+                str_off: bytes.gread_with(offset, endianness)?,
+                str_len: bytes.gread_with(offset, endianness)?,
+            },
+            endianness,
+        ))
+    }
+}
+
+/// A member of a `BTF_KIND_STRUCT` or `BTF_KIND_UNION`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BtfMember {
+    /// Empty for anonymous members
+    pub name: String,
+    pub type_id: u32,
+    pub bit_offset: u32,
+    /// Non-zero for bitfields
+    pub bitfield_size: u8,
+}
+
+/// A value of a `BTF_KIND_ENUM` or `BTF_KIND_ENUM64`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BtfEnumValue {
+    pub name: String,
+    /// Unsigned `BTF_KIND_ENUM64` values above `i64::MAX` wrap around, see `BtfKind::Enum::signed`
+    pub value: i64,
+}
+
+/// A parameter of a `BTF_KIND_FUNC_PROTO`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BtfParam {
+    /// Empty in prototypes not attached to a `BTF_KIND_FUNC`
+    pub name: String,
+    /// `0` for the trailing `...` of variadic functions
+    pub type_id: u32,
+}
+
+/// A variable of a `BTF_KIND_DATASEC`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BtfVarSecInfo {
+    /// The `BTF_KIND_VAR` or `BTF_KIND_FUNC`
+    pub type_id: u32,
+    /// Offset of the variable in the section
+    pub offset: u32,
+    pub size: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BtfKind {
+    Int {
+        size: u32,
+        /// `BTF_INT_SIGNED`, `BTF_INT_CHAR` and `BTF_INT_BOOL` flags
+        encoding: u8,
+        bit_offset: u8,
+        bits: u8,
+    },
+    Ptr {
+        type_id: u32,
+    },
+    Array {
+        type_id: u32,
+        index_type_id: u32,
+        nelems: u32,
+    },
+    Struct {
+        size: u32,
+        members: Vec<BtfMember>,
+    },
+    Union {
+        size: u32,
+        members: Vec<BtfMember>,
+    },
+    Enum {
+        size: u32,
+        signed: bool,
+        values: Vec<BtfEnumValue>,
+    },
+    Fwd {
+        is_union: bool,
+    },
+    Typedef {
+        type_id: u32,
+    },
+    Volatile {
+        type_id: u32,
+    },
+    Const {
+        type_id: u32,
+    },
+    Restrict {
+        type_id: u32,
+    },
+    Func {
+        /// The `BTF_KIND_FUNC_PROTO`
+        type_id: u32,
+        /// `BTF_LINKAGE_*`
+        linkage: u32,
+    },
+    FuncProto {
+        return_type_id: u32,
+        params: Vec<BtfParam>,
+    },
+    Var {
+        type_id: u32,
+        /// `BTF_LINKAGE_*`
+        linkage: u32,
+    },
+    DataSec {
+        size: u32,
+        variables: Vec<BtfVarSecInfo>,
+    },
+    Float {
+        size: u32,
+    },
+    DeclTag {
+        type_id: u32,
+        /// `-1` if the tag is on the type itself, otherwise the index of the member or parameter
+        component_idx: i32,
+    },
+    TypeTag {
+        type_id: u32,
+    },
+    Enum64 {
+        size: u32,
+        signed: bool,
+        values: Vec<BtfEnumValue>,
+    },
+}
+
+impl BtfKind {
+    /// `BTF_KIND_*` value of this kind
+    pub fn kind(&self) -> u32 {
+        match self {
+            BtfKind::Int { .. } => BTF_KIND_INT,
+            BtfKind::Ptr { .. } => BTF_KIND_PTR,
+            BtfKind::Array { .. } => BTF_KIND_ARRAY,
+            BtfKind::Struct { .. } => BTF_KIND_STRUCT,
+            BtfKind::Union { .. } => BTF_KIND_UNION,
+            BtfKind::Enum { .. } => BTF_KIND_ENUM,
+            BtfKind::Fwd { .. } => BTF_KIND_FWD,
+            BtfKind::Typedef { .. } => BTF_KIND_TYPEDEF,
+            BtfKind::Volatile { .. } => BTF_KIND_VOLATILE,
+            BtfKind::Const { .. } => BTF_KIND_CONST,
+            BtfKind::Restrict { .. } => BTF_KIND_RESTRICT,
+            BtfKind::Func { .. } => BTF_KIND_FUNC,
+            BtfKind::FuncProto { .. } => BTF_KIND_FUNC_PROTO,
+            BtfKind::Var { .. } => BTF_KIND_VAR,
+            BtfKind::DataSec { .. } => BTF_KIND_DATASEC,
+            BtfKind::Float { .. } => BTF_KIND_FLOAT,
+            BtfKind::DeclTag { .. } => BTF_KIND_DECL_TAG,
+            BtfKind::TypeTag { .. } => BTF_KIND_TYPE_TAG,
+            BtfKind::Enum64 { .. } => BTF_KIND_ENUM64,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BtfType {
+    /// Empty for anonymous types
+    pub name: String,
+    pub kind: BtfKind,
+}
+
+/// The contents of a `.BTF` section, the type information of a BPF object (or of the kernel, `/sys/kernel/btf/vmlinux`)
+pub struct Btf {
+    pub header: BtfHeader,
+    pub endianness: Endian,
+    /// Type ids start at `1`, `types[0]` is type id `1` and type id `0` is `void`
+    pub types: Vec<BtfType>,
+    strings: StrTab<'static>,
+}
+
+impl Btf {
+    pub fn parse(bytes: &[u8]) -> Result<Btf> {
+        Self::parse_with_limits(bytes, &Limits::default())
+    }
+
+    /// Same as `parse` but checks the number of types against `limits` instead of the default limits
+    pub fn parse_with_limits(bytes: &[u8], limits: &Limits) -> Result<Btf> {
+        let (header, endianness) = BtfHeader::parse(bytes)?;
+        let type_bytes = subsection(bytes, &header, header.type_off, header.type_len, "type_len")?;
+        let string_bytes = subsection(bytes, &header, header.str_off, header.str_len, "str_len")?;
+        let strings = StrTab::parse(Cow::Owned(string_bytes.to_vec()), 0)?;
+
+        // Every type is at least 12 bytes
+        let mut types: Vec<BtfType> =
+            limits.vec_with_capacity(u64::from(header.type_len) / 12, "BTF types")?;
+        let mut offset = 0;
+
+        while offset < type_bytes.len() {
+            let type_offset = offset;
+            let btf_type =
+                parse_type(type_bytes, &mut offset, endianness, &strings).context(|| {
+                    ContextFrame::new("btf_type").index(types.len() + 1).offset(
+                        u64::from(header.hdr_len) + u64::from(header.type_off) + type_offset as u64,
+                    )
+                })?;
+            types.push(btf_type);
+        }
+
+        Ok(Btf {
+            header,
+            endianness,
+            types,
+            strings,
+        })
+    }
+
+    /// Returns the type with id `type_id`, `None` for `0` (`void`) and ids out of range
+    pub fn get(&self, type_id: u32) -> Option<&BtfType> {
+        self.types.get((type_id as usize).checked_sub(1)?)
+    }
+
+    /// Returns the id of the first type named `name` with the kind `kind`
+    pub fn find_by_name(&self, name: &str, kind: u32) -> Option<u32> {
+        self.types
+            .iter()
+            .position(|btf_type| btf_type.name == name && btf_type.kind.kind() == kind)
+            .map(|index| index as u32 + 1)
+    }
+
+    /// Returns the string at `offset` in the string section, used by `.BTF.ext`
+    pub fn string_at(&self, offset: u32) -> Result<Cow<'_, str>> {
+        self.strings.get_at_offset(offset)
+    }
+
+    /// Follows typedefs, `const`, `volatile`, `restrict` and type tags to the underlying type id
+    pub fn skip_modifiers(&self, mut type_id: u32) -> u32 {
+        for _ in 0..MAX_RESOLVE_DEPTH {
+            match self.get(type_id).map(|btf_type| &btf_type.kind) {
+                Some(
+                    BtfKind::Typedef { type_id: next }
+                    | BtfKind::Volatile { type_id: next }
+                    | BtfKind::Const { type_id: next }
+                    | BtfKind::Restrict { type_id: next }
+                    | BtfKind::TypeTag { type_id: next },
+                ) => type_id = *next,
+                _ => break,
+            }
+        }
+
+        type_id
+    }
+
+    /// Size in bytes of a value of type `type_id`, `None` for `void`, functions and forward declarations
+    pub fn resolve_size(&self, type_id: u32) -> Option<u64> {
+        let mut type_id = type_id;
+        let mut multiplier: u64 = 1;
+
+        for _ in 0..MAX_RESOLVE_DEPTH {
+            type_id = self.skip_modifiers(type_id);
+
+            let size = match &self.get(type_id)?.kind {
+                BtfKind::Int { size, .. }
+                | BtfKind::Struct { size, .. }
+                | BtfKind::Union { size, .. }
+                | BtfKind::Enum { size, .. }
+                | BtfKind::Enum64 { size, .. }
+                | BtfKind::DataSec { size, .. }
+                | BtfKind::Float { size } => u64::from(*size),
+                // Pointer sized, BPF is always 64-bit
+                BtfKind::Ptr { .. } => 8,
+                BtfKind::Array {
+                    type_id: element_type_id,
+                    nelems,
+                    ..
+                } => {
+                    multiplier = multiplier.checked_mul(u64::from(*nelems))?;
+                    type_id = *element_type_id;
+                    continue;
+                }
+                BtfKind::Var {
+                    type_id: var_type_id,
+                    ..
+                } => {
+                    type_id = *var_type_id;
+                    continue;
+                }
+                _ => return None,
+            };
+
+            return size.checked_mul(multiplier);
+        }
+
+        None
+    }
+}
+
+fn parse_type(
+    bytes: &[u8],
+    offset: &mut usize,
+    endianness: Endian,
+    strings: &StrTab,
+) -> Result<BtfType> {
+    let name_off: u32 = bytes.gread_with(offset, endianness)?;
+    let info: u32 = bytes.gread_with(offset, endianness)?;
+    let size_or_type: u32 = bytes.gread_with(offset, endianness)?;
+
+    let vlen = (info & 0xffff) as usize;
+    let kind = (info >> 24) & 0x1f;
+    let kind_flag = info >> 31 != 0;
+    let name = strings.get_at_offset(name_off)?.into_owned();
+
+    let read_u32 =
+        |offset: &mut usize| -> Result<u32> { Ok(bytes.gread_with(offset, endianness)?) };
+    let read_name = |offset: &mut usize| -> Result<String> {
+        let name_off: u32 = bytes.gread_with(offset, endianness)?;
+        Ok(strings.get_at_offset(name_off)?.into_owned())
+    };
+
+    let kind = match kind {
+        BTF_KIND_INT => {
+            let encoding = read_u32(offset)?;
+            BtfKind::Int {
+                size: size_or_type,
+                encoding: ((encoding >> 24) & 0x0f) as u8,
+                bit_offset: ((encoding >> 16) & 0xff) as u8,
+                bits: (encoding & 0xff) as u8,
+            }
+        }
+        BTF_KIND_PTR => BtfKind::Ptr {
+            type_id: size_or_type,
+        },
+        BTF_KIND_ARRAY => BtfKind::Array {
+            type_id: read_u32(offset)?,
+            index_type_id: read_u32(offset)?,
+            nelems: read_u32(offset)?,
+        },
+        BTF_KIND_STRUCT | BTF_KIND_UNION => {
+            let mut members: Vec<BtfMember> = Vec::with_capacity(vlen.min(bytes.len() / 12));
+            for _ in 0..vlen {
+                let name = read_name(offset)?;
+                let type_id = read_u32(offset)?;
+                let member_offset = read_u32(offset)?;
+                let (bit_offset, bitfield_size) = if kind_flag {
+                    (member_offset & 0xffffff, (member_offset >> 24) as u8)
+                } else {
+                    (member_offset, 0)
+                };
+                members.push(BtfMember {
+                    name,
+                    type_id,
+                    bit_offset,
+                    bitfield_size,
+                });
+            }
+
+            if kind == BTF_KIND_STRUCT {
+                BtfKind::Struct {
+                    size: size_or_type,
+                    members,
+                }
+            } else {
+                BtfKind::Union {
+                    size: size_or_type,
+                    members,
+                }
+            }
+        }
+        BTF_KIND_ENUM => {
+            let mut values: Vec<BtfEnumValue> = Vec::with_capacity(vlen.min(bytes.len() / 8));
+            for _ in 0..vlen {
+                let name = read_name(offset)?;
+                let value = read_u32(offset)?;
+                values.push(BtfEnumValue {
+                    name,
+                    value: if kind_flag {
+                        i64::from(value as i32)
+                    } else {
+                        i64::from(value)
+                    },
+                });
+            }
+            BtfKind::Enum {
+                size: size_or_type,
+                signed: kind_flag,
+                values,
+            }
+        }
+        BTF_KIND_ENUM64 => {
+            let mut values: Vec<BtfEnumValue> = Vec::with_capacity(vlen.min(bytes.len() / 12));
+            for _ in 0..vlen {
+                let name = read_name(offset)?;
+                let low = read_u32(offset)?;
+                let high = read_u32(offset)?;
+                values.push(BtfEnumValue {
+                    name,
+                    value: ((u64::from(high) << 32) | u64::from(low)) as i64,
+                });
+            }
+            BtfKind::Enum64 {
+                size: size_or_type,
+                signed: kind_flag,
+                values,
+            }
+        }
+        BTF_KIND_FWD => BtfKind::Fwd {
+            is_union: kind_flag,
+        },
+        BTF_KIND_TYPEDEF => BtfKind::Typedef {
+            type_id: size_or_type,
+        },
+        BTF_KIND_VOLATILE => BtfKind::Volatile {
+            type_id: size_or_type,
+        },
+        BTF_KIND_CONST => BtfKind::Const {
+            type_id: size_or_type,
+        },
+        BTF_KIND_RESTRICT => BtfKind::Restrict {
+            type_id: size_or_type,
+        },
+        BTF_KIND_FUNC => BtfKind::Func {
+            type_id: size_or_type,
+            linkage: vlen as u32,
+        },
+        BTF_KIND_FUNC_PROTO => {
+            let mut params: Vec<BtfParam> = Vec::with_capacity(vlen.min(bytes.len() / 8));
+            for _ in 0..vlen {
+                params.push(BtfParam {
+                    name: read_name(offset)?,
+                    type_id: read_u32(offset)?,
+                });
+            }
+            BtfKind::FuncProto {
+                return_type_id: size_or_type,
+                params,
+            }
+        }
+        BTF_KIND_VAR => BtfKind::Var {
+            type_id: size_or_type,
+            linkage: read_u32(offset)?,
+        },
+        BTF_KIND_DATASEC => {
+            let mut variables: Vec<BtfVarSecInfo> = Vec::with_capacity(vlen.min(bytes.len() / 12));
+            for _ in 0..vlen {
+                variables.push(BtfVarSecInfo {
+                    type_id: read_u32(offset)?,
+                    offset: read_u32(offset)?,
+                    size: read_u32(offset)?,
+                });
+            }
+            BtfKind::DataSec {
+                size: size_or_type,
+                variables,
+            }
+        }
+        BTF_KIND_FLOAT => BtfKind::Float { size: size_or_type },
+        BTF_KIND_DECL_TAG => BtfKind::DeclTag {
+            type_id: size_or_type,
+            component_idx: read_u32(offset)? as i32,
+        },
+        BTF_KIND_TYPE_TAG => BtfKind::TypeTag {
+            type_id: size_or_type,
+        },
+        unknown => {
+            return Err(Error::Malformed(
+                Details::new(format!("Unknown BTF kind `{}`", unknown))
+                    .structure("elf::bpf::BtfType")
+                    .field("info"),
+            ))
+        }
+    };
+
+    Ok(BtfType { name, kind })
+}
+
+/// Detects the byte order of `.BTF` or `.BTF.ext` from the magic number
+pub(crate) fn btf_endianness(bytes: &[u8], structure: &'static str) -> Result<Endian> {
+    match bytes.pread_with::<u16>(0, scroll::LE)? {
+        BTF_MAGIC => Ok(scroll::LE),
+        magic if magic.swap_bytes() == BTF_MAGIC => Ok(scroll::BE),
+        magic => Err(Error::Malformed(
+            Details::new(format!(
+                "Invalid BTF magic `{:#06x}`, expected `{:#06x}`",
+                magic, BTF_MAGIC
+            ))
+            .structure(structure)
+            .field("magic")
+            .offset(0),
+        )),
+    }
+}
+
+fn subsection<'b>(
+    bytes: &'b [u8],
+    header: &BtfHeader,
+    offset: u32,
+    len: u32,
+    field: &'static str,
+) -> Result<&'b [u8]> {
+    let start = u64::from(header.hdr_len) + u64::from(offset);
+    let end = start + u64::from(len);
+
+    bytes
+        .get(start as usize..end as usize)
+        .filter(|_| end <= bytes.len() as u64)
+        .ok_or_else(|| {
+            Error::Malformed(
+                Details::new(format!(
+                    "BTF subsection `{}..{}` is out of bounds for `{}` bytes",
+                    start,
+                    end,
+                    bytes.len()
+                ))
+                .structure("elf::bpf::BtfHeader")
+                .field(field),
+            )
+        })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Builds a little endian `.BTF` section out of the raw words of each type and a string section
+    pub(crate) fn build_btf(types: &[&[u32]], strings: &[u8]) -> Vec<u8> {
+        let words: Vec<u32> = types.concat();
+        let type_len = words.len() as u32 * 4;
+        let mut bytes: Vec<u8> = Vec::new();
+        bytes.extend_from_slice(&BTF_MAGIC.to_le_bytes());
+        bytes.extend_from_slice(&[1, 0]);
+        for word in [24, 0, type_len, type_len, strings.len() as u32] {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        for word in words {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        bytes.extend_from_slice(strings);
+        bytes
+    }
+
+    pub(crate) const STRINGS: &[u8] = b"\0int\0key\0value\0pair\0";
+
+    /// 1: `int`, 2: `const int`, 3: `int[4]`, 4: `struct pair { const int key; int value[4]; }`
+    pub(crate) const TYPES: &[&[u32]] = &[
+        &[1, BTF_KIND_INT << 24, 4, (BTF_INT_SIGNED << 24) | 32],
+        &[0, BTF_KIND_CONST << 24, 1],
+        &[0, BTF_KIND_ARRAY << 24, 0, 1, 1, 4],
+        &[15, (BTF_KIND_STRUCT << 24) | 2, 20, 5, 2, 0, 9, 3, 32],
+    ];
+
+    #[test]
+    fn parse_btf_types() {
+        let btf = Btf::parse(&build_btf(TYPES, STRINGS)).unwrap();
+
+        assert_eq!(btf.types.len(), 4);
+        assert_eq!(btf.get(1).unwrap().name, "int");
+        assert_eq!(
+            btf.get(1).unwrap().kind,
+            BtfKind::Int {
+                size: 4,
+                encoding: BTF_INT_SIGNED as u8,
+                bit_offset: 0,
+                bits: 32
+            }
+        );
+        assert_eq!(btf.skip_modifiers(2), 1);
+        assert_eq!(btf.resolve_size(3), Some(16));
+        assert_eq!(btf.find_by_name("pair", BTF_KIND_STRUCT), Some(4));
+
+        let BtfKind::Struct { size, members } = &btf.get(4).unwrap().kind else {
+            panic!("expected a struct");
+        };
+        assert_eq!(*size, 20);
+        assert_eq!(
+            (members[1].name.as_str(), members[1].bit_offset),
+            ("value", 32)
+        );
+
+        let mut bytes = build_btf(TYPES, STRINGS);
+        bytes[0] = 0;
+        assert!(Btf::parse(&bytes).is_err());
+    }
+}
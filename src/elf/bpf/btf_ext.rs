@@ -0,0 +1,339 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::elf::bpf::{btf_endianness, Btf};
+use crate::error::ResultExt;
+use crate::{ContextFrame, Details, Error, Limits};
+use scroll::{Endian, Pread};
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BtfExtHeader {
+    pub magic: u16,
+    pub version: u8,
+    pub flags: u8,
+    pub hdr_len: u32,
+    /// Offset of the func info from the end of the header
+    pub func_info_off: u32,
+    pub func_info_len: u32,
+    /// Offset of the line info from the end of the header
+    pub line_info_off: u32,
+    pub line_info_len: u32,
+    /// Offset of the CO-RE relocations from the end of the header, `0` if `hdr_len` is too small to hold it
+    pub core_relo_off: u32,
+    pub core_relo_len: u32,
+}
+
+impl BtfExtHeader {
+    /// Reads the header at the start of `bytes`, the byte order is detected from `magic`
+    pub fn parse(bytes: &[u8]) -> Result<(BtfExtHeader, Endian)> {
+        let endianness = btf_endianness(bytes, "bpf::BtfExtHeader")?;
+        let offset = &mut 0;
+
+        let mut header = BtfExtHeader {
+            magic: bytes.gread_with(offset, endianness)?,
+            version: bytes.gread_with(offset, endianness)?,
+            flags: bytes.gread_with(offset, endianness)?,
+            hdr_len: bytes.gread_with(offset, endianness)?,
+            func_info_off: bytes.gread_with(offset, endianness)?,
+            func_info_len: bytes.gread_with(offset, endianness)?,
+            line_info_off: bytes.gread_with(offset, endianness)?,
+            line_info_len: bytes.gread_with(offset, endianness)?,
+            core_relo_off: 0,
+            core_relo_len: 0,
+        };
+
+        // The CO-RE relocation fields were added later, older headers end before them
+        if header.hdr_len >= 32 {
+            header.core_relo_off = bytes.gread_with(offset, endianness)?;
+            header.core_relo_len = bytes.gread_with(offset, endianness)?;
+        }
+
+        Ok((header, endianness))
+    }
+}
+
+/// `struct bpf_func_info`, the BTF `BTF_KIND_FUNC` of the function starting at an instruction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BpfFuncInfo {
+    /// Offset in bytes of the first instruction in the program section
+    pub insn_off: u32,
+    pub type_id: u32,
+}
+
+/// `struct bpf_line_info`, the source line of an instruction
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BpfLineInfo {
+    /// Offset in bytes of the instruction in the program section
+    pub insn_off: u32,
+    pub file_name: String,
+    /// The source code of the line
+    pub line: String,
+    pub line_number: u32,
+    pub column: u32,
+}
+
+/// `struct bpf_core_relo`, a field or type access libbpf adjusts to the running kernel's BTF
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BpfCoreRelo {
+    /// Offset in bytes of the instruction in the program section
+    pub insn_off: u32,
+    /// The root type of the access in the object's BTF
+    pub type_id: u32,
+    /// Member indices from the root type, e.g. `0:1:2` for `&root[0].member1.member2`
+    pub access_str: String,
+    /// `BPF_CORE_*`
+    pub kind: u32,
+}
+
+/// The records of one program section, e.g. `kprobe/do_sys_open`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BtfExtSection<T> {
+    pub section_name: String,
+    pub records: Vec<T>,
+}
+
+/// The contents of a `.BTF.ext` section, per program section function, line and CO-RE relocation information
+///
+/// Strings are stored in the string section of `.BTF`, so parsing needs the parsed `Btf`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BtfExt {
+    pub header: BtfExtHeader,
+    pub func_info: Vec<BtfExtSection<BpfFuncInfo>>,
+    pub line_info: Vec<BtfExtSection<BpfLineInfo>>,
+    pub core_relos: Vec<BtfExtSection<BpfCoreRelo>>,
+}
+
+impl BtfExt {
+    pub fn parse(bytes: &[u8], btf: &Btf) -> Result<BtfExt> {
+        Self::parse_with_limits(bytes, btf, &Limits::default())
+    }
+
+    /// Same as `parse` but checks the number of records against `limits` instead of the default limits
+    pub fn parse_with_limits(bytes: &[u8], btf: &Btf, limits: &Limits) -> Result<BtfExt> {
+        let (header, endianness) = BtfExtHeader::parse(bytes)?;
+        let info = InfoContext {
+            bytes,
+            header_len: header.hdr_len,
+            endianness,
+            btf,
+            limits,
+        };
+
+        let func_info = info
+            .parse(header.func_info_off, header.func_info_len, 8, |record| {
+                Ok(BpfFuncInfo {
+                    insn_off: record.pread_with(0, endianness)?,
+                    type_id: record.pread_with(4, endianness)?,
+                })
+            })
+            .context(|| ContextFrame::new("func_info"))?;
+        let line_info = info
+            .parse(header.line_info_off, header.line_info_len, 16, |record| {
+                let line_col: u32 = record.pread_with(12, endianness)?;
+                Ok(BpfLineInfo {
+                    insn_off: record.pread_with(0, endianness)?,
+                    file_name: btf
+                        .string_at(record.pread_with(4, endianness)?)?
+                        .into_owned(),
+                    line: btf
+                        .string_at(record.pread_with(8, endianness)?)?
+                        .into_owned(),
+                    line_number: line_col >> 10,
+                    column: line_col & 0x3ff,
+                })
+            })
+            .context(|| ContextFrame::new("line_info"))?;
+        let core_relos = info
+            .parse(header.core_relo_off, header.core_relo_len, 16, |record| {
+                Ok(BpfCoreRelo {
+                    insn_off: record.pread_with(0, endianness)?,
+                    type_id: record.pread_with(4, endianness)?,
+                    access_str: btf
+                        .string_at(record.pread_with(8, endianness)?)?
+                        .into_owned(),
+                    kind: record.pread_with(12, endianness)?,
+                })
+            })
+            .context(|| ContextFrame::new("core_relo"))?;
+
+        Ok(BtfExt {
+            header,
+            func_info,
+            line_info,
+            core_relos,
+        })
+    }
+
+    /// Func info of the program section `section_name`
+    pub fn func_info_for(&self, section_name: &str) -> &[BpfFuncInfo] {
+        records_for(&self.func_info, section_name)
+    }
+
+    /// Line info of the program section `section_name`
+    pub fn line_info_for(&self, section_name: &str) -> &[BpfLineInfo] {
+        records_for(&self.line_info, section_name)
+    }
+
+    /// CO-RE relocations of the program section `section_name`
+    pub fn core_relos_for(&self, section_name: &str) -> &[BpfCoreRelo] {
+        records_for(&self.core_relos, section_name)
+    }
+}
+
+fn records_for<'b, T>(sections: &'b [BtfExtSection<T>], section_name: &str) -> &'b [T] {
+    sections
+        .iter()
+        .find(|section| section.section_name == section_name)
+        .map_or(&[], |section| section.records.as_slice())
+}
+
+struct InfoContext<'b> {
+    bytes: &'b [u8],
+    header_len: u32,
+    endianness: Endian,
+    btf: &'b Btf,
+    limits: &'b Limits,
+}
+
+impl<'b> InfoContext<'b> {
+    /// Parses a `rec_size` word followed by `btf_ext_info_sec` blocks, each a section name, a record count and the
+    /// records. Records can be larger than `min_record_size` in newer versions, the extra bytes are skipped.
+    fn parse<T>(
+        &self,
+        info_off: u32,
+        info_len: u32,
+        min_record_size: u32,
+        parse_record: impl Fn(&[u8]) -> Result<T>,
+    ) -> Result<Vec<BtfExtSection<T>>> {
+        let mut result: Vec<BtfExtSection<T>> = Vec::new();
+
+        if info_len == 0 {
+            return Ok(result);
+        }
+
+        let start = u64::from(self.header_len) + u64::from(info_off);
+        let end = start + u64::from(info_len);
+        let bytes = self
+            .bytes
+            .get(start as usize..end as usize)
+            .filter(|_| end <= self.bytes.len() as u64)
+            .ok_or_else(|| {
+                Error::Malformed(
+                    Details::new(format!(
+                        "BTF.ext subsection `{}..{}` is out of bounds for `{}` bytes",
+                        start,
+                        end,
+                        self.bytes.len()
+                    ))
+                    .structure("elf::bpf::BtfExtHeader"),
+                )
+            })?;
+
+        let offset = &mut 0;
+        let record_size: u32 = bytes.gread_with(offset, self.endianness)?;
+
+        if record_size < min_record_size {
+            return Err(Error::Malformed(
+                Details::new(format!(
+                    "BTF.ext record size `{}` is smaller than `{}`",
+                    record_size, min_record_size
+                ))
+                .structure("elf::bpf::BtfExt")
+                .field("rec_size")
+                .offset(start),
+            ));
+        }
+
+        while *offset < bytes.len() {
+            let sec_name_off: u32 = bytes.gread_with(offset, self.endianness)?;
+            let num_info: u32 = bytes.gread_with(offset, self.endianness)?;
+            let records_len = u64::from(num_info) * u64::from(record_size);
+
+            if records_len > (bytes.len() - *offset) as u64 {
+                return Err(Error::Malformed(
+                    Details::new(format!(
+                        "BTF.ext section has `{}` records of `{}` bytes, more than the `{}` bytes left",
+                        num_info,
+                        record_size,
+                        bytes.len() - *offset
+                    ))
+                    .structure("elf::bpf::BtfExtSection")
+                    .field("num_info")
+                    .offset(start + *offset as u64),
+                ));
+            }
+
+            let mut records: Vec<T> = self
+                .limits
+                .vec_with_capacity(u64::from(num_info), "BTF.ext records")?;
+            for record in
+                bytes[*offset..*offset + records_len as usize].chunks_exact(record_size as usize)
+            {
+                records.push(parse_record(record)?);
+            }
+            *offset += records_len as usize;
+
+            result.push(BtfExtSection {
+                section_name: self.btf.string_at(sec_name_off)?.into_owned(),
+                records,
+            });
+        }
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf::bpf::btf::tests::{build_btf, STRINGS, TYPES};
+
+    #[test]
+    fn parse_func_and_line_info() {
+        let strings = [STRINGS, b"xdp\0prog.c\0return 0;\0"].concat();
+        let btf = Btf::parse(&build_btf(TYPES, &strings)).unwrap();
+
+        let mut func_info: Vec<u32> = vec![8, 20, 1, 0, 4];
+        let line_info: Vec<u32> = vec![16, 20, 1, 8, 24, 31, (3 << 10) | 5];
+        func_info.extend_from_slice(&line_info);
+        let mut bytes: Vec<u8> = Vec::new();
+        bytes.extend_from_slice(&0xeb9fu16.to_le_bytes());
+        bytes.extend_from_slice(&[1, 0]);
+        for word in [32u32, 0, 20, 20, 28, 0, 0] {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        for word in func_info {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+
+        let btf_ext = BtfExt::parse(&bytes, &btf).unwrap();
+        assert_eq!(
+            btf_ext.func_info_for("xdp"),
+            &[BpfFuncInfo {
+                insn_off: 0,
+                type_id: 4
+            }]
+        );
+        let line = &btf_ext.line_info_for("xdp")[0];
+        assert_eq!(
+            (line.file_name.as_str(), line.line.as_str()),
+            ("prog.c", "return 0;")
+        );
+        assert_eq!((line.line_number, line.column), (3, 5));
+        assert!(btf_ext.core_relos.is_empty());
+    }
+}
@@ -0,0 +1,240 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::elf::bpf::{Btf, BtfKind, BtfMember, BTF_KIND_DATASEC};
+use crate::{Details, Error};
+use scroll::{Endian, Pread};
+use scroll_derive::{IOread, IOwrite, Pread, Pwrite, SizeWith};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// A legacy map definition from the `maps` section, `struct bpf_map_def`
+///
+/// Loaders like iproute2 append their own fields, the size of each definition is the size of its symbol.
+#[repr(C)]
+#[derive(Debug, PartialEq, Pread, Pwrite, IOread, IOwrite, SizeWith)]
+pub struct BpfMapDef {
+    /// `BPF_MAP_TYPE_*`
+    pub map_type: u32,
+    pub key_size: u32,
+    pub value_size: u32,
+    pub max_entries: u32,
+    pub map_flags: u32,
+}
+
+impl BpfMapDef {
+    /// Parses the definition at `offset` in the `maps` section, usually the `st_value` of the map's symbol
+    pub fn parse(bytes: &[u8], offset: usize, endianness: Endian) -> Result<BpfMapDef> {
+        Ok(bytes.pread_with::<BpfMapDef>(offset, endianness)?)
+    }
+}
+
+/// A map defined in the `.maps` section, described by the BTF of a `struct { __uint(type, ...); ... }` variable
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BtfMapDefinition {
+    /// Name of the variable
+    pub name: String,
+    /// Offset of the variable in the `.maps` section
+    pub offset: u32,
+    /// `BPF_MAP_TYPE_*`
+    pub map_type: Option<u32>,
+    /// BTF type of the key if it was given with `__type(key, ...)`
+    pub key_type_id: Option<u32>,
+    /// From `__uint(key_size, ...)` or the size of `key_type_id`
+    pub key_size: Option<u64>,
+    /// BTF type of the value if it was given with `__type(value, ...)`
+    pub value_type_id: Option<u32>,
+    /// From `__uint(value_size, ...)` or the size of `value_type_id`
+    pub value_size: Option<u64>,
+    pub max_entries: Option<u32>,
+    pub map_flags: Option<u32>,
+    /// `LIBBPF_PIN_*`
+    pub pinning: Option<u32>,
+}
+
+impl Btf {
+    /// Decodes the map definitions of the `.maps` section
+    ///
+    /// libbpf encodes integer attributes as `int (*name)[value]` and types as `type *name`, both are decoded here.
+    /// Attributes that aren't understood (like `values` of map-in-maps) are skipped.
+    pub fn map_definitions(&self) -> Result<Vec<BtfMapDefinition>> {
+        let Some(datasec_id) = self.find_by_name(".maps", BTF_KIND_DATASEC) else {
+            return Ok(Vec::new());
+        };
+        let Some(BtfKind::DataSec { variables, .. }) =
+            self.get(datasec_id).map(|btf_type| &btf_type.kind)
+        else {
+            return Ok(Vec::new());
+        };
+
+        let mut result: Vec<BtfMapDefinition> = Vec::with_capacity(variables.len());
+
+        for variable in variables {
+            let var = self.get(variable.type_id).ok_or_else(|| {
+                invalid_type(format!(
+                    "`.maps` variable type id `{}` is out of range",
+                    variable.type_id
+                ))
+            })?;
+            let BtfKind::Var { type_id, .. } = var.kind else {
+                return Err(invalid_type(format!(
+                    "`.maps` variable `{}` isn't a `BTF_KIND_VAR`",
+                    var.name
+                )));
+            };
+            let Some(BtfKind::Struct { members, .. }) = self
+                .get(self.skip_modifiers(type_id))
+                .map(|btf_type| &btf_type.kind)
+            else {
+                return Err(invalid_type(format!(
+                    "`.maps` variable `{}` isn't a struct",
+                    var.name
+                )));
+            };
+
+            let mut definition = BtfMapDefinition {
+                name: var.name.clone(),
+                offset: variable.offset,
+                ..Default::default()
+            };
+
+            for member in members {
+                match member.name.as_str() {
+                    "type" => definition.map_type = self.map_uint(member),
+                    "key_size" => definition.key_size = self.map_uint(member).map(u64::from),
+                    "value_size" => definition.value_size = self.map_uint(member).map(u64::from),
+                    "max_entries" => definition.max_entries = self.map_uint(member),
+                    "map_flags" => definition.map_flags = self.map_uint(member),
+                    "pinning" => definition.pinning = self.map_uint(member),
+                    "key" => {
+                        definition.key_type_id = self.map_type(member);
+                        definition.key_size = definition
+                            .key_type_id
+                            .and_then(|type_id| self.resolve_size(type_id));
+                    }
+                    "value" => {
+                        definition.value_type_id = self.map_type(member);
+                        definition.value_size = definition
+                            .value_type_id
+                            .and_then(|type_id| self.resolve_size(type_id));
+                    }
+                    _ => {}
+                }
+            }
+
+            result.push(definition);
+        }
+
+        Ok(result)
+    }
+
+    /// `__uint(name, value)` is `int (*name)[value]`
+    fn map_uint(&self, member: &BtfMember) -> Option<u32> {
+        let BtfKind::Ptr { type_id } = self.get(self.skip_modifiers(member.type_id))?.kind else {
+            return None;
+        };
+
+        match self.get(self.skip_modifiers(type_id))?.kind {
+            BtfKind::Array { nelems, .. } => Some(nelems),
+            _ => None,
+        }
+    }
+
+    /// `__type(name, type)` is `type *name`
+    fn map_type(&self, member: &BtfMember) -> Option<u32> {
+        match self.get(self.skip_modifiers(member.type_id))?.kind {
+            BtfKind::Ptr { type_id } => Some(type_id),
+            _ => None,
+        }
+    }
+}
+
+fn invalid_type(message: String) -> Error {
+    Error::Malformed(Details::new(message).structure("elf::bpf::BtfMapDefinition"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf::bpf::btf::tests::{build_btf, STRINGS, TYPES};
+    use crate::elf::bpf::{
+        BPF_MAP_TYPE_HASH, BTF_KIND_ARRAY, BTF_KIND_PTR, BTF_KIND_STRUCT, BTF_KIND_VAR,
+    };
+
+    #[test]
+    fn decode_btf_map_definitions() {
+        // 20 "type", 25 "max_entries", 37 "key", 41 "value", 47 "events", 54 ".maps"
+        let strings = [STRINGS, b"type\0max_entries\0key\0value\0events\0.maps\0"].concat();
+        let mut types: Vec<&[u32]> = TYPES.to_vec();
+        let maps_types: [&[u32]; 8] = [
+            // 5: `int[BPF_MAP_TYPE_HASH]`, 6: `int (*)[BPF_MAP_TYPE_HASH]`, 7: `int[1024]`, 8: `int (*)[1024]`
+            &[0, BTF_KIND_ARRAY << 24, 0, 1, 1, BPF_MAP_TYPE_HASH],
+            &[0, BTF_KIND_PTR << 24, 5],
+            &[0, BTF_KIND_ARRAY << 24, 0, 1, 1, 1024],
+            &[0, BTF_KIND_PTR << 24, 7],
+            // 9: `int *`, 10: `struct pair *`, 11: the anonymous map struct, 12: the `events` variable
+            &[0, BTF_KIND_PTR << 24, 1],
+            &[0, BTF_KIND_PTR << 24, 4],
+            &[
+                0,
+                (BTF_KIND_STRUCT << 24) | 4,
+                32,
+                20,
+                6,
+                0,
+                25,
+                8,
+                64,
+                37,
+                9,
+                128,
+                41,
+                10,
+                192,
+            ],
+            &[47, BTF_KIND_VAR << 24, 11, 1],
+        ];
+        types.extend_from_slice(&maps_types);
+        let datasec = [54, (BTF_KIND_DATASEC << 24) | 1, 32, 12, 0, 32];
+        types.push(&datasec);
+
+        let btf = Btf::parse(&build_btf(&types, &strings)).unwrap();
+        let definitions = btf.map_definitions().unwrap();
+
+        assert_eq!(
+            definitions,
+            vec![BtfMapDefinition {
+                name: "events".to_string(),
+                offset: 0,
+                map_type: Some(BPF_MAP_TYPE_HASH),
+                key_type_id: Some(1),
+                key_size: Some(4),
+                value_type_id: Some(4),
+                value_size: Some(20),
+                max_entries: Some(1024),
+                ..Default::default()
+            }]
+        );
+
+        let legacy = [1u32, 4, 8, 16, 0]
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect::<Vec<u8>>();
+        assert_eq!(
+            BpfMapDef::parse(&legacy, 0, scroll::LE).unwrap().value_size,
+            8
+        );
+    }
+}
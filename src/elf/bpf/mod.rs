@@ -16,6 +16,13 @@
 
 use crate::stringable_consts_blocks::option_stringable_consts_block;
 
+mod btf;
+pub use btf::*;
+mod btf_ext;
+pub use btf_ext::*;
+mod maps;
+pub use maps::*;
+
 // Relocations
 option_stringable_consts_block! {
     const stringable: u32 {
@@ -31,3 +38,112 @@ option_stringable_consts_block! {
 
     pub fn r_to_str(value: u32) -> Option<&'static str>;
 }
+
+/// Magic number of `.BTF` and `.BTF.ext`, read in the file's byte order
+pub const BTF_MAGIC: u16 = 0xeb9f;
+
+// BTF type kinds
+option_stringable_consts_block! {
+    const stringable: u32 {
+        pub BTF_KIND_UNKN = 0;
+        pub BTF_KIND_INT = 1;
+        pub BTF_KIND_PTR = 2;
+        pub BTF_KIND_ARRAY = 3;
+        pub BTF_KIND_STRUCT = 4;
+        pub BTF_KIND_UNION = 5;
+        pub BTF_KIND_ENUM = 6;
+        pub BTF_KIND_FWD = 7;
+        pub BTF_KIND_TYPEDEF = 8;
+        pub BTF_KIND_VOLATILE = 9;
+        pub BTF_KIND_CONST = 10;
+        pub BTF_KIND_RESTRICT = 11;
+        pub BTF_KIND_FUNC = 12;
+        pub BTF_KIND_FUNC_PROTO = 13;
+        pub BTF_KIND_VAR = 14;
+        pub BTF_KIND_DATASEC = 15;
+        pub BTF_KIND_FLOAT = 16;
+        pub BTF_KIND_DECL_TAG = 17;
+        pub BTF_KIND_TYPE_TAG = 18;
+        pub BTF_KIND_ENUM64 = 19;
+    }
+
+    const ignore: u32 {
+        // BTF_KIND_INT encodings, these are flags
+        pub BTF_INT_SIGNED = 1 << 0;
+        pub BTF_INT_CHAR = 1 << 1;
+        pub BTF_INT_BOOL = 1 << 2;
+
+        // BTF_KIND_FUNC and BTF_KIND_VAR linkage
+        pub BTF_LINKAGE_STATIC = 0;
+        pub BTF_LINKAGE_GLOBAL = 1;
+        pub BTF_LINKAGE_EXTERN = 2;
+    }
+
+    pub fn btf_kind_to_str(value: u32) -> Option<&'static str>;
+}
+
+// CO-RE relocation kinds used in `.BTF.ext`
+option_stringable_consts_block! {
+    const stringable: u32 {
+        pub BPF_CORE_FIELD_BYTE_OFFSET = 0;
+        pub BPF_CORE_FIELD_BYTE_SIZE = 1;
+        pub BPF_CORE_FIELD_EXISTS = 2;
+        pub BPF_CORE_FIELD_SIGNED = 3;
+        pub BPF_CORE_FIELD_LSHIFT_U64 = 4;
+        pub BPF_CORE_FIELD_RSHIFT_U64 = 5;
+        pub BPF_CORE_TYPE_ID_LOCAL = 6;
+        pub BPF_CORE_TYPE_ID_TARGET = 7;
+        pub BPF_CORE_TYPE_EXISTS = 8;
+        pub BPF_CORE_TYPE_SIZE = 9;
+        pub BPF_CORE_ENUMVAL_EXISTS = 10;
+        pub BPF_CORE_ENUMVAL_VALUE = 11;
+        pub BPF_CORE_TYPE_MATCHES = 12;
+    }
+
+    const ignore: u32 {}
+
+    pub fn bpf_core_to_str(value: u32) -> Option<&'static str>;
+}
+
+// Map types
+option_stringable_consts_block! {
+    const stringable: u32 {
+        pub BPF_MAP_TYPE_UNSPEC = 0;
+        pub BPF_MAP_TYPE_HASH = 1;
+        pub BPF_MAP_TYPE_ARRAY = 2;
+        pub BPF_MAP_TYPE_PROG_ARRAY = 3;
+        pub BPF_MAP_TYPE_PERF_EVENT_ARRAY = 4;
+        pub BPF_MAP_TYPE_PERCPU_HASH = 5;
+        pub BPF_MAP_TYPE_PERCPU_ARRAY = 6;
+        pub BPF_MAP_TYPE_STACK_TRACE = 7;
+        pub BPF_MAP_TYPE_CGROUP_ARRAY = 8;
+        pub BPF_MAP_TYPE_LRU_HASH = 9;
+        pub BPF_MAP_TYPE_LRU_PERCPU_HASH = 10;
+        pub BPF_MAP_TYPE_LPM_TRIE = 11;
+        pub BPF_MAP_TYPE_ARRAY_OF_MAPS = 12;
+        pub BPF_MAP_TYPE_HASH_OF_MAPS = 13;
+        pub BPF_MAP_TYPE_DEVMAP = 14;
+        pub BPF_MAP_TYPE_SOCKMAP = 15;
+        pub BPF_MAP_TYPE_CPUMAP = 16;
+        pub BPF_MAP_TYPE_XSKMAP = 17;
+        pub BPF_MAP_TYPE_SOCKHASH = 18;
+        pub BPF_MAP_TYPE_CGROUP_STORAGE = 19;
+        pub BPF_MAP_TYPE_REUSEPORT_SOCKARRAY = 20;
+        pub BPF_MAP_TYPE_PERCPU_CGROUP_STORAGE = 21;
+        pub BPF_MAP_TYPE_QUEUE = 22;
+        pub BPF_MAP_TYPE_STACK = 23;
+        pub BPF_MAP_TYPE_SK_STORAGE = 24;
+        pub BPF_MAP_TYPE_DEVMAP_HASH = 25;
+        pub BPF_MAP_TYPE_STRUCT_OPS = 26;
+        pub BPF_MAP_TYPE_RINGBUF = 27;
+        pub BPF_MAP_TYPE_INODE_STORAGE = 28;
+        pub BPF_MAP_TYPE_TASK_STORAGE = 29;
+        pub BPF_MAP_TYPE_BLOOM_FILTER = 30;
+        pub BPF_MAP_TYPE_USER_RINGBUF = 31;
+        pub BPF_MAP_TYPE_CGRP_STORAGE = 32;
+    }
+
+    const ignore: u32 {}
+
+    pub fn bpf_map_type_to_str(value: u32) -> Option<&'static str>;
+}
@@ -15,8 +15,11 @@
  */
 
 use crate::stringable_consts_blocks::stringable_consts_block;
+use crate::{Details, Error, Limits};
 use std::borrow::Cow;
 
+type Result<T> = std::result::Result<T, Error>;
+
 pub struct CompressionHeader {
     /// Compression format
     pub ch_type: u32,
@@ -67,3 +70,129 @@ stringable_consts_block! {
         }
     }
 }
+
+impl<'a> CompressedSection<'a> {
+    /// Decompresses `bytes` into the original section contents, checking the result is `ch_size` bytes long
+    ///
+    /// Only available with the `compression` feature, otherwise this always returns `Error::InvalidArguments`.
+    pub fn decompress(&self) -> Result<Vec<u8>> {
+        self.decompress_with_limits(&Limits::default())
+    }
+
+    /// Same as `decompress` but checks `ch_size` against `limits` instead of the default limits
+    pub fn decompress_with_limits(&self, limits: &Limits) -> Result<Vec<u8>> {
+        let expected_size =
+            limits.check_allocation(self.header.ch_size, "a decompressed section")?;
+
+        let result = decompress_bytes(self.header.ch_type, &self.bytes, expected_size)?;
+
+        if result.len() != expected_size {
+            Err(Error::Malformed(
+                Details::new(format!(
+                    "Compressed section decompressed to `{}` bytes but the compression header has a size of `{}`",
+                    result.len(),
+                    expected_size
+                ))
+                .structure("elf::CompressionHeader")
+                .field("ch_size"),
+            ))
+        } else {
+            Ok(result)
+        }
+    }
+}
+
+#[cfg(feature = "compression")]
+fn decompress_bytes(ch_type: u32, bytes: &[u8], expected_size: usize) -> Result<Vec<u8>> {
+    use std::io::Read;
+
+    match ch_type {
+        COMPRESS_ZLIB => {
+            miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(bytes, expected_size).map_err(
+                |error| {
+                    Error::Malformed(
+                        Details::new(format!("Invalid zlib compressed section, {}", error))
+                            .structure("elf::CompressedSection"),
+                    )
+                },
+            )
+        }
+        COMPRESS_ZSTD => {
+            let mut decoder = ruzstd::StreamingDecoder::new(bytes).map_err(|error| {
+                Error::Malformed(
+                    Details::new(format!("Invalid zstd compressed section, {}", error))
+                        .structure("elf::CompressedSection"),
+                )
+            })?;
+            let mut result: Vec<u8> = Vec::with_capacity(expected_size);
+            // Reading one byte past the expected size is enough to notice the header lied
+            decoder
+                .by_ref()
+                .take(expected_size as u64 + 1)
+                .read_to_end(&mut result)?;
+            Ok(result)
+        }
+        unknown => Err(Error::InvalidArguments(
+            Details::new(format!(
+                "Unsupported section compression type `{}` (`{}`)",
+                unknown,
+                compress_to_str(unknown)
+            ))
+            .structure("elf::CompressionHeader")
+            .field("ch_type"),
+        )),
+    }
+}
+
+#[cfg(not(feature = "compression"))]
+fn decompress_bytes(ch_type: u32, _bytes: &[u8], _expected_size: usize) -> Result<Vec<u8>> {
+    Err(Error::InvalidArguments(
+        Details::new(format!(
+            "Decompressing `{}` sections requires the `compression` feature",
+            compress_to_str(ch_type)
+        ))
+        .structure("elf::CompressionHeader")
+        .field("ch_type"),
+    ))
+}
+
+#[cfg(all(test, feature = "compression"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decompress_zlib() {
+        let original = b"exofmt exofmt exofmt exofmt exofmt".to_vec();
+        let section = CompressedSection {
+            header: CompressionHeader {
+                ch_type: COMPRESS_ZLIB,
+                ch_reserved: 0,
+                ch_size: original.len() as u64,
+                ch_addralign: 1,
+            },
+            bytes: Cow::Owned(miniz_oxide::deflate::compress_to_vec_zlib(&original, 6)),
+        };
+
+        assert_eq!(section.decompress().unwrap(), original);
+    }
+
+    #[test]
+    fn decompress_zstd() {
+        let original = b"exofmt exofmt exofmt exofmt exofmt".to_vec();
+        // Single segment frame with a one byte content size, holding one raw (stored) last block
+        let mut frame = vec![0x28, 0xb5, 0x2f, 0xfd, 0x20, original.len() as u8];
+        frame.extend_from_slice(&(((original.len() as u32) << 3) | 1).to_le_bytes()[..3]);
+        frame.extend_from_slice(&original);
+        let section = CompressedSection {
+            header: CompressionHeader {
+                ch_type: COMPRESS_ZSTD,
+                ch_reserved: 0,
+                ch_size: original.len() as u64,
+                ch_addralign: 1,
+            },
+            bytes: Cow::Owned(frame),
+        };
+
+        assert_eq!(section.decompress().unwrap(), original);
+    }
+}
@@ -0,0 +1,340 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::elf::{
+    aarch64, amdgpu, android, arc, arm, avr, bpf, c6000, csky, freebsd, gnu, hexagon, lanai,
+    loongarch, m68k, mips, msp430, netbsd, openbsd, ppc, ppc64, riscv, s390, sparc, sunw, ve, x86,
+    x86_64, xtensa, Header, EI_OSABI, EM_386, EM_68K, EM_AARCH64, EM_AMDGPU, EM_ARC,
+    EM_ARC_COMPACT, EM_ARC_COMPACT2, EM_ARM, EM_AVR, EM_BPF, EM_CSKY, EM_HEXAGON, EM_IAMCU,
+    EM_LANAI, EM_LOONGARCH, EM_MIPS, EM_MIPS_RS3_LE, EM_MSP430, EM_PPC, EM_PPC64, EM_RISCV,
+    EM_S390, EM_SPARC, EM_SPARC32PLUS, EM_SPARCV9, EM_TI_C6000, EM_VE, EM_X86_64, EM_XTENSA,
+    ET_CORE, OSABI_FREEBSD, OSABI_GNU, OSABI_NETBSD, OSABI_NONE, OSABI_OPENBSD, OSABI_SOLARIS,
+    PT_LOPROC, SHT_LOPROC, STT_LOPROC,
+};
+
+/// Names constants whose meaning depends on the file's machine and OS ABI
+///
+/// The base `*_to_str` functions (e.g. `elf::sht_to_str`) only know the values from the generic ABI, anything in the
+/// OS or processor ranges comes back as an unknown range. `Describer` tries the `*_to_str` functions of the modules for
+/// `e_machine` and `EI_OSABI` first:
+///
+/// - Values in the processor range are looked up in the machine module, then the OS modules
+/// - Everything else is looked up in the OS modules, then the machine module
+/// - `OSABI_NONE` and `OSABI_GNU` use the `gnu` and `android` modules, Linux binaries rarely set `OSABI_GNU` even when
+///   they use GNU extensions. The BSDs fall back to `gnu` after their own module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Describer {
+    pub e_machine: u16,
+    pub osabi: u8,
+    pub e_type: u16,
+}
+
+impl Describer {
+    pub fn new(header: &Header) -> Self {
+        Describer {
+            e_machine: header.e_machine,
+            osabi: header.e_ident[EI_OSABI],
+            e_type: header.e_type,
+        }
+    }
+
+    /// Name of an `sh_type`, e.g. `SHT_AARCH64_ATTRIBUTES`
+    pub fn describe_section_type(&self, sh_type: u32) -> &'static str {
+        let machine = match self.e_machine {
+            EM_AARCH64 => aarch64::sht_to_str(sh_type),
+            EM_ARM => arm::sht_to_str(sh_type),
+            EM_CSKY => csky::sht_to_str(sh_type),
+            EM_HEXAGON => hexagon::sht_to_str(sh_type),
+            EM_MIPS | EM_MIPS_RS3_LE => mips::sht_to_str(sh_type),
+            EM_MSP430 => msp430::sht_to_str(sh_type),
+            EM_RISCV => riscv::sht_to_str(sh_type),
+            EM_X86_64 => x86_64::sht_to_str(sh_type),
+            _ => None,
+        };
+        let os = self.first_os_match(|os| match os {
+            Os::Gnu => gnu::sht_to_str(sh_type),
+            Os::Android => android::sht_to_str(sh_type),
+            Os::Sunw => sunw::sht_to_str(sh_type),
+            _ => None,
+        });
+
+        pick(sh_type >= SHT_LOPROC, machine, os).unwrap_or_else(|| crate::elf::sht_to_str(sh_type))
+    }
+
+    /// Name of a `p_type`, e.g. `PT_GNU_STACK` or `PT_ARM_EXIDX`
+    pub fn describe_program_type(&self, p_type: u32) -> &'static str {
+        let machine = match self.e_machine {
+            EM_AARCH64 => aarch64::pt_to_str(p_type),
+            EM_ARM => arm::pt_to_str(p_type),
+            EM_MIPS | EM_MIPS_RS3_LE => mips::pt_to_str(p_type),
+            EM_RISCV => riscv::pt_to_str(p_type),
+            _ => None,
+        };
+        let os = self.first_os_match(|os| match os {
+            Os::Gnu => gnu::pt_to_str(p_type),
+            Os::OpenBsd => openbsd::pt_to_str(p_type),
+            Os::Sunw => sunw::pt_to_str(p_type),
+            _ => None,
+        });
+
+        pick(p_type >= PT_LOPROC, machine, os).unwrap_or_else(|| crate::elf::pt_to_str(p_type))
+    }
+
+    /// Name of a `d_tag`, e.g. `DT_GNU_HASH` or `DT_MIPS_RLD_MAP`
+    pub fn describe_dynamic_tag(&self, d_tag: u64) -> &'static str {
+        let machine = match self.e_machine {
+            EM_AARCH64 => aarch64::dt_to_str(d_tag),
+            EM_HEXAGON => hexagon::dt_to_str(d_tag),
+            EM_MIPS | EM_MIPS_RS3_LE => mips::dt_to_str(d_tag),
+            EM_PPC => ppc::dt_to_str(d_tag),
+            EM_PPC64 => ppc64::dt_to_str(d_tag),
+            EM_RISCV => riscv::dt_to_str(d_tag),
+            _ => None,
+        };
+        let os = self.first_os_match(|os| match os {
+            Os::Gnu => gnu::dt_to_str(d_tag),
+            Os::Android => android::dt_to_str(d_tag),
+            Os::Sunw => sunw::dt_to_str(d_tag),
+            _ => None,
+        });
+
+        pick(
+            (crate::elf::DT_LOPROC..=crate::elf::DT_HIPROC).contains(&d_tag),
+            machine,
+            os,
+        )
+        .unwrap_or_else(|| crate::elf::dt_to_str(d_tag))
+    }
+
+    /// Name of a relocation type, e.g. `R_AARCH64_JUMP_SLOT`
+    ///
+    /// Relocation types are entirely machine-specific, `R_UNKNOWN` is returned for machines without a module.
+    pub fn describe_relocation_type(&self, r_type: u32) -> &'static str {
+        let name = match self.e_machine {
+            EM_386 | EM_IAMCU => x86::r_to_str(r_type),
+            EM_68K => m68k::r_to_str(r_type),
+            EM_AARCH64 => aarch64::r_to_str(r_type),
+            EM_AMDGPU => amdgpu::r_to_str(r_type),
+            EM_ARC | EM_ARC_COMPACT | EM_ARC_COMPACT2 => arc::r_to_str(r_type),
+            EM_ARM => arm::r_to_str(r_type),
+            EM_AVR => avr::r_to_str(r_type),
+            EM_BPF => bpf::r_to_str(r_type),
+            EM_CSKY => csky::r_to_str(r_type),
+            EM_HEXAGON => hexagon::r_to_str(r_type),
+            EM_LANAI => lanai::r_to_str(r_type),
+            EM_LOONGARCH => loongarch::r_to_str(r_type),
+            EM_MIPS | EM_MIPS_RS3_LE => mips::r_to_str(r_type),
+            EM_MSP430 => msp430::r_to_str(r_type),
+            EM_PPC => ppc::r_to_str(r_type),
+            EM_PPC64 => ppc64::r_to_str(r_type),
+            EM_RISCV => riscv::r_to_str(r_type),
+            EM_S390 => s390::r_to_str(r_type),
+            EM_SPARC | EM_SPARC32PLUS | EM_SPARCV9 => sparc::r_to_str(r_type),
+            EM_VE => ve::r_to_str(r_type),
+            EM_X86_64 => x86_64::r_to_str(r_type),
+            EM_XTENSA => xtensa::r_to_str(r_type),
+            _ => None,
+        };
+
+        name.unwrap_or("R_UNKNOWN")
+    }
+
+    /// Name of a symbol's `st_type`, e.g. `STT_GNU_IFUNC` or `STT_ARM_TFUNC`
+    pub fn describe_symbol_type(&self, st_type: u8) -> &'static str {
+        let machine = match self.e_machine {
+            EM_AMDGPU => amdgpu::stt_to_str(st_type),
+            EM_ARM => arm::stt_to_str(st_type),
+            _ => None,
+        };
+        let os = self.first_os_match(|os| match os {
+            Os::Gnu => gnu::stt_to_str(st_type),
+            _ => None,
+        });
+
+        pick(st_type >= STT_LOPROC, machine, os).unwrap_or_else(|| crate::elf::stt_to_str(st_type))
+    }
+
+    /// Name of a symbol's `st_bind`, e.g. `STB_GNU_UNIQUE`
+    pub fn describe_symbol_binding(&self, st_bind: u8) -> &'static str {
+        self.first_os_match(|os| match os {
+            Os::Gnu => gnu::stb_to_str(st_bind),
+            _ => None,
+        })
+        .unwrap_or_else(|| crate::elf::stb_to_str(st_bind))
+    }
+
+    /// Name of `EI_OSABI`, values from `OSABI_FIRST_ARCH` up are named by the machine module
+    pub fn describe_osabi(&self) -> &'static str {
+        let machine = match self.e_machine {
+            EM_AMDGPU => amdgpu::osabi_to_str(self.osabi),
+            EM_ARM => arm::osabi_to_str(self.osabi),
+            EM_TI_C6000 => c6000::osabi_to_str(self.osabi),
+            _ => None,
+        };
+
+        machine.unwrap_or_else(|| crate::elf::osabi_to_str(self.osabi))
+    }
+
+    /// Name of a note's `n_type`, which depends on the note's `n_name` more than on the file
+    ///
+    /// `CORE` and `LINUX` notes (and unowned notes in `ET_CORE` files) are core dump notes, some of those are
+    /// machine-specific register sets like `NT_ARM_VFP`.
+    pub fn describe_note_type(&self, n_name: &str, n_type: u32) -> &'static str {
+        let name = match n_name {
+            "GNU" => gnu::nt_to_str(n_type),
+            "Android" => android::nt_to_str(n_type),
+            // FreeBSD core and non-core note types don't overlap
+            "FreeBSD" => freebsd::nt_to_str(n_type).or_else(|| freebsd::core_nt_to_str(n_type)),
+            "NetBSD" | "PaX" => netbsd::ident_nt_to_str(n_type),
+            // Per-LWP and per-thread core notes are owned by `<name>@<id>`
+            _ if n_name.split('@').next() == Some("NetBSD-CORE") => netbsd::nt_to_str(n_type),
+            _ if n_name.split('@').next() == Some("OpenBSD") => openbsd::nt_to_str(n_type),
+            "AMD" | "AMDGPU" => amdgpu::nt_to_str(n_type),
+            "CORE" | "LINUX" => match self.e_machine {
+                EM_386 | EM_IAMCU | EM_X86_64 => x86::nt_to_str(n_type),
+                EM_AARCH64 | EM_ARM => arm::nt_to_str(n_type),
+                EM_PPC | EM_PPC64 => ppc::nt_to_str(n_type),
+                EM_S390 => s390::nt_to_str(n_type),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        name.unwrap_or_else(|| {
+            if self.e_type == ET_CORE || n_name == "CORE" || n_name == "LINUX" {
+                crate::elf::core_nt_to_str(n_type)
+            } else {
+                crate::elf::nt_to_str(n_type)
+            }
+        })
+    }
+
+    fn first_os_match(&self, lookup: impl Fn(Os) -> Option<&'static str>) -> Option<&'static str> {
+        let oses: &[Os] = match self.osabi {
+            OSABI_NONE | OSABI_GNU => &[Os::Gnu, Os::Android],
+            OSABI_FREEBSD => &[Os::FreeBsd, Os::Gnu],
+            OSABI_NETBSD => &[Os::NetBsd, Os::Gnu],
+            OSABI_OPENBSD => &[Os::OpenBsd, Os::Gnu],
+            OSABI_SOLARIS => &[Os::Sunw],
+            _ => &[],
+        };
+
+        oses.iter().find_map(|os| lookup(*os))
+    }
+}
+
+/// The OS modules `Describer` can look names up in
+#[derive(Clone, Copy)]
+enum Os {
+    Gnu,
+    Android,
+    FreeBsd,
+    NetBsd,
+    OpenBsd,
+    Sunw,
+}
+
+fn pick(
+    machine_first: bool,
+    machine: Option<&'static str>,
+    os: Option<&'static str>,
+) -> Option<&'static str> {
+    if machine_first {
+        machine.or(os)
+    } else {
+        os.or(machine)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn describer(e_machine: u16, osabi: u8) -> Describer {
+        Describer {
+            e_machine,
+            osabi,
+            e_type: crate::elf::ET_DYN,
+        }
+    }
+
+    #[test]
+    fn describe_by_machine_and_osabi() {
+        let aarch64 = describer(EM_AARCH64, OSABI_NONE);
+        assert_eq!(
+            aarch64.describe_relocation_type(aarch64::R_AARCH64_JUMP_SLOT),
+            "R_AARCH64_JUMP_SLOT"
+        );
+        assert_eq!(
+            aarch64.describe_program_type(gnu::PT_GNU_STACK),
+            "PT_GNU_STACK"
+        );
+        assert_eq!(
+            aarch64.describe_section_type(aarch64::SHT_AARCH64_MEMTAG_GLOBALS_STATIC),
+            "SHT_AARCH64_MEMTAG_GLOBALS_STATIC"
+        );
+        assert_eq!(
+            aarch64.describe_dynamic_tag(android::DT_ANDROID_RELA),
+            "DT_ANDROID_RELA"
+        );
+
+        // The same processor range value means different things on different machines
+        assert_eq!(
+            aarch64.describe_program_type(0x70000002),
+            "PT_AARCH64_MEMTAG_MTE"
+        );
+        assert_eq!(
+            describer(EM_MIPS, OSABI_NONE).describe_program_type(0x70000002),
+            "PT_MIPS_OPTIONS"
+        );
+        assert_eq!(
+            describer(0, OSABI_NONE).describe_program_type(0x70000002),
+            "PT_UNKNOWN_IN_PROC_RANGE"
+        );
+
+        let arm = describer(EM_ARM, arm::OSABI_ARM);
+        assert_eq!(arm.describe_osabi(), "OSABI_ARM");
+        assert_eq!(
+            arm.describe_symbol_type(arm::STT_ARM_TFUNC),
+            "STT_ARM_TFUNC"
+        );
+        assert_eq!(
+            arm.describe_note_type("LINUX", arm::NT_ARM_VFP),
+            "NT_ARM_VFP"
+        );
+        assert_eq!(
+            describer(EM_X86_64, OSABI_NONE).describe_relocation_type(u32::MAX),
+            "R_UNKNOWN"
+        );
+
+        let solaris = describer(EM_SPARCV9, OSABI_SOLARIS);
+        assert_eq!(
+            solaris.describe_program_type(sunw::PT_SUNW_EH_FRAME),
+            "PT_SUNW_EH_FRAME"
+        );
+        assert_eq!(
+            solaris.describe_section_type(sunw::SHT_SUNW_CAP),
+            "SHT_SUNW_CAP"
+        );
+        assert_eq!(
+            solaris.describe_note_type("GNU", gnu::NT_GNU_BUILD_ID),
+            "NT_GNU_BUILD_ID"
+        );
+        assert_eq!(
+            solaris.describe_note_type("NetBSD-CORE@3", netbsd::NT_NETBSDCORE_LWPSTATUS),
+            "NT_NETBSDCORE_LWPSTATUS"
+        );
+    }
+}
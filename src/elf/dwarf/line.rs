@@ -0,0 +1,817 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// `.debug_line` line number programs
+// Source: DWARF 5 specification, section 6.2 (versions 2 to 4 are described in their own specifications, section 6.2)
+#![allow(non_upper_case_globals)]
+
+use super::{
+    get_string_at, read_initial_length, read_offset, read_sized, DW_FORM_block, DW_FORM_data1,
+    DW_FORM_data16, DW_FORM_data2, DW_FORM_data4, DW_FORM_data8, DW_FORM_line_strp, DW_FORM_sdata,
+    DW_FORM_string, DW_FORM_strp, DW_FORM_udata, DwarfFormat,
+};
+use crate::elf::{Reader, SectionHeader, StrTab};
+use crate::leb128;
+use crate::stringable_consts_blocks::option_stringable_consts_block;
+use crate::utils::{read_block, read_cstring};
+use crate::{Details, Error};
+use scroll::{Endian, IOread};
+use std::io::Cursor;
+
+type Result<T> = std::result::Result<T, Error>;
+
+// Standard opcodes
+option_stringable_consts_block! {
+    const stringable: u8 {
+        pub DW_LNS_copy = 0x01;
+        pub DW_LNS_advance_pc = 0x02;
+        pub DW_LNS_advance_line = 0x03;
+        pub DW_LNS_set_file = 0x04;
+        pub DW_LNS_set_column = 0x05;
+        pub DW_LNS_negate_stmt = 0x06;
+        pub DW_LNS_set_basic_block = 0x07;
+        pub DW_LNS_const_add_pc = 0x08;
+        pub DW_LNS_fixed_advance_pc = 0x09;
+        pub DW_LNS_set_prologue_end = 0x0a;
+        pub DW_LNS_set_epilogue_begin = 0x0b;
+        pub DW_LNS_set_isa = 0x0c;
+    }
+
+    const ignore: u8 {}
+
+    pub fn dw_lns_to_str(value: u8) -> Option<&'static str>;
+}
+
+// Extended opcodes
+option_stringable_consts_block! {
+    const stringable: u8 {
+        pub DW_LNE_end_sequence = 0x01;
+        pub DW_LNE_set_address = 0x02;
+        /// Removed in DWARF 5
+        pub DW_LNE_define_file = 0x03;
+        pub DW_LNE_set_discriminator = 0x04;
+    }
+
+    const ignore: u8 {
+        pub DW_LNE_lo_user = 0x80;
+        pub DW_LNE_hi_user = 0xff;
+    }
+
+    pub fn dw_lne_to_str(value: u8) -> Option<&'static str>;
+}
+
+// Line number header entry content types (DWARF 5)
+option_stringable_consts_block! {
+    const stringable: u64 {
+        pub DW_LNCT_path = 0x1;
+        pub DW_LNCT_directory_index = 0x2;
+        pub DW_LNCT_timestamp = 0x3;
+        pub DW_LNCT_size = 0x4;
+        pub DW_LNCT_MD5 = 0x5;
+        /// LLVM extension, the source code of the file
+        pub DW_LNCT_LLVM_source = 0x2001;
+    }
+
+    const ignore: u64 {
+        pub DW_LNCT_lo_user = 0x2000;
+        pub DW_LNCT_hi_user = 0x3fff;
+    }
+
+    pub fn dw_lnct_to_str(value: u64) -> Option<&'static str>;
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FileEntry {
+    pub path: String,
+    /// Index into `LineProgramHeader::include_directories`, see `LineProgramHeader::get_directory`
+    pub directory_index: u64,
+    /// Modification time, `0` if unknown
+    pub modification_time: u64,
+    /// Size in bytes, `0` if unknown
+    pub size: u64,
+    pub md5: Option<[u8; 16]>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineProgramHeader {
+    /// Offset of the unit from the start of `.debug_line`, this is what `DW_AT_stmt_list` refers to
+    pub offset: u64,
+    pub format: DwarfFormat,
+    pub version: u16,
+    /// Only present in the header since DWARF 5
+    pub address_size: Option<u8>,
+    pub segment_selector_size: u8,
+    pub minimum_instruction_length: u8,
+    /// Always `1` before DWARF 4 and for non-VLIW architectures
+    pub maximum_operations_per_instruction: u8,
+    pub default_is_stmt: bool,
+    pub line_base: i8,
+    pub line_range: u8,
+    pub opcode_base: u8,
+    /// Number of `uleb128` operands of every standard opcode, the first entry is for opcode `1`
+    pub standard_opcode_lengths: Vec<u8>,
+    /// Since DWARF 5 the first entry is the compilation directory, before that the compilation directory is implied and
+    /// the first entry has the index `1`
+    pub include_directories: Vec<String>,
+    /// Since DWARF 5 the first entry is the primary source file, before that the first entry has the index `1`
+    pub files: Vec<FileEntry>,
+}
+
+impl LineProgramHeader {
+    /// Gets a file by the index used by `DW_LNS_set_file` and `LineRow::file`
+    pub fn get_file(&self, index: u64) -> Option<&FileEntry> {
+        if self.version >= 5 {
+            self.files.get(usize::try_from(index).ok()?)
+        } else {
+            self.files.get(usize::try_from(index.checked_sub(1)?).ok()?)
+        }
+    }
+
+    /// Gets a directory by a `FileEntry::directory_index`, `None` for the implied compilation directory before DWARF 5
+    pub fn get_directory(&self, index: u64) -> Option<&str> {
+        let index = if self.version >= 5 {
+            index
+        } else {
+            index.checked_sub(1)?
+        };
+
+        self.include_directories
+            .get(usize::try_from(index).ok()?)
+            .map(|directory| directory.as_str())
+    }
+
+    /// Gets the path of a file joined with its directory, relative paths before DWARF 5 are relative to the
+    /// compilation directory which is only known from `.debug_info`
+    pub fn get_file_path(&self, index: u64) -> Option<String> {
+        let file = self.get_file(index)?;
+
+        match self.get_directory(file.directory_index) {
+            Some(directory) if !file.path.starts_with('/') && !directory.is_empty() => {
+                Some(format!("{}/{}", directory.trim_end_matches('/'), file.path))
+            }
+            _ => Some(file.path.clone()),
+        }
+    }
+}
+
+/// A row of the line number matrix
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineRow {
+    pub address: u64,
+    /// Index of the operation within a VLIW instruction, always `0` for non-VLIW architectures
+    pub op_index: u64,
+    pub file: u64,
+    /// `0` when the instruction can't be attributed to any source line
+    pub line: u64,
+    /// `0` for the left edge of the line
+    pub column: u64,
+    pub is_stmt: bool,
+    pub basic_block: bool,
+    /// The address of this row is the first byte after the sequence, it doesn't describe an instruction
+    pub end_sequence: bool,
+    pub prologue_end: bool,
+    pub epilogue_begin: bool,
+    pub isa: u64,
+    pub discriminator: u64,
+}
+
+impl LineRow {
+    fn new(default_is_stmt: bool) -> LineRow {
+        LineRow {
+            address: 0,
+            op_index: 0,
+            file: 1,
+            line: 1,
+            column: 0,
+            is_stmt: default_is_stmt,
+            basic_block: false,
+            end_sequence: false,
+            prologue_end: false,
+            epilogue_begin: false,
+            isa: 0,
+            discriminator: 0,
+        }
+    }
+}
+
+/// The header and the rows produced by running the line number program of a unit
+pub struct LineProgram {
+    pub header: LineProgramHeader,
+    /// Rows in the order the program emitted them, every sequence ends with a row where `end_sequence` is set
+    pub rows: Vec<LineRow>,
+}
+
+/// A source location, see `LineProgram::find_location`
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceLocation {
+    pub path: Option<String>,
+    pub line: u64,
+    pub column: u64,
+}
+
+/// The string sections that can be referenced by a line number program header
+#[derive(Clone, Copy, Default)]
+pub struct LineStrings<'a> {
+    /// `.debug_str`, referenced by `DW_FORM_strp`
+    pub debug_str: &'a [u8],
+    /// `.debug_line_str`, referenced by `DW_FORM_line_strp` (DWARF 5)
+    pub debug_line_str: &'a [u8],
+}
+
+impl LineProgram {
+    /// Parses and runs the line number program of the unit at `offset` in `.debug_line`
+    pub fn parse(
+        bytes: &[u8],
+        offset: u64,
+        endianness: Endian,
+        strings: LineStrings,
+    ) -> Result<LineProgram> {
+        let mut cursor = Cursor::new(bytes);
+        cursor.set_position(offset);
+
+        let (format, unit_length) = read_initial_length(&mut cursor, endianness)?;
+        let unit_end = cursor.position().saturating_add(unit_length);
+
+        if unit_end > bytes.len() as u64 {
+            return Err(Error::Malformed(
+                Details::new(format!(
+                    "Line number program at offset `{}` has a length of `{}` but only `{}` bytes are available",
+                    offset,
+                    unit_length,
+                    bytes.len() as u64 - cursor.position().min(bytes.len() as u64)
+                ))
+                .structure("elf::dwarf::LineProgramHeader"),
+            ));
+        }
+
+        // Restricting the cursor to the unit makes every read past its end an error
+        let unit_bytes = &bytes[..unit_end as usize];
+        let mut cursor = Cursor::new(unit_bytes);
+        cursor.set_position(
+            offset
+                + if format == DwarfFormat::Dwarf64 {
+                    12
+                } else {
+                    4
+                },
+        );
+
+        let version = cursor.ioread_with::<u16>(endianness)?;
+
+        if !(2..=5).contains(&version) {
+            return Err(Error::InvalidVersionNumber(
+                Details::new(format!(
+                    "Expected `2` to `5` for line number program version at offset `{}`, found `{}`",
+                    offset, version
+                ))
+                .structure("elf::dwarf::LineProgramHeader")
+                .field("version"),
+            ));
+        }
+
+        let (address_size, segment_selector_size) = if version >= 5 {
+            (Some(cursor.ioread::<u8>()?), cursor.ioread::<u8>()?)
+        } else {
+            (None, 0)
+        };
+
+        let header_length = read_offset(&mut cursor, format, endianness)?;
+        let program_start = cursor.position().saturating_add(header_length);
+        let minimum_instruction_length = cursor.ioread::<u8>()?;
+        let maximum_operations_per_instruction = if version >= 4 {
+            cursor.ioread::<u8>()?
+        } else {
+            1
+        };
+        let default_is_stmt = cursor.ioread::<u8>()? != 0;
+        let line_base = cursor.ioread::<i8>()?;
+        let line_range = cursor.ioread::<u8>()?;
+        let opcode_base = cursor.ioread::<u8>()?;
+
+        if line_range == 0 {
+            return Err(Error::Malformed(
+                Details::new(format!(
+                    "Line number program at offset `{}` has a `line_range` of `0`",
+                    offset
+                ))
+                .structure("elf::dwarf::LineProgramHeader")
+                .field("line_range"),
+            ));
+        }
+
+        let mut standard_opcode_lengths: Vec<u8> =
+            Vec::with_capacity(opcode_base.saturating_sub(1) as usize);
+
+        for _ in 1..opcode_base {
+            standard_opcode_lengths.push(cursor.ioread::<u8>()?);
+        }
+
+        let mut include_directories: Vec<String> = Vec::new();
+        let mut files: Vec<FileEntry> = Vec::new();
+
+        if version >= 5 {
+            let context = EntryContext {
+                format,
+                endianness,
+                strings,
+            };
+
+            for entry in context.read_entries(&mut cursor)? {
+                include_directories.push(entry.path);
+            }
+
+            files = context.read_entries(&mut cursor)?;
+        } else {
+            loop {
+                let directory = read_cstring(&mut cursor)?;
+
+                if directory.is_empty() {
+                    break;
+                }
+
+                include_directories.push(directory);
+            }
+
+            loop {
+                let path = read_cstring(&mut cursor)?;
+
+                if path.is_empty() {
+                    break;
+                }
+
+                files.push(read_legacy_file_entry(&mut cursor, path)?);
+            }
+        }
+
+        let mut program = LineProgram {
+            header: LineProgramHeader {
+                offset,
+                format,
+                version,
+                address_size,
+                segment_selector_size,
+                minimum_instruction_length,
+                maximum_operations_per_instruction,
+                default_is_stmt,
+                line_base,
+                line_range,
+                opcode_base,
+                standard_opcode_lengths,
+                include_directories,
+                files,
+            },
+            rows: Vec::new(),
+        };
+
+        cursor.set_position(program_start);
+        program.run(&mut cursor, endianness)?;
+
+        Ok(program)
+    }
+
+    /// Advances the address and op_index by `operation_advance` operations
+    fn advance(&self, row: &mut LineRow, operation_advance: u64) {
+        let minimum_instruction_length = u64::from(self.header.minimum_instruction_length);
+        let maximum_operations_per_instruction =
+            u64::from(self.header.maximum_operations_per_instruction.max(1));
+        let operations = row.op_index.wrapping_add(operation_advance);
+
+        row.address = row.address.wrapping_add(
+            minimum_instruction_length
+                .wrapping_mul(operations / maximum_operations_per_instruction),
+        );
+        row.op_index = operations % maximum_operations_per_instruction;
+    }
+
+    fn push_row(&mut self, row: &mut LineRow) {
+        self.rows.push(row.clone());
+        row.basic_block = false;
+        row.prologue_end = false;
+        row.epilogue_begin = false;
+        row.discriminator = 0;
+    }
+
+    fn run(&mut self, cursor: &mut Cursor<&[u8]>, endianness: Endian) -> Result<()> {
+        let end = cursor.get_ref().len() as u64;
+        let mut row = LineRow::new(self.header.default_is_stmt);
+
+        while cursor.position() < end {
+            let opcode = cursor.ioread::<u8>()?;
+
+            if opcode >= self.header.opcode_base {
+                let adjusted_opcode = opcode - self.header.opcode_base;
+                self.advance(
+                    &mut row,
+                    u64::from(adjusted_opcode / self.header.line_range),
+                );
+                row.line = row.line.wrapping_add_signed(
+                    i64::from(self.header.line_base)
+                        + i64::from(adjusted_opcode % self.header.line_range),
+                );
+                self.push_row(&mut row);
+                continue;
+            }
+
+            match opcode {
+                0 => {
+                    let length: u64 = leb128::decode_uleb128(cursor)?;
+                    let instruction_end = cursor.position().saturating_add(length);
+
+                    if length == 0 || instruction_end > end {
+                        return Err(Error::Malformed(
+                            Details::new(format!(
+                                "Extended line number opcode at offset `{}` has an invalid length of `{}`",
+                                cursor.position(),
+                                length
+                            ))
+                            .structure("elf::dwarf::LineProgram"),
+                        ));
+                    }
+
+                    match cursor.ioread::<u8>()? {
+                        DW_LNE_end_sequence => {
+                            row.end_sequence = true;
+                            self.push_row(&mut row);
+                            row = LineRow::new(self.header.default_is_stmt);
+                        }
+                        DW_LNE_set_address => {
+                            row.address = read_sized(cursor, (length - 1) as u8, endianness)?;
+                            row.op_index = 0;
+                        }
+                        DW_LNE_define_file => {
+                            let path = read_cstring(cursor)?;
+                            let file = read_legacy_file_entry(cursor, path)?;
+                            self.header.files.push(file);
+                        }
+                        DW_LNE_set_discriminator => {
+                            row.discriminator = leb128::decode_uleb128(cursor)?
+                        }
+                        // Unknown and vendor opcodes can be skipped thanks to the length
+                        _ => {}
+                    }
+
+                    cursor.set_position(instruction_end);
+                }
+                DW_LNS_copy => self.push_row(&mut row),
+                DW_LNS_advance_pc => {
+                    let operation_advance: u64 = leb128::decode_uleb128(cursor)?;
+                    self.advance(&mut row, operation_advance);
+                }
+                DW_LNS_advance_line => {
+                    let line_advance: i64 = leb128::decode_sleb128(cursor)?;
+                    row.line = row.line.wrapping_add_signed(line_advance);
+                }
+                DW_LNS_set_file => row.file = leb128::decode_uleb128(cursor)?,
+                DW_LNS_set_column => row.column = leb128::decode_uleb128(cursor)?,
+                DW_LNS_negate_stmt => row.is_stmt = !row.is_stmt,
+                DW_LNS_set_basic_block => row.basic_block = true,
+                DW_LNS_const_add_pc => {
+                    let adjusted_opcode = 255 - self.header.opcode_base;
+                    self.advance(
+                        &mut row,
+                        u64::from(adjusted_opcode / self.header.line_range),
+                    );
+                }
+                DW_LNS_fixed_advance_pc => {
+                    row.address = row
+                        .address
+                        .wrapping_add(u64::from(cursor.ioread_with::<u16>(endianness)?));
+                    row.op_index = 0;
+                }
+                DW_LNS_set_prologue_end => row.prologue_end = true,
+                DW_LNS_set_epilogue_begin => row.epilogue_begin = true,
+                DW_LNS_set_isa => row.isa = leb128::decode_uleb128(cursor)?,
+                unknown => {
+                    // Standard opcodes this version doesn't know about, the header says how many operands to skip
+                    let operands = self.header.standard_opcode_lengths[(unknown - 1) as usize];
+
+                    for _ in 0..operands {
+                        leb128::decode_uleb128::<u64, _>(cursor)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Finds the row describing the instruction at `address`
+    pub fn find_row(&self, address: u64) -> Option<&LineRow> {
+        // Addresses only increase within a sequence, but sequences can be in any order
+        self.rows
+            .split_inclusive(|row| row.end_sequence)
+            .find_map(|sequence| {
+                let first = sequence.first()?;
+                let last = sequence.last()?;
+
+                if address < first.address || address >= last.address {
+                    return None;
+                }
+
+                Some(&sequence[sequence.partition_point(|row| row.address <= address) - 1])
+            })
+    }
+
+    /// Finds the file, line and column of the instruction at `address`
+    pub fn find_location(&self, address: u64) -> Option<SourceLocation> {
+        self.find_row(address).map(|row| SourceLocation {
+            path: self.header.get_file_path(row.file),
+            line: row.line,
+            column: row.column,
+        })
+    }
+}
+
+fn read_legacy_file_entry(cursor: &mut Cursor<&[u8]>, path: String) -> Result<FileEntry> {
+    Ok(FileEntry {
+        path,
+        directory_index: leb128::decode_uleb128(cursor)?,
+        modification_time: leb128::decode_uleb128(cursor)?,
+        size: leb128::decode_uleb128(cursor)?,
+        md5: None,
+    })
+}
+
+/// Reads the self-describing directory and file entries of DWARF 5
+struct EntryContext<'a> {
+    format: DwarfFormat,
+    endianness: Endian,
+    strings: LineStrings<'a>,
+}
+
+impl<'a> EntryContext<'a> {
+    fn read_entries(&self, cursor: &mut Cursor<&[u8]>) -> Result<Vec<FileEntry>> {
+        let format_count = cursor.ioread::<u8>()?;
+        let mut formats: Vec<(u64, u64)> = Vec::with_capacity(format_count as usize);
+
+        for _ in 0..format_count {
+            let content_type: u64 = leb128::decode_uleb128(cursor)?;
+            let form: u64 = leb128::decode_uleb128(cursor)?;
+            formats.push((content_type, form));
+        }
+
+        let count: u64 = leb128::decode_uleb128(cursor)?;
+        let remaining = (cursor.get_ref().len() as u64).saturating_sub(cursor.position());
+
+        // Entries with no content would be pointless, so every entry takes at least a byte
+        if count > remaining {
+            return Err(Error::TooManyArrayItems(
+                Details::new(format!(
+                    "Line number program header has `{}` entries but only `{}` bytes remain",
+                    count, remaining
+                ))
+                .structure("elf::dwarf::LineProgramHeader"),
+            ));
+        }
+
+        let mut entries: Vec<FileEntry> = Vec::with_capacity(count as usize);
+
+        for _ in 0..count {
+            let mut entry = FileEntry::default();
+
+            for (content_type, form) in &formats {
+                match *content_type {
+                    DW_LNCT_path => entry.path = self.read_string(cursor, *form)?,
+                    DW_LNCT_directory_index => {
+                        entry.directory_index = self.read_unsigned(cursor, *form)?
+                    }
+                    DW_LNCT_timestamp => {
+                        entry.modification_time = self.read_unsigned(cursor, *form)?
+                    }
+                    DW_LNCT_size => entry.size = self.read_unsigned(cursor, *form)?,
+                    DW_LNCT_MD5 => {
+                        if *form != DW_FORM_data16 {
+                            return Err(self.unsupported_form(*content_type, *form));
+                        }
+
+                        let mut md5 = [0u8; 16];
+                        md5.copy_from_slice(&read_block(cursor, 16)?);
+                        entry.md5 = Some(md5);
+                    }
+                    _ => self.skip(cursor, *content_type, *form)?,
+                }
+            }
+
+            entries.push(entry);
+        }
+
+        Ok(entries)
+    }
+
+    fn read_string(&self, cursor: &mut Cursor<&[u8]>, form: u64) -> Result<String> {
+        match form {
+            DW_FORM_string => read_cstring(cursor),
+            DW_FORM_line_strp => get_string_at(
+                self.strings.debug_line_str,
+                read_offset(cursor, self.format, self.endianness)?,
+                ".debug_line_str",
+            ),
+            DW_FORM_strp => get_string_at(
+                self.strings.debug_str,
+                read_offset(cursor, self.format, self.endianness)?,
+                ".debug_str",
+            ),
+            // `DW_FORM_strx*` needs `DW_AT_str_offsets_base` from `.debug_info`
+            unsupported => Err(self.unsupported_form(DW_LNCT_path, unsupported)),
+        }
+    }
+
+    fn read_unsigned(&self, cursor: &mut Cursor<&[u8]>, form: u64) -> Result<u64> {
+        match form {
+            DW_FORM_data1 => read_sized(cursor, 1, self.endianness),
+            DW_FORM_data2 => read_sized(cursor, 2, self.endianness),
+            DW_FORM_data4 => read_sized(cursor, 4, self.endianness),
+            DW_FORM_data8 => read_sized(cursor, 8, self.endianness),
+            DW_FORM_udata => leb128::decode_uleb128(cursor),
+            DW_FORM_block => {
+                // Timestamps and sizes can be stored as blocks, only the first 8 bytes are kept
+                let length: u64 = leb128::decode_uleb128(cursor)?;
+                let block = read_block(cursor, length)?;
+                let mut value: u64 = 0;
+
+                for (index, byte) in block.iter().take(8).enumerate() {
+                    value |= u64::from(*byte) << (8 * index);
+                }
+
+                Ok(value)
+            }
+            unsupported => Err(self.unsupported_form(DW_LNCT_directory_index, unsupported)),
+        }
+    }
+
+    fn skip(&self, cursor: &mut Cursor<&[u8]>, content_type: u64, form: u64) -> Result<()> {
+        match form {
+            DW_FORM_string | DW_FORM_line_strp | DW_FORM_strp => {
+                self.read_string(cursor, form)?;
+            }
+            DW_FORM_data1 | DW_FORM_data2 | DW_FORM_data4 | DW_FORM_data8 | DW_FORM_udata
+            | DW_FORM_block => {
+                self.read_unsigned(cursor, form)?;
+            }
+            DW_FORM_sdata => {
+                leb128::decode_sleb128::<i64, _>(cursor)?;
+            }
+            DW_FORM_data16 => {
+                read_block(cursor, 16)?;
+            }
+            unsupported => return Err(self.unsupported_form(content_type, unsupported)),
+        }
+
+        Ok(())
+    }
+
+    fn unsupported_form(&self, content_type: u64, form: u64) -> Error {
+        Error::Malformed(
+            Details::new(format!(
+                "Unsupported form `{}` (`0x{:x}`) for line number header entry content `{}`",
+                super::dw_form_to_str(form).unwrap_or("DW_FORM_unknown"),
+                form,
+                dw_lnct_to_str(content_type).unwrap_or("DW_LNCT_unknown")
+            ))
+            .structure("elf::dwarf::FileEntry"),
+        )
+    }
+}
+
+/// Every line number program of `.debug_line`
+pub struct DebugLine {
+    pub programs: Vec<LineProgram>,
+}
+
+impl DebugLine {
+    /// Parses every unit of `.debug_line` in order
+    pub fn parse(bytes: &[u8], endianness: Endian, strings: LineStrings) -> Result<DebugLine> {
+        let mut programs: Vec<LineProgram> = Vec::new();
+        let mut offset: u64 = 0;
+
+        while offset < bytes.len() as u64 {
+            let mut cursor = Cursor::new(bytes);
+            cursor.set_position(offset);
+            let (_, unit_length) = read_initial_length(&mut cursor, endianness)?;
+            let next_offset = cursor.position().saturating_add(unit_length);
+
+            programs.push(LineProgram::parse(bytes, offset, endianness, strings)?);
+            offset = next_offset;
+        }
+
+        Ok(DebugLine { programs })
+    }
+
+    /// Finds `.debug_line`, `.debug_line_str` and `.debug_str` by name and parses them, decompressing `SHF_COMPRESSED`
+    /// sections. Returns `None` if there is no `.debug_line` section.
+    pub fn read<'a, TReader: Reader<'a>>(
+        reader: &mut TReader,
+        section_headers: &[SectionHeader],
+        shstrtab: &StrTab,
+        endianness: Endian,
+    ) -> Result<Option<DebugLine>> {
+        let mut find_section = |name: &str| -> Result<Option<std::borrow::Cow<'a, [u8]>>> {
+            for section_header in section_headers {
+                if shstrtab.get_at_offset(section_header.sh_name)? == name {
+                    return Ok(Some(reader.get_decompressed_section_bytes(section_header)?));
+                }
+            }
+
+            Ok(None)
+        };
+
+        let Some(debug_line) = find_section(".debug_line")? else {
+            return Ok(None);
+        };
+        let debug_line_str = find_section(".debug_line_str")?.unwrap_or_default();
+        let debug_str = find_section(".debug_str")?.unwrap_or_default();
+
+        Ok(Some(DebugLine::parse(
+            &debug_line,
+            endianness,
+            LineStrings {
+                debug_str: &debug_str,
+                debug_line_str: &debug_line_str,
+            },
+        )?))
+    }
+
+    /// Finds the file, line and column of the instruction at `address` in any unit
+    pub fn find_location(&self, address: u64) -> Option<SourceLocation> {
+        self.programs
+            .iter()
+            .find_map(|program| program.find_location(address))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_dwarf5_line_program() {
+        let debug_line_str = b"/src\0main.c\0";
+        let mut header: Vec<u8> = vec![1, 1, 1, (-5i8) as u8, 14, 13];
+        header.extend_from_slice(&[0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1]);
+        // One directory and one file, both using `DW_FORM_line_strp` paths
+        header.extend_from_slice(&[1, DW_LNCT_path as u8, DW_FORM_line_strp as u8, 1]);
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&[2, DW_LNCT_path as u8, DW_FORM_line_strp as u8]);
+        header.extend_from_slice(&[DW_LNCT_directory_index as u8, DW_FORM_udata as u8, 1]);
+        header.extend_from_slice(&5u32.to_le_bytes());
+        header.push(0);
+
+        // `DW_LNE_set_address 0x1000`, `DW_LNS_set_file 0`, `DW_LNS_advance_line 9`, `DW_LNS_copy`,
+        // special opcode for address += 4 and line += 1, `DW_LNS_advance_pc 2`, `DW_LNE_end_sequence`
+        let mut program: Vec<u8> = vec![0, 9, DW_LNE_set_address];
+        program.extend_from_slice(&0x1000u64.to_le_bytes());
+        program.extend_from_slice(&[
+            DW_LNS_set_file,
+            0,
+            DW_LNS_advance_line,
+            9,
+            DW_LNS_copy,
+            75,
+            DW_LNS_advance_pc,
+            2,
+        ]);
+        program.extend_from_slice(&[0, 1, DW_LNE_end_sequence]);
+
+        let mut unit: Vec<u8> = vec![5, 0, 8, 0];
+        unit.extend_from_slice(&(header.len() as u32).to_le_bytes());
+        unit.extend_from_slice(&header);
+        unit.extend_from_slice(&program);
+
+        let mut debug_line: Vec<u8> = (unit.len() as u32).to_le_bytes().to_vec();
+        debug_line.extend_from_slice(&unit);
+
+        let strings = LineStrings {
+            debug_str: &[],
+            debug_line_str,
+        };
+        let debug_line = DebugLine::parse(&debug_line, scroll::LE, strings).unwrap();
+        let program = &debug_line.programs[0];
+
+        assert_eq!(program.header.include_directories, vec!["/src".to_owned()]);
+        assert_eq!(
+            program.header.get_file_path(0),
+            Some("/src/main.c".to_owned())
+        );
+        assert_eq!(program.rows.len(), 3);
+        assert!(program.rows[2].end_sequence);
+
+        let location = debug_line.find_location(0x1003).unwrap();
+        assert_eq!(location.line, 10);
+        assert_eq!(location.path, Some("/src/main.c".to_owned()));
+        assert_eq!(debug_line.find_location(0x1005).unwrap().line, 11);
+        assert!(debug_line.find_location(0x1006).is_none());
+    }
+}
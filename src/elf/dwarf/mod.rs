@@ -0,0 +1,164 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// DWARF debugging information
+//
+// Source: https://dwarfstd.org/doc/DWARF5.pdf
+#![allow(non_upper_case_globals)]
+
+mod line;
+pub use line::*;
+
+use crate::stringable_consts_blocks::option_stringable_consts_block;
+use crate::utils::read_cstring;
+use crate::{Details, Error};
+use scroll::{Endian, IOread};
+use std::io::Cursor;
+
+type Result<T> = std::result::Result<T, Error>;
+
+// Attribute forms, only the ones that can appear outside of `.debug_info` are read
+option_stringable_consts_block! {
+    const stringable: u64 {
+        pub DW_FORM_addr = 0x01;
+        pub DW_FORM_block2 = 0x03;
+        pub DW_FORM_block4 = 0x04;
+        pub DW_FORM_data2 = 0x05;
+        pub DW_FORM_data4 = 0x06;
+        pub DW_FORM_data8 = 0x07;
+        pub DW_FORM_string = 0x08;
+        pub DW_FORM_block = 0x09;
+        pub DW_FORM_block1 = 0x0a;
+        pub DW_FORM_data1 = 0x0b;
+        pub DW_FORM_flag = 0x0c;
+        pub DW_FORM_sdata = 0x0d;
+        pub DW_FORM_strp = 0x0e;
+        pub DW_FORM_udata = 0x0f;
+        pub DW_FORM_ref_addr = 0x10;
+        pub DW_FORM_ref1 = 0x11;
+        pub DW_FORM_ref2 = 0x12;
+        pub DW_FORM_ref4 = 0x13;
+        pub DW_FORM_ref8 = 0x14;
+        pub DW_FORM_ref_udata = 0x15;
+        pub DW_FORM_indirect = 0x16;
+        pub DW_FORM_sec_offset = 0x17;
+        pub DW_FORM_exprloc = 0x18;
+        pub DW_FORM_flag_present = 0x19;
+        pub DW_FORM_strx = 0x1a;
+        pub DW_FORM_addrx = 0x1b;
+        pub DW_FORM_ref_sup4 = 0x1c;
+        pub DW_FORM_strp_sup = 0x1d;
+        pub DW_FORM_data16 = 0x1e;
+        pub DW_FORM_line_strp = 0x1f;
+        pub DW_FORM_ref_sig8 = 0x20;
+        pub DW_FORM_implicit_const = 0x21;
+        pub DW_FORM_loclistx = 0x22;
+        pub DW_FORM_rnglistx = 0x23;
+        pub DW_FORM_ref_sup8 = 0x24;
+        pub DW_FORM_strx1 = 0x25;
+        pub DW_FORM_strx2 = 0x26;
+        pub DW_FORM_strx3 = 0x27;
+        pub DW_FORM_strx4 = 0x28;
+        pub DW_FORM_addrx1 = 0x29;
+        pub DW_FORM_addrx2 = 0x2a;
+        pub DW_FORM_addrx3 = 0x2b;
+        pub DW_FORM_addrx4 = 0x2c;
+    }
+
+    const ignore: u64 {}
+
+    pub fn dw_form_to_str(value: u64) -> Option<&'static str>;
+}
+
+/// Whether a unit uses the 32-bit or 64-bit DWARF format, which decides the size of section offsets
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DwarfFormat {
+    Dwarf32,
+    Dwarf64,
+}
+
+impl DwarfFormat {
+    pub fn offset_size(&self) -> u8 {
+        match self {
+            DwarfFormat::Dwarf32 => 4,
+            DwarfFormat::Dwarf64 => 8,
+        }
+    }
+}
+
+/// Reads an initial length field, returns the format and the length of the unit that follows it
+pub(crate) fn read_initial_length(
+    cursor: &mut Cursor<&[u8]>,
+    endianness: Endian,
+) -> Result<(DwarfFormat, u64)> {
+    match cursor.ioread_with::<u32>(endianness)? {
+        0xffffffff => Ok((DwarfFormat::Dwarf64, cursor.ioread_with::<u64>(endianness)?)),
+        reserved @ 0xfffffff0..=0xfffffffe => Err(Error::Malformed(
+            Details::new(format!(
+                "DWARF initial length `0x{:x}` is reserved",
+                reserved
+            ))
+            .structure("elf::dwarf::DwarfFormat"),
+        )),
+        length => Ok((DwarfFormat::Dwarf32, u64::from(length))),
+    }
+}
+
+/// Reads a section offset, 4 bytes for `DwarfFormat::Dwarf32` and 8 bytes for `DwarfFormat::Dwarf64`
+pub(crate) fn read_offset(
+    cursor: &mut Cursor<&[u8]>,
+    format: DwarfFormat,
+    endianness: Endian,
+) -> Result<u64> {
+    match format {
+        DwarfFormat::Dwarf32 => Ok(u64::from(cursor.ioread_with::<u32>(endianness)?)),
+        DwarfFormat::Dwarf64 => Ok(cursor.ioread_with::<u64>(endianness)?),
+    }
+}
+
+/// Reads an unsigned value of `size` bytes, e.g. an address
+pub(crate) fn read_sized(cursor: &mut Cursor<&[u8]>, size: u8, endianness: Endian) -> Result<u64> {
+    match size {
+        1 => Ok(u64::from(cursor.ioread::<u8>()?)),
+        2 => Ok(u64::from(cursor.ioread_with::<u16>(endianness)?)),
+        4 => Ok(u64::from(cursor.ioread_with::<u32>(endianness)?)),
+        8 => Ok(cursor.ioread_with::<u64>(endianness)?),
+        unsupported => Err(Error::Malformed(Details::new(format!(
+            "Unsupported DWARF value size of `{}` bytes",
+            unsupported
+        )))),
+    }
+}
+
+/// Gets the nul terminated string at `offset` in a string section (`.debug_str` or `.debug_line_str`)
+pub(crate) fn get_string_at(bytes: &[u8], offset: u64, section_name: &str) -> Result<String> {
+    let mut cursor = Cursor::new(bytes);
+
+    if offset >= bytes.len() as u64 {
+        return Err(Error::Malformed(
+            Details::new(format!(
+                "String offset `{}` is out of bounds for `{}` with `{}` bytes",
+                offset,
+                section_name,
+                bytes.len()
+            ))
+            .structure("elf::dwarf::LineStrings"),
+        ));
+    }
+
+    cursor.set_position(offset);
+    read_cstring(&mut cursor)
+}
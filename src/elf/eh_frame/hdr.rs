@@ -0,0 +1,156 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// `.eh_frame_hdr`, pointed to by `PT_GNU_EH_FRAME`
+//
+// Layout of the section:
+//  - `version: u8` (`1`)
+//  - `eh_frame_ptr_enc: u8`
+//  - `fde_count_enc: u8`
+//  - `table_enc: u8`
+//  - `eh_frame_ptr` encoded with `eh_frame_ptr_enc`
+//  - `fde_count` encoded with `fde_count_enc`
+//  - `fde_count` pairs of `initial_location` and `fde_address` encoded with `table_enc`, sorted by `initial_location`
+
+use super::pointer::{DW_EH_PE_omit, PointerBases, PointerContext};
+use crate::elf::ElfClass;
+use crate::{Details, Error};
+use scroll::Endian;
+use scroll::IOread;
+use std::io::Cursor;
+
+type Result<T> = std::result::Result<T, Error>;
+
+pub const EH_FRAME_HDR_VERSION: u8 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EhFrameHdrEntry {
+    /// The address of the first instruction covered by the FDE
+    pub initial_location: u64,
+    /// The address of the FDE within `.eh_frame`
+    pub fde_address: u64,
+}
+
+pub struct EhFrameHdr {
+    pub version: u8,
+    /// The address of `.eh_frame`
+    pub eh_frame_ptr: u64,
+    /// The binary search table, empty if the linker didn't emit one (`fde_count_enc` or `table_enc` is `DW_EH_PE_omit`)
+    pub table: Vec<EhFrameHdrEntry>,
+}
+
+impl EhFrameHdr {
+    /// Parses the bytes of `.eh_frame_hdr` loaded at `address`
+    pub fn parse(
+        bytes: &[u8],
+        address: u64,
+        endianness: Endian,
+        class: ElfClass,
+    ) -> Result<EhFrameHdr> {
+        let context = PointerContext {
+            section_address: address,
+            address_size: match class {
+                ElfClass::Elf32 => 4,
+                ElfClass::Elf64 => 8,
+            },
+            endianness,
+            // `DW_EH_PE_datarel` is relative to the start of `.eh_frame_hdr`
+            bases: PointerBases {
+                data: Some(address),
+                ..Default::default()
+            },
+        };
+        let mut cursor = Cursor::new(bytes);
+
+        let version = cursor.ioread::<u8>()?;
+
+        if version != EH_FRAME_HDR_VERSION {
+            return Err(Error::InvalidVersionNumber(
+                Details::new(format!(
+                    "Expected `{}` for `.eh_frame_hdr` version, found `{}`",
+                    EH_FRAME_HDR_VERSION, version
+                ))
+                .structure("elf::eh_frame::EhFrameHdr")
+                .field("version"),
+            ));
+        }
+
+        let eh_frame_ptr_encoding = cursor.ioread::<u8>()?;
+        let fde_count_encoding = cursor.ioread::<u8>()?;
+        let table_encoding = cursor.ioread::<u8>()?;
+
+        let eh_frame_ptr = context
+            .read_pointer(&mut cursor, eh_frame_ptr_encoding)?
+            .map_or(0, |pointer| pointer.address);
+        let mut table: Vec<EhFrameHdrEntry> = Vec::new();
+
+        if fde_count_encoding != DW_EH_PE_omit && table_encoding != DW_EH_PE_omit {
+            let fde_count = context
+                .read_pointer(&mut cursor, fde_count_encoding)?
+                .map_or(0, |pointer| pointer.address);
+
+            // Every entry is at least two bytes, which stops a bogus count from allocating too much
+            let remaining = (bytes.len() as u64).saturating_sub(cursor.position());
+
+            if fde_count > remaining / 2 {
+                return Err(Error::TooManyArrayItems(
+                    Details::new(format!(
+                        "`.eh_frame_hdr` has an FDE count of `{}` but only `{}` bytes remain",
+                        fde_count, remaining
+                    ))
+                    .structure("elf::eh_frame::EhFrameHdr")
+                    .field("table"),
+                ));
+            }
+
+            table.reserve(fde_count as usize);
+
+            for _ in 0..fde_count {
+                let initial_location = context
+                    .read_pointer(&mut cursor, table_encoding)?
+                    .map_or(0, |pointer| pointer.address);
+                let fde_address = context
+                    .read_pointer(&mut cursor, table_encoding)?
+                    .map_or(0, |pointer| pointer.address);
+
+                table.push(EhFrameHdrEntry {
+                    initial_location,
+                    fde_address,
+                });
+            }
+        }
+
+        Ok(EhFrameHdr {
+            version,
+            eh_frame_ptr,
+            table,
+        })
+    }
+
+    /// Binary searches the table for the entry of the FDE that may cover `address`
+    ///
+    /// The table only stores where each FDE starts, the FDE itself still needs to be checked to know if `address` is
+    /// within its range.
+    pub fn find(&self, address: u64) -> Option<&EhFrameHdrEntry> {
+        match self
+            .table
+            .partition_point(|entry| entry.initial_location <= address)
+        {
+            0 => None,
+            index => self.table.get(index - 1),
+        }
+    }
+}
@@ -0,0 +1,549 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Call frame instructions, the same instruction set is used by `.eh_frame` and `.debug_frame`
+// Source: DWARF 5 specification, section 6.4.2
+#![allow(non_upper_case_globals)]
+
+use super::pointer::PointerContext;
+use crate::leb128;
+use crate::stringable_consts_blocks::option_stringable_consts_block;
+use crate::utils::read_block;
+use crate::{Details, Error};
+use scroll::IOread;
+use std::collections::BTreeMap;
+use std::io::Cursor;
+
+type Result<T> = std::result::Result<T, Error>;
+
+// Primary opcodes, stored in the high 2 bits with the operand in the low 6 bits
+pub const DW_CFA_advance_loc: u8 = 0x40;
+pub const DW_CFA_offset: u8 = 0x80;
+pub const DW_CFA_restore: u8 = 0xc0;
+
+// Extended opcodes
+option_stringable_consts_block! {
+    const stringable: u8 {
+        pub DW_CFA_nop = 0x00;
+        pub DW_CFA_set_loc = 0x01;
+        pub DW_CFA_advance_loc1 = 0x02;
+        pub DW_CFA_advance_loc2 = 0x03;
+        pub DW_CFA_advance_loc4 = 0x04;
+        pub DW_CFA_offset_extended = 0x05;
+        pub DW_CFA_restore_extended = 0x06;
+        pub DW_CFA_undefined = 0x07;
+        pub DW_CFA_same_value = 0x08;
+        pub DW_CFA_register = 0x09;
+        pub DW_CFA_remember_state = 0x0a;
+        pub DW_CFA_restore_state = 0x0b;
+        pub DW_CFA_def_cfa = 0x0c;
+        pub DW_CFA_def_cfa_register = 0x0d;
+        pub DW_CFA_def_cfa_offset = 0x0e;
+        pub DW_CFA_def_cfa_expression = 0x0f;
+        pub DW_CFA_expression = 0x10;
+        pub DW_CFA_offset_extended_sf = 0x11;
+        pub DW_CFA_def_cfa_sf = 0x12;
+        pub DW_CFA_def_cfa_offset_sf = 0x13;
+        pub DW_CFA_val_offset = 0x14;
+        pub DW_CFA_val_offset_sf = 0x15;
+        pub DW_CFA_val_expression = 0x16;
+        /// SPARC `DW_CFA_GNU_window_save` and AArch64 `DW_CFA_AARCH64_negate_ra_state` share this value
+        pub DW_CFA_GNU_window_save = 0x2d;
+        pub DW_CFA_GNU_args_size = 0x2e;
+        pub DW_CFA_GNU_negative_offset_extended = 0x2f;
+    }
+
+    const ignore: u8 {
+        pub DW_CFA_AARCH64_negate_ra_state = 0x2d;
+        pub DW_CFA_lo_user = 0x1c;
+        pub DW_CFA_hi_user = 0x3f;
+    }
+
+    pub fn dw_cfa_to_str(value: u8) -> Option<&'static str>;
+}
+
+/// A decoded call frame instruction
+///
+/// Operands are stored as they are encoded, offsets marked as factored still need to be multiplied by the CIE's data
+/// alignment factor and `AdvanceLoc` by the code alignment factor.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CallFrameInstruction {
+    Nop,
+    SetLoc(u64),
+    /// `DW_CFA_advance_loc`, `DW_CFA_advance_loc1`, `DW_CFA_advance_loc2` and `DW_CFA_advance_loc4`
+    AdvanceLoc(u64),
+    DefCfa {
+        register: u64,
+        offset: u64,
+    },
+    DefCfaSf {
+        register: u64,
+        factored_offset: i64,
+    },
+    DefCfaRegister(u64),
+    DefCfaOffset(u64),
+    DefCfaOffsetSf(i64),
+    DefCfaExpression(Vec<u8>),
+    Undefined(u64),
+    SameValue(u64),
+    /// `DW_CFA_offset`, `DW_CFA_offset_extended`, `DW_CFA_offset_extended_sf` and `DW_CFA_GNU_negative_offset_extended`
+    Offset {
+        register: u64,
+        factored_offset: i64,
+    },
+    /// `DW_CFA_val_offset` and `DW_CFA_val_offset_sf`
+    ValOffset {
+        register: u64,
+        factored_offset: i64,
+    },
+    Register {
+        register: u64,
+        from_register: u64,
+    },
+    Expression {
+        register: u64,
+        expression: Vec<u8>,
+    },
+    ValExpression {
+        register: u64,
+        expression: Vec<u8>,
+    },
+    /// `DW_CFA_restore` and `DW_CFA_restore_extended`
+    Restore(u64),
+    RememberState,
+    RestoreState,
+    GnuArgsSize(u64),
+    /// `DW_CFA_GNU_window_save` on SPARC, `DW_CFA_AARCH64_negate_ra_state` on AArch64
+    GnuWindowSave,
+}
+
+impl CallFrameInstruction {
+    /// Decodes every instruction in `bytes`, `context` is used to read `DW_CFA_set_loc` with `address_encoding`
+    pub(crate) fn parse_all(
+        bytes: &[u8],
+        bytes_offset: u64,
+        context: &PointerContext,
+        address_encoding: u8,
+    ) -> Result<Vec<CallFrameInstruction>> {
+        let mut result: Vec<CallFrameInstruction> = Vec::new();
+        // `PointerContext` resolves `DW_EH_PE_pcrel` using the position within the section, so the instructions need to be
+        // read as if they were at their real offset
+        let context = PointerContext {
+            section_address: context.section_address.wrapping_add(bytes_offset),
+            ..*context
+        };
+        let endianness = context.endianness;
+        let mut cursor = Cursor::new(bytes);
+
+        while (cursor.position() as usize) < bytes.len() {
+            let opcode = cursor.ioread::<u8>()?;
+            let low_bits = u64::from(opcode & 0x3f);
+
+            let instruction = match opcode & 0xc0 {
+                DW_CFA_advance_loc => CallFrameInstruction::AdvanceLoc(low_bits),
+                DW_CFA_offset => CallFrameInstruction::Offset {
+                    register: low_bits,
+                    factored_offset: leb128::decode_uleb128::<u64, _>(&mut cursor)? as i64,
+                },
+                DW_CFA_restore => CallFrameInstruction::Restore(low_bits),
+                _ => match opcode {
+                    DW_CFA_nop => CallFrameInstruction::Nop,
+                    DW_CFA_set_loc => CallFrameInstruction::SetLoc(
+                        context
+                            .read_pointer(&mut cursor, address_encoding)?
+                            .map_or(0, |pointer| pointer.address),
+                    ),
+                    DW_CFA_advance_loc1 => {
+                        CallFrameInstruction::AdvanceLoc(u64::from(cursor.ioread::<u8>()?))
+                    }
+                    DW_CFA_advance_loc2 => CallFrameInstruction::AdvanceLoc(u64::from(
+                        cursor.ioread_with::<u16>(endianness)?,
+                    )),
+                    DW_CFA_advance_loc4 => CallFrameInstruction::AdvanceLoc(u64::from(
+                        cursor.ioread_with::<u32>(endianness)?,
+                    )),
+                    DW_CFA_offset_extended => CallFrameInstruction::Offset {
+                        register: leb128::decode_uleb128(&mut cursor)?,
+                        factored_offset: leb128::decode_uleb128::<u64, _>(&mut cursor)? as i64,
+                    },
+                    DW_CFA_restore_extended => {
+                        CallFrameInstruction::Restore(leb128::decode_uleb128(&mut cursor)?)
+                    }
+                    DW_CFA_undefined => {
+                        CallFrameInstruction::Undefined(leb128::decode_uleb128(&mut cursor)?)
+                    }
+                    DW_CFA_same_value => {
+                        CallFrameInstruction::SameValue(leb128::decode_uleb128(&mut cursor)?)
+                    }
+                    DW_CFA_register => CallFrameInstruction::Register {
+                        register: leb128::decode_uleb128(&mut cursor)?,
+                        from_register: leb128::decode_uleb128(&mut cursor)?,
+                    },
+                    DW_CFA_remember_state => CallFrameInstruction::RememberState,
+                    DW_CFA_restore_state => CallFrameInstruction::RestoreState,
+                    DW_CFA_def_cfa => CallFrameInstruction::DefCfa {
+                        register: leb128::decode_uleb128(&mut cursor)?,
+                        offset: leb128::decode_uleb128(&mut cursor)?,
+                    },
+                    DW_CFA_def_cfa_register => {
+                        CallFrameInstruction::DefCfaRegister(leb128::decode_uleb128(&mut cursor)?)
+                    }
+                    DW_CFA_def_cfa_offset => {
+                        CallFrameInstruction::DefCfaOffset(leb128::decode_uleb128(&mut cursor)?)
+                    }
+                    DW_CFA_def_cfa_expression => {
+                        let length: u64 = leb128::decode_uleb128(&mut cursor)?;
+                        CallFrameInstruction::DefCfaExpression(read_block(&mut cursor, length)?)
+                    }
+                    DW_CFA_expression => {
+                        let register: u64 = leb128::decode_uleb128(&mut cursor)?;
+                        let length: u64 = leb128::decode_uleb128(&mut cursor)?;
+                        CallFrameInstruction::Expression {
+                            register,
+                            expression: read_block(&mut cursor, length)?,
+                        }
+                    }
+                    DW_CFA_offset_extended_sf => CallFrameInstruction::Offset {
+                        register: leb128::decode_uleb128(&mut cursor)?,
+                        factored_offset: leb128::decode_sleb128(&mut cursor)?,
+                    },
+                    DW_CFA_def_cfa_sf => CallFrameInstruction::DefCfaSf {
+                        register: leb128::decode_uleb128(&mut cursor)?,
+                        factored_offset: leb128::decode_sleb128(&mut cursor)?,
+                    },
+                    DW_CFA_def_cfa_offset_sf => {
+                        CallFrameInstruction::DefCfaOffsetSf(leb128::decode_sleb128(&mut cursor)?)
+                    }
+                    DW_CFA_val_offset => CallFrameInstruction::ValOffset {
+                        register: leb128::decode_uleb128(&mut cursor)?,
+                        factored_offset: leb128::decode_uleb128::<u64, _>(&mut cursor)? as i64,
+                    },
+                    DW_CFA_val_offset_sf => CallFrameInstruction::ValOffset {
+                        register: leb128::decode_uleb128(&mut cursor)?,
+                        factored_offset: leb128::decode_sleb128(&mut cursor)?,
+                    },
+                    DW_CFA_val_expression => {
+                        let register: u64 = leb128::decode_uleb128(&mut cursor)?;
+                        let length: u64 = leb128::decode_uleb128(&mut cursor)?;
+                        CallFrameInstruction::ValExpression {
+                            register,
+                            expression: read_block(&mut cursor, length)?,
+                        }
+                    }
+                    DW_CFA_GNU_window_save => CallFrameInstruction::GnuWindowSave,
+                    DW_CFA_GNU_args_size => {
+                        CallFrameInstruction::GnuArgsSize(leb128::decode_uleb128(&mut cursor)?)
+                    }
+                    DW_CFA_GNU_negative_offset_extended => CallFrameInstruction::Offset {
+                        register: leb128::decode_uleb128(&mut cursor)?,
+                        factored_offset: -(leb128::decode_uleb128::<u64, _>(&mut cursor)? as i64),
+                    },
+                    unknown => {
+                        return Err(Error::Malformed(
+                            Details::new(format!(
+                                "Unknown call frame instruction `0x{:02x}` at offset `{}`",
+                                unknown,
+                                bytes_offset + cursor.position() - 1
+                            ))
+                            .structure("elf::eh_frame::CallFrameInstruction"),
+                        ))
+                    }
+                },
+            };
+
+            result.push(instruction);
+        }
+
+        Ok(result)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CfaRule {
+    /// `CFA = register + offset`
+    RegisterAndOffset { register: u64, offset: i64 },
+    /// The CFA is the result of a DWARF expression
+    Expression(Vec<u8>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RegisterRule {
+    /// The register can't be recovered
+    Undefined,
+    /// The register has the same value as in the callee
+    SameValue,
+    /// The register is saved at `CFA + offset`
+    Offset(i64),
+    /// The register's value is `CFA + offset`
+    ValOffset(i64),
+    /// The register is saved in another register
+    Register(u64),
+    /// The register is saved at the address computed by a DWARF expression
+    Expression(Vec<u8>),
+    /// The register's value is computed by a DWARF expression
+    ValExpression(Vec<u8>),
+}
+
+/// A row of the unwind table, the rules to recover the caller's frame for every address within `start_address..end_address`
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnwindRow {
+    pub start_address: u64,
+    pub end_address: u64,
+    pub cfa: CfaRule,
+    /// Registers without an entry have the architecture's default rule, usually `SameValue` or `Undefined`
+    pub registers: BTreeMap<u64, RegisterRule>,
+    /// The return address register is mangled, `DW_CFA_AARCH64_negate_ra_state` has been toggled an odd number of times
+    pub ra_state_negated: bool,
+}
+
+impl UnwindRow {
+    fn set_cfa_offset(&mut self, offset: i64) -> Result<()> {
+        match &mut self.cfa {
+            CfaRule::RegisterAndOffset {
+                offset: cfa_offset, ..
+            } => {
+                *cfa_offset = offset;
+                Ok(())
+            }
+            CfaRule::Expression(_) => Err(Error::Malformed(
+                Details::new(
+                    "Call frame instruction changes the CFA offset but the CFA is an expression",
+                )
+                .structure("elf::eh_frame::UnwindRow")
+                .field("cfa"),
+            )),
+        }
+    }
+
+    fn set_cfa_register(&mut self, register: u64) -> Result<()> {
+        match &mut self.cfa {
+            CfaRule::RegisterAndOffset {
+                register: cfa_register,
+                ..
+            } => {
+                *cfa_register = register;
+                Ok(())
+            }
+            CfaRule::Expression(_) => Err(Error::Malformed(
+                Details::new(
+                    "Call frame instruction changes the CFA register but the CFA is an expression",
+                )
+                .structure("elf::eh_frame::UnwindRow")
+                .field("cfa"),
+            )),
+        }
+    }
+}
+
+/// Executes call frame instructions to build the unwind row for an address
+pub(crate) struct CfaEvaluator {
+    pub code_alignment_factor: u64,
+    pub data_alignment_factor: i64,
+}
+
+impl CfaEvaluator {
+    /// Runs `initial_instructions` (from the CIE) then `instructions` (from the FDE) and returns the row containing
+    /// `target_address`, or `None` if `target_address` is outside of `start_address..end_address`
+    pub fn find_row(
+        &self,
+        initial_instructions: &[CallFrameInstruction],
+        instructions: &[CallFrameInstruction],
+        start_address: u64,
+        end_address: u64,
+        target_address: u64,
+    ) -> Result<Option<UnwindRow>> {
+        if target_address < start_address || target_address >= end_address {
+            return Ok(None);
+        }
+
+        let mut row = UnwindRow {
+            start_address,
+            end_address,
+            cfa: CfaRule::RegisterAndOffset {
+                register: 0,
+                offset: 0,
+            },
+            registers: BTreeMap::new(),
+            ra_state_negated: false,
+        };
+
+        // Initial instructions can't advance the location, the CIE's rules are used by `DW_CFA_restore`
+        self.execute(&mut row, initial_instructions, None, None)?;
+        let initial_row = row.clone();
+
+        Ok(self
+            .execute(
+                &mut row,
+                instructions,
+                Some(&initial_row),
+                Some(target_address),
+            )?
+            .then_some(row))
+    }
+
+    /// Returns `false` if the location advanced past `target_address` before getting to the row containing it
+    fn execute(
+        &self,
+        row: &mut UnwindRow,
+        instructions: &[CallFrameInstruction],
+        initial_row: Option<&UnwindRow>,
+        target_address: Option<u64>,
+    ) -> Result<bool> {
+        let mut state_stack: Vec<UnwindRow> = Vec::new();
+
+        for instruction in instructions {
+            match instruction {
+                CallFrameInstruction::Nop | CallFrameInstruction::GnuArgsSize(_) => {}
+                CallFrameInstruction::SetLoc(_) | CallFrameInstruction::AdvanceLoc(_) => {
+                    let next_address = match instruction {
+                        CallFrameInstruction::SetLoc(address) => *address,
+                        CallFrameInstruction::AdvanceLoc(delta) => row
+                            .start_address
+                            .wrapping_add(delta.wrapping_mul(self.code_alignment_factor)),
+                        _ => unreachable!(),
+                    };
+
+                    match target_address {
+                        Some(target_address) if next_address > target_address => {
+                            row.end_address = next_address;
+                            return Ok(true);
+                        }
+                        Some(_) => row.start_address = next_address,
+                        None => {
+                            return Err(Error::Malformed(
+                                Details::new("CIE initial instructions can't change the location")
+                                    .structure("elf::eh_frame::Cie")
+                                    .field("initial_instructions"),
+                            ))
+                        }
+                    }
+                }
+                CallFrameInstruction::DefCfa { register, offset } => {
+                    row.cfa = CfaRule::RegisterAndOffset {
+                        register: *register,
+                        offset: *offset as i64,
+                    }
+                }
+                CallFrameInstruction::DefCfaSf {
+                    register,
+                    factored_offset,
+                } => {
+                    row.cfa = CfaRule::RegisterAndOffset {
+                        register: *register,
+                        offset: factored_offset.wrapping_mul(self.data_alignment_factor),
+                    }
+                }
+                CallFrameInstruction::DefCfaRegister(register) => {
+                    row.set_cfa_register(*register)?
+                }
+                CallFrameInstruction::DefCfaOffset(offset) => row.set_cfa_offset(*offset as i64)?,
+                CallFrameInstruction::DefCfaOffsetSf(factored_offset) => {
+                    row.set_cfa_offset(factored_offset.wrapping_mul(self.data_alignment_factor))?
+                }
+                CallFrameInstruction::DefCfaExpression(expression) => {
+                    row.cfa = CfaRule::Expression(expression.clone())
+                }
+                CallFrameInstruction::Undefined(register) => {
+                    row.registers.insert(*register, RegisterRule::Undefined);
+                }
+                CallFrameInstruction::SameValue(register) => {
+                    row.registers.insert(*register, RegisterRule::SameValue);
+                }
+                CallFrameInstruction::Offset {
+                    register,
+                    factored_offset,
+                } => {
+                    row.registers.insert(
+                        *register,
+                        RegisterRule::Offset(
+                            factored_offset.wrapping_mul(self.data_alignment_factor),
+                        ),
+                    );
+                }
+                CallFrameInstruction::ValOffset {
+                    register,
+                    factored_offset,
+                } => {
+                    row.registers.insert(
+                        *register,
+                        RegisterRule::ValOffset(
+                            factored_offset.wrapping_mul(self.data_alignment_factor),
+                        ),
+                    );
+                }
+                CallFrameInstruction::Register {
+                    register,
+                    from_register,
+                } => {
+                    row.registers
+                        .insert(*register, RegisterRule::Register(*from_register));
+                }
+                CallFrameInstruction::Expression {
+                    register,
+                    expression,
+                } => {
+                    row.registers
+                        .insert(*register, RegisterRule::Expression(expression.clone()));
+                }
+                CallFrameInstruction::ValExpression {
+                    register,
+                    expression,
+                } => {
+                    row.registers
+                        .insert(*register, RegisterRule::ValExpression(expression.clone()));
+                }
+                CallFrameInstruction::Restore(register) => match initial_row {
+                    Some(initial_row) => match initial_row.registers.get(register) {
+                        Some(rule) => {
+                            row.registers.insert(*register, rule.clone());
+                        }
+                        None => {
+                            row.registers.remove(register);
+                        }
+                    },
+                    None => {
+                        return Err(Error::Malformed(
+                            Details::new(
+                                "`DW_CFA_restore` can't be used in CIE initial instructions",
+                            )
+                            .structure("elf::eh_frame::Cie")
+                            .field("initial_instructions"),
+                        ))
+                    }
+                },
+                CallFrameInstruction::RememberState => state_stack.push(row.clone()),
+                CallFrameInstruction::RestoreState => {
+                    let state = state_stack.pop().ok_or_else(|| {
+                        Error::Malformed(
+                            Details::new(
+                                "`DW_CFA_restore_state` without a matching `DW_CFA_remember_state`",
+                            )
+                            .structure("elf::eh_frame::CallFrameInstruction"),
+                        )
+                    })?;
+
+                    // The location isn't part of the remembered state
+                    row.cfa = state.cfa;
+                    row.registers = state.registers;
+                    row.ra_state_negated = state.ra_state_negated;
+                }
+                CallFrameInstruction::GnuWindowSave => row.ra_state_negated = !row.ra_state_negated,
+            }
+        }
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,560 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// `.eh_frame` and `.eh_frame_hdr` unwind information
+//
+// Sources:
+//  - https://refspecs.linuxfoundation.org/LSB_5.0.0/LSB-Core-generic/LSB-Core-generic/ehframechpt.html
+//  - DWARF 5 specification, section 6.4 (`.debug_frame`, which `.eh_frame` is based on)
+//
+// `.eh_frame` is a list of length prefixed records until the end of the section (or a zero length terminator), each
+// record being either a CIE (Common Information Entry, shared between functions) or an FDE (Frame Description Entry, one
+// per function, pointing back to its CIE).
+
+mod hdr;
+pub use hdr::*;
+mod instructions;
+pub use instructions::*;
+mod pointer;
+pub use pointer::*;
+
+use crate::elf::ElfClass;
+use crate::leb128;
+use crate::utils::read_cstring;
+use crate::{Details, Error};
+use scroll::{Endian, IOread, Pread};
+use std::collections::HashMap;
+use std::io::Cursor;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cie {
+    /// Offset of the CIE from the start of `.eh_frame`
+    pub offset: u64,
+    pub version: u8,
+    /// Augmentation string, e.g. `zR` or `zPLR`
+    pub augmentation: String,
+    pub code_alignment_factor: u64,
+    pub data_alignment_factor: i64,
+    pub return_address_register: u64,
+    /// `DW_EH_PE_*` encoding of the FDE `pc_begin` and `pc_range` fields (`R` augmentation)
+    pub fde_pointer_encoding: u8,
+    /// `DW_EH_PE_*` encoding of the FDE LSDA pointers (`L` augmentation)
+    pub lsda_encoding: Option<u8>,
+    /// The personality routine (`P` augmentation)
+    pub personality: Option<EncodedPointer>,
+    /// Frames using this CIE are signal handlers (`S` augmentation)
+    pub is_signal_frame: bool,
+    pub initial_instructions: Vec<CallFrameInstruction>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fde {
+    /// Offset of the FDE from the start of `.eh_frame`
+    pub offset: u64,
+    /// Offset of the FDE's CIE from the start of `.eh_frame`
+    pub cie_offset: u64,
+    pub pc_begin: u64,
+    pub pc_range: u64,
+    /// The language specific data area of the function, usually in `.gcc_except_table`
+    pub lsda: Option<EncodedPointer>,
+    pub instructions: Vec<CallFrameInstruction>,
+}
+
+impl Fde {
+    pub fn contains(&self, address: u64) -> bool {
+        address >= self.pc_begin && address - self.pc_begin < self.pc_range
+    }
+}
+
+pub struct EhFrame {
+    pub cies: Vec<Cie>,
+    /// FDEs in the order they appear in the section
+    pub fdes: Vec<Fde>,
+    cie_indices: HashMap<u64, usize>,
+}
+
+/// Reads the length of a CIE or FDE at `offset`, returns `None` for the zero length terminator
+///
+/// The returned tuple is the offset of the record's content and the content's length.
+fn read_record_length(
+    bytes: &[u8],
+    offset: usize,
+    endianness: Endian,
+) -> Result<Option<(usize, usize)>> {
+    let (content_offset, length) = match bytes.pread_with::<u32>(offset, endianness)? {
+        0 => return Ok(None),
+        // 64-bit DWARF, the real length follows
+        0xffffffff => (
+            offset + 12,
+            bytes.pread_with::<u64>(offset + 4, endianness)?,
+        ),
+        length => (offset + 4, u64::from(length)),
+    };
+
+    if length > (bytes.len() - content_offset.min(bytes.len())) as u64 {
+        return Err(Error::Malformed(
+            Details::new(format!(
+                "`.eh_frame` record at offset `{}` has a length of `{}` but only `{}` bytes are available",
+                offset,
+                length,
+                bytes.len() - content_offset.min(bytes.len())
+            ))
+            .structure("elf::eh_frame::EhFrame"),
+        ));
+    }
+
+    Ok(Some((content_offset, length as usize)))
+}
+
+impl EhFrame {
+    /// Parses every CIE and FDE in `bytes`, the bytes of `.eh_frame` loaded at `address`
+    ///
+    /// `bases` is only needed if the section uses `DW_EH_PE_textrel` or `DW_EH_PE_datarel` pointers, which GCC and LLVM
+    /// don't emit for any of the common targets.
+    pub fn parse(
+        bytes: &[u8],
+        address: u64,
+        endianness: Endian,
+        class: ElfClass,
+        bases: PointerBases,
+    ) -> Result<EhFrame> {
+        let context = PointerContext {
+            section_address: address,
+            address_size: match class {
+                ElfClass::Elf32 => 4,
+                ElfClass::Elf64 => 8,
+            },
+            endianness,
+            bases,
+        };
+
+        let mut cies: Vec<Cie> = Vec::new();
+        let mut cie_indices: HashMap<u64, usize> = HashMap::new();
+        // FDEs can technically appear before their CIE, so they are parsed once every CIE is known
+        let mut fde_records: Vec<(usize, usize, usize, u64)> = Vec::new();
+        let mut offset: usize = 0;
+
+        while offset < bytes.len() {
+            let Some((content_offset, length)) = read_record_length(bytes, offset, endianness)?
+            else {
+                break;
+            };
+            let id_size = if content_offset - offset == 12 { 8 } else { 4 };
+            let id = if id_size == 8 {
+                bytes.pread_with::<u64>(content_offset, endianness)?
+            } else {
+                u64::from(bytes.pread_with::<u32>(content_offset, endianness)?)
+            };
+
+            if id == 0 {
+                let cie = Self::parse_cie(
+                    bytes,
+                    offset as u64,
+                    content_offset + id_size,
+                    content_offset + length,
+                    &context,
+                )?;
+                cie_indices.insert(offset as u64, cies.len());
+                cies.push(cie);
+            } else {
+                // Unlike `.debug_frame`, the CIE pointer is relative to the CIE pointer field itself
+                let cie_offset = (content_offset as u64).checked_sub(id).ok_or_else(|| {
+                    Error::Malformed(
+                        Details::new(format!(
+                            "FDE at offset `{}` has a CIE pointer of `{}` pointing before the start of `.eh_frame`",
+                            offset, id
+                        ))
+                        .structure("elf::eh_frame::Fde")
+                        .field("cie_offset"),
+                    )
+                })?;
+                fde_records.push((
+                    offset,
+                    content_offset + id_size,
+                    content_offset + length,
+                    cie_offset,
+                ));
+            }
+
+            offset = content_offset + length;
+        }
+
+        let mut fdes: Vec<Fde> = Vec::with_capacity(fde_records.len());
+
+        for (offset, start, end, cie_offset) in fde_records {
+            let cie = cie_indices
+                .get(&cie_offset)
+                .map(|index| &cies[*index])
+                .ok_or_else(|| {
+                    Error::Malformed(
+                        Details::new(format!(
+                            "FDE at offset `{}` points to a CIE at offset `{}` which doesn't exist",
+                            offset, cie_offset
+                        ))
+                        .structure("elf::eh_frame::Fde")
+                        .field("cie_offset"),
+                    )
+                })?;
+
+            fdes.push(Self::parse_fde(
+                bytes,
+                offset as u64,
+                start,
+                end,
+                cie,
+                &context,
+            )?);
+        }
+
+        Ok(EhFrame {
+            cies,
+            fdes,
+            cie_indices,
+        })
+    }
+
+    fn parse_cie(
+        bytes: &[u8],
+        offset: u64,
+        start: usize,
+        end: usize,
+        context: &PointerContext,
+    ) -> Result<Cie> {
+        let mut cursor = Cursor::new(&bytes[..end]);
+        cursor.set_position(start as u64);
+
+        let version = cursor.ioread::<u8>()?;
+
+        if version != 1 && version != 3 {
+            return Err(Error::InvalidVersionNumber(
+                Details::new(format!(
+                    "Expected `1` or `3` for CIE version at offset `{}`, found `{}`",
+                    offset, version
+                ))
+                .structure("elf::eh_frame::Cie")
+                .field("version"),
+            ));
+        }
+
+        let augmentation = read_cstring(&mut cursor)?;
+
+        // The obsolete GCC `eh` augmentation is followed by the address of the exception table
+        if augmentation.starts_with("eh") {
+            context.read_value(&mut cursor, DW_EH_PE_absptr)?;
+        }
+
+        let code_alignment_factor: u64 = leb128::decode_uleb128(&mut cursor)?;
+        let data_alignment_factor: i64 = leb128::decode_sleb128(&mut cursor)?;
+        let return_address_register = if version == 1 {
+            u64::from(cursor.ioread::<u8>()?)
+        } else {
+            leb128::decode_uleb128(&mut cursor)?
+        };
+
+        let mut fde_pointer_encoding = DW_EH_PE_absptr;
+        let mut lsda_encoding: Option<u8> = None;
+        let mut personality: Option<EncodedPointer> = None;
+        let mut is_signal_frame = false;
+
+        if augmentation.starts_with('z') {
+            let data_length: u64 = leb128::decode_uleb128(&mut cursor)?;
+            let data_end = cursor.position().saturating_add(data_length);
+
+            for character in augmentation.chars().skip(1) {
+                match character {
+                    'L' => lsda_encoding = Some(cursor.ioread::<u8>()?),
+                    'P' => {
+                        let encoding = cursor.ioread::<u8>()?;
+                        personality = context.read_pointer(&mut cursor, encoding)?;
+                    }
+                    'R' => fde_pointer_encoding = cursor.ioread::<u8>()?,
+                    'S' => is_signal_frame = true,
+                    // `B` is the AArch64 BTI/PAuth B key, `G` is AArch64 MTE tagged frames, neither has data
+                    'B' | 'G' => {}
+                    // Anything after an unknown character can't be interpreted, but the data length allows skipping
+                    // over it
+                    _ => break,
+                }
+            }
+
+            cursor.set_position(data_end);
+        } else if !augmentation.is_empty() && augmentation != "eh" {
+            return Err(Error::Malformed(
+                Details::new(format!(
+                    "Unsupported CIE augmentation `{}` at offset `{}`",
+                    augmentation, offset
+                ))
+                .structure("elf::eh_frame::Cie")
+                .field("augmentation"),
+            ));
+        }
+
+        let instructions_start = cursor.position() as usize;
+
+        if instructions_start > end {
+            return Err(Error::Malformed(
+                Details::new(format!(
+                    "CIE augmentation data at offset `{}` extends past the end of the CIE",
+                    offset
+                ))
+                .structure("elf::eh_frame::Cie")
+                .field("augmentation"),
+            ));
+        }
+
+        let initial_instructions = CallFrameInstruction::parse_all(
+            &bytes[instructions_start..end],
+            instructions_start as u64,
+            context,
+            fde_pointer_encoding,
+        )?;
+
+        Ok(Cie {
+            offset,
+            version,
+            augmentation,
+            code_alignment_factor,
+            data_alignment_factor,
+            return_address_register,
+            fde_pointer_encoding,
+            lsda_encoding,
+            personality,
+            is_signal_frame,
+            initial_instructions,
+        })
+    }
+
+    fn parse_fde(
+        bytes: &[u8],
+        offset: u64,
+        start: usize,
+        end: usize,
+        cie: &Cie,
+        context: &PointerContext,
+    ) -> Result<Fde> {
+        let mut cursor = Cursor::new(&bytes[..end]);
+        cursor.set_position(start as u64);
+
+        let pc_begin = context
+            .read_pointer(&mut cursor, cie.fde_pointer_encoding)?
+            .map_or(0, |pointer| pointer.address);
+        let pc_range = context.read_value(&mut cursor, cie.fde_pointer_encoding)?;
+        let mut lsda: Option<EncodedPointer> = None;
+
+        if cie.augmentation.starts_with('z') {
+            let data_length: u64 = leb128::decode_uleb128(&mut cursor)?;
+            let data_end = cursor.position().saturating_add(data_length);
+
+            if let Some(lsda_encoding) = cie.lsda_encoding {
+                let context = PointerContext {
+                    bases: PointerBases {
+                        function: Some(pc_begin),
+                        ..context.bases
+                    },
+                    ..*context
+                };
+                lsda = context.read_pointer(&mut cursor, lsda_encoding)?;
+            }
+
+            cursor.set_position(data_end);
+        }
+
+        let instructions_start = cursor.position() as usize;
+
+        if instructions_start > end {
+            return Err(Error::Malformed(
+                Details::new(format!(
+                    "FDE augmentation data at offset `{}` extends past the end of the FDE",
+                    offset
+                ))
+                .structure("elf::eh_frame::Fde")
+                .field("lsda"),
+            ));
+        }
+
+        let instructions = CallFrameInstruction::parse_all(
+            &bytes[instructions_start..end],
+            instructions_start as u64,
+            context,
+            cie.fde_pointer_encoding,
+        )?;
+
+        Ok(Fde {
+            offset,
+            cie_offset: cie.offset,
+            pc_begin,
+            pc_range,
+            lsda,
+            instructions,
+        })
+    }
+
+    pub fn get_cie_at(&self, offset: u64) -> Option<&Cie> {
+        self.cie_indices
+            .get(&offset)
+            .map(|index| &self.cies[*index])
+    }
+
+    /// Gets the FDE starting at `offset` in `.eh_frame`, e.g. the `fde_address` of an `EhFrameHdrEntry` minus the address
+    /// of `.eh_frame`
+    pub fn get_fde_at(&self, offset: u64) -> Option<&Fde> {
+        self.fdes.iter().find(|fde| fde.offset == offset)
+    }
+
+    /// Finds the FDE covering `address` with a linear search, `EhFrameHdr::find` should be preferred when available
+    pub fn find_fde(&self, address: u64) -> Option<&Fde> {
+        self.fdes.iter().find(|fde| fde.contains(address))
+    }
+
+    /// Evaluates the CFA rules of the FDE covering `address` and returns the unwind row for `address`
+    pub fn find_unwind_row(&self, address: u64) -> Result<Option<UnwindRow>> {
+        match self.find_fde(address) {
+            Some(fde) => self.unwind_row(fde, address),
+            None => Ok(None),
+        }
+    }
+
+    /// Evaluates the CFA rules of `fde` and returns the unwind row for `address`, `None` if `fde` doesn't cover `address`
+    pub fn unwind_row(&self, fde: &Fde, address: u64) -> Result<Option<UnwindRow>> {
+        let cie = self.get_cie_at(fde.cie_offset).ok_or_else(|| {
+            Error::InvalidArguments(
+                Details::new(format!(
+                    "FDE at offset `{}` points to a CIE at offset `{}` which isn't part of this `.eh_frame`",
+                    fde.offset, fde.cie_offset
+                ))
+                .structure("elf::eh_frame::Fde")
+                .field("cie_offset"),
+            )
+        })?;
+
+        CfaEvaluator {
+            code_alignment_factor: cie.code_alignment_factor,
+            data_alignment_factor: cie.data_alignment_factor,
+        }
+        .find_row(
+            &cie.initial_instructions,
+            &fde.instructions,
+            fde.pc_begin,
+            fde.pc_begin.wrapping_add(fde.pc_range),
+            address,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_evaluate_eh_frame() {
+        let eh_frame_address: u64 = 0x2000;
+        let mut eh_frame: Vec<u8> = Vec::new();
+
+        // CIE: version 1, "zR", code alignment 1, data alignment -8, return address register 16,
+        // FDE pointers are `DW_EH_PE_pcrel | DW_EH_PE_sdata4`, `DW_CFA_def_cfa r7+8`, `DW_CFA_offset r16 cfa-8`
+        let cie: &[u8] = &[
+            0, 0, 0, 0, 1, b'z', b'R', 0, 1, 0x78, 16, 1, 0x1b, 0x0c, 7, 8, 0x90, 1, 0, 0,
+        ];
+        eh_frame.extend_from_slice(&(cie.len() as u32).to_le_bytes());
+        eh_frame.extend_from_slice(cie);
+
+        // FDE for `0x1000..0x1010`: `DW_CFA_advance_loc 1`, `DW_CFA_def_cfa_offset 16`, `DW_CFA_offset r6 cfa-16`,
+        // `DW_CFA_advance_loc 3`, `DW_CFA_def_cfa_register r6`
+        let fde_offset = eh_frame.len();
+        let mut fde: Vec<u8> = Vec::new();
+        fde.extend_from_slice(&((fde_offset + 4) as u32).to_le_bytes());
+        let pc_begin_address = eh_frame_address + fde_offset as u64 + 8;
+        fde.extend_from_slice(&((0x1000i64 - pc_begin_address as i64) as i32).to_le_bytes());
+        fde.extend_from_slice(&0x10u32.to_le_bytes());
+        fde.extend_from_slice(&[0, 0x41, 0x0e, 16, 0x86, 2, 0x43, 0x0d, 6, 0, 0, 0]);
+        eh_frame.extend_from_slice(&(fde.len() as u32).to_le_bytes());
+        eh_frame.extend_from_slice(&fde);
+        eh_frame.extend_from_slice(&0u32.to_le_bytes());
+
+        let parsed = EhFrame::parse(
+            &eh_frame,
+            eh_frame_address,
+            scroll::LE,
+            ElfClass::Elf64,
+            PointerBases::default(),
+        )
+        .unwrap();
+        assert_eq!(parsed.cies.len(), 1);
+        assert_eq!(parsed.cies[0].data_alignment_factor, -8);
+        assert_eq!(parsed.fdes.len(), 1);
+        assert_eq!(parsed.fdes[0].pc_begin, 0x1000);
+        assert_eq!(parsed.fdes[0].pc_range, 0x10);
+
+        let row = parsed.find_unwind_row(0x1000).unwrap().unwrap();
+        assert_eq!(
+            row.cfa,
+            CfaRule::RegisterAndOffset {
+                register: 7,
+                offset: 8
+            }
+        );
+        assert_eq!(row.end_address, 0x1001);
+        assert_eq!(row.registers.get(&16), Some(&RegisterRule::Offset(-8)));
+
+        let row = parsed.find_unwind_row(0x1002).unwrap().unwrap();
+        assert_eq!(
+            row.cfa,
+            CfaRule::RegisterAndOffset {
+                register: 7,
+                offset: 16
+            }
+        );
+        assert_eq!(row.registers.get(&6), Some(&RegisterRule::Offset(-16)));
+        assert_eq!((row.start_address, row.end_address), (0x1001, 0x1004));
+
+        let row = parsed.find_unwind_row(0x100f).unwrap().unwrap();
+        assert_eq!(
+            row.cfa,
+            CfaRule::RegisterAndOffset {
+                register: 6,
+                offset: 16
+            }
+        );
+        assert_eq!(row.end_address, 0x1010);
+        assert!(parsed.find_unwind_row(0x1010).unwrap().is_none());
+
+        // `.eh_frame_hdr` with `DW_EH_PE_pcrel | DW_EH_PE_sdata4` for `eh_frame_ptr`, `DW_EH_PE_udata4` for the count and
+        // `DW_EH_PE_datarel | DW_EH_PE_sdata4` for the table
+        let hdr_address: u64 = 0x1800;
+        let mut hdr: Vec<u8> = vec![1, 0x1b, 0x03, 0x3b];
+        hdr.extend_from_slice(&((eh_frame_address - hdr_address - 4) as i32).to_le_bytes());
+        hdr.extend_from_slice(&1u32.to_le_bytes());
+        hdr.extend_from_slice(&((0x1000i64 - hdr_address as i64) as i32).to_le_bytes());
+        hdr.extend_from_slice(
+            &((eh_frame_address + fde_offset as u64 - hdr_address) as i32).to_le_bytes(),
+        );
+
+        let hdr = EhFrameHdr::parse(&hdr, hdr_address, scroll::LE, ElfClass::Elf64).unwrap();
+        assert_eq!(hdr.eh_frame_ptr, eh_frame_address);
+
+        let entry = hdr.find(0x1008).unwrap();
+        assert_eq!(entry.initial_location, 0x1000);
+        assert!(hdr.find(0xfff).is_none());
+        assert_eq!(
+            parsed
+                .get_fde_at(entry.fde_address - hdr.eh_frame_ptr)
+                .map(|fde| fde.pc_begin),
+            Some(0x1000)
+        );
+    }
+}
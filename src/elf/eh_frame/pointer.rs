@@ -0,0 +1,198 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+#![allow(non_upper_case_globals)]
+
+use crate::leb128;
+use crate::stringable_consts_blocks::option_stringable_consts_block;
+use crate::{Details, Error};
+use scroll::{Endian, IOread};
+use std::io::{Cursor, Seek, SeekFrom};
+
+type Result<T> = std::result::Result<T, Error>;
+
+// Pointer encodings, the low 4 bits are the value format and the high 4 bits are how the value is applied
+// Source: https://refspecs.linuxfoundation.org/LSB_5.0.0/LSB-Core-generic/LSB-Core-generic/ehframechpt.html
+option_stringable_consts_block! {
+    const stringable: u8 {
+        /// Pointer sized value
+        pub DW_EH_PE_absptr = 0x00;
+        pub DW_EH_PE_uleb128 = 0x01;
+        pub DW_EH_PE_udata2 = 0x02;
+        pub DW_EH_PE_udata4 = 0x03;
+        pub DW_EH_PE_udata8 = 0x04;
+        pub DW_EH_PE_sleb128 = 0x09;
+        pub DW_EH_PE_sdata2 = 0x0a;
+        pub DW_EH_PE_sdata4 = 0x0b;
+        pub DW_EH_PE_sdata8 = 0x0c;
+    }
+
+    const ignore: u8 {}
+
+    pub fn dw_eh_pe_format_to_str(value: u8) -> Option<&'static str>;
+}
+
+option_stringable_consts_block! {
+    const stringable: u8 {
+        /// Relative to the address of the encoded value
+        pub DW_EH_PE_pcrel = 0x10;
+        /// Relative to the start of `.text`
+        pub DW_EH_PE_textrel = 0x20;
+        /// Relative to the start of the data section, for `.eh_frame_hdr` this is the start of `.eh_frame_hdr`
+        pub DW_EH_PE_datarel = 0x30;
+        /// Relative to the start of the function
+        pub DW_EH_PE_funcrel = 0x40;
+        /// Aligned to the address size
+        pub DW_EH_PE_aligned = 0x50;
+    }
+
+    const ignore: u8 {
+        /// The value is the address of the real value
+        pub DW_EH_PE_indirect = 0x80;
+        /// No value is present
+        pub DW_EH_PE_omit = 0xff;
+    }
+
+    pub fn dw_eh_pe_application_to_str(value: u8) -> Option<&'static str>;
+}
+
+/// A pointer read using a `DW_EH_PE_*` encoding
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EncodedPointer {
+    pub address: u64,
+    /// `DW_EH_PE_indirect` was set, `address` is the location of the real pointer (usually a GOT slot) rather than the
+    /// pointer itself
+    pub indirect: bool,
+}
+
+/// Base addresses used to resolve relative pointer encodings
+#[derive(Clone, Copy, Default)]
+pub struct PointerBases {
+    /// Base of `DW_EH_PE_textrel`
+    pub text: Option<u64>,
+    /// Base of `DW_EH_PE_datarel`
+    pub data: Option<u64>,
+    /// Base of `DW_EH_PE_funcrel`
+    pub function: Option<u64>,
+}
+
+/// Everything needed to read values out of a `.eh_frame` or `.eh_frame_hdr` section
+#[derive(Clone, Copy)]
+pub(crate) struct PointerContext {
+    /// The virtual address of the first byte of the section
+    pub section_address: u64,
+    pub address_size: u8,
+    pub endianness: Endian,
+    pub bases: PointerBases,
+}
+
+impl PointerContext {
+    /// Reads only the value format of `encoding` (e.g. an FDE's `pc_range`), ignoring how it is applied
+    pub fn read_value(&self, cursor: &mut Cursor<&[u8]>, encoding: u8) -> Result<u64> {
+        Ok(match encoding & 0x0f {
+            DW_EH_PE_absptr => match self.address_size {
+                4 => u64::from(cursor.ioread_with::<u32>(self.endianness)?),
+                _ => cursor.ioread_with::<u64>(self.endianness)?,
+            },
+            DW_EH_PE_uleb128 => leb128::decode_uleb128::<u64, _>(cursor)?,
+            DW_EH_PE_udata2 => u64::from(cursor.ioread_with::<u16>(self.endianness)?),
+            DW_EH_PE_udata4 => u64::from(cursor.ioread_with::<u32>(self.endianness)?),
+            DW_EH_PE_udata8 => cursor.ioread_with::<u64>(self.endianness)?,
+            DW_EH_PE_sleb128 => leb128::decode_sleb128::<i64, _>(cursor)? as u64,
+            DW_EH_PE_sdata2 => i64::from(cursor.ioread_with::<i16>(self.endianness)?) as u64,
+            DW_EH_PE_sdata4 => i64::from(cursor.ioread_with::<i32>(self.endianness)?) as u64,
+            DW_EH_PE_sdata8 => cursor.ioread_with::<i64>(self.endianness)? as u64,
+            unknown => {
+                return Err(Error::Malformed(
+                    Details::new(format!(
+                        "Unknown pointer encoding value format `0x{:x}`",
+                        unknown
+                    ))
+                    .structure("elf::eh_frame::EncodedPointer"),
+                ))
+            }
+        })
+    }
+
+    /// Reads a pointer encoded with `encoding`, returns `None` for `DW_EH_PE_omit`
+    pub fn read_pointer(
+        &self,
+        cursor: &mut Cursor<&[u8]>,
+        encoding: u8,
+    ) -> Result<Option<EncodedPointer>> {
+        if encoding == DW_EH_PE_omit {
+            return Ok(None);
+        }
+
+        let value_address = self.section_address.wrapping_add(cursor.position());
+
+        let base = match encoding & 0x70 {
+            DW_EH_PE_absptr => 0,
+            DW_EH_PE_pcrel => value_address,
+            DW_EH_PE_textrel => self.require_base(self.bases.text, "DW_EH_PE_textrel")?,
+            DW_EH_PE_datarel => self.require_base(self.bases.data, "DW_EH_PE_datarel")?,
+            DW_EH_PE_funcrel => self.require_base(self.bases.function, "DW_EH_PE_funcrel")?,
+            DW_EH_PE_aligned => {
+                let alignment = u64::from(self.address_size);
+                let misalignment = value_address % alignment;
+
+                if misalignment != 0 {
+                    cursor.seek(SeekFrom::Current((alignment - misalignment) as i64))?;
+                }
+
+                0
+            }
+            unknown => {
+                return Err(Error::Malformed(
+                    Details::new(format!(
+                        "Unknown pointer encoding application `0x{:x}`",
+                        unknown
+                    ))
+                    .structure("elf::eh_frame::EncodedPointer"),
+                ))
+            }
+        };
+
+        let value = if encoding & 0x70 == DW_EH_PE_aligned {
+            self.read_value(cursor, DW_EH_PE_absptr)?
+        } else {
+            self.read_value(cursor, encoding)?
+        };
+
+        let mut address = base.wrapping_add(value);
+
+        if self.address_size == 4 {
+            address &= 0xffffffff;
+        }
+
+        Ok(Some(EncodedPointer {
+            address,
+            indirect: encoding & DW_EH_PE_indirect != 0,
+        }))
+    }
+
+    fn require_base(&self, base: Option<u64>, name: &str) -> Result<u64> {
+        base.ok_or_else(|| {
+            Error::InvalidArguments(
+                Details::new(format!(
+                    "Pointer is encoded with `{}` but no base address was provided for it",
+                    name
+                ))
+                .structure("elf::eh_frame::PointerBases"),
+            )
+        })
+    }
+}
@@ -0,0 +1,326 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::elf::freebsd::*;
+use crate::elf::{ElfClass, Note};
+use crate::{Details, Error};
+use bitflags::bitflags;
+use scroll::{Endian, Pread};
+
+type Result<T> = std::result::Result<T, Error>;
+
+bitflags! {
+    /// The contents of a `NT_FREEBSD_FEATURE_CTL` note, features the binary opts out of (or into)
+    #[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct FreeBsdFeatureCtl: u32 {
+        const ASLR_DISABLE = NT_FREEBSD_FCTL_ASLR_DISABLE;
+        const PROTMAX_DISABLE = NT_FREEBSD_FCTL_PROTMAX_DISABLE;
+        const STKGAP_DISABLE = NT_FREEBSD_FCTL_STKGAP_DISABLE;
+        const WXNEEDED = NT_FREEBSD_FCTL_WXNEEDED;
+        const LA48 = NT_FREEBSD_FCTL_LA48;
+        const ASG_DISABLE = NT_FREEBSD_FCTL_ASG_DISABLE;
+    }
+}
+
+impl FreeBsdFeatureCtl {
+    /// Parses the descriptor of a `FreeBSD` note of type `NT_FREEBSD_FEATURE_CTL`, unknown bits are kept
+    pub fn from_note(note: &Note, endianness: Endian) -> Result<FreeBsdFeatureCtl> {
+        check_note(note, &[NT_FREEBSD_FEATURE_CTL])?;
+        Ok(FreeBsdFeatureCtl::from_bits_retain(read_u32(
+            &note.n_desc,
+            0,
+            endianness,
+            "n_desc",
+        )?))
+    }
+}
+
+/// The contents of a `NT_FREEBSD_ABI_TAG` note (`.note.tag`), the `__FreeBSD_version` the binary was built on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FreeBsdAbiTag {
+    /// e.g. `1400097` for 14.0-RELEASE
+    pub version: u32,
+}
+
+impl FreeBsdAbiTag {
+    pub fn from_note(note: &Note, endianness: Endian) -> Result<FreeBsdAbiTag> {
+        check_note(note, &[NT_FREEBSD_ABI_TAG])?;
+        Ok(FreeBsdAbiTag {
+            version: read_u32(&note.n_desc, 0, endianness, "n_desc")?,
+        })
+    }
+
+    pub fn major(&self) -> u32 {
+        self.version / 100000
+    }
+
+    pub fn minor(&self) -> u32 {
+        self.version / 1000 % 100
+    }
+}
+
+/// Parses the descriptor of a `NT_FREEBSD_ARCH_TAG` note, the `MACHINE_ARCH` the binary was built for (e.g. `amd64`)
+pub fn arch_tag_from_note(note: &Note) -> Result<String> {
+    check_note(note, &[NT_FREEBSD_ARCH_TAG])?;
+    Ok(fixed_string(&note.n_desc))
+}
+
+/// The contents of a `NT_FREEBSD_THRMISC` core note, one per thread
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FreeBsdThrMisc {
+    /// `pr_tname`, the thread name
+    pub name: String,
+}
+
+impl FreeBsdThrMisc {
+    /// Size of `pr_tname`, `MAXCOMLEN + 1`
+    const NAME_SIZE: usize = 20;
+
+    pub fn from_note(note: &Note) -> Result<FreeBsdThrMisc> {
+        check_note(note, &[NT_FREEBSD_THRMISC])?;
+        Ok(FreeBsdThrMisc {
+            name: fixed_string(&note.n_desc[..note.n_desc.len().min(Self::NAME_SIZE)]),
+        })
+    }
+}
+
+/// A `NT_FREEBSD_PROCSTAT_*` core note, each starts with the size of the structures that follow
+///
+/// The structures of `NT_FREEBSD_PROCSTAT_PROC`, `NT_FREEBSD_PROCSTAT_FILES` and `NT_FREEBSD_PROCSTAT_VMMAP` are
+/// `kinfo_*` structures that change between releases and are left as bytes, see `entries`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FreeBsdProcstat<'a> {
+    pub n_type: u32,
+    /// Size in bytes of one structure in `data`
+    pub structsize: u32,
+    pub data: &'a [u8],
+}
+
+impl<'a> FreeBsdProcstat<'a> {
+    pub fn from_note(note: &'a Note, endianness: Endian) -> Result<FreeBsdProcstat<'a>> {
+        check_note(
+            note,
+            &[
+                NT_FREEBSD_PROCSTAT_PROC,
+                NT_FREEBSD_PROCSTAT_FILES,
+                NT_FREEBSD_PROCSTAT_VMMAP,
+                NT_FREEBSD_PROCSTAT_GROUPS,
+                NT_FREEBSD_PROCSTAT_UMASK,
+                NT_FREEBSD_PROCSTAT_RLIMIT,
+                NT_FREEBSD_PROCSTAT_OSREL,
+                NT_FREEBSD_PROCSTAT_PSSTRINGS,
+                NT_FREEBSD_PROCSTAT_AUXV,
+            ],
+        )?;
+
+        Ok(FreeBsdProcstat {
+            n_type: note.n_type,
+            structsize: read_u32(&note.n_desc, 0, endianness, "structsize")?,
+            data: &note.n_desc[4..],
+        })
+    }
+
+    /// Splits `data` into `structsize` byte structures, a trailing partial structure is dropped
+    pub fn entries(&self) -> impl Iterator<Item = &'a [u8]> {
+        self.data.chunks_exact(self.structsize.max(1) as usize)
+    }
+
+    /// `NT_FREEBSD_PROCSTAT_OSREL`, the `__FreeBSD_version` of the process
+    pub fn osrel(&self, endianness: Endian) -> Result<u32> {
+        self.expect_type(NT_FREEBSD_PROCSTAT_OSREL)?;
+        read_u32(self.data, 0, endianness, "osrel")
+    }
+
+    /// `NT_FREEBSD_PROCSTAT_UMASK`
+    pub fn umask(&self, endianness: Endian) -> Result<u16> {
+        self.expect_type(NT_FREEBSD_PROCSTAT_UMASK)?;
+        self.data
+            .pread_with::<u16>(0, endianness)
+            .map_err(|_| truncated(self.data.len(), 2, "umask"))
+    }
+
+    /// `NT_FREEBSD_PROCSTAT_GROUPS`, the group ids of the process
+    pub fn groups(&self, endianness: Endian) -> Result<Vec<u32>> {
+        self.expect_type(NT_FREEBSD_PROCSTAT_GROUPS)?;
+        self.data
+            .chunks_exact(4)
+            .map(|group| read_u32(group, 0, endianness, "groups"))
+            .collect()
+    }
+
+    /// `NT_FREEBSD_PROCSTAT_PSSTRINGS`, the address of the process's `ps_strings`
+    pub fn psstrings(&self, endianness: Endian) -> Result<u64> {
+        self.expect_type(NT_FREEBSD_PROCSTAT_PSSTRINGS)?;
+        read_word(self.data, 0, self.class()?, endianness, "psstrings")
+    }
+
+    /// `NT_FREEBSD_PROCSTAT_RLIMIT`, the `(rlim_cur, rlim_max)` of every resource indexed by `RLIMIT_*`
+    pub fn rlimits(&self, endianness: Endian) -> Result<Vec<(u64, u64)>> {
+        self.expect_type(NT_FREEBSD_PROCSTAT_RLIMIT)?;
+        let class = match self.structsize {
+            8 => ElfClass::Elf32,
+            _ => ElfClass::Elf64,
+        };
+        let word_size = self.structsize as usize / 2;
+
+        self.entries()
+            .map(|rlimit| {
+                Ok((
+                    read_word(rlimit, 0, class, endianness, "rlim_cur")?,
+                    read_word(rlimit, word_size, class, endianness, "rlim_max")?,
+                ))
+            })
+            .collect()
+    }
+
+    fn class(&self) -> Result<ElfClass> {
+        match self.structsize {
+            4 => Ok(ElfClass::Elf32),
+            8 => Ok(ElfClass::Elf64),
+            unknown => Err(Error::Malformed(
+                Details::new(format!(
+                    "Expected a `structsize` of `4` or `8` but got `{}`",
+                    unknown
+                ))
+                .structure("elf::freebsd::FreeBsdProcstat")
+                .field("structsize"),
+            )),
+        }
+    }
+
+    fn expect_type(&self, n_type: u32) -> Result<()> {
+        if self.n_type != n_type {
+            return Err(Error::InvalidArguments(
+                Details::new(format!(
+                    "Expected a `{}` note but got a `{}` note",
+                    core_nt_to_str(n_type).unwrap_or("NT_UNKNOWN"),
+                    core_nt_to_str(self.n_type).unwrap_or("NT_UNKNOWN")
+                ))
+                .structure("elf::freebsd::FreeBsdProcstat")
+                .field("n_type"),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+fn check_note(note: &Note, n_types: &[u32]) -> Result<()> {
+    if note.n_name != "FreeBSD" || !n_types.contains(&note.n_type) {
+        return Err(Error::InvalidArguments(
+            Details::new(format!(
+                "Expected a `FreeBSD` note of type `{}` but got a `{}` note of type `{}`",
+                nt_to_str(n_types[0])
+                    .or_else(|| core_nt_to_str(n_types[0]))
+                    .unwrap_or("NT_UNKNOWN"),
+                note.n_name,
+                note.n_type
+            ))
+            .structure("elf::Note")
+            .field("n_type"),
+        ));
+    }
+
+    Ok(())
+}
+
+fn truncated(len: usize, expected: usize, field: &'static str) -> Error {
+    Error::Malformed(
+        Details::new(format!(
+            "FreeBSD note descriptor is `{}` bytes, expected at least `{}` for `{}`",
+            len, expected, field
+        ))
+        .structure("elf::Note")
+        .field("n_desc"),
+    )
+}
+
+fn read_u32(bytes: &[u8], offset: usize, endianness: Endian, field: &'static str) -> Result<u32> {
+    bytes
+        .pread_with::<u32>(offset, endianness)
+        .map_err(|_| truncated(bytes.len(), offset + 4, field))
+}
+
+fn read_word(
+    bytes: &[u8],
+    offset: usize,
+    class: ElfClass,
+    endianness: Endian,
+    field: &'static str,
+) -> Result<u64> {
+    match class {
+        ElfClass::Elf32 => read_u32(bytes, offset, endianness, field).map(u64::from),
+        ElfClass::Elf64 => bytes
+            .pread_with::<u64>(offset, endianness)
+            .map_err(|_| truncated(bytes.len(), offset + 8, field)),
+    }
+}
+
+fn fixed_string(bytes: &[u8]) -> String {
+    let end = bytes
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    fn note(n_type: u32, n_desc: Vec<u8>) -> Note<'static> {
+        Note {
+            n_type,
+            n_name: Cow::Borrowed("FreeBSD"),
+            n_desc: Cow::Owned(n_desc),
+        }
+    }
+
+    #[test]
+    fn parse_freebsd_notes() {
+        let abi_tag = note(NT_FREEBSD_ABI_TAG, 1400097u32.to_le_bytes().to_vec());
+        let abi_tag = FreeBsdAbiTag::from_note(&abi_tag, scroll::LE).unwrap();
+        assert_eq!((abi_tag.major(), abi_tag.minor()), (14, 0));
+
+        let feature_ctl = note(NT_FREEBSD_FEATURE_CTL, vec![0x09, 0, 0, 0]);
+        assert_eq!(
+            FreeBsdFeatureCtl::from_note(&feature_ctl, scroll::LE).unwrap(),
+            FreeBsdFeatureCtl::ASLR_DISABLE | FreeBsdFeatureCtl::WXNEEDED
+        );
+        assert!(FreeBsdAbiTag::from_note(&feature_ctl, scroll::LE).is_err());
+
+        let mut desc = 4u32.to_le_bytes().to_vec();
+        desc.extend_from_slice(&0u32.to_le_bytes());
+        desc.extend_from_slice(&5u32.to_le_bytes());
+        let groups = note(NT_FREEBSD_PROCSTAT_GROUPS, desc);
+        let procstat = FreeBsdProcstat::from_note(&groups, scroll::LE).unwrap();
+        assert_eq!(procstat.groups(scroll::LE).unwrap(), vec![0, 5]);
+        assert!(procstat.osrel(scroll::LE).is_err());
+
+        let mut desc = 16u32.to_le_bytes().to_vec();
+        desc.extend_from_slice(&8192u64.to_le_bytes());
+        desc.extend_from_slice(&u64::MAX.to_le_bytes());
+        let rlimit = note(NT_FREEBSD_PROCSTAT_RLIMIT, desc);
+        let procstat = FreeBsdProcstat::from_note(&rlimit, scroll::LE).unwrap();
+        assert_eq!(
+            procstat.rlimits(scroll::LE).unwrap(),
+            vec![(8192, u64::MAX)]
+        );
+
+        let thrmisc = note(NT_FREEBSD_THRMISC, b"worker\0\0".to_vec());
+        assert_eq!(FreeBsdThrMisc::from_note(&thrmisc).unwrap().name, "worker");
+    }
+}
@@ -0,0 +1,355 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+// Finding separate debug files, see "Separate Debug Files" in the GDB manual
+
+use crate::elf::gnu::NT_GNU_BUILD_ID;
+use crate::elf::Note;
+use crate::{Details, Error};
+use scroll::{Endian, Pread};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// The contents of a `.gnu_debuglink` section
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DebugLink {
+    /// File name of the debug file, without any directory
+    pub filename: String,
+    /// `gnu_debuglink_crc32` of the whole debug file
+    pub crc: u32,
+}
+
+impl DebugLink {
+    /// Parses a NUL terminated file name, padded to a multiple of 4 bytes, followed by the CRC in the file's byte order
+    pub fn parse(bytes: &[u8], endianness: Endian) -> Result<DebugLink> {
+        let (filename, filename_end) = read_filename(bytes, "elf::gnu::DebugLink")?;
+        let crc_offset = (filename_end + 1 + 3) & !3;
+        let crc = bytes
+            .pread_with::<u32>(crc_offset, endianness)
+            .map_err(|_| {
+                Error::Malformed(
+                    Details::new(format!(
+                        "Debug link is `{}` bytes, too small for a CRC at offset `{}`",
+                        bytes.len(),
+                        crc_offset
+                    ))
+                    .structure("elf::gnu::DebugLink")
+                    .field("crc")
+                    .offset(crc_offset as u64),
+                )
+            })?;
+
+        Ok(DebugLink { filename, crc })
+    }
+
+    /// Checks the CRC of the file at `path` matches `crc`
+    pub fn matches_file(&self, path: &Path) -> Result<bool> {
+        let mut file = File::open(path)?;
+        let mut crc = 0u32;
+        let mut buffer = [0u8; 64 * 1024];
+
+        loop {
+            let read = file.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            crc = gnu_debuglink_crc32_update(crc, &buffer[..read]);
+        }
+
+        Ok(crc == self.crc)
+    }
+}
+
+/// The contents of a `.gnu_debugaltlink` section, pointing at the supplementary file made by `dwz`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DebugAltLink {
+    /// Path of the supplementary file, usually absolute
+    pub filename: String,
+    /// Build ID of the supplementary file
+    pub build_id: Vec<u8>,
+}
+
+impl DebugAltLink {
+    /// Parses a NUL terminated file name followed by the build ID bytes
+    pub fn parse(bytes: &[u8]) -> Result<DebugAltLink> {
+        let (filename, filename_end) = read_filename(bytes, "elf::gnu::DebugAltLink")?;
+
+        Ok(DebugAltLink {
+            filename,
+            build_id: bytes[filename_end + 1..].to_vec(),
+        })
+    }
+}
+
+/// Returns the descriptor of a `GNU` note of type `NT_GNU_BUILD_ID`
+pub fn build_id_from_note<'b>(note: &'b Note) -> Result<&'b [u8]> {
+    if note.n_name != "GNU" || note.n_type != NT_GNU_BUILD_ID {
+        return Err(Error::InvalidArguments(
+            Details::new(format!(
+                "Expected a `GNU` note of type `NT_GNU_BUILD_ID` but got a `{}` note of type `{}`",
+                note.n_name, note.n_type
+            ))
+            .structure("elf::Note")
+            .field("n_type"),
+        ));
+    }
+
+    Ok(&note.n_desc)
+}
+
+/// Formats a build ID as lowercase hex, the way `file` and `readelf -n` print it
+pub fn build_id_to_hex(build_id: &[u8]) -> String {
+    build_id
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut index = 0;
+
+    while index < 256 {
+        let mut crc = index as u32;
+        let mut bit = 0;
+
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                0xedb88320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+
+        table[index] = crc;
+        index += 1;
+    }
+
+    table
+}
+
+static CRC32_TABLE: [u32; 256] = crc32_table();
+
+/// The CRC-32 (the zlib/IEEE 802.3 one) stored in `.gnu_debuglink`
+pub fn gnu_debuglink_crc32(bytes: &[u8]) -> u32 {
+    gnu_debuglink_crc32_update(0, bytes)
+}
+
+/// Continues `gnu_debuglink_crc32` with more bytes, `crc` starts at `0`
+pub fn gnu_debuglink_crc32_update(crc: u32, bytes: &[u8]) -> u32 {
+    let mut crc = !crc;
+
+    for &byte in bytes {
+        crc = CRC32_TABLE[((crc ^ u32::from(byte)) & 0xff) as usize] ^ (crc >> 8);
+    }
+
+    !crc
+}
+
+/// Finds the separate debug file of a binary the way GDB does
+///
+/// Build ID lookups (`<debug directory>/.build-id/xx/yyyy.debug`) are tried first, then the `.gnu_debuglink` name
+/// next to the binary, in its `.debug` subdirectory and under each debug directory followed by the binary's directory.
+/// Debug link candidates are only accepted if their CRC matches.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DebugFileResolver {
+    pub debug_directories: Vec<PathBuf>,
+}
+
+impl Default for DebugFileResolver {
+    fn default() -> Self {
+        DebugFileResolver {
+            debug_directories: vec![PathBuf::from("/usr/lib/debug")],
+        }
+    }
+}
+
+impl DebugFileResolver {
+    pub fn new(debug_directories: Vec<PathBuf>) -> Self {
+        DebugFileResolver { debug_directories }
+    }
+
+    /// Returns `<debug directory>/.build-id/xx/yyyy<suffix>` for each debug directory, `suffix` is `.debug` for debug
+    /// files and empty for the binaries themselves
+    pub fn build_id_paths(&self, build_id: &[u8], suffix: &str) -> Vec<PathBuf> {
+        let Some((first, rest)) = build_id.split_first() else {
+            return Vec::new();
+        };
+
+        self.debug_directories
+            .iter()
+            .map(|directory| {
+                directory
+                    .join(".build-id")
+                    .join(format!("{:02x}", first))
+                    .join(format!("{}{}", build_id_to_hex(rest), suffix))
+            })
+            .collect()
+    }
+
+    /// Returns every candidate for `.gnu_debuglink` of the binary at `binary_path`, in the order they are tried
+    ///
+    /// A file name that isn't a single plain path component (e.g. `../x` or `/x`) has no candidates
+    pub fn debug_link_paths(&self, binary_path: &Path, debug_link: &DebugLink) -> Vec<PathBuf> {
+        let mut components = Path::new(&debug_link.filename).components();
+        if !matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(_)), None)
+        ) {
+            return Vec::new();
+        }
+
+        let binary_directory = binary_path.parent().unwrap_or_else(|| Path::new(""));
+        let mut result = vec![
+            binary_directory.join(&debug_link.filename),
+            binary_directory.join(".debug").join(&debug_link.filename),
+        ];
+
+        for directory in &self.debug_directories {
+            // `join` would replace the debug directory with an absolute binary directory
+            let relative = binary_directory
+                .strip_prefix("/")
+                .unwrap_or(binary_directory);
+            result.push(directory.join(relative).join(&debug_link.filename));
+        }
+
+        result
+    }
+
+    /// Finds the debug file of the binary at `binary_path`, `None` if no candidate exists (or matches the CRC)
+    pub fn resolve(
+        &self,
+        binary_path: &Path,
+        build_id: Option<&[u8]>,
+        debug_link: Option<&DebugLink>,
+    ) -> Result<Option<PathBuf>> {
+        if let Some(build_id) = build_id {
+            if let Some(path) = self
+                .build_id_paths(build_id, ".debug")
+                .into_iter()
+                .find(|path| path.is_file())
+            {
+                return Ok(Some(path));
+            }
+        }
+
+        if let Some(debug_link) = debug_link {
+            for path in self.debug_link_paths(binary_path, debug_link) {
+                // The binary itself can be a candidate when the debug link names it
+                if path.is_file() && path != binary_path && debug_link.matches_file(&path)? {
+                    return Ok(Some(path));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Finds the supplementary file of `.gnu_debugaltlink`, by build ID first and then by its file name, a relative
+    /// name is relative to the directory of the debug file at `debug_file_path`
+    pub fn resolve_alt_link(
+        &self,
+        debug_file_path: &Path,
+        alt_link: &DebugAltLink,
+    ) -> Option<PathBuf> {
+        let by_name = debug_file_path
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(&alt_link.filename);
+
+        self.build_id_paths(&alt_link.build_id, ".debug")
+            .into_iter()
+            .chain(std::iter::once(by_name))
+            .find(|path| path.is_file())
+    }
+}
+
+fn read_filename(bytes: &[u8], structure: &'static str) -> Result<(String, usize)> {
+    let end = bytes.iter().position(|&byte| byte == 0).ok_or_else(|| {
+        Error::Malformed(
+            Details::new("Debug link file name isn't NUL terminated")
+                .structure(structure)
+                .field("filename"),
+        )
+    })?;
+
+    match std::str::from_utf8(&bytes[..end]) {
+        Ok(filename) => Ok((filename.to_string(), end)),
+        Err(utf8_error) => Err(Error::Malformed(
+            Details::new(format!("Invalid debug link file name, {}", utf8_error))
+                .structure(structure)
+                .field("filename"),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_debug_links_and_lookup_paths() {
+        assert_eq!(gnu_debuglink_crc32(b"123456789"), 0xcbf43926);
+        assert_eq!(
+            gnu_debuglink_crc32_update(gnu_debuglink_crc32(b"1234"), b"56789"),
+            0xcbf43926
+        );
+
+        let mut bytes = b"libexofmt.so.debug\0".to_vec();
+        bytes.resize(20, 0);
+        bytes.extend_from_slice(&0xcbf43926u32.to_le_bytes());
+        let debug_link = DebugLink::parse(&bytes, scroll::LE).unwrap();
+        assert_eq!(debug_link.filename, "libexofmt.so.debug");
+        assert_eq!(debug_link.crc, 0xcbf43926);
+        assert!(DebugLink::parse(&bytes[..20], scroll::LE).is_err());
+
+        let alt_link = DebugAltLink::parse(b"/usr/lib/debug/.dwz/exofmt\0\xab\xcd").unwrap();
+        assert_eq!(alt_link.build_id, vec![0xab, 0xcd]);
+
+        let resolver = DebugFileResolver::default();
+        assert_eq!(
+            resolver.build_id_paths(&[0x12, 0x34, 0x56], ".debug"),
+            vec![PathBuf::from("/usr/lib/debug/.build-id/12/3456.debug")]
+        );
+        assert_eq!(
+            resolver.debug_link_paths(Path::new("/usr/lib/libexofmt.so"), &debug_link),
+            vec![
+                PathBuf::from("/usr/lib/libexofmt.so.debug"),
+                PathBuf::from("/usr/lib/.debug/libexofmt.so.debug"),
+                PathBuf::from("/usr/lib/debug/usr/lib/libexofmt.so.debug"),
+            ]
+        );
+
+        for filename in [
+            "../../etc/passwd",
+            "/etc/passwd",
+            "debug/libexofmt.so",
+            ".",
+            "",
+        ] {
+            let debug_link = DebugLink {
+                filename: filename.to_owned(),
+                crc: 0,
+            };
+            assert!(resolver
+                .debug_link_paths(Path::new("/usr/lib/libexofmt.so"), &debug_link)
+                .is_empty());
+        }
+    }
+}
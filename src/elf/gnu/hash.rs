@@ -14,11 +14,12 @@
  * limitations under the License.
  */
 
-use scroll::ctx::{FromCtx, SizeWith};
-use scroll::{Endian, IOread};
+use scroll::ctx::{FromCtx, IntoCtx, SizeWith};
+use scroll::{Endian, IOread, IOwrite};
 
 use crate::elf::gnu::common::HashHeader;
-use crate::Error;
+use crate::elf::{Hash, StrTab, Sym};
+use crate::{Details, Error, Limits};
 use std::io::Seek;
 
 type Result<T> = std::result::Result<T, Error>;
@@ -53,6 +54,31 @@ pub struct HashTable<TMaskword = u64> {
     pub hash_values: Vec<u32>,
 }
 
+/// The hash function used by `.gnu.hash`, this is Dan Bernstein's string hash (`h * 33 + c`)
+pub fn gnu_hash(name: &str) -> u32 {
+    name.bytes().fold(5381u32, |h, byte| {
+        h.wrapping_mul(33).wrapping_add(u32::from(byte))
+    })
+}
+
+/// The `shift2` value used when building a table, both `ld` and `lld` use `26`
+pub const DEFAULT_SHIFT2: u32 = 26;
+
+/// The output of `HashTable::build`
+pub struct DynSymHashTables<TMaskword = u64> {
+    /// `.dynsym` in the order required by `.gnu.hash`, symbols which aren't hashed (undefined or local) keep their
+    /// relative order and come first, followed by the hashed symbols grouped by bucket
+    pub symbols: Vec<Sym>,
+    /// `old_indices[i]` is the index `symbols[i]` had in the `.dynsym` passed to `build`
+    ///
+    /// Anything that refers to `.dynsym` by index (relocations, `.gnu.version`, etc.) needs to be remapped using this.
+    pub old_indices: Vec<usize>,
+    /// The `.hash` table for `symbols`
+    pub hash: Hash,
+    /// The `.gnu.hash` table for `symbols`
+    pub gnu_hash: HashTable<TMaskword>,
+}
+
 // TODO: I don't like this... but oh well.
 //       I kind of wish I could do a `HashTable::parse_32` and `HashTable::parse_64` but that would require testing to see if `TMaskword` can be replaced
 //       entirely with a `u64`... which I fucking hope it can.
@@ -67,16 +93,29 @@ where
         reader: &'a mut TRead,
         endianness: Endian,
         dynsym_len: usize,
+    ) -> Result<HashTable<u64>> {
+        Self::parse_with_limits(reader, endianness, dynsym_len, &Limits::default())
+    }
+
+    /// Same as `parse` but checks the header's counts against `limits` instead of the default limits
+    pub fn parse_with_limits<TRead: IOread<Endian> + Seek>(
+        reader: &'a mut TRead,
+        endianness: Endian,
+        dynsym_len: usize,
+        limits: &Limits,
     ) -> Result<HashTable<u64>> {
         let hash_header = reader.ioread_with::<HashHeader>(endianness)?;
 
         if dynsym_len >= (hash_header.symndx as usize) {
             let bloom_filters_len = hash_header.maskwords as usize;
-            let mut bloom_filters: Vec<u64> = Vec::with_capacity(bloom_filters_len);
+            let mut bloom_filters: Vec<u64> =
+                limits.vec_with_capacity(hash_header.maskwords as u64, "GNU hash bloom filters")?;
             let buckets_len = hash_header.nbuckets as usize;
-            let mut buckets: Vec<u32> = Vec::with_capacity(buckets_len);
+            let mut buckets: Vec<u32> =
+                limits.vec_with_capacity(hash_header.nbuckets as u64, "GNU hash buckets")?;
             let hash_values_len = dynsym_len - (hash_header.symndx as usize);
-            let mut hash_values: Vec<u32> = Vec::with_capacity(hash_values_len);
+            let mut hash_values: Vec<u32> =
+                limits.vec_with_capacity(hash_values_len as u64, "GNU hash values")?;
 
             for _ in 0..bloom_filters_len {
                 bloom_filters.push(u64::from(reader.ioread_with::<TMaskword>(endianness)?));
@@ -98,11 +137,270 @@ where
                 hash_values,
             })
         } else {
-            Err(Error::Malformed(format!(
-                "GNU hash header provided `symndx` value ({}) larger than the number of dynsym entries ({})!",
-                hash_header.symndx,
-                dynsym_len,
-            )))
+            Err(Error::Malformed(
+                Details::new(format!(
+                    "GNU hash header provided `symndx` value ({}) larger than the number of dynsym entries ({})!",
+                    hash_header.symndx,
+                    dynsym_len,
+                ))
+                .structure("elf::gnu::HashTable")
+                .field("symndx"),
+            ))
+        }
+    }
+}
+
+// Like `parse`, `TMaskword` is used to pick between the 32-bit (`u32`) and 64-bit (`u64`) bloom filter word size
+impl<TMaskword> HashTable<TMaskword>
+where
+    TMaskword: SizeWith<Endian>,
+    TMaskword: IntoCtx<Endian>,
+    TMaskword: TryFrom<u64>,
+    TMaskword: Copy,
+{
+    /// Sorts `symbols` (`.dynsym`) and builds both `.hash` and `.gnu.hash` for the result, resolving names through `dynstr`
+    ///
+    /// Bucket count, bloom filter size and `shift2` are picked the same way as `lld`.
+    pub fn build(symbols: Vec<Sym>, dynstr: &StrTab) -> Result<DynSymHashTables<TMaskword>> {
+        let maskword_bits = (::std::mem::size_of::<TMaskword>() * 8) as u32;
+
+        if symbols.len() > u32::MAX as usize {
+            return Err(Error::TooManyArrayItems(
+                Details::new(format!(
+                    "Attempted to build a hash table with `{}` symbols, more than the max of `{}`",
+                    symbols.len(),
+                    u32::MAX
+                ))
+                .structure("elf::gnu::HashTable"),
+            ));
+        }
+
+        let mut unhashed: Vec<usize> = Vec::new();
+        let mut hashed: Vec<(usize, u32)> = Vec::new();
+
+        for (index, sym) in symbols.iter().enumerate() {
+            if sym.st_shndx == crate::elf::SHN_UNDEF as u16
+                || sym.st_bind() == crate::elf::STB_LOCAL
+            {
+                unhashed.push(index);
+            } else {
+                hashed.push((index, gnu_hash(&dynstr.get_at_offset(sym.st_name)?)));
+            }
+        }
+
+        let nbuckets = ((hashed.len() / 4) as u32).max(1);
+        // This is a stable sort so symbols within the same bucket keep their relative order
+        hashed.sort_by_key(|(_, hash)| hash % nbuckets);
+
+        let symndx = unhashed.len() as u32;
+        // At least 12 bits per symbol, rounded to a power of 2 words. This has to be at least `1`, even for empty tables.
+        let maskwords =
+            ((hashed.len() as u64 * 12) / u64::from(maskword_bits) + 1).next_power_of_two();
+        let maskwords = u32::try_from(maskwords).map_err(|_| {
+            Error::TooManyArrayItems(
+                Details::new(format!(
+                    "Attempted to build a bloom filter with `{}` words for `{}` symbols, more than the max of `{}`",
+                    maskwords,
+                    hashed.len(),
+                    u32::MAX
+                ))
+                .structure("elf::gnu::HashTable")
+                .field("bloom_filters"),
+            )
+        })?;
+
+        // Built using `u64` words, every bit set is below `maskword_bits` so they always fit back into a `TMaskword`
+        let mut bloom_filters: Vec<u64> = vec![0; maskwords as usize];
+        let mut buckets: Vec<u32> = vec![0; nbuckets as usize];
+        let mut hash_values: Vec<u32> = Vec::with_capacity(hashed.len());
+
+        for (position, (_, hash)) in hashed.iter().enumerate() {
+            let bloom_index = ((hash / maskword_bits) & (maskwords - 1)) as usize;
+            bloom_filters[bloom_index] |= 1u64 << (hash % maskword_bits);
+            bloom_filters[bloom_index] |= 1u64 << ((hash >> DEFAULT_SHIFT2) % maskword_bits);
+
+            let bucket = hash % nbuckets;
+
+            if buckets[bucket as usize] == 0 {
+                buckets[bucket as usize] = symndx + position as u32;
+            }
+
+            // The low bit marks the end of a bucket's chain
+            let is_chain_end = hashed
+                .get(position + 1)
+                .map_or(true, |(_, next_hash)| next_hash % nbuckets != bucket);
+
+            hash_values.push(if is_chain_end { hash | 1 } else { hash & !1 });
         }
+
+        let old_indices: Vec<usize> = unhashed
+            .into_iter()
+            .chain(hashed.iter().map(|(index, _)| *index))
+            .collect();
+        let sorted_symbols: Vec<Sym> = old_indices
+            .iter()
+            .map(|index| symbols[*index].clone())
+            .collect();
+
+        let names = sorted_symbols
+            .iter()
+            .map(|sym| dynstr.get_at_offset(sym.st_name))
+            .collect::<Result<Vec<_>>>()?;
+        let names: Vec<&str> = names.iter().map(|name| name.as_ref()).collect();
+
+        let bloom_filters = bloom_filters
+            .into_iter()
+            .map(|bloom_filter| {
+                TMaskword::try_from(bloom_filter).map_err(|_| {
+                    Error::InvalidArguments(
+                        Details::new(format!(
+                            "GNU hash bloom filter word `0x{:x}` doesn't fit in a `{}` byte mask word",
+                            bloom_filter,
+                            ::std::mem::size_of::<TMaskword>()
+                        ))
+                        .structure("elf::gnu::HashTable")
+                        .field("bloom_filters"),
+                    )
+                })
+            })
+            .collect::<Result<Vec<TMaskword>>>()?;
+
+        Ok(DynSymHashTables {
+            hash: Hash::build(&names)?,
+            gnu_hash: HashTable {
+                symndx,
+                shift2: DEFAULT_SHIFT2,
+                bloom_filters,
+                buckets,
+                hash_values,
+            },
+            symbols: sorted_symbols,
+            old_indices,
+        })
+    }
+
+    /// Writes this table in the `SHT_GNU_HASH` layout, the bloom filter is written using `TMaskword` sized words
+    pub fn write<TWrite: IOwrite<Endian>>(
+        &self,
+        writer: &mut TWrite,
+        endianness: Endian,
+    ) -> Result<()> {
+        let header = HashHeader {
+            nbuckets: u32::try_from(self.buckets.len()).map_err(|_| {
+                Error::TooManyArrayItems(
+                    Details::new(format!(
+                        "GNU hash table has `{}` buckets, more than the max of `{}`",
+                        self.buckets.len(),
+                        u32::MAX
+                    ))
+                    .structure("elf::gnu::HashTable")
+                    .field("buckets"),
+                )
+            })?,
+            symndx: self.symndx,
+            maskwords: u32::try_from(self.bloom_filters.len()).map_err(|_| {
+                Error::TooManyArrayItems(
+                    Details::new(format!(
+                        "GNU hash table has `{}` mask words, more than the max of `{}`",
+                        self.bloom_filters.len(),
+                        u32::MAX
+                    ))
+                    .structure("elf::gnu::HashTable")
+                    .field("bloom_filters"),
+                )
+            })?,
+            shift2: self.shift2,
+        };
+
+        writer.iowrite_with(header, endianness)?;
+
+        for bloom_filter in &self.bloom_filters {
+            writer.iowrite_with(*bloom_filter, endianness)?;
+        }
+
+        for value in self.buckets.iter().chain(self.hash_values.iter()) {
+            writer.iowrite_with(*value, endianness)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+    use std::io::Cursor;
+
+    fn sym(st_name: u32, st_shndx: u16) -> Sym {
+        Sym {
+            st_name,
+            st_info: (crate::elf::STB_GLOBAL << 4) | crate::elf::STT_FUNC,
+            st_other: 0,
+            st_shndx,
+            st_value: 0,
+            st_size: 0,
+        }
+    }
+
+    #[test]
+    fn gnu_hash_values() {
+        assert_eq!(gnu_hash(""), 0x00001505);
+        assert_eq!(gnu_hash("printf"), 0x156b2bb8);
+        assert_eq!(gnu_hash("exit"), 0x7c967e3f);
+    }
+
+    #[test]
+    fn build_and_round_trip() {
+        let dynstr = StrTab::parse(Cow::Borrowed(b"\0printf\0main\0exit\0foo\0bar\0"), 0).unwrap();
+        let symbols = vec![
+            sym(0, 0),
+            sym(1, 0),
+            sym(8, 12),
+            sym(13, 0),
+            sym(18, 12),
+            sym(22, 12),
+        ];
+
+        let tables = HashTable::<u32>::build(symbols, &dynstr).unwrap();
+
+        assert_eq!(tables.gnu_hash.symndx, 3);
+        assert_eq!(tables.old_indices[..3], [0, 1, 3]);
+        assert_eq!(tables.hash.chains.len(), 6);
+
+        // Every hashed symbol must be found through its bucket's chain
+        for (index, sym) in tables.symbols.iter().enumerate().skip(3) {
+            let hash = gnu_hash(&dynstr.get_at_offset(sym.st_name).unwrap());
+            let mut chain_index = tables.gnu_hash.buckets
+                [(hash % tables.gnu_hash.buckets.len() as u32) as usize]
+                as usize;
+
+            loop {
+                let chain_hash = tables.gnu_hash.hash_values[chain_index - 3];
+
+                if chain_index == index {
+                    assert_eq!(chain_hash | 1, hash | 1);
+                    break;
+                }
+
+                assert_eq!(chain_hash & 1, 0);
+                chain_index += 1;
+            }
+        }
+
+        let mut bytes = Cursor::new(Vec::new());
+        tables.gnu_hash.write(&mut bytes, scroll::LE).unwrap();
+        bytes.set_position(0);
+
+        let parsed = HashTable::<u32>::parse(&mut bytes, scroll::LE, tables.symbols.len()).unwrap();
+        let bloom_filters: Vec<u64> = tables
+            .gnu_hash
+            .bloom_filters
+            .iter()
+            .map(|word| u64::from(*word))
+            .collect();
+        assert_eq!(parsed.bloom_filters, bloom_filters);
+        assert_eq!(parsed.buckets, tables.gnu_hash.buckets);
+        assert_eq!(parsed.hash_values, tables.gnu_hash.hash_values);
     }
 }
@@ -18,6 +18,8 @@ use crate::stringable_consts_blocks::option_stringable_consts_block;
 
 pub mod common;
 
+mod debuglink;
+pub use debuglink::*;
 mod hash;
 pub use hash::*;
 mod version;
@@ -34,9 +36,16 @@ option_stringable_consts_block! {
         pub PT_GNU_RELRO = 0x6474e552;
         /// .note.gnu.property notes sections.
         pub PT_GNU_PROPERTY = 0x6474e553;
+        /// SFrame stack trace information.
+        pub PT_GNU_SFRAME = 0x6474e554;
     }
 
-    const ignore: u32 {}
+    const ignore: u32 {
+        /// Start of the memory binding segments.
+        pub PT_GNU_MBIND_LO = 0x6474e555;
+        /// End of the memory binding segments.
+        pub PT_GNU_MBIND_HI = 0x6474f554;
+    }
 
     pub fn pt_to_str(value: u32) -> Option<&'static str>;
 }
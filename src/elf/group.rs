@@ -0,0 +1,231 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::elf::{
+    Reader, SectionHeader, StrTab, Sym, GRP_COMDAT, SHN_LORESERVE, SHT_GROUP, STT_SECTION,
+};
+use crate::error::ResultExt;
+use crate::{ContextFrame, Details, Error};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// A `SHT_GROUP` section, a set of sections that have to be kept or discarded together
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SectionGroup {
+    /// Index of the `SHT_GROUP` section itself
+    pub section_index: u32,
+    /// `GRP_COMDAT` plus any OS or processor specific flags
+    pub flags: u32,
+    /// Section indices of the members
+    pub members: Vec<u32>,
+    /// Name of the signature symbol (`sh_info` in the `sh_link` symbol table), for a `STT_SECTION` signature symbol
+    /// this is the name of its section like GNU `as` does
+    pub signature: String,
+}
+
+impl SectionGroup {
+    /// Splits the words of a `SHT_GROUP` section (see `Reader::read_group_section`) into the flags and the members
+    pub fn from_words(
+        section_index: u32,
+        words: &[u32],
+        signature: String,
+    ) -> Result<SectionGroup> {
+        let Some((&flags, members)) = words.split_first() else {
+            return Err(Error::Malformed(
+                Details::new(format!(
+                    "Group section `{}` is empty, it should start with a flag word",
+                    section_index
+                ))
+                .structure("elf::SectionGroup")
+                .field("flags"),
+            ));
+        };
+
+        Ok(SectionGroup {
+            section_index,
+            flags,
+            members: members.to_vec(),
+            signature,
+        })
+    }
+
+    /// Only one COMDAT group with a given signature is kept by the linker
+    pub fn is_comdat(&self) -> bool {
+        self.flags & GRP_COMDAT != 0
+    }
+}
+
+/// Every `SHT_GROUP` section of an object file along with a map from member section to its group
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SectionGroups {
+    /// In section header order
+    pub groups: Vec<SectionGroup>,
+    /// Member section index to index in `groups`
+    owners: HashMap<u32, usize>,
+}
+
+impl SectionGroups {
+    /// A section should only be a member of one group, if it appears in several it is mapped to the first one
+    pub fn new(groups: Vec<SectionGroup>) -> SectionGroups {
+        let mut owners: HashMap<u32, usize> = HashMap::new();
+
+        for (index, group) in groups.iter().enumerate() {
+            for &member in &group.members {
+                owners.entry(member).or_insert(index);
+            }
+        }
+
+        SectionGroups { groups, owners }
+    }
+
+    /// Reads every `SHT_GROUP` section and resolves its signature through its `sh_link` symbol table
+    ///
+    /// `shstrtab` is only needed to name `STT_SECTION` signatures.
+    pub fn read<'a, TReader: Reader<'a>>(
+        reader: &mut TReader,
+        section_headers: &[SectionHeader],
+        shstrtab: &StrTab,
+    ) -> Result<SectionGroups> {
+        let mut symbol_tables: HashMap<u32, (Vec<Sym>, StrTab<'a>)> = HashMap::new();
+        let mut groups: Vec<SectionGroup> = Vec::new();
+
+        for (index, section_header) in section_headers.iter().enumerate() {
+            if section_header.sh_type != SHT_GROUP {
+                continue;
+            }
+
+            let group =
+                Self::read_group(reader, section_headers, shstrtab, &mut symbol_tables, index)
+                    .context(|| {
+                        ContextFrame::new("SHT_GROUP")
+                            .index(index)
+                            .offset(section_header.sh_offset)
+                    })?;
+            groups.push(group);
+        }
+
+        Ok(Self::new(groups))
+    }
+
+    fn read_group<'a, TReader: Reader<'a>>(
+        reader: &mut TReader,
+        section_headers: &[SectionHeader],
+        shstrtab: &StrTab,
+        symbol_tables: &mut HashMap<u32, (Vec<Sym>, StrTab<'a>)>,
+        index: usize,
+    ) -> Result<SectionGroup> {
+        let section_header = &section_headers[index];
+        let words = reader.read_group_section(section_header)?;
+
+        let symtab_header = section_headers
+            .get(section_header.sh_link as usize)
+            .ok_or_else(|| out_of_range("sh_link", section_header.sh_link))?;
+        let strtab_header = section_headers
+            .get(symtab_header.sh_link as usize)
+            .ok_or_else(|| out_of_range("sh_link", symtab_header.sh_link))?;
+
+        if let Entry::Vacant(entry) = symbol_tables.entry(section_header.sh_link) {
+            let symbols = reader.read_sym_table(symtab_header.sh_offset, symtab_header.sh_size)?;
+            let strtab = reader.read_str_table_section(strtab_header)?;
+            entry.insert((symbols, strtab));
+        }
+
+        let (symbols, strtab) = &symbol_tables[&section_header.sh_link];
+        let symbol = symbols
+            .get(section_header.sh_info as usize)
+            .ok_or_else(|| out_of_range("sh_info", section_header.sh_info))?;
+
+        let signature = if symbol.st_type() == STT_SECTION
+            && symbol.st_shndx != 0
+            && u32::from(symbol.st_shndx) < SHN_LORESERVE
+        {
+            match section_headers.get(symbol.st_shndx as usize) {
+                Some(signature_section) => shstrtab.get_at_offset(signature_section.sh_name)?,
+                None => return Err(out_of_range("st_shndx", u32::from(symbol.st_shndx))),
+            }
+        } else {
+            strtab.get_at_offset(symbol.st_name)?
+        };
+
+        let group = SectionGroup::from_words(index as u32, &words, signature.into_owned())?;
+
+        if let Some(&member) = group
+            .members
+            .iter()
+            .find(|&&member| member as usize >= section_headers.len())
+        {
+            return Err(out_of_range("members", member));
+        }
+
+        Ok(group)
+    }
+
+    /// Returns the group `section_index` is a member of
+    pub fn group_of(&self, section_index: u32) -> Option<&SectionGroup> {
+        self.owners
+            .get(&section_index)
+            .map(|&index| &self.groups[index])
+    }
+
+    /// Map from member section index to index in `groups`
+    pub fn owners(&self) -> &HashMap<u32, usize> {
+        &self.owners
+    }
+
+    /// Returns every COMDAT group with the signature `signature`
+    pub fn comdat_groups<'b>(
+        &'b self,
+        signature: &'b str,
+    ) -> impl Iterator<Item = &'b SectionGroup> + 'b {
+        self.groups
+            .iter()
+            .filter(move |group| group.is_comdat() && group.signature == signature)
+    }
+}
+
+fn out_of_range(field: &'static str, value: u32) -> Error {
+    Error::Malformed(
+        Details::new(format!(
+            "Group section has a `{}` of `{}`, which is out of range",
+            field, value
+        ))
+        .structure("elf::SectionGroup")
+        .field(field),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_members_to_groups() {
+        let inline =
+            SectionGroup::from_words(1, &[GRP_COMDAT, 4, 5], "_ZN6exofmt3fooEv".to_string())
+                .unwrap();
+        let plain = SectionGroup::from_words(2, &[0, 6], "plain".to_string()).unwrap();
+        assert!(inline.is_comdat() && !plain.is_comdat());
+        assert!(SectionGroup::from_words(3, &[], String::new()).is_err());
+
+        let groups = SectionGroups::new(vec![inline, plain]);
+        assert_eq!(groups.group_of(5).unwrap().section_index, 1);
+        assert_eq!(groups.group_of(6).unwrap().signature, "plain");
+        assert!(groups.group_of(3).is_none());
+        assert_eq!(groups.comdat_groups("_ZN6exofmt3fooEv").count(), 1);
+        assert_eq!(groups.owners().len(), 3);
+    }
+}
@@ -14,17 +14,92 @@
  * limitations under the License.
  */
 
+use crate::{Details, Error};
+use scroll::{Endian, IOwrite};
+
+type Result<T> = std::result::Result<T, Error>;
+
 pub struct Hash {
     pub buckets: Vec<u32>,
     pub chains: Vec<u32>,
 }
 
+impl Hash {
+    /// Builds the `.hash` table for a `.dynsym` where `names[i]` is the name of `.dynsym[i]`
+    ///
+    /// The bucket count matches the symbol count, the same as `lld`. The `STN_UNDEF` symbol at index `0` is never hashed.
+    pub fn build(names: &[&str]) -> Result<Hash> {
+        let n_symbols = u32::try_from(names.len()).map_err(|_| {
+            Error::TooManyArrayItems(
+                Details::new(format!(
+                    "Attempted to build a hash table with `{}` symbols, more than the max of `{}`",
+                    names.len(),
+                    u32::MAX
+                ))
+                .structure("elf::Hash"),
+            )
+        })?;
+        let n_buckets = n_symbols.max(1);
+        let mut buckets: Vec<u32> = vec![0; n_buckets as usize];
+        let mut chains: Vec<u32> = vec![0; names.len()];
+
+        for (index, name) in names.iter().enumerate().skip(1) {
+            let bucket = (generate_hash(name) % n_buckets) as usize;
+
+            chains[index] = buckets[bucket];
+            buckets[bucket] = index as u32;
+        }
+
+        Ok(Hash { buckets, chains })
+    }
+
+    /// Writes the table in the `SHT_HASH` layout, which is the same for both 32-bit and 64-bit files
+    pub fn write<TWrite: IOwrite<Endian>>(
+        &self,
+        writer: &mut TWrite,
+        endianness: Endian,
+    ) -> Result<()> {
+        let header = crate::elf::common::HashHeader {
+            n_buckets: u32::try_from(self.buckets.len()).map_err(|_| {
+                Error::TooManyArrayItems(
+                    Details::new(format!(
+                        "Hash table has `{}` buckets, more than the max of `{}`",
+                        self.buckets.len(),
+                        u32::MAX
+                    ))
+                    .structure("elf::Hash")
+                    .field("buckets"),
+                )
+            })?,
+            n_chains: u32::try_from(self.chains.len()).map_err(|_| {
+                Error::TooManyArrayItems(
+                    Details::new(format!(
+                        "Hash table has `{}` chains, more than the max of `{}`",
+                        self.chains.len(),
+                        u32::MAX
+                    ))
+                    .structure("elf::Hash")
+                    .field("chains"),
+                )
+            })?,
+        };
+
+        writer.iowrite_with(header, endianness)?;
+
+        for value in self.buckets.iter().chain(self.chains.iter()) {
+            writer.iowrite_with(*value, endianness)?;
+        }
+
+        Ok(())
+    }
+}
+
 pub fn generate_hash(name: &str) -> u32 {
     let mut h: u32 = 0;
     let mut g: u32;
 
     for byte in name.as_bytes() {
-        h = (h << 4) + u32::from(*byte);
+        h = (h << 4).wrapping_add(u32::from(*byte));
 
         g = h & 0xf0000000;
 
@@ -37,3 +112,26 @@ pub fn generate_hash(name: &str) -> u32 {
 
     h
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_hash_table() {
+        assert_eq!(generate_hash("printf"), 0x077905a6);
+        // The shifted hash reaches `0x0ffffff1` before adding the next byte
+        assert_eq!(generate_hash("|=(yl1~}gG81"), 0x0063bab1);
+
+        let hash = Hash::build(&["", "printf", "|=(yl1~}gG81"]).unwrap();
+        assert_eq!(hash.buckets.len(), 3);
+        assert_eq!(hash.chains[0], 0);
+        for (index, name) in ["printf", "|=(yl1~}gG81"].iter().enumerate() {
+            let mut symbol = hash.buckets[(generate_hash(name) % 3) as usize];
+            while symbol != index as u32 + 1 {
+                assert_ne!(symbol, 0);
+                symbol = hash.chains[symbol as usize];
+            }
+        }
+    }
+}
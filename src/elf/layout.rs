@@ -0,0 +1,525 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+// Section to segment mapping and file layout, for explaining where the bytes of a file go
+
+use crate::elf::gnu::{
+    PT_GNU_EH_FRAME, PT_GNU_MBIND_HI, PT_GNU_MBIND_LO, PT_GNU_RELRO, PT_GNU_SFRAME, PT_GNU_STACK,
+};
+use crate::elf::{
+    Header, ProgramHeader, Reader, SHFlags, SectionHeader, EI_CLASS, PT_DYNAMIC, PT_LOAD, PT_NOTE,
+    PT_PHDR, PT_TLS, SHT_NOBITS, SHT_NULL,
+};
+use crate::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// A `SHF_TLS` `SHT_NOBITS` section (`.tbss`) outside of `PT_TLS`, it takes no space in the segment
+///
+/// Same as binutils' `ELF_TBSS_SPECIAL`.
+pub fn is_tbss_special(section_header: &SectionHeader, program_header: &ProgramHeader) -> bool {
+    section_header.sh_flags.contains(SHFlags::TLS)
+        && section_header.sh_type == SHT_NOBITS
+        && program_header.p_type != PT_TLS
+}
+
+/// Size `section_header` takes up in `program_header`, `0` if `is_tbss_special`
+pub fn section_size_in_segment(
+    section_header: &SectionHeader,
+    program_header: &ProgramHeader,
+) -> u64 {
+    if is_tbss_special(section_header, program_header) {
+        0
+    } else {
+        section_header.sh_size
+    }
+}
+
+/// Whether `program_header` contains `section_header`, using binutils' `ELF_SECTION_IN_SEGMENT` rules
+///
+/// - Only `PT_LOAD`, `PT_GNU_RELRO` and `PT_TLS` contain `SHF_TLS` sections, `PT_TLS` contains nothing else and
+///   `PT_PHDR` contains no sections at all
+/// - `PT_LOAD`, `PT_DYNAMIC` and the GNU segments only contain `SHF_ALLOC` sections
+/// - Sections other than `SHT_NOBITS` must be within the segment's file range, `SHF_ALLOC` sections must be within its
+///   memory range
+/// - Empty sections at the edges of `PT_DYNAMIC` and `PT_NOTE` aren't part of them
+///
+/// `strict` additionally requires the section to start before the end of the segment, so empty sections at the end of
+/// a segment aren't counted. readelf's section to segment mapping is strict.
+pub fn section_in_segment(
+    section_header: &SectionHeader,
+    program_header: &ProgramHeader,
+    strict: bool,
+) -> bool {
+    let is_tls = section_header.sh_flags.contains(SHFlags::TLS);
+    let is_alloc = section_header.sh_flags.contains(SHFlags::ALLOC);
+    let is_nobits = section_header.sh_type == SHT_NOBITS;
+    let p_type = program_header.p_type;
+    let size = section_size_in_segment(section_header, program_header);
+
+    let type_allowed = if is_tls {
+        p_type == PT_TLS || p_type == PT_GNU_RELRO || p_type == PT_LOAD
+    } else {
+        p_type != PT_TLS && p_type != PT_PHDR
+    };
+
+    let alloc_only = matches!(
+        p_type,
+        PT_LOAD | PT_DYNAMIC | PT_GNU_EH_FRAME | PT_GNU_STACK | PT_GNU_RELRO | PT_GNU_SFRAME
+    ) || (PT_GNU_MBIND_LO..=PT_GNU_MBIND_HI).contains(&p_type);
+
+    let in_file = is_nobits
+        || within(
+            section_header.sh_offset,
+            size,
+            program_header.p_offset,
+            program_header.p_filesz,
+            strict,
+        );
+    let in_memory = !is_alloc
+        || within(
+            section_header.sh_addr,
+            size,
+            program_header.p_vaddr,
+            program_header.p_memsz,
+            strict,
+        );
+
+    let not_empty_at_edge = (p_type != PT_DYNAMIC && p_type != PT_NOTE)
+        || section_header.sh_size != 0
+        || program_header.p_memsz == 0
+        || ((is_nobits
+            || (section_header.sh_offset > program_header.p_offset
+                && section_header.sh_offset - program_header.p_offset < program_header.p_filesz))
+            && (!is_alloc
+                || (section_header.sh_addr > program_header.p_vaddr
+                    && section_header.sh_addr - program_header.p_vaddr < program_header.p_memsz)));
+
+    type_allowed && (is_alloc || !alloc_only) && in_file && in_memory && not_empty_at_edge
+}
+
+/// `start..start + size` is within `range_start..range_start + range_size`
+fn within(start: u64, size: u64, range_start: u64, range_size: u64, strict: bool) -> bool {
+    let Some(relative) = start.checked_sub(range_start) else {
+        return false;
+    };
+
+    // binutils relies on `p_filesz - 1` wrapping around for empty segments
+    (!strict || relative <= range_size.wrapping_sub(1))
+        && relative
+            .checked_add(size)
+            .is_some_and(|end| end <= range_size)
+}
+
+/// The sections in each segment, the same mapping as `readelf --segments`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SegmentMap {
+    /// Section indices for every program header, in section header order
+    pub segments: Vec<Vec<usize>>,
+}
+
+impl SegmentMap {
+    /// Maps sections with `section_in_segment` in strict mode, `.tbss` is left out of segments other than `PT_TLS`
+    pub fn new(program_headers: &[ProgramHeader], section_headers: &[SectionHeader]) -> Self {
+        let segments = program_headers
+            .iter()
+            .map(|program_header| {
+                section_headers
+                    .iter()
+                    .enumerate()
+                    // Section `0` is the null section and never part of a segment
+                    .skip(1)
+                    .filter(|(_, section_header)| {
+                        !is_tbss_special(section_header, program_header)
+                            && section_in_segment(section_header, program_header, true)
+                    })
+                    .map(|(index, _)| index)
+                    .collect()
+            })
+            .collect();
+
+        SegmentMap { segments }
+    }
+
+    /// Section indices of segment `program_header_index`
+    pub fn sections_of(&self, program_header_index: usize) -> &[usize] {
+        self.segments
+            .get(program_header_index)
+            .map_or(&[], |sections| sections.as_slice())
+    }
+
+    /// Program header indices of the segments containing `section_index`
+    pub fn segments_of(&self, section_index: usize) -> Vec<usize> {
+        self.segments
+            .iter()
+            .enumerate()
+            .filter(|(_, sections)| sections.contains(&section_index))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// `SHF_ALLOC` sections that aren't in any `PT_LOAD` segment
+    pub fn unloaded_sections(
+        &self,
+        program_headers: &[ProgramHeader],
+        section_headers: &[SectionHeader],
+    ) -> Vec<usize> {
+        (1..section_headers.len())
+            .filter(|&index| {
+                section_headers[index].sh_flags.contains(SHFlags::ALLOC)
+                    && !self.segments_of(index).into_iter().any(|segment| {
+                        program_headers
+                            .get(segment)
+                            .is_some_and(|program_header| program_header.p_type == PT_LOAD)
+                    })
+            })
+            .collect()
+    }
+}
+
+/// What occupies a `LayoutRegion`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LayoutRegionKind {
+    FileHeader,
+    ProgramHeaders,
+    SectionHeaders,
+    /// Index of the section
+    Section(usize),
+}
+
+/// A range of file offsets used by one structure
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LayoutRegion {
+    pub kind: LayoutRegionKind,
+    pub offset: u64,
+    pub size: u64,
+    /// `sh_addralign` for sections, `1` if the section has no alignment
+    pub alignment: u64,
+}
+
+impl LayoutRegion {
+    pub fn end(&self) -> u64 {
+        self.offset.saturating_add(self.size)
+    }
+}
+
+/// File offsets between two regions that no region uses
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LayoutGap {
+    pub offset: u64,
+    pub size: u64,
+    /// `None` for a gap at the start of the file, which can't happen as the file header is at offset `0`
+    pub after: Option<LayoutRegionKind>,
+    /// `None` for bytes after the last region
+    pub before: Option<LayoutRegionKind>,
+    /// The gap only exists to align `before`
+    pub is_padding: bool,
+}
+
+/// Two regions using the same file offsets
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LayoutOverlap {
+    pub first: LayoutRegionKind,
+    pub second: LayoutRegionKind,
+    pub offset: u64,
+    pub size: u64,
+}
+
+/// Where the bytes of a file are used, see `layout_report_parsed`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LayoutReport {
+    pub segment_map: SegmentMap,
+    /// Every region with a non-zero size, sorted by offset
+    pub regions: Vec<LayoutRegion>,
+    pub gaps: Vec<LayoutGap>,
+    pub overlaps: Vec<LayoutOverlap>,
+    /// Size of the file if it was given, used for the bytes after the last region
+    pub file_size: Option<u64>,
+}
+
+impl LayoutReport {
+    /// Bytes used to align regions
+    pub fn padding_size(&self) -> u64 {
+        self.gaps
+            .iter()
+            .filter(|gap| gap.is_padding)
+            .map(|gap| gap.size)
+            .sum()
+    }
+
+    /// Bytes no region uses and that aren't alignment padding
+    pub fn unused_size(&self) -> u64 {
+        self.gaps
+            .iter()
+            .filter(|gap| !gap.is_padding)
+            .map(|gap| gap.size)
+            .sum()
+    }
+}
+
+/// Reads the headers of a file and computes its layout, see `layout_report_parsed`
+///
+/// `file_size` is needed to report bytes after the last region, e.g. an appended signature.
+pub fn layout_report<'a, TReader: Reader<'a>>(
+    reader: &mut TReader,
+    file_size: Option<u64>,
+) -> Result<LayoutReport> {
+    let header = reader.read_header()?;
+    let program_headers =
+        reader.read_program_headers(header.e_phoff, header.e_phentsize, header.e_phnum)?;
+    let section_headers =
+        reader.read_section_headers(header.e_shoff, header.e_shentsize, header.e_shnum)?;
+
+    Ok(layout_report_parsed(
+        &header,
+        &program_headers,
+        &section_headers,
+        file_size,
+    ))
+}
+
+/// Maps sections to segments and lists the gaps, alignment padding and overlaps between the file header, the header
+/// tables and the sections
+///
+/// `SHT_NOBITS` and empty sections don't use file offsets and aren't regions.
+pub fn layout_report_parsed(
+    header: &Header,
+    program_headers: &[ProgramHeader],
+    section_headers: &[SectionHeader],
+    file_size: Option<u64>,
+) -> LayoutReport {
+    let table_alignment = if header.e_ident[EI_CLASS] == 2 { 8 } else { 4 };
+
+    let mut regions: Vec<LayoutRegion> = vec![
+        LayoutRegion {
+            kind: LayoutRegionKind::FileHeader,
+            offset: 0,
+            size: u64::from(header.e_ehsize),
+            alignment: 1,
+        },
+        LayoutRegion {
+            kind: LayoutRegionKind::ProgramHeaders,
+            offset: header.e_phoff,
+            size: u64::from(header.e_phentsize) * program_headers.len() as u64,
+            alignment: table_alignment,
+        },
+        LayoutRegion {
+            kind: LayoutRegionKind::SectionHeaders,
+            offset: header.e_shoff,
+            size: u64::from(header.e_shentsize) * section_headers.len() as u64,
+            alignment: table_alignment,
+        },
+    ];
+
+    regions.extend(
+        section_headers
+            .iter()
+            .enumerate()
+            .filter(|(_, section_header)| {
+                section_header.sh_type != SHT_NULL && section_header.sh_type != SHT_NOBITS
+            })
+            .map(|(index, section_header)| LayoutRegion {
+                kind: LayoutRegionKind::Section(index),
+                offset: section_header.sh_offset,
+                size: section_header.sh_size,
+                alignment: section_header.sh_addralign.max(1),
+            }),
+    );
+
+    regions.retain(|region| region.size != 0);
+    regions.sort_by_key(|region| (region.offset, region.end(), region.kind));
+
+    let mut gaps: Vec<LayoutGap> = Vec::new();
+    let mut overlaps: Vec<LayoutOverlap> = Vec::new();
+    // The region reaching furthest into the file so far
+    let mut furthest: Option<&LayoutRegion> = None;
+
+    for region in &regions {
+        match furthest {
+            Some(previous) if region.offset < previous.end() => {
+                overlaps.push(LayoutOverlap {
+                    first: previous.kind,
+                    second: region.kind,
+                    offset: region.offset,
+                    size: region.end().min(previous.end()) - region.offset,
+                });
+            }
+            Some(previous) if region.offset > previous.end() => {
+                gaps.push(LayoutGap {
+                    offset: previous.end(),
+                    size: region.offset - previous.end(),
+                    after: Some(previous.kind),
+                    before: Some(region.kind),
+                    is_padding: align_up(previous.end(), region.alignment) == region.offset,
+                });
+            }
+            None if region.offset > 0 => {
+                gaps.push(LayoutGap {
+                    offset: 0,
+                    size: region.offset,
+                    after: None,
+                    before: Some(region.kind),
+                    is_padding: false,
+                });
+            }
+            _ => {}
+        }
+
+        if furthest.map_or(true, |previous| region.end() > previous.end()) {
+            furthest = Some(region);
+        }
+    }
+
+    if let (Some(previous), Some(file_size)) = (furthest, file_size) {
+        if file_size > previous.end() {
+            gaps.push(LayoutGap {
+                offset: previous.end(),
+                size: file_size - previous.end(),
+                after: Some(previous.kind),
+                before: None,
+                is_padding: false,
+            });
+        }
+    }
+
+    LayoutReport {
+        segment_map: SegmentMap::new(program_headers, section_headers),
+        regions,
+        gaps,
+        overlaps,
+        file_size,
+    }
+}
+
+fn align_up(value: u64, alignment: u64) -> u64 {
+    match value % alignment {
+        0 => value,
+        remainder => value.saturating_add(alignment - remainder),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf::{PFlags, SHT_PROGBITS};
+
+    fn segment(p_type: u32, p_offset: u64, p_filesz: u64, p_memsz: u64) -> ProgramHeader {
+        ProgramHeader {
+            p_type,
+            p_flags: PFlags::empty(),
+            p_offset,
+            p_vaddr: 0x10000 + p_offset,
+            p_paddr: 0x10000 + p_offset,
+            p_filesz,
+            p_memsz,
+            p_align: 0x1000,
+        }
+    }
+
+    fn section(sh_type: u32, sh_flags: SHFlags, sh_offset: u64, sh_size: u64) -> SectionHeader {
+        SectionHeader {
+            sh_name: 0,
+            sh_type,
+            sh_flags,
+            sh_addr: 0x10000 + sh_offset,
+            sh_offset,
+            sh_size,
+            sh_link: 0,
+            sh_info: 0,
+            sh_addralign: 16,
+            sh_entsize: 0,
+        }
+    }
+
+    #[test]
+    fn map_sections_and_report_layout() {
+        let alloc = SHFlags::ALLOC;
+        let tls = SHFlags::ALLOC | SHFlags::TLS;
+        let section_headers = vec![
+            section(SHT_NULL, SHFlags::empty(), 0, 0),
+            // 1: `.text`, 2: `.tdata`, 3: `.tbss`, 4: `.bss`, 5: `.comment`, 6: overlaps `.comment`
+            section(SHT_PROGBITS, alloc, 0x100, 0x38),
+            section(SHT_PROGBITS, tls, 0x140, 0x10),
+            section(SHT_NOBITS, tls, 0x150, 0x20),
+            section(SHT_NOBITS, alloc, 0x150, 0x40),
+            section(SHT_PROGBITS, SHFlags::empty(), 0x200, 0x10),
+            section(SHT_PROGBITS, SHFlags::empty(), 0x208, 0x18),
+        ];
+        let program_headers = vec![
+            segment(PT_LOAD, 0, 0x150, 0x190),
+            segment(PT_TLS, 0x140, 0x10, 0x30),
+        ];
+
+        // `.tbss` doesn't take space in `PT_LOAD`, so `.bss` can start at the same address
+        assert!(is_tbss_special(&section_headers[3], &program_headers[0]));
+        assert!(section_in_segment(
+            &section_headers[3],
+            &program_headers[1],
+            true
+        ));
+
+        let header = Header {
+            e_ident: [0x7f, b'E', b'L', b'F', 2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            e_type: 2,
+            e_machine: 62,
+            e_version: 1,
+            e_entry: 0x10100,
+            e_phoff: 0x40,
+            e_shoff: 0x220,
+            e_flags: 0,
+            e_ehsize: 0x40,
+            e_phentsize: 0x38,
+            e_phnum: 2,
+            e_shentsize: 0x40,
+            e_shnum: 7,
+            e_shstrndx: 0,
+        };
+        let report = layout_report_parsed(&header, &program_headers, &section_headers, Some(0x400));
+
+        assert_eq!(report.segment_map.sections_of(0), &[1, 2, 4]);
+        assert_eq!(report.segment_map.sections_of(1), &[2, 3]);
+        assert_eq!(report.segment_map.segments_of(2), vec![0, 1]);
+        assert_eq!(
+            report
+                .segment_map
+                .unloaded_sections(&program_headers, &section_headers),
+            vec![3]
+        );
+
+        assert_eq!(report.regions.len(), 7);
+        assert_eq!(
+            report
+                .gaps
+                .iter()
+                .map(|gap| (gap.offset, gap.size, gap.is_padding))
+                .collect::<Vec<_>>(),
+            vec![
+                (0xb0, 0x50, false),
+                (0x138, 0x8, true),
+                (0x150, 0xb0, false),
+                (0x3e0, 0x20, false),
+            ]
+        );
+        assert_eq!(
+            report.overlaps,
+            vec![LayoutOverlap {
+                first: LayoutRegionKind::Section(5),
+                second: LayoutRegionKind::Section(6),
+                offset: 0x208,
+                size: 0x8,
+            }]
+        );
+    }
+}
@@ -0,0 +1,555 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Linux kernel module (`.ko`) conventions on top of relocatable objects
+//!
+//! Modules keep their metadata in a few specially named sections and `scripts/sign-file` appends a signature after
+//! the end of the ELF file.
+
+use crate::elf::{ElfClass, Reader, SectionHeader, StrTab, SHT_REL, SHT_RELA};
+use crate::stringable_consts_blocks::option_stringable_consts_block;
+use crate::{Details, Error};
+use scroll::{Endian, Pread};
+use std::io::Cursor;
+
+type Result<T> = std::result::Result<T, Error>;
+
+pub const MODINFO_SECTION_NAME: &str = ".modinfo";
+pub const VERSIONS_SECTION_NAME: &str = "__versions";
+pub const THIS_MODULE_SECTION_NAME: &str = ".gnu.linkonce.this_module";
+
+/// Trailer at the very end of a signed module
+pub const MODULE_SIG_STRING: &[u8] = b"~Module signature appended~\n";
+/// Size of `struct module_signature`, which sits right before `MODULE_SIG_STRING`
+pub const MODULE_SIGNATURE_SIZE: usize = 12;
+/// Size of a `struct modversion_info` and of the `MODULE_NAME_LEN` buffers including the `unsigned long` before them
+pub const MODVERSION_INFO_SIZE: usize = 64;
+
+// Signature `id_type` values
+option_stringable_consts_block! {
+    const stringable: u8 {
+        /// OpenPGP generated key ID
+        pub PKEY_ID_PGP = 0;
+        /// X.509 arbitrary subjectKeyIdentifier
+        pub PKEY_ID_X509 = 1;
+        /// Signature in PKCS#7 message, the only type the kernel still accepts
+        pub PKEY_ID_PKCS7 = 2;
+    }
+
+    const ignore: u8 {}
+
+    pub fn pkey_id_to_str(value: u8) -> Option<&'static str>;
+}
+
+/// The `key=value` strings of `.modinfo`, in section order
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ModInfo {
+    pub entries: Vec<(String, String)>,
+}
+
+impl ModInfo {
+    /// Parses NUL separated `key=value` strings, the padding NULs between them are skipped
+    pub fn parse(bytes: &[u8]) -> Result<ModInfo> {
+        let mut entries: Vec<(String, String)> = Vec::new();
+        let mut offset = 0;
+
+        for string in bytes.split(|&byte| byte == 0) {
+            if !string.is_empty() {
+                let string = std::str::from_utf8(string).map_err(|_| {
+                    Error::Malformed(
+                        Details::new("`.modinfo` entry isn't valid UTF-8")
+                            .structure("elf::linux_module::ModInfo")
+                            .offset(offset as u64),
+                    )
+                })?;
+                let (key, value) = string.split_once('=').ok_or_else(|| {
+                    Error::Malformed(
+                        Details::new(format!("`.modinfo` entry `{}` has no `=`", string))
+                            .structure("elf::linux_module::ModInfo")
+                            .offset(offset as u64),
+                    )
+                })?;
+                entries.push((key.to_string(), value.to_string()));
+            }
+
+            offset += string.len() + 1;
+        }
+
+        Ok(ModInfo { entries })
+    }
+
+    /// Returns the first value of `key`
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(entry_key, _)| entry_key == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns every value of `key`, keys like `alias`, `parm` and `firmware` are repeated
+    pub fn get_all<'b>(&'b self, key: &'b str) -> impl Iterator<Item = &'b str> + 'b {
+        self.entries
+            .iter()
+            .filter(move |(entry_key, _)| entry_key == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn license(&self) -> Option<&str> {
+        self.get("license")
+    }
+
+    pub fn vermagic(&self) -> Option<&str> {
+        self.get("vermagic")
+    }
+
+    pub fn aliases(&self) -> Vec<&str> {
+        self.get_all("alias").collect()
+    }
+
+    /// The comma separated `depends` entry split into module names
+    pub fn depends(&self) -> Vec<&str> {
+        self.get("depends")
+            .map(|depends| {
+                depends
+                    .split(',')
+                    .filter(|module| !module.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// A `struct modversion_info` of `__versions`, the CRC of a symbol the module was built against
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModVersion {
+    /// `unsigned long`, so only the low 32 bits are used on 32-bit kernels
+    pub crc: u64,
+    pub name: String,
+}
+
+impl ModVersion {
+    /// Parses every entry of `__versions`, the CRC is 4 bytes for `Elf32` and 8 bytes for `Elf64`
+    pub fn parse_section(
+        bytes: &[u8],
+        class: ElfClass,
+        endianness: Endian,
+    ) -> Result<Vec<ModVersion>> {
+        if bytes.len() % MODVERSION_INFO_SIZE != 0 {
+            return Err(Error::Malformed(
+                Details::new(format!(
+                    "`__versions` has a size of `{}`, which isn't a multiple of `{}`",
+                    bytes.len(),
+                    MODVERSION_INFO_SIZE
+                ))
+                .structure("elf::linux_module::ModVersion"),
+            ));
+        }
+
+        bytes
+            .chunks_exact(MODVERSION_INFO_SIZE)
+            .enumerate()
+            .map(|(index, entry)| {
+                let (crc, name_offset) = match class {
+                    ElfClass::Elf32 => (u64::from(entry.pread_with::<u32>(0, endianness)?), 4),
+                    ElfClass::Elf64 => (entry.pread_with::<u64>(0, endianness)?, 8),
+                };
+                let name = fixed_string(&entry[name_offset..]).map_err(|_| {
+                    Error::Malformed(
+                        Details::new("`__versions` symbol name isn't valid UTF-8")
+                            .structure("elf::linux_module::ModVersion")
+                            .field("name")
+                            .offset((index * MODVERSION_INFO_SIZE + name_offset) as u64),
+                    )
+                })?;
+
+                Ok(ModVersion { crc, name })
+            })
+            .collect()
+    }
+}
+
+/// A relocation against `.gnu.linkonce.this_module`, the way `struct module` gets pointers to the module's functions
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ThisModuleRelocation {
+    /// Offset in `struct module`
+    pub offset: u64,
+    pub symbol: String,
+}
+
+/// The `struct module` of `.gnu.linkonce.this_module`
+///
+/// The layout of `struct module` changes between kernel versions and configurations, only `name` is at a stable offset.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ThisModule {
+    pub name: String,
+    /// Size of `struct module` for the kernel the module was built against
+    pub size: u64,
+    pub relocations: Vec<ThisModuleRelocation>,
+}
+
+impl ThisModule {
+    /// Reads `name`, which follows `enum module_state state` and `struct list_head list`
+    pub fn parse(bytes: &[u8], class: ElfClass) -> Result<ThisModule> {
+        let name_offset = match class {
+            ElfClass::Elf32 => 12,
+            ElfClass::Elf64 => 24,
+        };
+        let name_size = match class {
+            ElfClass::Elf32 => MODVERSION_INFO_SIZE - 4,
+            ElfClass::Elf64 => MODVERSION_INFO_SIZE - 8,
+        };
+
+        let name_bytes = bytes
+            .get(name_offset..name_offset + name_size)
+            .ok_or_else(|| {
+                Error::Malformed(
+                    Details::new(format!(
+                        "`.gnu.linkonce.this_module` has a size of `{}`, which is too small for `struct module`",
+                        bytes.len()
+                    ))
+                    .structure("elf::linux_module::ThisModule"),
+                )
+            })?;
+        let name = fixed_string(name_bytes).map_err(|_| {
+            Error::Malformed(
+                Details::new("Module name isn't valid UTF-8")
+                    .structure("elf::linux_module::ThisModule")
+                    .field("name")
+                    .offset(name_offset as u64),
+            )
+        })?;
+
+        Ok(ThisModule {
+            name,
+            size: bytes.len() as u64,
+            relocations: Vec::new(),
+        })
+    }
+
+    /// The module sets `init` with `module_init`
+    pub fn has_init(&self) -> bool {
+        self.relocations
+            .iter()
+            .any(|relocation| relocation.symbol == "init_module")
+    }
+
+    /// The module sets `exit` with `module_exit`, modules without one can't be unloaded
+    pub fn has_exit(&self) -> bool {
+        self.relocations
+            .iter()
+            .any(|relocation| relocation.symbol == "cleanup_module")
+    }
+}
+
+/// The signature appended by `scripts/sign-file`, `struct module_signature` followed by `MODULE_SIG_STRING`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModuleSignature<'a> {
+    /// Public-key crypto algorithm, `0` for `PKEY_ID_PKCS7`
+    pub algo: u8,
+    /// Digest algorithm, `0` for `PKEY_ID_PKCS7`
+    pub hash: u8,
+    /// `PKEY_ID_*`
+    pub id_type: u8,
+    /// Length of the signer's name, `0` for `PKEY_ID_PKCS7`
+    pub signer_len: u8,
+    /// Length of the key identifier, `0` for `PKEY_ID_PKCS7`
+    pub key_id_len: u8,
+    /// The DER encoded PKCS#7 message for `PKEY_ID_PKCS7`
+    pub signature: &'a [u8],
+    /// Size of the signed module, the bytes before `signature`
+    pub module_size: usize,
+}
+
+impl<'a> ModuleSignature<'a> {
+    /// Parses the signature at the end of `file`, returns `None` if the module isn't signed
+    pub fn parse(file: &'a [u8]) -> Result<Option<ModuleSignature<'a>>> {
+        let Some(without_marker) = file.strip_suffix(MODULE_SIG_STRING) else {
+            return Ok(None);
+        };
+
+        let Some(header_offset) = without_marker.len().checked_sub(MODULE_SIGNATURE_SIZE) else {
+            return Err(Error::Malformed(
+                Details::new("Module signature trailer is too small for `struct module_signature`")
+                    .structure("elf::linux_module::ModuleSignature"),
+            ));
+        };
+        let header = &without_marker[header_offset..];
+        // `sig_len` is always big endian
+        let sig_len: u32 = header.pread_with(8, scroll::BE)?;
+
+        let module_size = (header_offset as u64)
+            .checked_sub(u64::from(sig_len))
+            .ok_or_else(|| {
+                Error::Malformed(
+                    Details::new(format!(
+                        "Module signature has a `sig_len` of `{}`, which is larger than the `{}` bytes before it",
+                        sig_len, header_offset
+                    ))
+                    .structure("elf::linux_module::ModuleSignature")
+                    .field("sig_len")
+                    .offset(header_offset as u64 + 8),
+                )
+            })? as usize;
+
+        Ok(Some(ModuleSignature {
+            algo: header[0],
+            hash: header[1],
+            id_type: header[2],
+            signer_len: header[3],
+            key_id_len: header[4],
+            signature: &without_marker[module_size..header_offset],
+            module_size,
+        }))
+    }
+}
+
+/// The module specific parts of a `.ko`, each is `None` or empty if its section is missing
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LinuxModule<'a> {
+    pub modinfo: ModInfo,
+    pub versions: Vec<ModVersion>,
+    pub this_module: Option<ThisModule>,
+    pub signature: Option<ModuleSignature<'a>>,
+}
+
+impl<'a> LinuxModule<'a> {
+    /// Parses a whole `.ko` file including the appended signature
+    pub fn parse(file: &'a [u8]) -> Result<LinuxModule<'a>> {
+        let signature = ModuleSignature::parse(file)?;
+        let elf_bytes = match &signature {
+            Some(signature) => &file[..signature.module_size],
+            None => file,
+        };
+
+        let mut cursor = Cursor::new(elf_bytes);
+        let ident = crate::elf::get_elf_ident(&mut cursor)?;
+
+        let mut result = match ident.class {
+            ElfClass::Elf32 => {
+                let mut reader = crate::elf::elf32::IoReader::new(&mut cursor, ident.endianness)?;
+                Self::read_file(&mut reader, ident.class, ident.endianness)?
+            }
+            ElfClass::Elf64 => {
+                let mut reader = crate::elf::elf64::IoReader::new(&mut cursor, ident.endianness)?;
+                Self::read_file(&mut reader, ident.class, ident.endianness)?
+            }
+        };
+        result.signature = signature;
+
+        Ok(result)
+    }
+
+    fn read_file<'b, TReader: Reader<'b>>(
+        reader: &mut TReader,
+        class: ElfClass,
+        endianness: Endian,
+    ) -> Result<LinuxModule<'a>> {
+        let header = reader.read_header()?;
+        let section_headers =
+            reader.read_section_headers(header.e_shoff, header.e_shentsize, header.e_shnum)?;
+        let shstrtab_header = section_headers
+            .get(header.e_shstrndx as usize)
+            .ok_or_else(|| {
+                Error::Malformed(
+                    Details::new(format!(
+                        "`e_shstrndx` of `{}` is out of range for `{}` sections",
+                        header.e_shstrndx,
+                        section_headers.len()
+                    ))
+                    .structure("elf::Header")
+                    .field("e_shstrndx"),
+                )
+            })?;
+        let shstrtab = reader.read_str_table_section(shstrtab_header)?;
+
+        Self::read(reader, class, endianness, &section_headers, &shstrtab)
+    }
+
+    /// Reads the module sections of an already parsed ELF
+    ///
+    /// The signature lives past the end of the ELF and can't be found from the section headers, `signature` is always
+    /// `None`. Use `ModuleSignature::parse` on the whole file or `LinuxModule::parse` for it.
+    pub fn read<'b, TReader: Reader<'b>>(
+        reader: &mut TReader,
+        class: ElfClass,
+        endianness: Endian,
+        section_headers: &[SectionHeader],
+        shstrtab: &StrTab,
+    ) -> Result<LinuxModule<'a>> {
+        let mut result = LinuxModule {
+            modinfo: ModInfo::default(),
+            versions: Vec::new(),
+            this_module: None,
+            signature: None,
+        };
+
+        for (index, section_header) in section_headers.iter().enumerate() {
+            let name = shstrtab.get_at_offset(section_header.sh_name)?;
+
+            match name.as_ref() {
+                MODINFO_SECTION_NAME => {
+                    let bytes = reader.get_decompressed_section_bytes(section_header)?;
+                    result.modinfo = ModInfo::parse(&bytes)?;
+                }
+                VERSIONS_SECTION_NAME => {
+                    let bytes = reader.get_decompressed_section_bytes(section_header)?;
+                    result.versions = ModVersion::parse_section(&bytes, class, endianness)?;
+                }
+                THIS_MODULE_SECTION_NAME => {
+                    let bytes = reader.get_decompressed_section_bytes(section_header)?;
+                    let mut this_module = ThisModule::parse(&bytes, class)?;
+                    this_module.relocations =
+                        Self::read_this_module_relocations(reader, section_headers, index)?;
+                    result.this_module = Some(this_module);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn read_this_module_relocations<'b, TReader: Reader<'b>>(
+        reader: &mut TReader,
+        section_headers: &[SectionHeader],
+        this_module_index: usize,
+    ) -> Result<Vec<ThisModuleRelocation>> {
+        let mut result: Vec<ThisModuleRelocation> = Vec::new();
+
+        for section_header in section_headers {
+            if !(section_header.sh_type == SHT_REL || section_header.sh_type == SHT_RELA)
+                || section_header.sh_info as usize != this_module_index
+            {
+                continue;
+            }
+
+            let relocations: Vec<(u64, u32)> = if section_header.sh_type == SHT_RELA {
+                reader
+                    .read_rela_section(section_header)?
+                    .iter()
+                    .map(|rela| (rela.r_offset, rela.r_sym()))
+                    .collect()
+            } else {
+                reader
+                    .read_rel_section(section_header)?
+                    .iter()
+                    .map(|rel| (rel.r_offset, rel.r_sym()))
+                    .collect()
+            };
+
+            let symtab_header = section_headers
+                .get(section_header.sh_link as usize)
+                .ok_or_else(|| out_of_range("sh_link", section_header.sh_link))?;
+            let strtab_header = section_headers
+                .get(symtab_header.sh_link as usize)
+                .ok_or_else(|| out_of_range("sh_link", symtab_header.sh_link))?;
+            let symbols = reader.read_sym_table(symtab_header.sh_offset, symtab_header.sh_size)?;
+            let strtab = reader.read_str_table_section(strtab_header)?;
+
+            for (offset, r_sym) in relocations {
+                let symbol = symbols
+                    .get(r_sym as usize)
+                    .ok_or_else(|| out_of_range("r_sym", r_sym))?;
+
+                result.push(ThisModuleRelocation {
+                    offset,
+                    symbol: strtab.get_at_offset(symbol.st_name)?.into_owned(),
+                });
+            }
+        }
+
+        result.sort_by_key(|relocation| relocation.offset);
+
+        Ok(result)
+    }
+}
+
+/// Reads a NUL padded fixed size string
+fn fixed_string(bytes: &[u8]) -> std::result::Result<String, std::str::Utf8Error> {
+    let end = bytes
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(bytes.len());
+    std::str::from_utf8(&bytes[..end]).map(str::to_string)
+}
+
+fn out_of_range(field: &'static str, value: u32) -> Error {
+    Error::Malformed(
+        Details::new(format!(
+            "`.gnu.linkonce.this_module` relocation has a `{}` of `{}`, which is out of range",
+            field, value
+        ))
+        .structure("elf::linux_module::ThisModule")
+        .field(field),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_module_metadata() {
+        let modinfo = ModInfo::parse(
+            b"license=GPL\0alias=pci:v00008086d*\0alias=pci:v000010DEd*\0\0\0depends=usbcore,,hid\0vermagic=6.8.0 SMP mod_unload \0",
+        )
+        .unwrap();
+        assert_eq!(modinfo.license(), Some("GPL"));
+        assert_eq!(modinfo.vermagic(), Some("6.8.0 SMP mod_unload "));
+        assert_eq!(
+            modinfo.aliases(),
+            vec!["pci:v00008086d*", "pci:v000010DEd*"]
+        );
+        assert_eq!(modinfo.depends(), vec!["usbcore", "hid"]);
+
+        let mut versions = vec![0u8; MODVERSION_INFO_SIZE * 2];
+        versions[..8].copy_from_slice(&0x1234_5678u64.to_le_bytes());
+        versions[8..21].copy_from_slice(b"module_layout");
+        versions[64..68].copy_from_slice(&0x9abc_def0u32.to_le_bytes());
+        versions[72..78].copy_from_slice(b"printk");
+        let versions = ModVersion::parse_section(&versions, ElfClass::Elf64, scroll::LE).unwrap();
+        assert_eq!(
+            versions
+                .iter()
+                .map(|version| (version.crc, version.name.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(0x1234_5678, "module_layout"), (0x9abc_def0, "printk")]
+        );
+
+        let mut this_module = vec![0u8; 0x400];
+        this_module[24..30].copy_from_slice(b"e1000e");
+        assert_eq!(
+            ThisModule::parse(&this_module, ElfClass::Elf64)
+                .unwrap()
+                .name,
+            "e1000e"
+        );
+
+        let file = [
+            b"\x7fELF module".as_slice(),
+            b"pkcs7",
+            &[0, 0, PKEY_ID_PKCS7, 0, 0, 0, 0, 0, 0, 0, 0, 5],
+            MODULE_SIG_STRING,
+        ]
+        .concat();
+        let signature = ModuleSignature::parse(&file).unwrap().unwrap();
+        assert_eq!(signature.id_type, PKEY_ID_PKCS7);
+        assert_eq!(signature.signature, b"pkcs7");
+        assert_eq!(&file[..signature.module_size], b"\x7fELF module");
+        assert!(ModuleSignature::parse(&file[..signature.module_size])
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,618 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::elf::amdgpu::{
+    ABIVERSION_AMDGPU_HSA_V3, ABIVERSION_AMDGPU_HSA_V4, EF_AMDGPU_FEATURE_SRAMECC_ANY_V4,
+    EF_AMDGPU_FEATURE_SRAMECC_OFF_V4, EF_AMDGPU_FEATURE_SRAMECC_ON_V4,
+    EF_AMDGPU_FEATURE_SRAMECC_V3, EF_AMDGPU_FEATURE_SRAMECC_V4, EF_AMDGPU_FEATURE_XNACK_ANY_V4,
+    EF_AMDGPU_FEATURE_XNACK_OFF_V4, EF_AMDGPU_FEATURE_XNACK_ON_V4, EF_AMDGPU_FEATURE_XNACK_V3,
+    EF_AMDGPU_FEATURE_XNACK_V4, EF_AMDGPU_MACH, EF_AMDGPU_MACH_NONE, OSABI_AMDGPU_HSA,
+};
+use crate::elf::arm::{
+    EF_ARM_ABI_FLOAT_HARD, EF_ARM_ABI_FLOAT_SOFT, EF_ARM_BE8, EF_ARM_EABIMASK, EF_ARM_LE8,
+};
+use crate::elf::loongarch::{
+    EF_LOONGARCH_ABI_DOUBLE_FLOAT, EF_LOONGARCH_ABI_MODIFIER_MASK, EF_LOONGARCH_ABI_SINGLE_FLOAT,
+    EF_LOONGARCH_ABI_SOFT_FLOAT, EF_LOONGARCH_OBJABI_MASK, EF_LOONGARCH_OBJABI_V0,
+    EF_LOONGARCH_OBJABI_V1,
+};
+use crate::elf::mips::*;
+use crate::elf::riscv::{
+    EF_RISCV_FLOAT_ABI, EF_RISCV_FLOAT_ABI_DOUBLE, EF_RISCV_FLOAT_ABI_QUAD,
+    EF_RISCV_FLOAT_ABI_SINGLE, EF_RISCV_FLOAT_ABI_SOFT, EF_RISCV_RVC, EF_RISCV_RVE, EF_RISCV_TSO,
+};
+use crate::elf::{
+    Header, EI_ABIVERSION, EI_OSABI, EM_AMDGPU, EM_ARM, EM_LOONGARCH, EM_MIPS, EM_MIPS_RS3_LE,
+    EM_RISCV,
+};
+use std::fmt;
+
+/// `e_flags` decoded according to `e_machine`
+///
+/// `Display` prints the raw value followed by the decoded flags, the same as the `Flags:` line of `readelf -h`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MachineFlags {
+    Arm(ArmFlags),
+    Mips(MipsFlags),
+    RiscV(RiscVFlags),
+    LoongArch(LoongArchFlags),
+    AmdGpu(AmdGpuFlags),
+    /// `e_flags` of a machine without a decoder
+    Other(u32),
+}
+
+impl MachineFlags {
+    pub fn new(header: &Header) -> Self {
+        let e_flags = header.e_flags;
+
+        match header.e_machine {
+            EM_ARM => MachineFlags::Arm(ArmFlags::new(e_flags)),
+            EM_MIPS | EM_MIPS_RS3_LE => MachineFlags::Mips(MipsFlags::new(e_flags)),
+            EM_RISCV => MachineFlags::RiscV(RiscVFlags::new(e_flags)),
+            EM_LOONGARCH => MachineFlags::LoongArch(LoongArchFlags::new(e_flags)),
+            EM_AMDGPU => MachineFlags::AmdGpu(AmdGpuFlags::new(
+                e_flags,
+                header.e_ident[EI_OSABI],
+                header.e_ident[EI_ABIVERSION],
+            )),
+            _ => MachineFlags::Other(e_flags),
+        }
+    }
+
+    /// The raw `e_flags` value
+    pub fn e_flags(&self) -> u32 {
+        match self {
+            MachineFlags::Arm(flags) => flags.e_flags,
+            MachineFlags::Mips(flags) => flags.e_flags,
+            MachineFlags::RiscV(flags) => flags.e_flags,
+            MachineFlags::LoongArch(flags) => flags.e_flags,
+            MachineFlags::AmdGpu(flags) => flags.e_flags,
+            MachineFlags::Other(e_flags) => *e_flags,
+        }
+    }
+
+    /// The decoded flags in `readelf -h` order, e.g. `["Version5 EABI", "hard-float ABI"]`
+    pub fn descriptions(&self) -> Vec<String> {
+        match self {
+            MachineFlags::Arm(flags) => flags.descriptions(),
+            MachineFlags::Mips(flags) => flags.descriptions(),
+            MachineFlags::RiscV(flags) => flags.descriptions(),
+            MachineFlags::LoongArch(flags) => flags.descriptions(),
+            MachineFlags::AmdGpu(flags) => flags.descriptions(),
+            MachineFlags::Other(_) => Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for MachineFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{:x}", self.e_flags())?;
+
+        for description in self.descriptions() {
+            write!(f, ", {}", description)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FloatAbi {
+    Soft,
+    Single,
+    Double,
+    Quad,
+    /// ARM's hard-float ABI, the precision depends on the FPU
+    Hard,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ArmFlags {
+    pub e_flags: u32,
+    /// `0` for pre-EABI (GNU) objects
+    pub eabi_version: u8,
+    /// Only set for EABI version 5
+    pub float_abi: Option<FloatAbi>,
+    pub be8: bool,
+    pub le8: bool,
+    /// Bits that aren't defined for `eabi_version`
+    pub unknown: u32,
+}
+
+impl ArmFlags {
+    pub fn new(e_flags: u32) -> Self {
+        let eabi_version = ((e_flags & EF_ARM_EABIMASK) >> 24) as u8;
+        let (known, float_abi) = match eabi_version {
+            5 => {
+                let float_abi = if e_flags & EF_ARM_ABI_FLOAT_HARD != 0 {
+                    Some(FloatAbi::Hard)
+                } else if e_flags & EF_ARM_ABI_FLOAT_SOFT != 0 {
+                    Some(FloatAbi::Soft)
+                } else {
+                    None
+                };
+
+                (
+                    EF_ARM_BE8 | EF_ARM_LE8 | EF_ARM_ABI_FLOAT_HARD | EF_ARM_ABI_FLOAT_SOFT,
+                    float_abi,
+                )
+            }
+            4 => (EF_ARM_BE8 | EF_ARM_LE8, None),
+            _ => (0, None),
+        };
+
+        ArmFlags {
+            e_flags,
+            eabi_version,
+            float_abi,
+            be8: known & e_flags & EF_ARM_BE8 != 0,
+            le8: known & e_flags & EF_ARM_LE8 != 0,
+            unknown: e_flags & !(EF_ARM_EABIMASK | known),
+        }
+    }
+
+    fn descriptions(&self) -> Vec<String> {
+        let mut result: Vec<String> = Vec::new();
+
+        match self.eabi_version {
+            0 => result.push("GNU EABI".to_string()),
+            1..=5 => result.push(format!("Version{} EABI", self.eabi_version)),
+            _ => result.push("<unrecognized EABI>".to_string()),
+        }
+
+        if self.be8 {
+            result.push("BE8".to_string());
+        }
+
+        if self.le8 {
+            result.push("LE8".to_string());
+        }
+
+        match self.float_abi {
+            Some(FloatAbi::Hard) => result.push("hard-float ABI".to_string()),
+            Some(FloatAbi::Soft) => result.push("soft-float ABI".to_string()),
+            _ => {}
+        }
+
+        if self.unknown != 0 {
+            result.push("<unknown>".to_string());
+        }
+
+        result
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MipsFlags {
+    pub e_flags: u32,
+    pub noreorder: bool,
+    pub pic: bool,
+    pub cpic: bool,
+    /// N32 ABI
+    pub abi2: bool,
+    pub mode_32bit: bool,
+    pub nan2008: bool,
+    pub fp64: bool,
+    /// `EF_MIPS_MACH_*`
+    pub mach: u32,
+    /// `EF_MIPS_ABI_*`, `0` when the ABI is implied by the ELF class and `abi2`
+    pub abi: u32,
+    pub ase_mdmx: bool,
+    pub ase_m16: bool,
+    pub micromips: bool,
+    /// `EF_MIPS_ARCH_*`
+    pub arch: u32,
+}
+
+impl MipsFlags {
+    pub fn new(e_flags: u32) -> Self {
+        MipsFlags {
+            e_flags,
+            noreorder: e_flags & EF_MIPS_NOREORDER != 0,
+            pic: e_flags & EF_MIPS_PIC != 0,
+            cpic: e_flags & EF_MIPS_CPIC != 0,
+            abi2: e_flags & EF_MIPS_ABI2 != 0,
+            mode_32bit: e_flags & EF_MIPS_32BITMODE != 0,
+            nan2008: e_flags & EF_MIPS_NAN2008 != 0,
+            fp64: e_flags & EF_MIPS_FP64 != 0,
+            mach: e_flags & EF_MIPS_MACH,
+            abi: e_flags & EF_MIPS_ABI,
+            ase_mdmx: e_flags & EF_MIPS_ARCH_ASE_MDMX != 0,
+            ase_m16: e_flags & EF_MIPS_ARCH_ASE_M16 != 0,
+            micromips: e_flags & EF_MIPS_MICROMIPS != 0,
+            arch: e_flags & EF_MIPS_ARCH,
+        }
+    }
+
+    /// The `readelf` name of `mach`, e.g. `octeon2`
+    pub fn mach_name(&self) -> Option<&'static str> {
+        match self.mach {
+            EF_MIPS_MACH_3900 => Some("3900"),
+            EF_MIPS_MACH_4010 => Some("4010"),
+            EF_MIPS_MACH_4100 => Some("4100"),
+            EF_MIPS_MACH_4111 => Some("4111"),
+            EF_MIPS_MACH_4120 => Some("4120"),
+            EF_MIPS_MACH_4650 => Some("4650"),
+            EF_MIPS_MACH_5400 => Some("5400"),
+            EF_MIPS_MACH_5500 => Some("5500"),
+            EF_MIPS_MACH_5900 => Some("5900"),
+            EF_MIPS_MACH_SB1 => Some("sb1"),
+            EF_MIPS_MACH_9000 => Some("9000"),
+            EF_MIPS_MACH_LS2E => Some("loongson-2e"),
+            EF_MIPS_MACH_LS2F => Some("loongson-2f"),
+            EF_MIPS_MACH_LS3A => Some("loongson-3a"),
+            EF_MIPS_MACH_OCTEON => Some("octeon"),
+            EF_MIPS_MACH_OCTEON2 => Some("octeon2"),
+            EF_MIPS_MACH_OCTEON3 => Some("octeon3"),
+            EF_MIPS_MACH_XLR => Some("xlr"),
+            _ => None,
+        }
+    }
+
+    /// The `readelf` name of `abi`, e.g. `o32`
+    pub fn abi_name(&self) -> Option<&'static str> {
+        match self.abi {
+            EF_MIPS_ABI_O32 => Some("o32"),
+            EF_MIPS_ABI_O64 => Some("o64"),
+            EF_MIPS_ABI_EABI32 => Some("eabi32"),
+            EF_MIPS_ABI_EABI64 => Some("eabi64"),
+            _ => None,
+        }
+    }
+
+    /// The `readelf` name of `arch`, e.g. `mips32r2`
+    pub fn arch_name(&self) -> Option<&'static str> {
+        match self.arch {
+            EF_MIPS_ARCH_1 => Some("mips1"),
+            EF_MIPS_ARCH_2 => Some("mips2"),
+            EF_MIPS_ARCH_3 => Some("mips3"),
+            EF_MIPS_ARCH_4 => Some("mips4"),
+            EF_MIPS_ARCH_5 => Some("mips5"),
+            EF_MIPS_ARCH_32 => Some("mips32"),
+            EF_MIPS_ARCH_32R2 => Some("mips32r2"),
+            EF_MIPS_ARCH_32R6 => Some("mips32r6"),
+            EF_MIPS_ARCH_64 => Some("mips64"),
+            EF_MIPS_ARCH_64R2 => Some("mips64r2"),
+            EF_MIPS_ARCH_64R6 => Some("mips64r6"),
+            _ => None,
+        }
+    }
+
+    fn descriptions(&self) -> Vec<String> {
+        let mut result: Vec<String> = Vec::new();
+        let mut push_if = |condition: bool, description: &str| {
+            if condition {
+                result.push(description.to_string());
+            }
+        };
+
+        push_if(self.noreorder, "noreorder");
+        push_if(self.pic, "pic");
+        push_if(self.cpic, "cpic");
+        push_if(self.abi2, "abi2");
+        push_if(self.mode_32bit, "32bitmode");
+        push_if(self.nan2008, "nan2008");
+        push_if(self.fp64, "fp64");
+
+        if self.mach != EF_MIPS_MACH_NONE {
+            result.push(self.mach_name().unwrap_or("unknown CPU").to_string());
+        }
+
+        if self.abi != 0 {
+            result.push(self.abi_name().unwrap_or("unknown ABI").to_string());
+        }
+
+        if self.ase_mdmx {
+            result.push("mdmx".to_string());
+        }
+
+        if self.ase_m16 {
+            result.push("mips16".to_string());
+        }
+
+        if self.micromips {
+            result.push("micromips".to_string());
+        }
+
+        result.push(self.arch_name().unwrap_or("unknown ISA").to_string());
+
+        result
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RiscVFlags {
+    pub e_flags: u32,
+    /// Compressed instructions
+    pub rvc: bool,
+    /// RV32E base ISA
+    pub rve: bool,
+    /// Total store ordering memory model
+    pub tso: bool,
+    /// Never `FloatAbi::Hard`
+    pub float_abi: FloatAbi,
+}
+
+impl RiscVFlags {
+    pub fn new(e_flags: u32) -> Self {
+        let float_abi = match e_flags & EF_RISCV_FLOAT_ABI {
+            EF_RISCV_FLOAT_ABI_SOFT => FloatAbi::Soft,
+            EF_RISCV_FLOAT_ABI_SINGLE => FloatAbi::Single,
+            EF_RISCV_FLOAT_ABI_DOUBLE => FloatAbi::Double,
+            EF_RISCV_FLOAT_ABI_QUAD => FloatAbi::Quad,
+            _ => unreachable!("`EF_RISCV_FLOAT_ABI` is a two bit mask"),
+        };
+
+        RiscVFlags {
+            e_flags,
+            rvc: e_flags & EF_RISCV_RVC != 0,
+            rve: e_flags & EF_RISCV_RVE != 0,
+            tso: e_flags & EF_RISCV_TSO != 0,
+            float_abi,
+        }
+    }
+
+    fn descriptions(&self) -> Vec<String> {
+        let mut result: Vec<String> = Vec::new();
+
+        if self.rvc {
+            result.push("RVC".to_string());
+        }
+
+        if self.rve {
+            result.push("RVE".to_string());
+        }
+
+        if self.tso {
+            result.push("TSO".to_string());
+        }
+
+        result.push(
+            match self.float_abi {
+                FloatAbi::Soft => "soft-float ABI",
+                FloatAbi::Single => "single-float ABI",
+                FloatAbi::Double => "double-float ABI",
+                FloatAbi::Quad | FloatAbi::Hard => "quad-float ABI",
+            }
+            .to_string(),
+        );
+
+        result
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LoongArchFlags {
+    pub e_flags: u32,
+    /// `None` for reserved ABI modifiers
+    pub float_abi: Option<FloatAbi>,
+    /// `None` for reserved object file ABI versions
+    pub object_abi_version: Option<u8>,
+}
+
+impl LoongArchFlags {
+    pub fn new(e_flags: u32) -> Self {
+        let float_abi = match e_flags & EF_LOONGARCH_ABI_MODIFIER_MASK {
+            EF_LOONGARCH_ABI_SOFT_FLOAT => Some(FloatAbi::Soft),
+            EF_LOONGARCH_ABI_SINGLE_FLOAT => Some(FloatAbi::Single),
+            EF_LOONGARCH_ABI_DOUBLE_FLOAT => Some(FloatAbi::Double),
+            _ => None,
+        };
+        let object_abi_version = match e_flags & EF_LOONGARCH_OBJABI_MASK {
+            EF_LOONGARCH_OBJABI_V0 => Some(0),
+            EF_LOONGARCH_OBJABI_V1 => Some(1),
+            _ => None,
+        };
+
+        LoongArchFlags {
+            e_flags,
+            float_abi,
+            object_abi_version,
+        }
+    }
+
+    fn descriptions(&self) -> Vec<String> {
+        let mut result: Vec<String> = Vec::new();
+
+        match self.float_abi {
+            Some(FloatAbi::Soft) => result.push("SOFT-FLOAT".to_string()),
+            Some(FloatAbi::Single) => result.push("SINGLE-FLOAT".to_string()),
+            Some(FloatAbi::Double) => result.push("DOUBLE-FLOAT".to_string()),
+            _ => {}
+        }
+
+        if let Some(version) = self.object_abi_version {
+            result.push(format!("OBJ-v{}", version));
+        }
+
+        result
+    }
+}
+
+/// Setting of an AMDGPU target feature like `xnack` or `sramecc`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AmdGpuFeature {
+    Unsupported,
+    /// The code works with the feature either on or off
+    Any,
+    Off,
+    On,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AmdGpuFlags {
+    pub e_flags: u32,
+    /// `EF_AMDGPU_MACH_*`
+    pub mach: u32,
+    pub xnack: AmdGpuFeature,
+    pub sramecc: AmdGpuFeature,
+    /// Bits that aren't defined for the code object version
+    pub unknown: u32,
+}
+
+impl AmdGpuFlags {
+    /// The feature bits depend on the code object version, which comes from `EI_OSABI` and `EI_ABIVERSION`
+    pub fn new(e_flags: u32, osabi: u8, abiversion: u8) -> Self {
+        let mach = e_flags & EF_AMDGPU_MACH;
+        let mut rest = e_flags & !EF_AMDGPU_MACH;
+        let mut xnack = AmdGpuFeature::Unsupported;
+        let mut sramecc = AmdGpuFeature::Unsupported;
+        let abiversion = u32::from(abiversion);
+
+        let v4_feature = |value: u32, any: u32, off: u32, on: u32| {
+            if value == any {
+                AmdGpuFeature::Any
+            } else if value == off {
+                AmdGpuFeature::Off
+            } else if value == on {
+                AmdGpuFeature::On
+            } else {
+                AmdGpuFeature::Unsupported
+            }
+        };
+
+        if osabi != OSABI_AMDGPU_HSA || abiversion == ABIVERSION_AMDGPU_HSA_V3 {
+            if rest & EF_AMDGPU_FEATURE_XNACK_V3 != 0 {
+                xnack = AmdGpuFeature::On;
+            }
+
+            if rest & EF_AMDGPU_FEATURE_SRAMECC_V3 != 0 {
+                sramecc = AmdGpuFeature::On;
+            }
+
+            rest &= !(EF_AMDGPU_FEATURE_XNACK_V3 | EF_AMDGPU_FEATURE_SRAMECC_V3);
+        } else if abiversion >= ABIVERSION_AMDGPU_HSA_V4 {
+            xnack = v4_feature(
+                rest & EF_AMDGPU_FEATURE_XNACK_V4,
+                EF_AMDGPU_FEATURE_XNACK_ANY_V4,
+                EF_AMDGPU_FEATURE_XNACK_OFF_V4,
+                EF_AMDGPU_FEATURE_XNACK_ON_V4,
+            );
+            sramecc = v4_feature(
+                rest & EF_AMDGPU_FEATURE_SRAMECC_V4,
+                EF_AMDGPU_FEATURE_SRAMECC_ANY_V4,
+                EF_AMDGPU_FEATURE_SRAMECC_OFF_V4,
+                EF_AMDGPU_FEATURE_SRAMECC_ON_V4,
+            );
+
+            rest &= !(EF_AMDGPU_FEATURE_XNACK_V4 | EF_AMDGPU_FEATURE_SRAMECC_V4);
+        }
+
+        AmdGpuFlags {
+            e_flags,
+            mach,
+            xnack,
+            sramecc,
+            unknown: rest,
+        }
+    }
+
+    /// The target GPU as LLVM names it, e.g. `gfx90a` or `cayman`
+    pub fn target_name(&self) -> Option<String> {
+        if self.mach == EF_AMDGPU_MACH_NONE {
+            return Some("none".to_string());
+        }
+
+        let name = crate::elf::amdgpu::mach_to_str(self.mach)?;
+        let name = name
+            .strip_prefix("EF_AMDGPU_MACH_AMDGCN_")
+            .or_else(|| name.strip_prefix("EF_AMDGPU_MACH_R600_"))
+            .unwrap_or(name);
+
+        Some(name.to_ascii_lowercase())
+    }
+
+    fn descriptions(&self) -> Vec<String> {
+        let mut result: Vec<String> = Vec::new();
+
+        match self.target_name() {
+            Some(name) => result.push(name),
+            None => result.push(format!("<unknown AMDGPU GPU type: 0x{:x}>", self.mach)),
+        }
+
+        for (name, feature) in [("xnack", self.xnack), ("sramecc", self.sramecc)] {
+            match feature {
+                AmdGpuFeature::Unsupported => {}
+                AmdGpuFeature::Any => result.push(format!("{} any", name)),
+                AmdGpuFeature::Off => result.push(format!("{} off", name)),
+                AmdGpuFeature::On => result.push(format!("{} on", name)),
+            }
+        }
+
+        if self.unknown != 0 {
+            result.push(format!("unknown flags bits: 0x{:x}", self.unknown));
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(e_machine: u16, e_flags: u32, osabi: u8, abiversion: u8) -> Header {
+        let mut e_ident = [0u8; 16];
+        e_ident[EI_OSABI] = osabi;
+        e_ident[EI_ABIVERSION] = abiversion;
+
+        Header {
+            e_ident,
+            e_type: crate::elf::ET_DYN,
+            e_machine,
+            e_version: 1,
+            e_entry: 0,
+            e_phoff: 0,
+            e_shoff: 0,
+            e_flags,
+            e_ehsize: 0,
+            e_phentsize: 0,
+            e_phnum: 0,
+            e_shentsize: 0,
+            e_shnum: 0,
+            e_shstrndx: 0,
+        }
+    }
+
+    #[test]
+    fn display_like_readelf() {
+        let display = |e_machine: u16, e_flags: u32, osabi: u8, abiversion: u8| {
+            MachineFlags::new(&header(e_machine, e_flags, osabi, abiversion)).to_string()
+        };
+
+        assert_eq!(
+            display(EM_ARM, 0x5000400, 0, 0),
+            "0x5000400, Version5 EABI, hard-float ABI"
+        );
+        assert_eq!(display(EM_RISCV, 0x5, 0, 0), "0x5, RVC, double-float ABI");
+        assert_eq!(
+            display(EM_MIPS, 0x70001007, 0, 0),
+            "0x70001007, noreorder, pic, cpic, o32, mips32r2"
+        );
+        assert_eq!(
+            display(EM_LOONGARCH, 0x43, 0, 0),
+            "0x43, DOUBLE-FLOAT, OBJ-v1"
+        );
+        assert_eq!(
+            display(EM_AMDGPU, 0x53f, OSABI_AMDGPU_HSA, 2),
+            "0x53f, gfx90a, xnack any, sramecc any"
+        );
+        assert_eq!(
+            display(EM_AMDGPU, 0x130, OSABI_AMDGPU_HSA, 1),
+            "0x130, gfx908, xnack on"
+        );
+        assert_eq!(display(crate::elf::EM_X86_64, 0, 0, 0), "0x0");
+    }
+}
@@ -0,0 +1,283 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::stringable_consts_blocks::option_stringable_consts_block;
+use crate::{Details, Error};
+use bitflags::bitflags;
+use scroll::{Endian, Pread};
+use scroll_derive::{IOread, IOwrite, Pread, Pwrite, SizeWith};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// `Elf_MIPS_ABIFlags_v0`, the contents of `.MIPS.abiflags` (`SHT_MIPS_ABIFLAGS`/`PT_MIPS_ABIFLAGS`)
+///
+/// This has the same layout for both 32-bit and 64-bit files.
+#[repr(C)]
+#[derive(Debug, PartialEq, Pread, Pwrite, IOread, IOwrite, SizeWith)]
+pub struct ABIFlags {
+    /// Version of the structure, only `0` exists
+    pub version: u16,
+    /// ISA level, e.g. `32` for MIPS32 or `64` for MIPS64
+    pub isa_level: u8,
+    /// ISA revision, e.g. `2` for MIPS32r2
+    pub isa_rev: u8,
+    /// General purpose register size (`AFL_REG_*`)
+    pub gpr_size: u8,
+    /// Co-processor 1 (FPU) register size (`AFL_REG_*`)
+    pub cpr1_size: u8,
+    /// Co-processor 2 register size (`AFL_REG_*`)
+    pub cpr2_size: u8,
+    /// Floating point ABI (`VAL_GNU_MIPS_ABI_FP_*`)
+    pub fp_abi: u8,
+    /// Processor specific extension (`AFL_EXT_*`)
+    pub isa_ext: u32,
+    /// Application specific extensions (`AFL_ASE_*`)
+    pub ases: u32,
+    /// `AFL_FLAGS1_*`
+    pub flags1: u32,
+    /// Reserved, should be `0`
+    pub flags2: u32,
+}
+
+impl ABIFlags {
+    /// Parses the bytes of `.MIPS.abiflags`, e.g. from `Reader::get_section_bytes`
+    pub fn parse(bytes: &[u8], endianness: Endian) -> Result<ABIFlags> {
+        let abiflags = bytes.pread_with::<ABIFlags>(0, endianness)?;
+
+        if abiflags.version != 0 {
+            Err(Error::InvalidVersionNumber(
+                Details::new(format!(
+                    "Expected `0` for `.MIPS.abiflags` version, found `{}`",
+                    abiflags.version
+                ))
+                .structure("elf::mips::ABIFlags")
+                .field("version"),
+            ))
+        } else {
+            Ok(abiflags)
+        }
+    }
+
+    pub fn ases(&self) -> ASEFlags {
+        ASEFlags::from_bits_retain(self.ases)
+    }
+
+    /// Whether odd-numbered single precision registers may be used
+    pub fn has_odd_spreg(&self) -> bool {
+        self.flags1 & AFL_FLAGS1_ODDSPREG != 0
+    }
+}
+
+// Register sizes
+option_stringable_consts_block! {
+    const stringable: u8 {
+        /// No registers
+        pub AFL_REG_NONE = 0;
+        /// 32-bit registers
+        pub AFL_REG_32 = 1;
+        /// 64-bit registers
+        pub AFL_REG_64 = 2;
+        /// 128-bit registers
+        pub AFL_REG_128 = 3;
+    }
+
+    const ignore: u8 {}
+
+    pub fn afl_reg_to_str(value: u8) -> Option<&'static str>;
+}
+
+// Floating point ABIs, these are shared with the `Tag_GNU_MIPS_ABI_FP` GNU attribute
+option_stringable_consts_block! {
+    const stringable: u8 {
+        /// Not tagged or not using any ABIs affected by the differences
+        pub VAL_GNU_MIPS_ABI_FP_ANY = 0;
+        /// Using hard-float -mdouble-float
+        pub VAL_GNU_MIPS_ABI_FP_DOUBLE = 1;
+        /// Using hard-float -msingle-float
+        pub VAL_GNU_MIPS_ABI_FP_SINGLE = 2;
+        /// Using soft-float
+        pub VAL_GNU_MIPS_ABI_FP_SOFT = 3;
+        /// Using -mips32r2 -mfp64
+        pub VAL_GNU_MIPS_ABI_FP_OLD_64 = 4;
+        /// Using -mfpxx
+        pub VAL_GNU_MIPS_ABI_FP_XX = 5;
+        /// Using -mips32r2 -mfp64
+        pub VAL_GNU_MIPS_ABI_FP_64 = 6;
+        /// Using -mips32r2 -mfp64 -mno-odd-spreg
+        pub VAL_GNU_MIPS_ABI_FP_64A = 7;
+    }
+
+    const ignore: u8 {}
+
+    pub fn fp_abi_to_str(value: u8) -> Option<&'static str>;
+}
+
+// Processor specific extensions
+option_stringable_consts_block! {
+    const stringable: u32 {
+        pub AFL_EXT_NONE = 0;
+        /// RMI Xlr instruction
+        pub AFL_EXT_XLR = 1;
+        /// Cavium Networks Octeon2
+        pub AFL_EXT_OCTEON2 = 2;
+        /// Cavium Networks OcteonP
+        pub AFL_EXT_OCTEONP = 3;
+        /// Loongson 3A
+        pub AFL_EXT_LOONGSON_3A = 4;
+        /// Cavium Networks Octeon
+        pub AFL_EXT_OCTEON = 5;
+        /// MIPS R5900 instruction
+        pub AFL_EXT_5900 = 6;
+        /// MIPS R4650 instruction
+        pub AFL_EXT_4650 = 7;
+        /// LSI R4010 instruction
+        pub AFL_EXT_4010 = 8;
+        /// NEC VR4100 instruction
+        pub AFL_EXT_4100 = 9;
+        /// Toshiba R3900 instruction
+        pub AFL_EXT_3900 = 10;
+        /// MIPS R10000 instruction
+        pub AFL_EXT_10000 = 11;
+        /// Broadcom SB-1 instruction
+        pub AFL_EXT_SB1 = 12;
+        /// NEC VR4111/VR4181 instruction
+        pub AFL_EXT_4111 = 13;
+        /// NEC VR4120 instruction
+        pub AFL_EXT_4120 = 14;
+        /// NEC VR5400 instruction
+        pub AFL_EXT_5400 = 15;
+        /// NEC VR5500 instruction
+        pub AFL_EXT_5500 = 16;
+        /// ST Microelectronics Loongson 2E
+        pub AFL_EXT_LOONGSON_2E = 17;
+        /// ST Microelectronics Loongson 2F
+        pub AFL_EXT_LOONGSON_2F = 18;
+        /// Cavium Networks Octeon3
+        pub AFL_EXT_OCTEON3 = 19;
+    }
+
+    const ignore: u32 {}
+
+    pub fn afl_ext_to_str(value: u32) -> Option<&'static str>;
+}
+
+// Application specific extensions
+/// DSP ASE
+pub const AFL_ASE_DSP: u32 = 0x00000001;
+/// DSP R2 ASE
+pub const AFL_ASE_DSPR2: u32 = 0x00000002;
+/// Enhanced VA Scheme
+pub const AFL_ASE_EVA: u32 = 0x00000004;
+/// MCU (MicroController) ASE
+pub const AFL_ASE_MCU: u32 = 0x00000008;
+/// MDMX ASE
+pub const AFL_ASE_MDMX: u32 = 0x00000010;
+/// MIPS-3D ASE
+pub const AFL_ASE_MIPS3D: u32 = 0x00000020;
+/// MT ASE
+pub const AFL_ASE_MT: u32 = 0x00000040;
+/// SmartMIPS ASE
+pub const AFL_ASE_SMARTMIPS: u32 = 0x00000080;
+/// VZ ASE
+pub const AFL_ASE_VIRT: u32 = 0x00000100;
+/// MSA ASE
+pub const AFL_ASE_MSA: u32 = 0x00000200;
+/// MIPS16 ASE
+pub const AFL_ASE_MIPS16: u32 = 0x00000400;
+/// MICROMIPS ASE
+pub const AFL_ASE_MICROMIPS: u32 = 0x00000800;
+/// XPA ASE
+pub const AFL_ASE_XPA: u32 = 0x00001000;
+/// CRC ASE
+pub const AFL_ASE_CRC: u32 = 0x00008000;
+/// GINV ASE
+pub const AFL_ASE_GINV: u32 = 0x00020000;
+
+bitflags! {
+    #[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct ASEFlags: u32 {
+        const DSP = AFL_ASE_DSP;
+        const DSPR2 = AFL_ASE_DSPR2;
+        const EVA = AFL_ASE_EVA;
+        const MCU = AFL_ASE_MCU;
+        const MDMX = AFL_ASE_MDMX;
+        const MIPS3D = AFL_ASE_MIPS3D;
+        const MT = AFL_ASE_MT;
+        const SMARTMIPS = AFL_ASE_SMARTMIPS;
+        const VIRT = AFL_ASE_VIRT;
+        const MSA = AFL_ASE_MSA;
+        const MIPS16 = AFL_ASE_MIPS16;
+        const MICROMIPS = AFL_ASE_MICROMIPS;
+        const XPA = AFL_ASE_XPA;
+        const CRC = AFL_ASE_CRC;
+        const GINV = AFL_ASE_GINV;
+    }
+}
+
+// flags1
+/// Uses odd single-precision registers
+pub const AFL_FLAGS1_ODDSPREG: u32 = 1;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scroll::Pwrite;
+
+    #[test]
+    fn abiflags_size_check() {
+        assert_eq!(::std::mem::size_of::<ABIFlags>(), 0x18);
+    }
+
+    #[test]
+    fn parse_abiflags() {
+        let mut bytes = [0u8; 0x18];
+        bytes
+            .pwrite_with(
+                ABIFlags {
+                    version: 0,
+                    isa_level: 32,
+                    isa_rev: 2,
+                    gpr_size: AFL_REG_32,
+                    cpr1_size: AFL_REG_64,
+                    cpr2_size: AFL_REG_NONE,
+                    fp_abi: VAL_GNU_MIPS_ABI_FP_XX,
+                    isa_ext: AFL_EXT_NONE,
+                    ases: 0,
+                    flags1: AFL_FLAGS1_ODDSPREG,
+                    flags2: 0,
+                },
+                0,
+                scroll::BE,
+            )
+            .unwrap();
+
+        let abiflags = ABIFlags::parse(&bytes, scroll::BE).unwrap();
+        assert_eq!(abiflags.isa_level, 32);
+        assert_eq!(abiflags.isa_rev, 2);
+        assert_eq!(
+            fp_abi_to_str(abiflags.fp_abi),
+            Some("VAL_GNU_MIPS_ABI_FP_XX")
+        );
+        assert!(abiflags.has_odd_spreg());
+
+        // Unknown structure version
+        bytes.pwrite_with(1u16, 0, scroll::BE).unwrap();
+        assert!(matches!(
+            ABIFlags::parse(&bytes, scroll::BE),
+            Err(Error::InvalidVersionNumber(_))
+        ));
+    }
+}
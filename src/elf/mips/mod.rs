@@ -16,6 +16,11 @@
 
 use crate::stringable_consts_blocks::option_stringable_consts_block;
 
+mod abiflags;
+pub use abiflags::*;
+mod options;
+pub use options::*;
+
 // e_flags
 /// Don't reorder instructions
 pub const EF_MIPS_NOREORDER: u32 = 0x00000001;
@@ -0,0 +1,271 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::elf::ElfClass;
+use crate::{Details, Error};
+use scroll::{Endian, Pread};
+use scroll_derive::{IOread, IOwrite, Pread, Pwrite, SizeWith};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// `Elf_Options`, the header of every record within `.MIPS.options`
+#[repr(C)]
+#[derive(Debug, PartialEq, Pread, Pwrite, IOread, IOwrite, SizeWith)]
+pub struct OptionsHeader {
+    /// Determines interpretation of the record (`ODK_*`)
+    pub kind: u8,
+    /// Size of the record, including this header
+    pub size: u8,
+    /// Section header index of the section affected, `0` for the whole file
+    pub section: u16,
+    /// Kind specific information
+    pub info: u32,
+}
+
+/// `Elf32_RegInfo`, the contents of `.reginfo` and of 32-bit `ODK_REGINFO` records
+#[repr(C)]
+#[derive(Debug, PartialEq, Pread, Pwrite, IOread, IOwrite, SizeWith)]
+pub struct RegInfo32 {
+    pub ri_gprmask: u32,
+    pub ri_cprmask: [u32; 4],
+    pub ri_gp_value: i32,
+}
+
+/// `Elf64_RegInfo`, the contents of 64-bit `ODK_REGINFO` records
+#[repr(C)]
+#[derive(Debug, PartialEq, Pread, Pwrite, IOread, IOwrite, SizeWith)]
+pub struct RegInfo64 {
+    pub ri_gprmask: u32,
+    pub ri_pad: u32,
+    pub ri_cprmask: [u32; 4],
+    pub ri_gp_value: i64,
+}
+
+pub struct RegInfo {
+    /// Mask of general purpose registers used
+    pub ri_gprmask: u32,
+    /// Mask of co-processor registers used
+    pub ri_cprmask: [u32; 4],
+    /// The initial value of `$gp`
+    pub ri_gp_value: i64,
+}
+
+impl From<RegInfo32> for RegInfo {
+    fn from(value: RegInfo32) -> Self {
+        Self {
+            ri_gprmask: value.ri_gprmask,
+            ri_cprmask: value.ri_cprmask,
+            ri_gp_value: i64::from(value.ri_gp_value),
+        }
+    }
+}
+
+impl From<RegInfo64> for RegInfo {
+    fn from(value: RegInfo64) -> Self {
+        Self {
+            ri_gprmask: value.ri_gprmask,
+            ri_cprmask: value.ri_cprmask,
+            ri_gp_value: value.ri_gp_value,
+        }
+    }
+}
+
+impl RegInfo {
+    /// Parses the bytes of `.reginfo` (`SHT_MIPS_REGINFO`), which only exists in 32-bit files
+    pub fn parse(bytes: &[u8], endianness: Endian) -> Result<RegInfo> {
+        Ok(bytes.pread_with::<RegInfo32>(0, endianness)?.into())
+    }
+}
+
+pub enum MipsOptionData {
+    /// `ODK_REGINFO`
+    RegInfo(RegInfo),
+    /// `ODK_EXCEPTIONS`, the FPU exception masks are stored in `info`
+    Exceptions {
+        /// FPU exceptions that must be enabled (`OEX_FPU_*`)
+        fpe_min: u32,
+        /// FPU exceptions that may be enabled (`OEX_FPU_*`)
+        fpe_max: u32,
+        /// `OEX_PAGE0`, `OEX_SMM`, `OEX_FPDBUG` and `OEX_DISMISS`
+        flags: u32,
+    },
+    /// `ODK_HWPATCH`, `ODK_HWAND` and `ODK_HWOR`, the `OHW_*` masks are stored in `info`
+    HwPatch(u32),
+    /// `ODK_PAGESIZE`, the page size is stored in `info`
+    PageSize(u32),
+    /// Every other kind, the bytes after the header
+    Raw(Vec<u8>),
+}
+
+pub struct MipsOption {
+    pub kind: u8,
+    pub section: u16,
+    pub info: u32,
+    pub data: MipsOptionData,
+}
+
+impl MipsOption {
+    /// Parses every record within `.MIPS.options` (`SHT_MIPS_OPTIONS`), e.g. from `Reader::get_section_bytes`
+    pub fn parse_all(bytes: &[u8], endianness: Endian, class: ElfClass) -> Result<Vec<MipsOption>> {
+        let mut result: Vec<MipsOption> = Vec::new();
+        let mut offset: usize = 0;
+        let header_size = ::std::mem::size_of::<OptionsHeader>();
+
+        while offset + header_size <= bytes.len() {
+            let header = bytes.pread_with::<OptionsHeader>(offset, endianness)?;
+            let size = header.size as usize;
+
+            // `ODK_NULL` records are used as padding at the end of the section
+            if header.kind == crate::elf::mips::ODK_NULL && size == 0 {
+                break;
+            }
+
+            if size < header_size || size > bytes.len() - offset {
+                return Err(Error::Malformed(
+                    Details::new(format!(
+                        "`.MIPS.options` record at offset `{}` has an invalid size of `{}`, only `{}` bytes are available",
+                        offset,
+                        size,
+                        bytes.len() - offset
+                    ))
+                    .structure("elf::mips::OptionsHeader")
+                    .field("size"),
+                ));
+            }
+
+            let record_bytes = &bytes[offset + header_size..offset + size];
+
+            let data = match header.kind {
+                crate::elf::mips::ODK_REGINFO => MipsOptionData::RegInfo(match class {
+                    ElfClass::Elf32 => record_bytes.pread_with::<RegInfo32>(0, endianness)?.into(),
+                    ElfClass::Elf64 => record_bytes.pread_with::<RegInfo64>(0, endianness)?.into(),
+                }),
+                crate::elf::mips::ODK_EXCEPTIONS => MipsOptionData::Exceptions {
+                    fpe_min: header.info & crate::elf::mips::OEX_FPU_MIN,
+                    fpe_max: (header.info & crate::elf::mips::OEX_FPU_MAX) >> 8,
+                    flags: header.info
+                        & !(crate::elf::mips::OEX_FPU_MIN | crate::elf::mips::OEX_FPU_MAX),
+                },
+                crate::elf::mips::ODK_HWPATCH
+                | crate::elf::mips::ODK_HWAND
+                | crate::elf::mips::ODK_HWOR => MipsOptionData::HwPatch(header.info),
+                crate::elf::mips::ODK_PAGESIZE => MipsOptionData::PageSize(header.info),
+                _ => MipsOptionData::Raw(record_bytes.to_vec()),
+            };
+
+            result.push(MipsOption {
+                kind: header.kind,
+                section: header.section,
+                info: header.info,
+                data,
+            });
+
+            offset += size;
+        }
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scroll::Pwrite;
+
+    #[test]
+    fn options_header_size_check() {
+        assert_eq!(::std::mem::size_of::<OptionsHeader>(), 0x8);
+    }
+
+    #[test]
+    fn reg_info_32_size_check() {
+        assert_eq!(::std::mem::size_of::<RegInfo32>(), 0x18);
+    }
+
+    #[test]
+    fn reg_info_64_size_check() {
+        assert_eq!(::std::mem::size_of::<RegInfo64>(), 0x20);
+    }
+
+    #[test]
+    fn parse_reg_info() {
+        let mut bytes = [0u8; 0x18];
+        bytes
+            .pwrite_with(
+                RegInfo32 {
+                    ri_gprmask: 0xf0000001,
+                    ri_cprmask: [0, 1, 2, 3],
+                    ri_gp_value: -0x7ff0,
+                },
+                0,
+                scroll::BE,
+            )
+            .unwrap();
+
+        let reg_info = RegInfo::parse(&bytes, scroll::BE).unwrap();
+        assert_eq!(reg_info.ri_gprmask, 0xf0000001);
+        assert_eq!(reg_info.ri_cprmask, [0, 1, 2, 3]);
+        assert_eq!(reg_info.ri_gp_value, -0x7ff0);
+        assert!(RegInfo::parse(&bytes[..0x10], scroll::BE).is_err());
+    }
+
+    #[test]
+    fn parse_options() {
+        // `ODK_REGINFO` followed by `ODK_NULL` padding
+        let mut bytes = [0u8; 0x30];
+        let mut offset = 0;
+        bytes
+            .gwrite_with(
+                OptionsHeader {
+                    kind: crate::elf::mips::ODK_REGINFO,
+                    size: 0x28,
+                    section: 0,
+                    info: 0,
+                },
+                &mut offset,
+                scroll::LE,
+            )
+            .unwrap();
+        bytes
+            .gwrite_with(
+                RegInfo64 {
+                    ri_gprmask: 0x1234,
+                    ri_pad: 0,
+                    ri_cprmask: [4, 5, 6, 7],
+                    ri_gp_value: 0x120008000,
+                },
+                &mut offset,
+                scroll::LE,
+            )
+            .unwrap();
+
+        let options = MipsOption::parse_all(&bytes, scroll::LE, ElfClass::Elf64).unwrap();
+        assert_eq!(options.len(), 1);
+        assert_eq!(options[0].kind, crate::elf::mips::ODK_REGINFO);
+        match &options[0].data {
+            MipsOptionData::RegInfo(reg_info) => {
+                assert_eq!(reg_info.ri_gprmask, 0x1234);
+                assert_eq!(reg_info.ri_cprmask, [4, 5, 6, 7]);
+                assert_eq!(reg_info.ri_gp_value, 0x120008000);
+            }
+            _ => panic!("Expected `ODK_REGINFO` data"),
+        }
+
+        // A record claiming to be smaller than its own header
+        bytes.pwrite_with(4u8, 1, scroll::LE).unwrap();
+        assert!(MipsOption::parse_all(&bytes, scroll::LE, ElfClass::Elf64).is_err());
+    }
+}
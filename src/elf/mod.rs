@@ -15,6 +15,8 @@
  */
 
 pub mod common;
+pub mod dwarf;
+pub mod eh_frame;
 pub mod elf32;
 pub mod elf64;
 
@@ -31,6 +33,7 @@ pub mod freebsd;
 pub mod gnu;
 pub mod hexagon;
 pub mod lanai;
+pub mod linux_module;
 pub mod loongarch;
 pub mod m68k;
 pub mod mips;
@@ -41,6 +44,7 @@ pub mod ppc;
 pub mod ppc64;
 pub mod riscv;
 pub mod s390;
+pub mod size;
 pub mod sparc;
 pub mod sunw;
 pub mod ve;
@@ -69,6 +73,22 @@ mod compressed;
 pub use compressed::*;
 mod hash;
 pub use hash::*;
+mod attributes;
+pub use attributes::*;
+mod symbolizer;
+pub use symbolizer::*;
+mod validate;
+pub use validate::*;
+mod describe;
+pub use describe::*;
+mod machine_flags;
+pub use machine_flags::*;
+mod group;
+pub use group::*;
+mod plt;
+pub use plt::*;
+mod layout;
+pub use layout::*;
 
 use crate::Error;
 use scroll::{Endian, IOread};
@@ -77,7 +97,7 @@ use std::io::{Seek, SeekFrom};
 
 type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum ElfClass {
     Elf32,
     Elf64,
@@ -100,26 +120,44 @@ pub fn get_elf_ident<TRead: IOread<scroll::Endian> + Seek>(reader: &mut TRead) -
         // Reset the position to prevent any potential issues with parsing after calling this function...
         reader.seek(SeekFrom::Start(reset_position))?;
 
-        Err(Error::InvalidMagicNumber(magic_number.to_vec()))
+        Err(Error::InvalidMagicNumber(
+            crate::Details::new(format!(
+                "Expected `[7f, 45, 4c, 46]` for the ELF magic number, found `{:02x?}`",
+                magic_number
+            ))
+            .structure("elf::ElfIdent")
+            .field("magic")
+            .offset(reset_position),
+        ))
     } else {
         let class = match reader.ioread_with::<u8>(scroll::BE)? {
             1 => ElfClass::Elf32,
             2 => ElfClass::Elf64,
             unknown => {
-                return Err(Error::Malformed(format!(
-                "Invalid `e_ident[EI_CLASS]` value `{}`, expected `1` for 32-bit or `2` for 64-bit",
-                unknown
-            )))
+                return Err(Error::Malformed(
+                    crate::Details::new(format!(
+                        "Invalid `e_ident[EI_CLASS]` value `{}`, expected `1` for 32-bit or `2` for 64-bit",
+                        unknown
+                    ))
+                    .structure("elf::Header")
+                    .field("e_ident")
+                    .offset(4),
+                ))
             }
         };
         let endianness = match reader.ioread_with::<u8>(scroll::BE)? {
             1 => scroll::LE,
             2 => scroll::BE,
             unknown => {
-                return Err(Error::Malformed(format!(
-                    "Invalid `e_ident[EI_DATA]` value `{}`, expected `1` for little endian or `2` for big endian",
-                    unknown
-                )))
+                return Err(Error::Malformed(
+                    crate::Details::new(format!(
+                        "Invalid `e_ident[EI_DATA]` value `{}`, expected `1` for little endian or `2` for big endian",
+                        unknown
+                    ))
+                    .structure("elf::Header")
+                    .field("e_ident")
+                    .offset(5),
+                ))
             }
         };
 
@@ -150,6 +188,21 @@ pub trait Reader<'a> {
     fn read_dynamic_program(&mut self, program_header: &ProgramHeader) -> Result<Vec<Dyn>>;
 
     fn get_section_bytes(&mut self, section_header: &SectionHeader) -> Result<Cow<'a, [u8]>>;
+    /// Same as `get_section_bytes` but `SHF_COMPRESSED` sections are decompressed first
+    ///
+    /// The default implementation decompresses using the default limits.
+    fn get_decompressed_section_bytes(
+        &mut self,
+        section_header: &SectionHeader,
+    ) -> Result<Cow<'a, [u8]>> {
+        if section_header.sh_flags.contains(SHFlags::COMPRESSED) {
+            Ok(Cow::Owned(
+                self.read_compressed_section(section_header)?.decompress()?,
+            ))
+        } else {
+            self.get_section_bytes(section_header)
+        }
+    }
 
     fn read_str_table_section(&mut self, section_header: &SectionHeader) -> Result<StrTab<'a>>;
     fn read_sym_table_section(&mut self, section_header: &SectionHeader) -> Result<Vec<Sym>>;
@@ -163,6 +216,8 @@ pub trait Reader<'a> {
     fn read_rel_section(&mut self, section_header: &SectionHeader) -> Result<Vec<Rel>>;
     fn read_rela_section(&mut self, section_header: &SectionHeader) -> Result<Vec<RelA>>;
     fn read_relr_section(&mut self, section_header: &SectionHeader) -> Result<Vec<RelR>>;
+    /// Reads the words of a `SHT_GROUP` section, the flag word followed by the member section indices
+    fn read_group_section(&mut self, section_header: &SectionHeader) -> Result<Vec<u32>>;
 
     // GNU specific parsing
     fn read_gnu_hash_table_section(
@@ -184,10 +239,14 @@ macro_rules! validate_section_header_overflow {
         let (end, overflow) = offset.overflowing_add(size as u64);
 
         if end >= $stream_len || overflow {
-            Err(Error::Malformed(format!(
-                "Section offset of `{}` + size of `{}` is out of bounds for `{}` bytes",
-                offset, size, $stream_len
-            )))
+            Err(Error::Malformed(
+                crate::Details::new(format!(
+                    "Section offset of `{}` + size of `{}` is out of bounds for `{}` bytes",
+                    offset, size, $stream_len
+                ))
+                .structure("elf::SectionHeader")
+                .field("sh_offset"),
+            ))
         } else {
             Ok(())
         }
@@ -196,17 +255,19 @@ macro_rules! validate_section_header_overflow {
 
 macro_rules! validate_program_header_overflow {
     ($program_header:expr, $stream_len:expr) => {{
-        assert!($program_header.p_offset != 0);
-
         let offset = $program_header.p_offset as u64;
         let size = $program_header.p_filesz;
         let (end, overflow) = offset.overflowing_add(size as u64);
 
         if end >= $stream_len || overflow {
-            Err(Error::Malformed(format!(
-                "Program offset of `{}` + size of `{}` is out of bounds for `{}` bytes",
-                offset, size, $stream_len
-            )))
+            Err(Error::Malformed(
+                crate::Details::new(format!(
+                    "Program offset of `{}` + size of `{}` is out of bounds for `{}` bytes",
+                    offset, size, $stream_len
+                ))
+                .structure("elf::ProgramHeader")
+                .field("p_offset"),
+            ))
         } else {
             Ok(())
         }
@@ -215,17 +276,18 @@ macro_rules! validate_program_header_overflow {
 
 macro_rules! validate_offset_overflow {
     ($slice_offset:expr, $slice_size:expr, $stream_len:expr) => {{
-        assert!($slice_offset != 0);
-
         let offset = $slice_offset;
         let size = $slice_size;
         let (end, overflow) = offset.overflowing_add(size);
 
         if end >= $stream_len || overflow {
-            Err(Error::Malformed(format!(
-                "Bytes offset of `{}` + size of `{}` is out of bounds for `{}` bytes",
-                offset, size, $stream_len
-            )))
+            Err(Error::Malformed(
+                crate::Details::new(format!(
+                    "Bytes offset of `{}` + size of `{}` is out of bounds for `{}` bytes",
+                    offset, size, $stream_len
+                ))
+                .offset(offset),
+            ))
         } else {
             Ok(())
         }
@@ -235,10 +297,14 @@ macro_rules! validate_offset_overflow {
 macro_rules! validate_section_header_sh_flags_and_size {
     ($calling_function_name:literal, $section_header:expr, $expected_sh_flag:expr, $expected_sh_flag_str:literal, $stream_len:expr) => {
         if !$section_header.sh_flags.contains($expected_sh_flag) {
-            Err(Error::InvalidArguments(format!(
-                "Invalid `section_header` passed to `{}`, `sh_flags` must have `{}` flag",
-                $calling_function_name, $expected_sh_flag_str,
-            )))
+            Err(Error::InvalidArguments(
+                crate::Details::new(format!(
+                    "Invalid `section_header` passed to `{}`, `sh_flags` must have `{}` flag",
+                    $calling_function_name, $expected_sh_flag_str,
+                ))
+                .structure("elf::SectionHeader")
+                .field("sh_flags"),
+            ))
         } else {
             crate::elf::validate_section_header_overflow!($section_header, $stream_len)
         }
@@ -248,12 +314,16 @@ macro_rules! validate_section_header_sh_flags_and_size {
 macro_rules! validate_section_header_sh_type_and_size {
     ($calling_function_name:literal, $section_header:expr, $expected_sh_type:expr, $expected_sh_type_str:literal, $stream_len:expr) => {
         if $section_header.sh_type != $expected_sh_type {
-            Err(Error::InvalidArguments(format!(
-                "Invalid `section_header` passed to `{}`, expected `sh_type` of `{}` but found `{}`",
-                $calling_function_name,
-                $expected_sh_type_str,
-                crate::elf::sht_to_str($section_header.sh_type)
-            )))
+            Err(Error::InvalidArguments(
+                crate::Details::new(format!(
+                    "Invalid `section_header` passed to `{}`, expected `sh_type` of `{}` but found `{}`",
+                    $calling_function_name,
+                    $expected_sh_type_str,
+                    crate::elf::sht_to_str($section_header.sh_type)
+                ))
+                .structure("elf::SectionHeader")
+                .field("sh_type"),
+            ))
         } else {
             crate::elf::validate_section_header_overflow!($section_header, $stream_len)
         }
@@ -263,12 +333,16 @@ macro_rules! validate_section_header_sh_type_and_size {
 macro_rules! validate_program_header_p_type_and_size {
     ($calling_function_name:literal, $program_header:expr, $expected_p_type:expr, $expected_p_type_str:literal, $stream_len:expr) => {
         if $program_header.p_type != $expected_p_type {
-            Err(Error::InvalidArguments(format!(
-                "Invalid `program_header` passed to `{}`, expected `sh_type` of `{}` but found `{}`",
-                $calling_function_name,
-                $expected_p_type_str,
-                crate::elf::pt_to_str($program_header.p_type)
-            )))
+            Err(Error::InvalidArguments(
+                crate::Details::new(format!(
+                    "Invalid `program_header` passed to `{}`, expected `sh_type` of `{}` but found `{}`",
+                    $calling_function_name,
+                    $expected_p_type_str,
+                    crate::elf::pt_to_str($program_header.p_type)
+                ))
+                .structure("elf::ProgramHeader")
+                .field("p_type"),
+            ))
         } else {
             crate::elf::validate_program_header_overflow!($program_header, $stream_len)
         }
@@ -277,73 +351,58 @@ macro_rules! validate_program_header_p_type_and_size {
 
 // TODO: For future proofing, it might be good to at least do a `debug_assert!($section_header.sh_entsize == entsize)`...
 macro_rules! io_read_section_as_array {
-    ($reader:expr, $endianness:expr, $section_header:expr, $read_index_type:ty, $insert_index_type:ty) => {{
+    ($reader:expr, $endianness:expr, $limits:expr, $section_header:expr, $read_index_type:ty, $insert_index_type:ty) => {{
         let entsize = ::std::mem::size_of::<$read_index_type>();
         let length = $section_header.sh_size / (entsize as u64);
 
-        if let Ok(length) = usize::try_from(length) {
-            let mut result: Vec<$insert_index_type> = Vec::with_capacity(length);
+        match $limits.vec_with_capacity::<$insert_index_type>(length, "an array") {
+            Ok(mut result) => {
+                for _ in 0..length {
+                    let value = $reader.ioread_with::<$read_index_type>($endianness)?;
+                    result.push(<$insert_index_type>::from(value));
+                }
 
-            for _ in 0..length {
-                let value = $reader.ioread_with::<$read_index_type>($endianness)?;
-                result.push(<$insert_index_type>::from(value));
+                Ok(result)
             }
-
-            Ok(result)
-        } else {
-            Err(Error::TooManyArrayItems(format!(
-                "Attempted to parse array with `{}` items, more items than can fit in `usize` (`{}` max)",
-                length,
-                usize::MAX,
-            )))
+            Err(error) => Err(error),
         }
     }};
 }
 
 macro_rules! io_read_program_as_array {
-    ($reader:expr, $endianness:expr, $program_header:expr, $read_index_type:ty, $insert_index_type:ty) => {{
+    ($reader:expr, $endianness:expr, $limits:expr, $program_header:expr, $read_index_type:ty, $insert_index_type:ty) => {{
         let entsize = ::std::mem::size_of::<$read_index_type>();
         let length = $program_header.p_filesz / (entsize as u64);
 
-        if let Ok(length) = usize::try_from(length) {
-            let mut result: Vec<$insert_index_type> = Vec::with_capacity(length);
+        match $limits.vec_with_capacity::<$insert_index_type>(length, "an array") {
+            Ok(mut result) => {
+                for _ in 0..length {
+                    let value = $reader.ioread_with::<$read_index_type>($endianness)?;
+                    result.push(<$insert_index_type>::from(value));
+                }
 
-            for _ in 0..length {
-                let value = $reader.ioread_with::<$read_index_type>($endianness)?;
-                result.push(<$insert_index_type>::from(value));
+                Ok(result)
             }
-
-            Ok(result)
-        } else {
-            Err(Error::TooManyArrayItems(format!(
-                "Attempted to parse array with `{}` items, more items than can fit in `usize` (`{}` max)",
-                length,
-                usize::MAX,
-            )))
+            Err(error) => Err(error),
         }
     }};
 }
 
 macro_rules! io_read_as_array {
-    ($reader:expr, $endianness:expr, $slice_size:expr, $read_index_type:ty, $insert_index_type:ty) => {{
+    ($reader:expr, $endianness:expr, $limits:expr, $slice_size:expr, $read_index_type:ty, $insert_index_type:ty) => {{
         let entsize = ::std::mem::size_of::<$read_index_type>();
         let length = $slice_size / (entsize as u64);
 
-        if let Ok(length) = usize::try_from(length) {
-            let mut result: Vec<$insert_index_type> = Vec::with_capacity(length);
+        match $limits.vec_with_capacity::<$insert_index_type>(length, "an array") {
+            Ok(mut result) => {
+                for _ in 0..length {
+                    let value = $reader.ioread_with::<$read_index_type>($endianness)?;
+                    result.push(<$insert_index_type>::from(value));
+                }
 
-            for _ in 0..length {
-                let value = $reader.ioread_with::<$read_index_type>($endianness)?;
-                result.push(<$insert_index_type>::from(value));
+                Ok(result)
             }
-
-            Ok(result)
-        } else {
-            Err(Error::TooManyArrayItems(format!(
-                "Attempted to parse array with `{}` items, more items than can fit in `usize` (`{}` max)",
-                length,
-                usize::MAX,
-            )))
+            Err(error) => Err(error),
         }
     }};
 }
@@ -376,7 +435,8 @@ macro_rules! elf_io_reader_impl {
         use std::borrow::Cow;
         use std::io::{Seek, SeekFrom};
 
-        use crate::Error;
+        use crate::error::ResultExt;
+        use crate::{ContextFrame, Error};
 
         type Result<T> = std::result::Result<T, Error>;
 
@@ -384,16 +444,22 @@ macro_rules! elf_io_reader_impl {
             pub reader: &'a mut TRead,
             pub endianness: Endian,
             pub stream_len: u64,
+            pub limits: crate::Limits,
         }
 
         impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
             pub fn new(reader: &'a mut TRead, endianness: Endian) -> Result<Self> {
+                Self::with_limits(reader, endianness, crate::Limits::default())
+            }
+
+            pub fn with_limits(reader: &'a mut TRead, endianness: Endian, limits: crate::Limits) -> Result<Self> {
                 let stream_len = reader.seek(SeekFrom::End(0))?;
                 reader.seek(SeekFrom::Start(0))?;
                 Ok(Self {
                     reader,
                     endianness,
                     stream_len,
+                    limits,
                 })
             }
 
@@ -413,9 +479,22 @@ macro_rules! elf_io_reader_impl {
                 let mut result: Vec<crate::elf::ProgramHeader> = Vec::with_capacity(e_phnum as usize);
 
                 for phidx in 0..e_phnum {
-                    let offset: u64 = e_phoff + ((phidx as u64) * (e_phentsize as u64));
-
-                    result.push(self.read_program_header(offset)?)
+                    let offset = e_phoff
+                        .checked_add((phidx as u64) * (e_phentsize as u64))
+                        .ok_or_else(|| {
+                            Error::Malformed(
+                                crate::Details::new(format!(
+                                    "Program header `{}` at `{}` + `{}` * `{}` overflows",
+                                    phidx, e_phoff, phidx, e_phentsize
+                                ))
+                                .structure("elf::Header")
+                                .field("e_phoff"),
+                            )
+                        })?;
+
+                    result.push(self.read_program_header(offset).context(|| {
+                        ContextFrame::new("program_headers").index(phidx as usize).offset(offset)
+                    })?)
                 }
 
                 Ok(result)
@@ -440,9 +519,22 @@ macro_rules! elf_io_reader_impl {
                 let mut result: Vec<crate::elf::SectionHeader> = Vec::with_capacity(e_shnum as usize);
 
                 for shidx in 0..e_shnum {
-                    let offset: u64 = e_shoff + ((shidx as u64) * (e_shentsize as u64));
-
-                    result.push(self.read_section_header(offset)?)
+                    let offset = e_shoff
+                        .checked_add((shidx as u64) * (e_shentsize as u64))
+                        .ok_or_else(|| {
+                            Error::Malformed(
+                                crate::Details::new(format!(
+                                    "Section header `{}` at `{}` + `{}` * `{}` overflows",
+                                    shidx, e_shoff, shidx, e_shentsize
+                                ))
+                                .structure("elf::Header")
+                                .field("e_shoff"),
+                            )
+                        })?;
+
+                    result.push(self.read_section_header(offset).context(|| {
+                        ContextFrame::new("section_headers").index(shidx as usize).offset(offset)
+                    })?)
                 }
 
                 Ok(result)
@@ -458,31 +550,28 @@ macro_rules! elf_io_reader_impl {
 
             // Program parsing
             pub fn get_program_bytes(&mut self, program_header: &crate::elf::ProgramHeader) -> Result<Cow<'static, [u8]>> {
-                assert!(program_header.p_offset != 0);
-
                 let offset = program_header.p_offset as u64;
                 let size = program_header.p_filesz;
                 let (end, overflow) = offset.overflowing_add(size as u64);
 
                 if end >= self.stream_len || overflow {
-                    Err(Error::Malformed(format!(
-                        "Program offset of `{}` + size of `{}` is out of bounds for `{}` bytes",
-                        offset, size, self.stream_len
-                    )))
+                    Err(Error::Malformed(
+                        crate::Details::new(format!(
+                            "Program offset of `{}` + size of `{}` is out of bounds for `{}` bytes",
+                            offset, size, self.stream_len
+                        ))
+                        .structure("elf::ProgramHeader")
+                        .field("p_offset"),
+                    ))
                 } else {
                     self.reader.seek(SeekFrom::Start(offset))?;
 
-                    if let Ok(size) = usize::try_from(size) {
-                        let mut result: Vec<u8> = Vec::with_capacity(size);
-                        // Resize is a "safe" way to set length to what the capacity is
-                        result.resize(size, 0);
-                        self.reader.read_exact(&mut result)?;
-                        Ok(Cow::Owned(result))
-                    } else {
-                        Err(Error::TooManyArrayItems(format!(
-                            "Attempted to load the bytes of a program with a size of `{}`, `usize` can only hold `{}` bytes", size, usize::MAX
-                        )))
-                    }
+                    let size = self.limits.check_allocation(size, "the bytes of a program")?;
+                    let mut result: Vec<u8> = Vec::with_capacity(size);
+                    // Resize is a "safe" way to set length to what the capacity is
+                    result.resize(size, 0);
+                    self.reader.read_exact(&mut result)?;
+                    Ok(Cow::Owned(result))
                 }
             }
 
@@ -501,6 +590,7 @@ macro_rules! elf_io_reader_impl {
                 Ok(crate::elf::io_read_program_as_array!(
                     self.reader,
                     self.endianness,
+                    self.limits,
                     program_header,
                     $Dyn,
                     crate::elf::Dyn
@@ -517,24 +607,34 @@ macro_rules! elf_io_reader_impl {
                 let (end, overflow) = offset.overflowing_add(size as u64);
 
                 if end >= self.stream_len || overflow {
-                    Err(Error::Malformed(format!(
-                        "Section offset of `{}` + size of `{}` is out of bounds for `{}` bytes",
-                        offset, size, self.stream_len
-                    )))
+                    Err(Error::Malformed(
+                        crate::Details::new(format!(
+                            "Section offset of `{}` + size of `{}` is out of bounds for `{}` bytes",
+                            offset, size, self.stream_len
+                        ))
+                        .structure("elf::SectionHeader")
+                        .field("sh_offset"),
+                    ))
                 } else {
                     self.reader.seek(SeekFrom::Start(offset))?;
 
-                    if let Ok(size) = usize::try_from(size) {
-                        let mut result: Vec<u8> = Vec::with_capacity(size);
-                        // Resize is a "safe" way to set length to what the capacity is
-                        result.resize(size, 0);
-                        self.reader.read_exact(&mut result)?;
-                        Ok(Cow::Owned(result))
-                    } else {
-                        Err(Error::TooManyArrayItems(format!(
-                            "Attempted to load the bytes of a section with a size of `{}`, `usize` can only hold `{}` bytes", size, usize::MAX
-                        )))
-                    }
+                    let size = self.limits.check_allocation(size, "the bytes of a section")?;
+                    let mut result: Vec<u8> = Vec::with_capacity(size);
+                    // Resize is a "safe" way to set length to what the capacity is
+                    result.resize(size, 0);
+                    self.reader.read_exact(&mut result)?;
+                    Ok(Cow::Owned(result))
+                }
+            }
+
+            pub fn get_decompressed_section_bytes(
+                &mut self,
+                section_header: &crate::elf::SectionHeader,
+            ) -> Result<Cow<'static, [u8]>> {
+                if section_header.sh_flags.contains(crate::elf::SHFlags::COMPRESSED) {
+                    Ok(Cow::Owned(self.read_compressed_section(section_header)?.decompress_with_limits(&self.limits)?))
+                } else {
+                    self.get_section_bytes(section_header)
                 }
             }
 
@@ -543,10 +643,14 @@ macro_rules! elf_io_reader_impl {
                 section_header: &crate::elf::SectionHeader,
             ) -> Result<crate::elf::StrTab<'static>> {
                 if section_header.sh_type != crate::elf::SHT_STRTAB {
-                    return Err(Error::InvalidArguments(format!(
-                        "Invalid `section_header` passed to `read_str_table_section`, expected `sh_type` of `SHT_STRTAB` but found `{}`",
-                        crate::elf::sht_to_str(section_header.sh_type)
-                    )));
+                    return Err(Error::InvalidArguments(
+                        crate::Details::new(format!(
+                            "Invalid `section_header` passed to `read_str_table_section`, expected `sh_type` of `SHT_STRTAB` but found `{}`",
+                            crate::elf::sht_to_str(section_header.sh_type)
+                        ))
+                        .structure("elf::SectionHeader")
+                        .field("sh_type"),
+                    ));
                 }
 
                 let strtab_bytes = self.get_section_bytes(&section_header)?;
@@ -569,8 +673,22 @@ macro_rules! elf_io_reader_impl {
                     .seek(SeekFrom::Start(section_header.sh_offset))?;
 
                 let hash_header = self.reader.ioread_with::<crate::elf::common::HashHeader>(self.endianness)?;
-                let mut buckets: Vec<u32> = Vec::with_capacity(hash_header.n_buckets as usize);
-                let mut chains: Vec<u32> = Vec::with_capacity(hash_header.n_chains as usize);
+                let table_size = (2 + u64::from(hash_header.n_buckets) + u64::from(hash_header.n_chains)) * 4;
+
+                if table_size > section_header.sh_size {
+                    return Err(Error::Malformed(
+                        crate::Details::new(format!(
+                            "Hash section has `{}` buckets and `{}` chains, more than fits in its size of `{}` bytes",
+                            hash_header.n_buckets, hash_header.n_chains, section_header.sh_size
+                        ))
+                        .structure("elf::Hash")
+                        .field("n_buckets")
+                        .offset(section_header.sh_offset),
+                    ));
+                }
+
+                let mut buckets: Vec<u32> = self.limits.vec_with_capacity(u64::from(hash_header.n_buckets), "hash buckets")?;
+                let mut chains: Vec<u32> = self.limits.vec_with_capacity(u64::from(hash_header.n_chains), "hash chains")?;
 
                 for _ in 0..hash_header.n_buckets {
                     buckets.push(self.reader.ioread_with::<u32>(self.endianness)?);
@@ -602,27 +720,47 @@ macro_rules! elf_io_reader_impl {
                     .seek(SeekFrom::Start(section_header.sh_offset))?;
 
                 let note_header = self.reader.ioread_with::<crate::elf::common::NoteHeader>(self.endianness)?;
-                // I'm doing `- 1` to get rid of the unneeded nul character
-                let n_name_len = note_header.n_namesz as usize - 1;
+                let note_size = 12 + u64::from(note_header.n_namesz) + u64::from(note_header.n_descsz);
+
+                if note_size > section_header.sh_size {
+                    return Err(Error::Malformed(
+                        crate::Details::new(format!(
+                            "Note has a name size of `{}` and a descriptor size of `{}`, more than fits in its section size of `{}` bytes",
+                            note_header.n_namesz, note_header.n_descsz, section_header.sh_size
+                        ))
+                        .structure("elf::Note")
+                        .field("n_descsz")
+                        .offset(section_header.sh_offset),
+                    ));
+                }
+
+                // I'm doing `- 1` to get rid of the unneeded nul character, notes without a name don't have one
+                let n_name_len = self.limits.check_allocation(u64::from(note_header.n_namesz.saturating_sub(1)), "a note name")?;
                 let mut n_name_bytes: Vec<u8> = Vec::with_capacity(n_name_len);
                 n_name_bytes.resize(n_name_len, 0);
-                let mut n_desc: Vec<u8> = Vec::with_capacity(note_header.n_descsz as usize);
-                n_desc.resize(note_header.n_descsz as usize, 0);
-
-                // Since I'm ignoring the nul character for `n_name_bytes`, we need to read that nul into something...
-                let mut nul_char = [0u8; 1];
+                let n_desc_len = self.limits.check_allocation(u64::from(note_header.n_descsz), "a note descriptor")?;
+                let mut n_desc: Vec<u8> = Vec::with_capacity(n_desc_len);
+                n_desc.resize(n_desc_len, 0);
 
                 self.reader.read_exact(&mut n_name_bytes)?;
-                self.reader.read_exact(&mut nul_char)?;
+
+                if note_header.n_namesz != 0 {
+                    // Since I'm ignoring the nul character for `n_name_bytes`, we need to read that nul into something...
+                    let mut nul_char = [0u8; 1];
+                    self.reader.read_exact(&mut nul_char)?;
+                }
+
                 self.reader.read_exact(&mut n_desc)?;
 
                 let n_name = match String::from_utf8(n_name_bytes) {
                     Ok(string) => string,
                     Err(utf8_error) => {
-                        return Err(Error::Malformed(format!(
-                            "Invalid note name as offset {}, {}",
-                            section_header.sh_offset, utf8_error
-                        )))
+                        return Err(Error::Malformed(
+                            crate::Details::new(format!("Invalid note name, {}", utf8_error))
+                                .structure("elf::Note")
+                                .field("n_name")
+                                .offset(section_header.sh_offset),
+                        ))
                     }
                 };
 
@@ -651,6 +789,7 @@ macro_rules! elf_io_reader_impl {
                 Ok(crate::elf::io_read_section_as_array!(
                     self.reader,
                     self.endianness,
+                    self.limits,
                     section_header,
                     $Sym,
                     crate::elf::Sym
@@ -676,24 +815,29 @@ macro_rules! elf_io_reader_impl {
                     .reader
                     .ioread_with::<$CompressionHeader>(self.endianness)?;
 
-                if let Ok(sh_size) = usize::try_from(section_header.sh_size) {
-                    let compressed_bytes_length = sh_size - ::std::mem::size_of::<$CompressionHeader>();
-                    let mut compressed_bytes: Vec<u8> = Vec::with_capacity(compressed_bytes_length);
-                    compressed_bytes.resize(compressed_bytes_length, 0);
-
-                    self.reader.read_exact(&mut compressed_bytes)?;
-
-                    Ok(crate::elf::CompressedSection {
-                        header: compression_header.into(),
-                        bytes: Cow::Owned(compressed_bytes),
-                    })
-                } else {
-                    Err(Error::TooManyArrayItems(format!(
-                        "Compressed section had {} of compressed bytes, more than max value of `usize` ({})",
-                        section_header.sh_size,
-                        usize::MAX,
-                    )))
-                }
+                let compressed_bytes_length = section_header
+                    .sh_size
+                    .checked_sub(::std::mem::size_of::<$CompressionHeader>() as u64)
+                    .ok_or_else(|| {
+                        Error::Malformed(
+                            crate::Details::new(format!(
+                                "Compressed section has a size of `{}`, too small for its compression header",
+                                section_header.sh_size
+                            ))
+                            .structure("elf::CompressionHeader")
+                            .offset(section_header.sh_offset),
+                        )
+                    })?;
+                let compressed_bytes_length = self.limits.check_allocation(compressed_bytes_length, "compressed section bytes")?;
+                let mut compressed_bytes: Vec<u8> = Vec::with_capacity(compressed_bytes_length);
+                compressed_bytes.resize(compressed_bytes_length, 0);
+
+                self.reader.read_exact(&mut compressed_bytes)?;
+
+                Ok(crate::elf::CompressedSection {
+                    header: compression_header.into(),
+                    bytes: Cow::Owned(compressed_bytes),
+                })
             }
 
             pub fn read_dynamic_section(
@@ -714,6 +858,7 @@ macro_rules! elf_io_reader_impl {
                 Ok(crate::elf::io_read_section_as_array!(
                     self.reader,
                     self.endianness,
+                    self.limits,
                     section_header,
                     $Dyn,
                     crate::elf::Dyn
@@ -738,6 +883,7 @@ macro_rules! elf_io_reader_impl {
                 Ok(crate::elf::io_read_section_as_array!(
                     self.reader,
                     self.endianness,
+                    self.limits,
                     section_header,
                     $Rel,
                     crate::elf::Rel
@@ -762,6 +908,7 @@ macro_rules! elf_io_reader_impl {
                 Ok(crate::elf::io_read_section_as_array!(
                     self.reader,
                     self.endianness,
+                    self.limits,
                     section_header,
                     $RelA,
                     crate::elf::RelA
@@ -786,12 +933,38 @@ macro_rules! elf_io_reader_impl {
                 Ok(crate::elf::io_read_section_as_array!(
                     self.reader,
                     self.endianness,
+                    self.limits,
                     section_header,
                     $RelR,
                     crate::elf::RelR
                 )?)
             }
 
+            pub fn read_group_section(
+                &mut self,
+                section_header: &crate::elf::SectionHeader,
+            ) -> Result<Vec<u32>> {
+                crate::elf::validate_section_header_sh_type_and_size!(
+                    "read_group_section",
+                    section_header,
+                    crate::elf::SHT_GROUP,
+                    "SHT_GROUP",
+                    self.stream_len
+                )?;
+
+                self.reader
+                    .seek(SeekFrom::Start(section_header.sh_offset))?;
+
+                Ok(crate::elf::io_read_section_as_array!(
+                    self.reader,
+                    self.endianness,
+                    self.limits,
+                    section_header,
+                    u32,
+                    u32
+                )?)
+            }
+
             pub fn read_gnu_hash_table_section(
                 &mut self,
                 section_header: &crate::elf::SectionHeader,
@@ -807,7 +980,7 @@ macro_rules! elf_io_reader_impl {
 
                 self.reader.seek(SeekFrom::Start(section_header.sh_offset))?;
 
-                Ok(<$GnuHashTable>::parse(self.reader, self.endianness, dynsym_len)?)
+                <$GnuHashTable>::parse_with_limits(self.reader, self.endianness, dynsym_len, &self.limits)
             }
 
             pub fn get_bytes(
@@ -820,24 +993,22 @@ macro_rules! elf_io_reader_impl {
                 let (end, overflow) = offset.overflowing_add(size);
 
                 if end >= self.stream_len || overflow {
-                    Err(Error::Malformed(format!(
-                        "Bytes offset of `{}` + size of `{}` is out of bounds for `{}` bytes",
-                        offset, size, self.stream_len
-                    )))
+                    Err(Error::Malformed(
+                        crate::Details::new(format!(
+                            "Bytes offset of `{}` + size of `{}` is out of bounds for `{}` bytes",
+                            offset, size, self.stream_len
+                        ))
+                        .offset(offset),
+                    ))
                 } else {
                     self.reader.seek(SeekFrom::Start(offset))?;
 
-                    if let Ok(size) = usize::try_from(size) {
-                        let mut result: Vec<u8> = Vec::with_capacity(size);
-                        // Resize is a "safe" way to set length to what the capacity is
-                        result.resize(size, 0);
-                        self.reader.read_exact(&mut result)?;
-                        Ok(Cow::Owned(result))
-                    } else {
-                        Err(Error::TooManyArrayItems(format!(
-                            "Attempted to load the bytes with a size of `{}`, `usize` can only hold `{}` bytes", size, usize::MAX
-                        )))
-                    }
+                    let size = self.limits.check_allocation(size, "bytes")?;
+                    let mut result: Vec<u8> = Vec::with_capacity(size);
+                    // Resize is a "safe" way to set length to what the capacity is
+                    result.resize(size, 0);
+                    self.reader.read_exact(&mut result)?;
+                    Ok(Cow::Owned(result))
                 }
             }
 
@@ -855,6 +1026,7 @@ macro_rules! elf_io_reader_impl {
                 Ok(crate::elf::io_read_as_array!(
                     self.reader,
                     self.endianness,
+                    self.limits,
                     table_size,
                     $Sym,
                     crate::elf::Sym
@@ -897,6 +1069,13 @@ macro_rules! elf_io_reader_impl {
                 self.get_section_bytes(section_header)
             }
 
+            fn get_decompressed_section_bytes(
+                &mut self,
+                section_header: &crate::elf::SectionHeader,
+            ) -> Result<Cow<'static, [u8]>> {
+                self.get_decompressed_section_bytes(section_header)
+            }
+
             fn read_str_table_section(&mut self, section_header: &crate::elf::SectionHeader) -> Result<crate::elf::StrTab<'static>> {
                 self.read_str_table_section(section_header)
             }
@@ -937,6 +1116,10 @@ macro_rules! elf_io_reader_impl {
                 self.read_relr_section(section_header)
             }
 
+            fn read_group_section(&mut self, section_header: &crate::elf::SectionHeader) -> Result<Vec<u32>> {
+                self.read_group_section(section_header)
+            }
+
             fn read_gnu_hash_table_section(
                 &mut self,
                 section_header: &crate::elf::SectionHeader,
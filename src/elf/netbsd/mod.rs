@@ -16,15 +16,52 @@
 
 use crate::stringable_consts_blocks::option_stringable_consts_block;
 
+mod note;
+pub use note::*;
+
+// Note types
+option_stringable_consts_block! {
+    const stringable: u32 {
+        /// `__NetBSD_Version__` the binary was built on, owned by `NetBSD`
+        pub NT_NETBSD_IDENT = 1;
+        /// Emulation name, owned by `NetBSD`
+        pub NT_NETBSD_EMULATION = 2;
+        /// PaX features, owned by `PaX`
+        pub NT_NETBSD_PAX = 3;
+        /// `MACHINE_ARCH` the binary was built for, owned by `NetBSD`
+        pub NT_NETBSD_MARCH = 5;
+        /// Code model, owned by `NetBSD`
+        pub NT_NETBSD_CMODEL = 6;
+    }
+
+    const ignore: u32 {
+        // NT_NETBSD_PAX values, these are flags
+        pub NT_NETBSD_PAX_MPROTECT = 0x01;
+        pub NT_NETBSD_PAX_NOMPROTECT = 0x02;
+        pub NT_NETBSD_PAX_GUARD = 0x04;
+        pub NT_NETBSD_PAX_NOGUARD = 0x08;
+        pub NT_NETBSD_PAX_ASLR = 0x10;
+        pub NT_NETBSD_PAX_NOASLR = 0x20;
+    }
+
+    pub fn ident_nt_to_str(value: u32) -> Option<&'static str>;
+}
+
 // NetBSD core note types.
 option_stringable_consts_block! {
     const stringable: u32 {
+        /// Owned by `NetBSD-CORE`
         pub NT_NETBSDCORE_PROCINFO = 1;
+        /// Owned by `NetBSD-CORE`
         pub NT_NETBSDCORE_AUXV = 2;
+        /// Owned by `NetBSD-CORE@<lwpid>`, one per LWP
         pub NT_NETBSDCORE_LWPSTATUS = 24;
     }
 
-    const ignore: u32 {}
+    const ignore: u32 {
+        /// First machine-dependent per-LWP note type (`PT_FIRSTMACH`), e.g. the `PT_GETREGS` register sets
+        pub NT_NETBSDCORE_FIRSTMACH = 32;
+    }
 
     pub fn nt_to_str(value: u32) -> Option<&'static str>;
 }
@@ -0,0 +1,428 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::elf::netbsd::*;
+use crate::elf::{ElfClass, Note};
+use crate::{Details, Error};
+use bitflags::bitflags;
+use scroll::{Endian, Pread};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// amd64 `struct reg` (`__gregset_t`) register names, in the order of a `PT_GETREGS` note
+pub const AMD64_REGISTER_NAMES: [&str; 26] = [
+    "rdi", "rsi", "rdx", "rcx", "r8", "r9", "r10", "r11", "r12", "r13", "r14", "r15", "rbp", "rbx",
+    "rax", "gs", "fs", "es", "ds", "trapno", "err", "rip", "cs", "rflags", "rsp", "ss",
+];
+
+bitflags! {
+    /// The contents of a `PaX` note of type `NT_NETBSD_PAX`, set with `paxctl(8)`
+    #[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct NetBsdPax: u32 {
+        const MPROTECT = NT_NETBSD_PAX_MPROTECT;
+        const NOMPROTECT = NT_NETBSD_PAX_NOMPROTECT;
+        const GUARD = NT_NETBSD_PAX_GUARD;
+        const NOGUARD = NT_NETBSD_PAX_NOGUARD;
+        const ASLR = NT_NETBSD_PAX_ASLR;
+        const NOASLR = NT_NETBSD_PAX_NOASLR;
+    }
+}
+
+impl NetBsdPax {
+    /// Parses the descriptor of a `PaX` note of type `NT_NETBSD_PAX`, unknown bits are kept
+    pub fn from_note(note: &Note, endianness: Endian) -> Result<NetBsdPax> {
+        check_note(note, "PaX", NT_NETBSD_PAX)?;
+        let mut offset = 0;
+        Ok(NetBsdPax::from_bits_retain(read_u32(
+            &note.n_desc,
+            &mut offset,
+            endianness,
+            "n_desc",
+        )?))
+    }
+}
+
+/// The contents of a `NT_NETBSD_IDENT` note (`.note.netbsd.ident`), the `__NetBSD_Version__` the binary was built on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NetBsdIdent {
+    /// `MMmmrrpp00`, e.g. `1000000000` for 10.0
+    pub version: u32,
+}
+
+impl NetBsdIdent {
+    pub fn from_note(note: &Note, endianness: Endian) -> Result<NetBsdIdent> {
+        check_note(note, "NetBSD", NT_NETBSD_IDENT)?;
+        let mut offset = 0;
+        Ok(NetBsdIdent {
+            version: read_u32(&note.n_desc, &mut offset, endianness, "n_desc")?,
+        })
+    }
+
+    pub fn major(&self) -> u32 {
+        self.version / 100000000
+    }
+
+    pub fn minor(&self) -> u32 {
+        self.version / 1000000 % 100
+    }
+
+    pub fn patch(&self) -> u32 {
+        self.version / 100 % 100
+    }
+}
+
+/// Parses the descriptor of a `NT_NETBSD_MARCH` note, the `MACHINE_ARCH` the binary was built for (e.g. `earmv7hf`)
+pub fn march_from_note(note: &Note) -> Result<String> {
+    check_note(note, "NetBSD", NT_NETBSD_MARCH)?;
+    Ok(fixed_string(&note.n_desc))
+}
+
+/// Parses the descriptor of a `NT_NETBSD_EMULATION` note (e.g. `netbsd`)
+pub fn emulation_from_note(note: &Note) -> Result<String> {
+    check_note(note, "NetBSD", NT_NETBSD_EMULATION)?;
+    Ok(fixed_string(&note.n_desc))
+}
+
+/// Returns the LWP id of a `NetBSD-CORE@<lwpid>` per-LWP core note name
+pub fn lwpid_from_note_name(n_name: &str) -> Option<u32> {
+    n_name.strip_prefix("NetBSD-CORE@")?.parse().ok()
+}
+
+/// The contents of a `NetBSD-CORE` note of type `NT_NETBSDCORE_PROCINFO` (`struct netbsd_elfcore_procinfo`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NetBsdProcInfo {
+    pub cpi_version: u32,
+    pub cpi_cpisize: u32,
+    /// Signal that killed the process
+    pub cpi_signo: u32,
+    pub cpi_sigcode: u32,
+    pub cpi_sigpend: [u32; 4],
+    pub cpi_sigmask: [u32; 4],
+    pub cpi_sigignore: [u32; 4],
+    pub cpi_sigcatch: [u32; 4],
+    pub cpi_pid: i32,
+    pub cpi_ppid: i32,
+    pub cpi_pgrp: i32,
+    pub cpi_sid: i32,
+    pub cpi_ruid: u32,
+    pub cpi_euid: u32,
+    pub cpi_svuid: u32,
+    pub cpi_rgid: u32,
+    pub cpi_egid: u32,
+    pub cpi_svgid: u32,
+    /// Number of LWPs, each has its own `NetBSD-CORE@<lwpid>` notes
+    pub cpi_nlwps: u32,
+    /// `p_comm`, the process name
+    pub cpi_name: String,
+    /// LWP the killing signal was sent to, added in version 2
+    pub cpi_siglwp: Option<i32>,
+}
+
+impl NetBsdProcInfo {
+    pub fn from_note(note: &Note, endianness: Endian) -> Result<NetBsdProcInfo> {
+        check_note(note, "NetBSD-CORE", NT_NETBSDCORE_PROCINFO)?;
+        let bytes: &[u8] = &note.n_desc;
+        let offset = &mut 0;
+
+        let cpi_version = read_u32(bytes, offset, endianness, "cpi_version")?;
+        let cpi_cpisize = read_u32(bytes, offset, endianness, "cpi_cpisize")?;
+        let cpi_signo = read_u32(bytes, offset, endianness, "cpi_signo")?;
+        let cpi_sigcode = read_u32(bytes, offset, endianness, "cpi_sigcode")?;
+        let cpi_sigpend = read_sigset(bytes, offset, endianness, "cpi_sigpend")?;
+        let cpi_sigmask = read_sigset(bytes, offset, endianness, "cpi_sigmask")?;
+        let cpi_sigignore = read_sigset(bytes, offset, endianness, "cpi_sigignore")?;
+        let cpi_sigcatch = read_sigset(bytes, offset, endianness, "cpi_sigcatch")?;
+        let cpi_pid = read_u32(bytes, offset, endianness, "cpi_pid")? as i32;
+        let cpi_ppid = read_u32(bytes, offset, endianness, "cpi_ppid")? as i32;
+        let cpi_pgrp = read_u32(bytes, offset, endianness, "cpi_pgrp")? as i32;
+        let cpi_sid = read_u32(bytes, offset, endianness, "cpi_sid")? as i32;
+        let cpi_ruid = read_u32(bytes, offset, endianness, "cpi_ruid")?;
+        let cpi_euid = read_u32(bytes, offset, endianness, "cpi_euid")?;
+        let cpi_svuid = read_u32(bytes, offset, endianness, "cpi_svuid")?;
+        let cpi_rgid = read_u32(bytes, offset, endianness, "cpi_rgid")?;
+        let cpi_egid = read_u32(bytes, offset, endianness, "cpi_egid")?;
+        let cpi_svgid = read_u32(bytes, offset, endianness, "cpi_svgid")?;
+        let cpi_nlwps = read_u32(bytes, offset, endianness, "cpi_nlwps")?;
+        let cpi_name = read_name(bytes, offset, 32, "cpi_name")?;
+        let cpi_siglwp = if cpi_version >= 2 && (cpi_cpisize as usize) >= *offset + 4 {
+            Some(read_u32(bytes, offset, endianness, "cpi_siglwp")? as i32)
+        } else {
+            None
+        };
+
+        Ok(NetBsdProcInfo {
+            cpi_version,
+            cpi_cpisize,
+            cpi_signo,
+            cpi_sigcode,
+            cpi_sigpend,
+            cpi_sigmask,
+            cpi_sigignore,
+            cpi_sigcatch,
+            cpi_pid,
+            cpi_ppid,
+            cpi_pgrp,
+            cpi_sid,
+            cpi_ruid,
+            cpi_euid,
+            cpi_svuid,
+            cpi_rgid,
+            cpi_egid,
+            cpi_svgid,
+            cpi_nlwps,
+            cpi_name,
+            cpi_siglwp,
+        })
+    }
+}
+
+/// The contents of a `NetBSD-CORE@<lwpid>` note of type `NT_NETBSDCORE_LWPSTATUS` (`struct ptrace_lwpstatus`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NetBsdLwpStatus {
+    pub pl_lwpid: i32,
+    pub pl_sigpend: [u32; 4],
+    pub pl_sigmask: [u32; 4],
+    /// The LWP name
+    pub pl_name: String,
+    /// Address of the LWP's TLS area
+    pub pl_private: u64,
+}
+
+impl NetBsdLwpStatus {
+    pub fn from_note(note: &Note, class: ElfClass, endianness: Endian) -> Result<NetBsdLwpStatus> {
+        check_note(note, "NetBSD-CORE@", NT_NETBSDCORE_LWPSTATUS)?;
+        let bytes: &[u8] = &note.n_desc;
+        let offset = &mut 0;
+
+        Ok(NetBsdLwpStatus {
+            pl_lwpid: read_u32(bytes, offset, endianness, "pl_lwpid")? as i32,
+            pl_sigpend: read_sigset(bytes, offset, endianness, "pl_sigpend")?,
+            pl_sigmask: read_sigset(bytes, offset, endianness, "pl_sigmask")?,
+            pl_name: read_name(bytes, offset, 20, "pl_name")?,
+            pl_private: read_word(bytes, offset, class, endianness, "pl_private")?,
+        })
+    }
+}
+
+/// A machine-dependent `NetBSD-CORE@<lwpid>` note, e.g. the `PT_GETREGS` general purpose registers
+///
+/// Which type holds which register set depends on `e_machine`. The registers are split into words of the ELF class,
+/// for amd64 `PT_GETREGS` notes they are named by `AMD64_REGISTER_NAMES`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NetBsdLwpRegisters {
+    pub lwpid: u32,
+    pub n_type: u32,
+    pub registers: Vec<u64>,
+}
+
+impl NetBsdLwpRegisters {
+    pub fn from_note(
+        note: &Note,
+        class: ElfClass,
+        endianness: Endian,
+    ) -> Result<NetBsdLwpRegisters> {
+        let lwpid = lwpid_from_note_name(&note.n_name);
+
+        match lwpid {
+            Some(lwpid) if note.n_type >= NT_NETBSDCORE_FIRSTMACH => {
+                let word_size = match class {
+                    ElfClass::Elf32 => 4,
+                    ElfClass::Elf64 => 8,
+                };
+                let mut offset = 0;
+                let registers = (0..note.n_desc.len() / word_size)
+                    .map(|_| read_word(&note.n_desc, &mut offset, class, endianness, "n_desc"))
+                    .collect::<Result<Vec<u64>>>()?;
+
+                Ok(NetBsdLwpRegisters {
+                    lwpid,
+                    n_type: note.n_type,
+                    registers,
+                })
+            }
+            _ => Err(Error::InvalidArguments(
+                Details::new(format!(
+                    "Expected a machine-dependent `NetBSD-CORE@<lwpid>` note but got a `{}` note of type `{}`",
+                    note.n_name, note.n_type
+                ))
+                .structure("elf::Note")
+                .field("n_type"),
+            )),
+        }
+    }
+}
+
+fn check_note(note: &Note, n_name: &str, n_type: u32) -> Result<()> {
+    let name_matches = match n_name.strip_suffix('@') {
+        Some(prefix) => note
+            .n_name
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with('@')),
+        None => note.n_name == n_name,
+    };
+
+    if !name_matches || note.n_type != n_type {
+        return Err(Error::InvalidArguments(
+            Details::new(format!(
+                "Expected a `{}` note of type `{}` but got a `{}` note of type `{}`",
+                n_name,
+                if n_name.starts_with("NetBSD-CORE") {
+                    nt_to_str(n_type)
+                } else {
+                    ident_nt_to_str(n_type)
+                }
+                .unwrap_or("NT_UNKNOWN"),
+                note.n_name,
+                note.n_type
+            ))
+            .structure("elf::Note")
+            .field("n_type"),
+        ));
+    }
+
+    Ok(())
+}
+
+fn truncated(len: usize, expected: usize, field: &'static str) -> Error {
+    Error::Malformed(
+        Details::new(format!(
+            "NetBSD note descriptor is `{}` bytes, expected at least `{}` for `{}`",
+            len, expected, field
+        ))
+        .structure("elf::Note")
+        .field("n_desc"),
+    )
+}
+
+fn read_u32(
+    bytes: &[u8],
+    offset: &mut usize,
+    endianness: Endian,
+    field: &'static str,
+) -> Result<u32> {
+    let expected = *offset + 4;
+    bytes
+        .gread_with::<u32>(offset, endianness)
+        .map_err(|_| truncated(bytes.len(), expected, field))
+}
+
+fn read_word(
+    bytes: &[u8],
+    offset: &mut usize,
+    class: ElfClass,
+    endianness: Endian,
+    field: &'static str,
+) -> Result<u64> {
+    match class {
+        ElfClass::Elf32 => read_u32(bytes, offset, endianness, field).map(u64::from),
+        ElfClass::Elf64 => {
+            let expected = *offset + 8;
+            bytes
+                .gread_with::<u64>(offset, endianness)
+                .map_err(|_| truncated(bytes.len(), expected, field))
+        }
+    }
+}
+
+fn read_sigset(
+    bytes: &[u8],
+    offset: &mut usize,
+    endianness: Endian,
+    field: &'static str,
+) -> Result<[u32; 4]> {
+    let mut sigset = [0u32; 4];
+    for word in sigset.iter_mut() {
+        *word = read_u32(bytes, offset, endianness, field)?;
+    }
+    Ok(sigset)
+}
+
+fn read_name(bytes: &[u8], offset: &mut usize, size: usize, field: &'static str) -> Result<String> {
+    let name = bytes
+        .get(*offset..*offset + size)
+        .ok_or_else(|| truncated(bytes.len(), *offset + size, field))?;
+    *offset += size;
+    Ok(fixed_string(name))
+}
+
+fn fixed_string(bytes: &[u8]) -> String {
+    let end = bytes
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    #[test]
+    fn parse_netbsd_notes() {
+        let ident = Note {
+            n_type: NT_NETBSD_IDENT,
+            n_name: Cow::Borrowed("NetBSD"),
+            n_desc: Cow::Owned(1000000300u32.to_le_bytes().to_vec()),
+        };
+        let ident = NetBsdIdent::from_note(&ident, scroll::LE).unwrap();
+        assert_eq!((ident.major(), ident.minor(), ident.patch()), (10, 0, 3));
+
+        let mut desc: Vec<u8> = Vec::new();
+        for word in [2u32, 160, 11, 1] {
+            desc.extend_from_slice(&word.to_le_bytes());
+        }
+        desc.extend_from_slice(&[0u8; 64]);
+        for word in [1234u32, 1, 1234, 1234, 1000, 1000, 1000, 100, 100, 100, 3] {
+            desc.extend_from_slice(&word.to_le_bytes());
+        }
+        let mut name = [0u8; 32];
+        name[..4].copy_from_slice(b"init");
+        desc.extend_from_slice(&name);
+        desc.extend_from_slice(&2u32.to_le_bytes());
+        let procinfo = Note {
+            n_type: NT_NETBSDCORE_PROCINFO,
+            n_name: Cow::Borrowed("NetBSD-CORE"),
+            n_desc: Cow::Owned(desc),
+        };
+        let procinfo = NetBsdProcInfo::from_note(&procinfo, scroll::LE).unwrap();
+        assert_eq!((procinfo.cpi_signo, procinfo.cpi_pid), (11, 1234));
+        assert_eq!(
+            (procinfo.cpi_nlwps, procinfo.cpi_name.as_str()),
+            (3, "init")
+        );
+        assert_eq!(procinfo.cpi_siglwp, Some(2));
+
+        let registers = Note {
+            n_type: NT_NETBSDCORE_FIRSTMACH + 1,
+            n_name: Cow::Borrowed("NetBSD-CORE@2"),
+            n_desc: Cow::Owned([0x42u8; 26 * 8].to_vec()),
+        };
+        let registers =
+            NetBsdLwpRegisters::from_note(&registers, ElfClass::Elf64, scroll::LE).unwrap();
+        assert_eq!(registers.lwpid, 2);
+        assert_eq!(registers.registers.len(), AMD64_REGISTER_NAMES.len());
+
+        // Per-LWP notes are owned by `NetBSD-CORE@<lwpid>`, not `NetBSD-CORE`
+        let mut lwpstatus = Note {
+            n_type: NT_NETBSDCORE_LWPSTATUS,
+            n_name: Cow::Borrowed("NetBSD-CORE"),
+            n_desc: Cow::Owned(vec![0; 64]),
+        };
+        assert!(NetBsdLwpStatus::from_note(&lwpstatus, ElfClass::Elf64, scroll::LE).is_err());
+        lwpstatus.n_name = Cow::Borrowed("NetBSD-CORE@1");
+        assert!(NetBsdLwpStatus::from_note(&lwpstatus, ElfClass::Elf64, scroll::LE).is_ok());
+    }
+}
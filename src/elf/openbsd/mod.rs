@@ -16,6 +16,9 @@
 
 use crate::stringable_consts_blocks::option_stringable_consts_block;
 
+mod note;
+pub use note::*;
+
 // Segment types
 option_stringable_consts_block! {
     const stringable: u32 {
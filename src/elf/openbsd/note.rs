@@ -0,0 +1,242 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::elf::openbsd::*;
+use crate::elf::{ElfClass, Note};
+use crate::{Details, Error};
+use scroll::{Endian, Pread};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// amd64 `struct reg` register names, in the order of a `NT_OPENBSD_REGS` note
+pub const AMD64_REGISTER_NAMES: [&str; 24] = [
+    "rdi", "rsi", "rdx", "rcx", "r8", "r9", "r10", "r11", "r12", "r13", "r14", "r15", "rbp", "rbx",
+    "rax", "rsp", "rip", "rflags", "cs", "ss", "ds", "es", "fs", "gs",
+];
+
+/// Returns the thread id of an `OpenBSD@<tid>` per-thread core note name
+pub fn tid_from_note_name(n_name: &str) -> Option<u32> {
+    n_name.strip_prefix("OpenBSD@")?.parse().ok()
+}
+
+/// The contents of an `OpenBSD` note of type `NT_OPENBSD_PROCINFO` (`struct elfcore_procinfo`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpenBsdProcInfo {
+    pub cpi_version: u32,
+    pub cpi_cpisize: u32,
+    /// Signal that killed the process
+    pub cpi_signo: u32,
+    pub cpi_sigcode: u32,
+    pub cpi_sigpend: u32,
+    pub cpi_sigmask: u32,
+    pub cpi_sigignore: u32,
+    pub cpi_sigcatch: u32,
+    pub cpi_pid: i32,
+    pub cpi_ppid: i32,
+    pub cpi_pgrp: i32,
+    pub cpi_sid: i32,
+    pub cpi_ruid: u32,
+    pub cpi_euid: u32,
+    pub cpi_svuid: u32,
+    pub cpi_rgid: u32,
+    pub cpi_egid: u32,
+    pub cpi_svgid: u32,
+    /// `ps_comm`, the process name
+    pub cpi_name: String,
+}
+
+impl OpenBsdProcInfo {
+    /// Size of `cpi_name`
+    const NAME_SIZE: usize = 32;
+
+    pub fn from_note(note: &Note, endianness: Endian) -> Result<OpenBsdProcInfo> {
+        if note.n_name != "OpenBSD" || note.n_type != NT_OPENBSD_PROCINFO {
+            return Err(Error::InvalidArguments(
+                Details::new(format!(
+                    "Expected an `OpenBSD` note of type `NT_OPENBSD_PROCINFO` but got a `{}` note of type `{}`",
+                    note.n_name, note.n_type
+                ))
+                .structure("elf::Note")
+                .field("n_type"),
+            ));
+        }
+
+        let bytes: &[u8] = &note.n_desc;
+        let mut words = [0u32; 18];
+        for (index, word) in words.iter_mut().enumerate() {
+            *word = bytes
+                .pread_with::<u32>(index * 4, endianness)
+                .map_err(|_| truncated(bytes.len(), (index + 1) * 4, "elfcore_procinfo"))?;
+        }
+        let name = bytes
+            .get(72..72 + Self::NAME_SIZE)
+            .ok_or_else(|| truncated(bytes.len(), 72 + Self::NAME_SIZE, "cpi_name"))?;
+        let name_end = name
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(name.len());
+
+        Ok(OpenBsdProcInfo {
+            cpi_version: words[0],
+            cpi_cpisize: words[1],
+            cpi_signo: words[2],
+            cpi_sigcode: words[3],
+            cpi_sigpend: words[4],
+            cpi_sigmask: words[5],
+            cpi_sigignore: words[6],
+            cpi_sigcatch: words[7],
+            cpi_pid: words[8] as i32,
+            cpi_ppid: words[9] as i32,
+            cpi_pgrp: words[10] as i32,
+            cpi_sid: words[11] as i32,
+            cpi_ruid: words[12],
+            cpi_euid: words[13],
+            cpi_svuid: words[14],
+            cpi_rgid: words[15],
+            cpi_egid: words[16],
+            cpi_svgid: words[17],
+            cpi_name: String::from_utf8_lossy(&name[..name_end]).into_owned(),
+        })
+    }
+}
+
+/// An `OpenBSD@<tid>` note of type `NT_OPENBSD_REGS`, `NT_OPENBSD_FPREGS`, `NT_OPENBSD_XFPREGS` or
+/// `NT_OPENBSD_WCOOKIE`
+///
+/// The layout of the registers depends on `e_machine`, they are split into words of the ELF class. For amd64
+/// `NT_OPENBSD_REGS` notes they are named by `AMD64_REGISTER_NAMES`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpenBsdThreadRegisters {
+    pub tid: u32,
+    pub n_type: u32,
+    pub registers: Vec<u64>,
+}
+
+impl OpenBsdThreadRegisters {
+    pub fn from_note(
+        note: &Note,
+        class: ElfClass,
+        endianness: Endian,
+    ) -> Result<OpenBsdThreadRegisters> {
+        let tid = match tid_from_note_name(&note.n_name) {
+            Some(tid)
+                if matches!(
+                    note.n_type,
+                    NT_OPENBSD_REGS | NT_OPENBSD_FPREGS | NT_OPENBSD_XFPREGS | NT_OPENBSD_WCOOKIE
+                ) =>
+            {
+                tid
+            }
+            _ => {
+                return Err(Error::InvalidArguments(
+                    Details::new(format!(
+                    "Expected an `OpenBSD@<tid>` register note but got a `{}` note of type `{}`",
+                    note.n_name, note.n_type
+                ))
+                    .structure("elf::Note")
+                    .field("n_type"),
+                ))
+            }
+        };
+
+        let registers = match class {
+            ElfClass::Elf32 => note
+                .n_desc
+                .chunks_exact(4)
+                .map(|word| word.pread_with::<u32>(0, endianness).map(u64::from))
+                .collect::<std::result::Result<Vec<u64>, scroll::Error>>()?,
+            ElfClass::Elf64 => note
+                .n_desc
+                .chunks_exact(8)
+                .map(|word| word.pread_with::<u64>(0, endianness))
+                .collect::<std::result::Result<Vec<u64>, scroll::Error>>()?,
+        };
+
+        Ok(OpenBsdThreadRegisters {
+            tid,
+            n_type: note.n_type,
+            registers,
+        })
+    }
+
+    /// Looks up an amd64 `NT_OPENBSD_REGS` register by name
+    pub fn amd64_register(&self, name: &str) -> Option<u64> {
+        if self.n_type != NT_OPENBSD_REGS {
+            return None;
+        }
+
+        let index = AMD64_REGISTER_NAMES
+            .iter()
+            .position(|register| *register == name)?;
+        self.registers.get(index).copied()
+    }
+}
+
+fn truncated(len: usize, expected: usize, field: &'static str) -> Error {
+    Error::Malformed(
+        Details::new(format!(
+            "OpenBSD note descriptor is `{}` bytes, expected at least `{}`",
+            len, expected
+        ))
+        .structure("elf::openbsd::OpenBsdProcInfo")
+        .field(field),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    #[test]
+    fn parse_openbsd_notes() {
+        let mut desc: Vec<u8> = Vec::new();
+        for word in [1u32, 104, 6, 0, 0, 0, 0, 0, 4321, 1, 4321, 4321] {
+            desc.extend_from_slice(&word.to_le_bytes());
+        }
+        desc.extend_from_slice(&[0u8; 24]);
+        let mut name = [0u8; 32];
+        name[..3].copy_from_slice(b"ksh");
+        desc.extend_from_slice(&name);
+        let procinfo = Note {
+            n_type: NT_OPENBSD_PROCINFO,
+            n_name: Cow::Borrowed("OpenBSD"),
+            n_desc: Cow::Owned(desc),
+        };
+        let procinfo = OpenBsdProcInfo::from_note(&procinfo, scroll::LE).unwrap();
+        assert_eq!(
+            (
+                procinfo.cpi_signo,
+                procinfo.cpi_pid,
+                procinfo.cpi_name.as_str()
+            ),
+            (6, 4321, "ksh")
+        );
+
+        let mut desc: Vec<u8> = Vec::new();
+        for register in 0..AMD64_REGISTER_NAMES.len() as u64 {
+            desc.extend_from_slice(&register.to_le_bytes());
+        }
+        let regs = Note {
+            n_type: NT_OPENBSD_REGS,
+            n_name: Cow::Borrowed("OpenBSD@100042"),
+            n_desc: Cow::Owned(desc),
+        };
+        let regs = OpenBsdThreadRegisters::from_note(&regs, ElfClass::Elf64, scroll::LE).unwrap();
+        assert_eq!(regs.tid, 100042);
+        assert_eq!(regs.amd64_register("rip"), Some(16));
+        assert_eq!(regs.amd64_register("rsp"), Some(15));
+    }
+}
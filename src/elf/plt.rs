@@ -0,0 +1,592 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+// Pairs PLT stubs with the symbols they call by decoding the GOT slot each stub jumps through
+
+use crate::elf::{
+    aarch64, arm, riscv, x86, x86_64, Reader, Rel, RelA, StrTab, Sym, DT_PLTGOT, EM_386,
+    EM_AARCH64, EM_ARM, EM_RISCV, EM_X86_64, SHT_DYNAMIC, SHT_DYNSYM, SHT_REL, SHT_RELA,
+};
+use crate::error::ResultExt;
+use crate::{ContextFrame, Details, Error};
+use scroll::Pread;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Sections holding PLT stubs, `.plt.sec` holds the stubs called by code when IBT is enabled and `.plt.got` holds
+/// stubs for functions whose address is also taken
+pub const PLT_SECTION_NAMES: [&str; 3] = [".plt", ".plt.sec", ".plt.got"];
+
+/// A dynamic relocation, from `DT_JMPREL` or `.rel(a).dyn`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GotRelocation {
+    pub r_offset: u64,
+    pub r_sym: u32,
+    pub r_type: u32,
+}
+
+impl From<&Rel> for GotRelocation {
+    fn from(rel: &Rel) -> Self {
+        GotRelocation {
+            r_offset: rel.r_offset,
+            r_sym: rel.r_sym(),
+            r_type: rel.r_type(),
+        }
+    }
+}
+
+impl From<&RelA> for GotRelocation {
+    fn from(rela: &RelA) -> Self {
+        GotRelocation {
+            r_offset: rela.r_offset,
+            r_sym: rela.r_sym(),
+            r_type: rela.r_type(),
+        }
+    }
+}
+
+/// The contents of a section holding PLT stubs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PltSection<'a> {
+    pub name: &'a str,
+    /// `sh_addr`
+    pub address: u64,
+    pub bytes: &'a [u8],
+}
+
+/// Everything `PltAnalysis::analyze` needs, see `PltAnalysis::read` to read it from a file
+pub struct PltInput<'a> {
+    pub e_machine: u16,
+    /// `DT_PLTGOT`, only needed for i386 position independent stubs which address the GOT relative to `%ebx`
+    pub got_address: Option<u64>,
+    pub sections: &'a [PltSection<'a>],
+    /// Every dynamic relocation, relocations that don't fill a GOT slot are ignored
+    pub relocations: &'a [GotRelocation],
+    /// `.dynsym`
+    pub symbols: &'a [Sym],
+    /// `.dynstr`
+    pub strtab: &'a StrTab<'a>,
+}
+
+/// A GOT slot filled in by the dynamic linker with the address of a symbol
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GotSlot {
+    pub address: u64,
+    pub symbol_index: u32,
+    pub name: String,
+    /// `*_JUMP_SLOT` for lazily bound PLT slots, `*_GLOB_DAT` for everything else
+    pub r_type: u32,
+}
+
+/// A PLT stub jumping through a GOT slot
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PltEntry {
+    /// Address of the first instruction of the stub, including any `endbr64`/`bti c` landing pad
+    pub address: u64,
+    /// Distance to the next stub, or to the end of the section for the last stub
+    pub size: u64,
+    pub got_address: u64,
+    pub symbol_index: u32,
+    /// Name of the called symbol, without the `@plt` suffix
+    pub name: String,
+    /// The section the stub is in, e.g. `.plt.sec`
+    pub section: String,
+}
+
+impl PltEntry {
+    /// The name `objdump` gives the stub, e.g. `printf@plt`
+    pub fn plt_name(&self) -> String {
+        format!("{}@plt", self.name)
+    }
+}
+
+/// PLT stubs and GOT slots of a dynamically linked file
+///
+/// Stubs are decoded for x86-64, i386, AArch64, ARM (GNU ld and LLD layouts, ARM mode with an optional Thumb `bx pc`
+/// prefix) and RISC-V. Stubs that don't jump through a GOT slot, like `PLT0` and the lazy `.plt` entries of IBT
+/// enabled files, are skipped.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PltAnalysis {
+    /// Sorted by address
+    pub entries: Vec<PltEntry>,
+    /// Sorted by address
+    pub got_slots: Vec<GotSlot>,
+}
+
+impl PltAnalysis {
+    /// Reads `.dynsym`, the dynamic relocations, `DT_PLTGOT` and the PLT sections and analyzes them
+    ///
+    /// Files without a `.dynsym` section have no PLT and an empty analysis is returned.
+    pub fn read<'a, TReader: Reader<'a>>(reader: &mut TReader) -> Result<PltAnalysis> {
+        let header = reader.read_header()?;
+        let section_headers =
+            reader.read_section_headers(header.e_shoff, header.e_shentsize, header.e_shnum)?;
+
+        let Some(dynsym_index) = section_headers
+            .iter()
+            .position(|section_header| section_header.sh_type == SHT_DYNSYM)
+        else {
+            return Ok(PltAnalysis::default());
+        };
+        let dynsym_header = &section_headers[dynsym_index];
+        let symbols = reader.read_sym_table(dynsym_header.sh_offset, dynsym_header.sh_size)?;
+        let strtab = match section_headers.get(dynsym_header.sh_link as usize) {
+            Some(strtab_header) => reader.read_str_table_section(strtab_header)?,
+            None => StrTab::parse(Default::default(), 0)?,
+        };
+        let shstrtab = match section_headers.get(usize::from(header.e_shstrndx)) {
+            Some(shstrtab_header) => reader.read_str_table_section(shstrtab_header)?,
+            None => StrTab::parse(Default::default(), 0)?,
+        };
+
+        let mut relocations: Vec<GotRelocation> = Vec::new();
+        let mut got_address: Option<u64> = None;
+        let mut section_bytes: Vec<(String, u64, std::borrow::Cow<'a, [u8]>)> = Vec::new();
+
+        for (index, section_header) in section_headers.iter().enumerate() {
+            let context = || {
+                ContextFrame::new("section")
+                    .index(index)
+                    .offset(section_header.sh_offset)
+            };
+
+            match section_header.sh_type {
+                SHT_REL if section_header.sh_link as usize == dynsym_index => relocations.extend(
+                    reader
+                        .read_rel_section(section_header)
+                        .context(context)?
+                        .iter()
+                        .map(GotRelocation::from),
+                ),
+                SHT_RELA if section_header.sh_link as usize == dynsym_index => relocations.extend(
+                    reader
+                        .read_rela_section(section_header)
+                        .context(context)?
+                        .iter()
+                        .map(GotRelocation::from),
+                ),
+                SHT_DYNAMIC => {
+                    got_address = reader
+                        .read_dynamic_section(section_header)
+                        .context(context)?
+                        .iter()
+                        .find(|entry| entry.d_tag == DT_PLTGOT)
+                        .map(|entry| entry.d_val);
+                }
+                _ => {
+                    let name = shstrtab.get_at_offset(section_header.sh_name)?;
+
+                    if PLT_SECTION_NAMES.contains(&name.as_ref()) {
+                        section_bytes.push((
+                            name.into_owned(),
+                            section_header.sh_addr,
+                            reader.get_section_bytes(section_header).context(context)?,
+                        ));
+                    }
+                }
+            }
+        }
+
+        let sections: Vec<PltSection> = section_bytes
+            .iter()
+            .map(|(name, address, bytes)| PltSection {
+                name,
+                address: *address,
+                bytes,
+            })
+            .collect();
+
+        Self::analyze(&PltInput {
+            e_machine: header.e_machine,
+            got_address,
+            sections: &sections,
+            relocations: &relocations,
+            symbols: &symbols,
+            strtab: &strtab,
+        })
+    }
+
+    /// Decodes every stub of `input.sections` and names it after the symbol of the GOT slot it jumps through
+    pub fn analyze(input: &PltInput) -> Result<PltAnalysis> {
+        if !matches!(
+            input.e_machine,
+            EM_X86_64 | EM_386 | EM_AARCH64 | EM_ARM | EM_RISCV
+        ) {
+            return Err(Error::InvalidArguments(
+                Details::new(format!(
+                    "PLT stubs can't be decoded for `{}`",
+                    crate::elf::em_to_str(input.e_machine)
+                ))
+                .structure("elf::PltInput")
+                .field("e_machine"),
+            ));
+        }
+
+        let mut got_slots: Vec<GotSlot> = Vec::new();
+
+        for relocation in input.relocations {
+            if relocation.r_sym == 0 || !is_got_relocation(input.e_machine, relocation.r_type) {
+                continue;
+            }
+
+            let symbol = input
+                .symbols
+                .get(relocation.r_sym as usize)
+                .ok_or_else(|| {
+                    Error::Malformed(
+                        Details::new(format!(
+                            "Relocation symbol index `{}` is out of range for `{}` dynamic symbols",
+                            relocation.r_sym,
+                            input.symbols.len()
+                        ))
+                        .structure("elf::RelA")
+                        .field("r_info"),
+                    )
+                })?;
+
+            got_slots.push(GotSlot {
+                address: relocation.r_offset,
+                symbol_index: relocation.r_sym,
+                name: input.strtab.get_at_offset(symbol.st_name)?.into_owned(),
+                r_type: relocation.r_type,
+            });
+        }
+
+        got_slots.sort_by_key(|slot| slot.address);
+        got_slots.dedup_by_key(|slot| slot.address);
+
+        let mut entries: Vec<PltEntry> = Vec::new();
+
+        for section in input.sections {
+            let stubs = decode_stubs(input.e_machine, section, input.got_address);
+            let section_end = section.address.saturating_add(section.bytes.len() as u64);
+            let mut matched: Vec<(u64, u64, &GotSlot)> = stubs
+                .into_iter()
+                .filter_map(|(address, got_address)| {
+                    got_slots
+                        .binary_search_by_key(&got_address, |slot| slot.address)
+                        .ok()
+                        .map(|index| (address, got_address, &got_slots[index]))
+                })
+                .collect();
+            matched.dedup_by_key(|(address, _, _)| *address);
+
+            for (index, &(address, got_address, slot)) in matched.iter().enumerate() {
+                let end = matched
+                    .get(index + 1)
+                    .map_or(section_end, |(next_address, _, _)| *next_address);
+
+                entries.push(PltEntry {
+                    address,
+                    size: end.saturating_sub(address),
+                    got_address,
+                    symbol_index: slot.symbol_index,
+                    name: slot.name.clone(),
+                    section: section.name.to_string(),
+                });
+            }
+        }
+
+        entries.sort_by_key(|entry| entry.address);
+
+        Ok(PltAnalysis { entries, got_slots })
+    }
+
+    /// Returns the stub containing `address`
+    pub fn lookup(&self, address: u64) -> Option<&PltEntry> {
+        let index = self
+            .entries
+            .partition_point(|entry| entry.address <= address)
+            .checked_sub(1)?;
+        let entry = &self.entries[index];
+
+        (address - entry.address < entry.size).then_some(entry)
+    }
+
+    /// Returns the GOT slot at `address`
+    pub fn got_slot(&self, address: u64) -> Option<&GotSlot> {
+        self.got_slots
+            .binary_search_by_key(&address, |slot| slot.address)
+            .ok()
+            .map(|index| &self.got_slots[index])
+    }
+}
+
+fn is_got_relocation(e_machine: u16, r_type: u32) -> bool {
+    match e_machine {
+        EM_X86_64 => r_type == x86_64::R_X86_64_JUMP_SLOT || r_type == x86_64::R_X86_64_GLOB_DAT,
+        EM_386 => r_type == x86::R_386_JUMP_SLOT || r_type == x86::R_386_GLOB_DAT,
+        EM_AARCH64 => {
+            r_type == aarch64::R_AARCH64_JUMP_SLOT || r_type == aarch64::R_AARCH64_GLOB_DAT
+        }
+        EM_ARM => r_type == arm::R_ARM_JUMP_SLOT || r_type == arm::R_ARM_GLOB_DAT,
+        // RISC-V GOT data slots use plain `R_RISCV_64`/`R_RISCV_32`, which can't be told apart from other pointers
+        EM_RISCV => r_type == riscv::R_RISCV_JUMP_SLOT,
+        _ => false,
+    }
+}
+
+/// Returns the `(stub address, GOT slot address)` of every stub-like instruction sequence in `section`
+fn decode_stubs(e_machine: u16, section: &PltSection, got_address: Option<u64>) -> Vec<(u64, u64)> {
+    let bytes = section.bytes;
+    let mut stubs: Vec<(u64, u64)> = Vec::new();
+
+    match e_machine {
+        EM_X86_64 | EM_386 => {
+            let endbr: [u8; 4] = if e_machine == EM_X86_64 {
+                [0xf3, 0x0f, 0x1e, 0xfa]
+            } else {
+                [0xf3, 0x0f, 0x1e, 0xfb]
+            };
+
+            for offset in 0..bytes.len().saturating_sub(5) {
+                let Ok(displacement) = bytes.pread_with::<i32>(offset + 2, scroll::LE) else {
+                    break;
+                };
+                let next_instruction = section.address.wrapping_add(offset as u64 + 6);
+
+                let target = match (e_machine, bytes[offset], bytes[offset + 1]) {
+                    // `jmp *disp32(%rip)`
+                    (EM_X86_64, 0xff, 0x25) => {
+                        next_instruction.wrapping_add(displacement as i64 as u64)
+                    }
+                    // `jmp *abs32`
+                    (EM_386, 0xff, 0x25) => u64::from(displacement as u32),
+                    // `jmp *disp32(%ebx)`, `%ebx` holds the address of the GOT
+                    (EM_386, 0xff, 0xa3) => match got_address {
+                        Some(got_address) => {
+                            u64::from((got_address as u32).wrapping_add(displacement as u32))
+                        }
+                        None => continue,
+                    },
+                    _ => continue,
+                };
+
+                let mut start = offset;
+                // `bnd` prefix
+                if start >= 1 && bytes[start - 1] == 0xf2 {
+                    start -= 1;
+                }
+                if start >= 4 && bytes[start - 4..start] == endbr {
+                    start -= 4;
+                }
+
+                stubs.push((section.address.wrapping_add(start as u64), target));
+            }
+        }
+        EM_AARCH64 => {
+            let words = words(bytes);
+
+            for (index, pair) in words.windows(2).enumerate() {
+                let (adrp, ldr) = (pair[0], pair[1]);
+
+                // `adrp x16, page` followed by `ldr x17, [x16, #offset]`
+                if adrp & 0x9f00001f != 0x90000010 || ldr & 0xffc003ff != 0xf9400211 {
+                    continue;
+                }
+
+                let pc = section.address.wrapping_add(index as u64 * 4);
+                let immediate = (((adrp >> 5) & 0x7ffff) << 2) | ((adrp >> 29) & 0x3);
+                // Sign extend the 21 bit page count
+                let pages = ((immediate << 11) as i32 >> 11) as i64;
+                let page = (pc & !0xfff).wrapping_add((pages << 12) as u64);
+                let target = page.wrapping_add(u64::from((ldr >> 10) & 0xfff) * 8);
+
+                // `bti c`
+                let start = if index >= 1 && words[index - 1] == 0xd503245f {
+                    pc - 4
+                } else {
+                    pc
+                };
+
+                stubs.push((start, target));
+            }
+        }
+        EM_ARM => {
+            let words = words(bytes);
+
+            for (index, &first) in words.iter().enumerate() {
+                // `add ip, pc, #imm`, then any number of `add ip, ip, #imm`, then `ldr pc, [ip, #imm]!`
+                if first & 0xfffff000 != 0xe28fc000 {
+                    continue;
+                }
+
+                let pc = section.address.wrapping_add(index as u64 * 4);
+                let mut target = (pc as u32)
+                    .wrapping_add(8)
+                    .wrapping_add(arm_immediate(first));
+
+                for &word in &words[index + 1..] {
+                    if word & 0xfffff000 == 0xe28cc000 {
+                        target = target.wrapping_add(arm_immediate(word));
+                    } else {
+                        if word & 0xfffff000 == 0xe5bcf000 {
+                            target = target.wrapping_add(word & 0xfff);
+
+                            // Thumb callers enter through a `bx pc; nop` prefix
+                            let start = if index >= 1 && words[index - 1] == 0x46c04778 {
+                                pc - 4
+                            } else {
+                                pc
+                            };
+
+                            stubs.push((start, u64::from(target)));
+                        }
+
+                        break;
+                    }
+                }
+            }
+        }
+        EM_RISCV => {
+            let words = words(bytes);
+
+            for (index, pair) in words.windows(2).enumerate() {
+                let (auipc, load) = (pair[0], pair[1]);
+
+                // `auipc t3, hi` followed by `lw t3, lo(t3)` or `ld t3, lo(t3)`
+                if auipc & 0xfff != 0xe17
+                    || (load & 0xfffff != 0xe2e03 && load & 0xfffff != 0xe3e03)
+                {
+                    continue;
+                }
+
+                let pc = section.address.wrapping_add(index as u64 * 4);
+                let high = (auipc & 0xfffff000) as i32 as i64;
+                let low = (load as i32 >> 20) as i64;
+
+                stubs.push((pc, pc.wrapping_add(high.wrapping_add(low) as u64)));
+            }
+        }
+        _ => {}
+    }
+
+    stubs
+}
+
+/// Instructions are little endian on every supported machine, even big endian AArch64 and BE8 ARM
+fn words(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks_exact(4)
+        .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+        .collect()
+}
+
+/// Decodes the rotated 8 bit immediate of an ARM data processing instruction
+fn arm_immediate(instruction: u32) -> u32 {
+    (instruction & 0xff).rotate_right(((instruction >> 8) & 0xf) * 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    fn sym(st_name: u32) -> Sym {
+        Sym {
+            st_name,
+            st_info: 0x12,
+            st_other: 0,
+            st_shndx: 0,
+            st_value: 0,
+            st_size: 0,
+        }
+    }
+
+    #[test]
+    fn analyze_x86_64_and_aarch64_stubs() {
+        let strtab = StrTab::parse(Cow::Borrowed(b"\0printf\0malloc\0"), 0).unwrap();
+        let symbols = vec![sym(0), sym(1), sym(8)];
+        let relocations = [
+            GotRelocation {
+                r_offset: 0x4018,
+                r_sym: 1,
+                r_type: x86_64::R_X86_64_JUMP_SLOT,
+            },
+            GotRelocation {
+                r_offset: 0x3ff0,
+                r_sym: 2,
+                r_type: x86_64::R_X86_64_GLOB_DAT,
+            },
+        ];
+
+        // `.plt.sec` entry at 0x1060: `endbr64; bnd jmp *0x2fae(%rip); nopl`
+        let mut plt_sec = vec![0xf3, 0x0f, 0x1e, 0xfa, 0xf2, 0xff, 0x25];
+        plt_sec.extend_from_slice(&(0x4018i32 - 0x106b).to_le_bytes());
+        plt_sec.extend_from_slice(&[0x0f, 0x1f, 0x44, 0x00, 0x00]);
+        // `.plt.got` entry at 0x1050: `jmp *0x2f9a(%rip); xchg %ax,%ax`
+        let mut plt_got = vec![0xff, 0x25];
+        plt_got.extend_from_slice(&(0x3ff0i32 - 0x1056).to_le_bytes());
+        plt_got.extend_from_slice(&[0x66, 0x90]);
+
+        let sections = [
+            PltSection {
+                name: ".plt.got",
+                address: 0x1050,
+                bytes: &plt_got,
+            },
+            PltSection {
+                name: ".plt.sec",
+                address: 0x1060,
+                bytes: &plt_sec,
+            },
+        ];
+        let analysis = PltAnalysis::analyze(&PltInput {
+            e_machine: EM_X86_64,
+            got_address: None,
+            sections: &sections,
+            relocations: &relocations,
+            symbols: &symbols,
+            strtab: &strtab,
+        })
+        .unwrap();
+
+        assert_eq!(analysis.entries.len(), 2);
+        assert_eq!(analysis.entries[0].plt_name(), "malloc@plt");
+        let printf = analysis.lookup(0x1068).unwrap();
+        assert_eq!((printf.address, printf.size), (0x1060, 16));
+        assert_eq!(printf.name, "printf");
+        assert_eq!(analysis.got_slot(0x3ff0).unwrap().name, "malloc");
+
+        // AArch64 entry at 0x10020: `adrp x16, 0x20000; ldr x17, [x16, #0x18]; add x16, x16, #0x18; br x17`
+        let mut plt = vec![0u8; 0x20];
+        for word in [0x90000090u32, 0xf9400e11, 0x91006210, 0xd61f0220] {
+            plt.extend_from_slice(&word.to_le_bytes());
+        }
+        let relocations = [GotRelocation {
+            r_offset: 0x20018,
+            r_sym: 1,
+            r_type: aarch64::R_AARCH64_JUMP_SLOT,
+        }];
+        let sections = [PltSection {
+            name: ".plt",
+            address: 0x10000,
+            bytes: &plt,
+        }];
+        let analysis = PltAnalysis::analyze(&PltInput {
+            e_machine: EM_AARCH64,
+            got_address: None,
+            sections: &sections,
+            relocations: &relocations,
+            symbols: &symbols,
+            strtab: &strtab,
+        })
+        .unwrap();
+
+        assert_eq!(analysis.entries.len(), 1);
+        assert_eq!(analysis.entries[0].address, 0x10020);
+        assert_eq!(analysis.entries[0].plt_name(), "printf@plt");
+    }
+}
@@ -0,0 +1,46 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// `riscv` build attribute tags, the names match what `readelf -A` prints
+// Source: https://github.com/riscv-non-isa/riscv-elf-psabi-doc/blob/master/riscv-elf.adoc#attributes
+#![allow(non_upper_case_globals)]
+
+use crate::stringable_consts_blocks::option_stringable_consts_block;
+
+option_stringable_consts_block! {
+    const stringable: u64 {
+        /// ULEB128, the stack alignment in bytes
+        pub Tag_RISCV_stack_align = 4;
+        /// NTBS, the ISA string the file was compiled for (e.g. `rv64i2p1_m2p0_a2p1_c2p0`)
+        pub Tag_RISCV_arch = 5;
+        /// ULEB128, `1` if the file may have misaligned memory accesses
+        pub Tag_RISCV_unaligned_access = 6;
+        /// ULEB128, major version of the privileged specification
+        pub Tag_RISCV_priv_spec = 8;
+        /// ULEB128, minor version of the privileged specification
+        pub Tag_RISCV_priv_spec_minor = 10;
+        /// ULEB128, revision of the privileged specification
+        pub Tag_RISCV_priv_spec_revision = 12;
+        /// ULEB128, the atomic ABI the file uses
+        pub Tag_RISCV_atomic_abi = 14;
+        /// ULEB128, how the `x3`/`gp` register is used
+        pub Tag_RISCV_x3_reg_usage = 16;
+    }
+
+    const ignore: u64 {}
+
+    pub fn attribute_tag_to_str(value: u64) -> Option<&'static str>;
+}
@@ -16,6 +16,9 @@
 
 use crate::stringable_consts_blocks::option_stringable_consts_block;
 
+mod attributes;
+pub use attributes::*;
+
 // e_flags
 pub const EF_RISCV_RVC: u32 = 0x0001;
 pub const EF_RISCV_FLOAT_ABI: u32 = 0x0006;
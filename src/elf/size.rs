@@ -0,0 +1,827 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Size attribution, "where do the bytes of this file go"
+//!
+//! Every byte of the file and every byte of the loaded image is attributed to exactly one owner of the chosen
+//! `SizeDimension`. Bytes the dimension can't explain fall back to the section they are in (`[section .text]`), then to
+//! the ELF headers, then to `[Unmapped]`, so the entries of a report always add up to the totals.
+
+use crate::elf::dwarf::DebugLine;
+use crate::elf::{
+    Header, PFlags, ProgramHeader, Reader, SHFlags, SectionHeader, StrTab, Sym, EM_ARM, ET_REL,
+    PT_LOAD, SHN_LORESERVE, SHN_UNDEF, SHT_DYNSYM, SHT_NOBITS, SHT_NULL, SHT_SYMTAB, STB_GLOBAL,
+    STT_FILE, STT_FUNC, STT_SECTION, STT_TLS,
+};
+use crate::{Details, Error};
+use scroll::Endian;
+use std::collections::{BTreeMap, HashMap};
+
+type Result<T> = std::result::Result<T, Error>;
+
+pub const UNMAPPED_NAME: &str = "[Unmapped]";
+pub const ELF_HEADER_NAME: &str = "[ELF Header]";
+pub const PROGRAM_HEADERS_NAME: &str = "[Program Headers]";
+pub const SECTION_HEADERS_NAME: &str = "[Section Headers]";
+
+/// What bytes are attributed to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SizeDimension {
+    /// Section names
+    Sections,
+    /// `PT_LOAD` segments, named like `LOAD #1 [R X]`
+    Segments,
+    /// Symbol names from `.symtab`, or `.dynsym` for stripped files
+    Symbols,
+    /// Compile units from the `.debug_line` sequences, named after their primary source file
+    ///
+    /// The line tables of relocatable objects aren't relocated, so compile units are only attributed in linked files.
+    CompileUnits,
+}
+
+/// The bytes attributed to one owner
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SizeEntry {
+    pub name: String,
+    /// Bytes of the loaded image, including `SHT_NOBITS`
+    pub vm_size: u64,
+    /// Bytes of the file
+    pub file_size: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SizeReport {
+    pub dimension: SizeDimension,
+    /// Sorted by the larger of `file_size` and `vm_size`, largest first
+    pub entries: Vec<SizeEntry>,
+    /// Size of every `PT_LOAD` segment together, or of every `SHF_ALLOC` section for files without segments
+    pub total_vm_size: u64,
+    pub total_file_size: u64,
+}
+
+impl SizeReport {
+    pub fn get(&self, name: &str) -> Option<&SizeEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+}
+
+/// The change of one owner between two reports
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SizeDelta {
+    pub name: String,
+    pub old_vm_size: u64,
+    pub new_vm_size: u64,
+    pub old_file_size: u64,
+    pub new_file_size: u64,
+}
+
+impl SizeDelta {
+    pub fn vm_delta(&self) -> i64 {
+        self.new_vm_size as i64 - self.old_vm_size as i64
+    }
+
+    pub fn file_delta(&self) -> i64 {
+        self.new_file_size as i64 - self.old_file_size as i64
+    }
+}
+
+/// Per owner differences between two builds
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SizeDiff {
+    /// Owners whose size changed, sorted by the larger absolute delta, largest first
+    pub deltas: Vec<SizeDelta>,
+    pub vm_delta: i64,
+    pub file_delta: i64,
+}
+
+impl SizeDiff {
+    /// Compares two reports of the same dimension, owners that only exist in one of them have a size of `0` in the
+    /// other
+    pub fn new(old: &SizeReport, new: &SizeReport) -> Result<SizeDiff> {
+        if old.dimension != new.dimension {
+            return Err(Error::InvalidArguments(
+                Details::new(format!(
+                    "Can't diff a `{:?}` size report with a `{:?}` size report",
+                    old.dimension, new.dimension
+                ))
+                .structure("elf::size::SizeReport")
+                .field("dimension"),
+            ));
+        }
+
+        let mut deltas: Vec<SizeDelta> = Vec::new();
+        let mut indices: HashMap<&str, usize> = HashMap::new();
+
+        for (entry, is_new) in old
+            .entries
+            .iter()
+            .map(|entry| (entry, false))
+            .chain(new.entries.iter().map(|entry| (entry, true)))
+        {
+            let index = *indices.entry(entry.name.as_str()).or_insert_with(|| {
+                deltas.push(SizeDelta {
+                    name: entry.name.clone(),
+                    old_vm_size: 0,
+                    new_vm_size: 0,
+                    old_file_size: 0,
+                    new_file_size: 0,
+                });
+                deltas.len() - 1
+            });
+            let delta = &mut deltas[index];
+
+            if is_new {
+                delta.new_vm_size += entry.vm_size;
+                delta.new_file_size += entry.file_size;
+            } else {
+                delta.old_vm_size += entry.vm_size;
+                delta.old_file_size += entry.file_size;
+            }
+        }
+
+        deltas.retain(|delta| delta.vm_delta() != 0 || delta.file_delta() != 0);
+        deltas.sort_by(|left, right| {
+            let magnitude =
+                |delta: &SizeDelta| delta.vm_delta().abs().max(delta.file_delta().abs());
+            magnitude(right)
+                .cmp(&magnitude(left))
+                .then_with(|| left.name.cmp(&right.name))
+        });
+
+        Ok(SizeDiff {
+            deltas,
+            vm_delta: new.total_vm_size as i64 - old.total_vm_size as i64,
+            file_delta: new.total_file_size as i64 - old.total_file_size as i64,
+        })
+    }
+}
+
+/// Everything `analyze_parsed` looks at, for callers that already parsed the file
+pub struct SizeInput<'a> {
+    pub header: &'a Header,
+    pub program_headers: &'a [ProgramHeader],
+    pub section_headers: &'a [SectionHeader],
+    pub shstrtab: &'a StrTab<'a>,
+    /// `.symtab` (or `.dynsym`) and its string table, only needed for `SizeDimension::Symbols`
+    pub symbols: Option<(&'a [Sym], &'a StrTab<'a>)>,
+    /// Only needed for `SizeDimension::CompileUnits`
+    pub debug_line: Option<&'a DebugLine>,
+    pub file_size: u64,
+}
+
+/// Reads what `dimension` needs and attributes the bytes of the file, see `analyze_parsed`
+///
+/// `file_size` is the size of the whole file, bytes after the last section (e.g. a signature) are `[Unmapped]`.
+pub fn analyze<'a, TReader: Reader<'a>>(
+    reader: &mut TReader,
+    endianness: Endian,
+    file_size: u64,
+    dimension: SizeDimension,
+) -> Result<SizeReport> {
+    let header = reader.read_header()?;
+    let program_headers =
+        reader.read_program_headers(header.e_phoff, header.e_phentsize, header.e_phnum)?;
+    let section_headers =
+        reader.read_section_headers(header.e_shoff, header.e_shentsize, header.e_shnum)?;
+    let shstrtab = match section_headers.get(header.e_shstrndx as usize) {
+        Some(section_header) if header.e_shstrndx != 0 => {
+            reader.read_str_table_section(section_header)?
+        }
+        _ => StrTab::parse(std::borrow::Cow::Borrowed(b"\0"), 0)?,
+    };
+
+    let symbols = if dimension == SizeDimension::Symbols {
+        let symbol_table = section_headers
+            .iter()
+            .find(|section_header| section_header.sh_type == SHT_SYMTAB)
+            .or_else(|| {
+                section_headers
+                    .iter()
+                    .find(|section_header| section_header.sh_type == SHT_DYNSYM)
+            });
+
+        match symbol_table {
+            Some(symtab_header) => match section_headers.get(symtab_header.sh_link as usize) {
+                Some(strtab_header) => Some((
+                    reader.read_sym_table(symtab_header.sh_offset, symtab_header.sh_size)?,
+                    reader.read_str_table_section(strtab_header)?,
+                )),
+                None => None,
+            },
+            None => None,
+        }
+    } else {
+        None
+    };
+
+    let debug_line = if dimension == SizeDimension::CompileUnits {
+        DebugLine::read(reader, &section_headers, &shstrtab, endianness)?
+    } else {
+        None
+    };
+
+    analyze_parsed(
+        &SizeInput {
+            header: &header,
+            program_headers: &program_headers,
+            section_headers: &section_headers,
+            shstrtab: &shstrtab,
+            symbols: symbols
+                .as_ref()
+                .map(|(symbols, strtab)| (symbols.as_slice(), strtab)),
+            debug_line: debug_line.as_ref(),
+            file_size,
+        },
+        dimension,
+    )
+}
+
+/// Attributes every byte of the file and of the loaded image to one owner of `dimension`
+///
+/// Overlapping owners (e.g. symbol aliases) are resolved in favour of the first one, symbols are ordered by address
+/// with global symbols first.
+pub fn analyze_parsed(input: &SizeInput, dimension: SizeDimension) -> Result<SizeReport> {
+    let layout = ImageLayout::new(input);
+    let mut attribution = Attribution::new(&layout.vm_domain, input.file_size);
+
+    let mut section_names: Vec<String> = Vec::with_capacity(input.section_headers.len());
+    for section_header in input.section_headers {
+        section_names.push(
+            input
+                .shstrtab
+                .get_at_offset(section_header.sh_name)?
+                .into_owned(),
+        );
+    }
+
+    match dimension {
+        SizeDimension::Sections => {}
+        SizeDimension::Segments => {
+            let mut load_index = 0;
+
+            for program_header in input.program_headers {
+                if program_header.p_type != PT_LOAD {
+                    continue;
+                }
+
+                let name = format!(
+                    "LOAD #{} [{}{}{}]",
+                    load_index,
+                    if program_header.p_flags.contains(PFlags::READ) {
+                        'R'
+                    } else {
+                        ' '
+                    },
+                    if program_header.p_flags.contains(PFlags::WRITE) {
+                        'W'
+                    } else {
+                        ' '
+                    },
+                    if program_header.p_flags.contains(PFlags::EXECUTE) {
+                        'X'
+                    } else {
+                        ' '
+                    },
+                );
+                attribution.claim_vm(&name, program_header.p_vaddr, program_header.p_memsz);
+                attribution.claim_file(&name, program_header.p_offset, program_header.p_filesz);
+                load_index += 1;
+            }
+        }
+        SizeDimension::Symbols => {
+            if let Some((symbols, strtab)) = input.symbols {
+                for (address, size, name) in layout.symbol_ranges(symbols, strtab)? {
+                    attribution.claim_mapped(&layout, &name, address, size);
+                }
+            }
+        }
+        SizeDimension::CompileUnits => {
+            if let (Some(debug_line), false) = (input.debug_line, input.header.e_type == ET_REL) {
+                for program in &debug_line.programs {
+                    let name = program
+                        .header
+                        .get_file_path(if program.header.version >= 5 { 0 } else { 1 })
+                        .unwrap_or_else(|| format!("[unit 0x{:x}]", program.header.offset));
+                    let mut sequence_start: Option<u64> = None;
+
+                    for row in &program.rows {
+                        match (sequence_start, row.end_sequence) {
+                            (None, false) => sequence_start = Some(row.address),
+                            (Some(start), true) => {
+                                attribution.claim_mapped(
+                                    &layout,
+                                    &name,
+                                    start,
+                                    row.address.saturating_sub(start),
+                                );
+                                sequence_start = None;
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Fallbacks, sections first then the headers and whatever is left
+    for (index, section_header) in input.section_headers.iter().enumerate() {
+        if section_header.sh_type == SHT_NULL {
+            continue;
+        }
+
+        let name = if dimension == SizeDimension::Sections {
+            section_names[index].clone()
+        } else {
+            format!("[section {}]", section_names[index])
+        };
+
+        if let Some(base) = layout.section_bases[index] {
+            attribution.claim_vm(&name, base, section_header.sh_size);
+        }
+        if section_header.sh_type != SHT_NOBITS {
+            attribution.claim_file(&name, section_header.sh_offset, section_header.sh_size);
+        }
+    }
+
+    attribution.claim_file(ELF_HEADER_NAME, 0, u64::from(input.header.e_ehsize));
+    attribution.claim_file(
+        PROGRAM_HEADERS_NAME,
+        input.header.e_phoff,
+        u64::from(input.header.e_phentsize) * input.program_headers.len() as u64,
+    );
+    attribution.claim_file(
+        SECTION_HEADERS_NAME,
+        input.header.e_shoff,
+        u64::from(input.header.e_shentsize) * input.section_headers.len() as u64,
+    );
+    for &(start, end) in &layout.vm_domain {
+        attribution.claim_vm(UNMAPPED_NAME, start, end - start);
+    }
+    attribution.claim_file(UNMAPPED_NAME, 0, input.file_size);
+
+    Ok(attribution.into_report(dimension, &layout.vm_domain, input.file_size))
+}
+
+/// Where sections are in memory
+struct ImageLayout<'a> {
+    section_headers: &'a [SectionHeader],
+    /// Address of every `SHF_ALLOC` section, relocatable objects get made up addresses so their sections don't overlap
+    section_bases: Vec<Option<u64>>,
+    is_relocatable: bool,
+    is_arm: bool,
+    /// Sorted and merged address ranges of the loaded image
+    vm_domain: Vec<(u64, u64)>,
+}
+
+impl<'a> ImageLayout<'a> {
+    fn new(input: &SizeInput<'a>) -> Self {
+        let is_relocatable = input.header.e_type == ET_REL;
+        let mut next_base: u64 = 0;
+
+        let section_bases: Vec<Option<u64>> = input
+            .section_headers
+            .iter()
+            .map(|section_header| {
+                if !section_header.sh_flags.contains(SHFlags::ALLOC) {
+                    None
+                } else if is_relocatable {
+                    let base = next_base;
+                    next_base = next_base.saturating_add(section_header.sh_size);
+                    Some(base)
+                } else {
+                    Some(section_header.sh_addr)
+                }
+            })
+            .collect();
+
+        let mut ranges: Vec<(u64, u64)> = if input
+            .program_headers
+            .iter()
+            .any(|program_header| program_header.p_type == PT_LOAD)
+        {
+            input
+                .program_headers
+                .iter()
+                .filter(|program_header| program_header.p_type == PT_LOAD)
+                .map(|program_header| {
+                    (
+                        program_header.p_vaddr,
+                        program_header
+                            .p_vaddr
+                            .saturating_add(program_header.p_memsz),
+                    )
+                })
+                .collect()
+        } else {
+            input
+                .section_headers
+                .iter()
+                .zip(&section_bases)
+                .filter_map(|(section_header, base)| {
+                    base.map(|base| (base, base.saturating_add(section_header.sh_size)))
+                })
+                .collect()
+        };
+
+        ranges.sort_unstable();
+        let mut vm_domain: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match vm_domain.last_mut() {
+                Some((_, last_end)) if start <= *last_end => *last_end = (*last_end).max(end),
+                _ if start < end => vm_domain.push((start, end)),
+                _ => {}
+            }
+        }
+
+        ImageLayout {
+            section_headers: input.section_headers,
+            section_bases,
+            is_relocatable,
+            is_arm: input.header.e_machine == EM_ARM,
+            vm_domain,
+        }
+    }
+
+    /// Address ranges of the sized symbols defined in a section, global symbols first among symbols at one address
+    fn symbol_ranges(&self, symbols: &[Sym], strtab: &StrTab) -> Result<Vec<(u64, u64, String)>> {
+        let mut ranges: Vec<(u64, bool, u64, String)> = Vec::new();
+
+        for symbol in symbols {
+            let st_type = symbol.st_type();
+            let shndx = u32::from(symbol.st_shndx);
+
+            if symbol.st_size == 0
+                || shndx == SHN_UNDEF
+                || shndx >= SHN_LORESERVE
+                || matches!(st_type, STT_SECTION | STT_FILE | STT_TLS)
+            {
+                continue;
+            }
+
+            let Some(Some(base)) = self.section_bases.get(shndx as usize) else {
+                continue;
+            };
+
+            let mut address = if self.is_relocatable {
+                base.saturating_add(symbol.st_value)
+            } else {
+                symbol.st_value
+            };
+            if self.is_arm && st_type == STT_FUNC {
+                // The Thumb bit
+                address &= !1;
+            }
+
+            ranges.push((
+                address,
+                symbol.st_bind() != STB_GLOBAL,
+                symbol.st_size,
+                strtab.get_at_offset(symbol.st_name)?.into_owned(),
+            ));
+        }
+
+        ranges.sort_by_key(|(address, is_not_global, _, _)| (*address, *is_not_global));
+
+        Ok(ranges
+            .into_iter()
+            .map(|(address, _, size, name)| (address, size, name))
+            .collect())
+    }
+
+    /// File ranges backing `address..address + size`, through the sections that have contents in the file
+    fn file_ranges(&self, address: u64, size: u64) -> Vec<(u64, u64)> {
+        let end = address.saturating_add(size);
+
+        self.section_headers
+            .iter()
+            .zip(&self.section_bases)
+            .filter(|(section_header, _)| section_header.sh_type != SHT_NOBITS)
+            .filter_map(|(section_header, base)| {
+                let base = (*base)?;
+                let start = address.max(base);
+                let stop = end.min(base.saturating_add(section_header.sh_size));
+
+                (start < stop).then(|| {
+                    (
+                        section_header.sh_offset.saturating_add(start - base),
+                        stop - start,
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+/// Disjoint claimed ranges, first come first served
+#[derive(Default)]
+struct Coverage {
+    /// Start to end of merged ranges
+    ranges: BTreeMap<u64, u64>,
+}
+
+impl Coverage {
+    /// Claims `start..end` and returns how many bytes of it weren't claimed yet
+    fn claim(&mut self, start: u64, end: u64) -> u64 {
+        if start >= end {
+            return 0;
+        }
+
+        let touching: Vec<(u64, u64)> = self
+            .ranges
+            .range(..=end)
+            .rev()
+            .take_while(|(_, &range_end)| range_end >= start)
+            .map(|(&range_start, &range_end)| (range_start, range_end))
+            .collect();
+
+        let mut already_claimed = 0;
+        let (mut merged_start, mut merged_end) = (start, end);
+
+        for (range_start, range_end) in touching {
+            already_claimed += range_end.min(end).saturating_sub(range_start.max(start));
+            merged_start = merged_start.min(range_start);
+            merged_end = merged_end.max(range_end);
+            self.ranges.remove(&range_start);
+        }
+
+        self.ranges.insert(merged_start, merged_end);
+
+        end - start - already_claimed
+    }
+}
+
+struct Attribution<'a> {
+    vm_domain: &'a [(u64, u64)],
+    file_size: u64,
+    vm: Coverage,
+    file: Coverage,
+    /// Name to `(vm_size, file_size)`, in first claim order
+    sizes: Vec<(String, u64, u64)>,
+    indices: HashMap<String, usize>,
+}
+
+impl<'a> Attribution<'a> {
+    fn new(vm_domain: &'a [(u64, u64)], file_size: u64) -> Self {
+        Attribution {
+            vm_domain,
+            file_size,
+            vm: Coverage::default(),
+            file: Coverage::default(),
+            sizes: Vec::new(),
+            indices: HashMap::new(),
+        }
+    }
+
+    fn entry(&mut self, name: &str) -> &mut (String, u64, u64) {
+        let index = match self.indices.get(name) {
+            Some(&index) => index,
+            None => {
+                self.sizes.push((name.to_string(), 0, 0));
+                self.indices.insert(name.to_string(), self.sizes.len() - 1);
+                self.sizes.len() - 1
+            }
+        };
+
+        &mut self.sizes[index]
+    }
+
+    /// Claims the parts of `address..address + size` within the loaded image
+    fn claim_vm(&mut self, name: &str, address: u64, size: u64) {
+        let end = address.saturating_add(size);
+        let mut claimed = 0;
+
+        for &(domain_start, domain_end) in self.vm_domain {
+            claimed += self
+                .vm
+                .claim(address.max(domain_start), end.min(domain_end));
+        }
+
+        if claimed != 0 {
+            self.entry(name).1 += claimed;
+        }
+    }
+
+    /// Claims the parts of `offset..offset + size` within the file
+    fn claim_file(&mut self, name: &str, offset: u64, size: u64) {
+        let end = offset.saturating_add(size).min(self.file_size);
+        let claimed = self.file.claim(offset, end);
+
+        if claimed != 0 {
+            self.entry(name).2 += claimed;
+        }
+    }
+
+    /// Claims an address range and the file bytes backing it
+    fn claim_mapped(&mut self, layout: &ImageLayout, name: &str, address: u64, size: u64) {
+        self.claim_vm(name, address, size);
+
+        for (offset, file_size) in layout.file_ranges(address, size) {
+            self.claim_file(name, offset, file_size);
+        }
+    }
+
+    fn into_report(
+        self,
+        dimension: SizeDimension,
+        vm_domain: &[(u64, u64)],
+        file_size: u64,
+    ) -> SizeReport {
+        let mut entries: Vec<SizeEntry> = self
+            .sizes
+            .into_iter()
+            .map(|(name, vm_size, file_size)| SizeEntry {
+                name,
+                vm_size,
+                file_size,
+            })
+            .collect();
+
+        entries.sort_by(|left, right| {
+            right
+                .file_size
+                .max(right.vm_size)
+                .cmp(&left.file_size.max(left.vm_size))
+                .then_with(|| left.name.cmp(&right.name))
+        });
+
+        SizeReport {
+            dimension,
+            entries,
+            total_vm_size: vm_domain.iter().map(|(start, end)| end - start).sum(),
+            total_file_size: file_size,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf::{SHT_PROGBITS, STB_LOCAL, STT_OBJECT};
+    use std::borrow::Cow;
+
+    fn section(
+        sh_name: u32,
+        sh_type: u32,
+        sh_flags: SHFlags,
+        sh_addr: u64,
+        sh_offset: u64,
+        sh_size: u64,
+    ) -> SectionHeader {
+        SectionHeader {
+            sh_name,
+            sh_type,
+            sh_flags,
+            sh_addr,
+            sh_offset,
+            sh_size,
+            sh_link: 0,
+            sh_info: 0,
+            sh_addralign: 1,
+            sh_entsize: 0,
+        }
+    }
+
+    fn sym(st_name: u32, st_info: u8, st_shndx: u16, st_value: u64, st_size: u64) -> Sym {
+        Sym {
+            st_name,
+            st_info,
+            st_other: 0,
+            st_shndx,
+            st_value,
+            st_size,
+        }
+    }
+
+    fn report(text_size: u64, dimension: SizeDimension) -> SizeReport {
+        let header = Header {
+            e_ident: [0x7f, b'E', b'L', b'F', 2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            e_type: crate::elf::ET_DYN,
+            e_machine: crate::elf::EM_X86_64,
+            e_version: 1,
+            e_entry: 0,
+            e_phoff: 0x40,
+            e_shoff: 0x200,
+            e_flags: 0,
+            e_ehsize: 0x40,
+            e_phentsize: 0x38,
+            e_phnum: 1,
+            e_shentsize: 0x40,
+            e_shnum: 5,
+            e_shstrndx: 4,
+        };
+        let program_headers = [ProgramHeader {
+            p_type: PT_LOAD,
+            p_flags: PFlags::READ | PFlags::EXECUTE,
+            p_offset: 0,
+            p_vaddr: 0,
+            p_paddr: 0,
+            p_filesz: 0x180,
+            p_memsz: 0x200,
+            p_align: 0x1000,
+        }];
+        let alloc = SHFlags::ALLOC;
+        let section_headers = [
+            section(0, SHT_NULL, SHFlags::empty(), 0, 0, 0),
+            section(1, SHT_PROGBITS, alloc, 0x100, 0x100, text_size),
+            section(7, SHT_PROGBITS, alloc, 0x140, 0x140, 0x40),
+            section(13, SHT_NOBITS, alloc, 0x180, 0x180, 0x80),
+            section(18, SHT_PROGBITS, SHFlags::empty(), 0, 0x180, 0x20),
+        ];
+        let shstrtab =
+            StrTab::parse(Cow::Borrowed(b"\0.text\0.data\0.bss\0.shstrtab\0"), 0).unwrap();
+        let strtab = StrTab::parse(Cow::Borrowed(b"\0main\0table\0alias\0"), 0).unwrap();
+        let symbols = [
+            sym(0, 0, 0, 0, 0),
+            sym(12, (STB_LOCAL << 4) | STT_FUNC, 1, 0x100, 0x10),
+            sym(1, (STB_GLOBAL << 4) | STT_FUNC, 1, 0x100, 0x20),
+            sym(6, (STB_GLOBAL << 4) | STT_OBJECT, 2, 0x140, 0x30),
+        ];
+
+        analyze_parsed(
+            &SizeInput {
+                header: &header,
+                program_headers: &program_headers,
+                section_headers: &section_headers,
+                shstrtab: &shstrtab,
+                symbols: Some((&symbols, &strtab)),
+                debug_line: None,
+                file_size: 0x340,
+            },
+            dimension,
+        )
+        .unwrap()
+    }
+
+    fn sizes(report: &SizeReport, name: &str) -> (u64, u64) {
+        report
+            .get(name)
+            .map_or((0, 0), |entry| (entry.vm_size, entry.file_size))
+    }
+
+    #[test]
+    fn attribute_and_diff_sizes() {
+        let sections = report(0x40, SizeDimension::Sections);
+        assert_eq!(sizes(&sections, ".text"), (0x40, 0x40));
+        assert_eq!(sizes(&sections, ".bss"), (0x80, 0));
+        assert_eq!(sizes(&sections, ".shstrtab"), (0, 0x20));
+        assert_eq!(sizes(&sections, SECTION_HEADERS_NAME), (0, 0x140));
+        // The headers are part of `PT_LOAD` but not of any section
+        assert_eq!(sizes(&sections, UNMAPPED_NAME), (0x100, 0xe8));
+
+        let symbols = report(0x40, SizeDimension::Symbols);
+        // `main` is global so it wins over its local alias
+        assert_eq!(sizes(&symbols, "main"), (0x20, 0x20));
+        assert_eq!(sizes(&symbols, "alias"), (0, 0));
+        assert_eq!(sizes(&symbols, "[section .text]"), (0x20, 0x20));
+        assert_eq!(sizes(&symbols, "table"), (0x30, 0x30));
+
+        for report in [&sections, &symbols, &report(0x40, SizeDimension::Segments)] {
+            assert_eq!(
+                report
+                    .entries
+                    .iter()
+                    .map(|entry| entry.file_size)
+                    .sum::<u64>(),
+                report.total_file_size
+            );
+            assert_eq!(
+                report
+                    .entries
+                    .iter()
+                    .map(|entry| entry.vm_size)
+                    .sum::<u64>(),
+                report.total_vm_size
+            );
+        }
+
+        let diff = SizeDiff::new(
+            &report(0x30, SizeDimension::Symbols),
+            &report(0x40, SizeDimension::Symbols),
+        )
+        .unwrap();
+        assert_eq!(
+            diff.deltas
+                .iter()
+                .map(|delta| (delta.name.as_str(), delta.vm_delta(), delta.file_delta()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("[Unmapped]", -0x10, -0x10),
+                ("[section .text]", 0x10, 0x10)
+            ]
+        );
+        assert_eq!((diff.vm_delta, diff.file_delta), (0, 0));
+    }
+}
@@ -14,7 +14,7 @@
  * limitations under the License.
  */
 
-use crate::Error;
+use crate::{Details, Error};
 use std::borrow::Cow;
 use std::collections::hash_map;
 use std::collections::HashMap;
@@ -26,6 +26,8 @@ pub struct StrTab<'a> {
     bytes: Cow<'a, [u8]>,
     delimeter: u8,
     strings: HashMap<u32, Cow<'a, str>>,
+    /// Reverse index of `strings`, the lowest offset is kept when a string occurs more than once
+    offsets: HashMap<String, u32>,
 }
 
 impl<'a> StrTab<'a> {
@@ -34,6 +36,7 @@ impl<'a> StrTab<'a> {
             bytes,
             delimeter,
             strings: HashMap::new(),
+            offsets: HashMap::new(),
         };
 
         let mut current_offset: u32 = 0;
@@ -46,14 +49,23 @@ impl<'a> StrTab<'a> {
 
                 if str_bytes.len() != 0 {
                     match str::from_utf8(str_bytes) {
-                        Ok(str_result) => result
-                            .strings
-                            .insert(current_offset, Cow::Owned(str_result.to_owned())),
+                        Ok(str_result) => {
+                            result
+                                .offsets
+                                .entry(str_result.to_owned())
+                                .or_insert(current_offset);
+                            result
+                                .strings
+                                .insert(current_offset, Cow::Owned(str_result.to_owned()))
+                        }
                         Err(utf8_error) => {
-                            return Err(Error::Malformed(format!(
-                                "Invalid string found in strtab at offset {}, {}",
-                                current_offset, utf8_error
-                            )))
+                            return Err(Error::Malformed(
+                                Details::new(format!(
+                                    "Invalid string found in strtab at offset {}, {}",
+                                    current_offset, utf8_error
+                                ))
+                                .structure("elf::StrTab"),
+                            ))
                         }
                     };
                 }
@@ -73,11 +85,14 @@ impl<'a> StrTab<'a> {
         'a: 'b,
     {
         if offset as usize >= self.bytes.len() {
-            return Err(Error::Malformed(format!(
-                "Offset `{}` is out of range for strtab length `{}`",
-                offset,
-                self.bytes.len()
-            )));
+            return Err(Error::Malformed(
+                Details::new(format!(
+                    "Offset `{}` is out of range for strtab length `{}`",
+                    offset,
+                    self.bytes.len()
+                ))
+                .structure("elf::StrTab"),
+            ));
         }
 
         let current_offset: usize = offset as usize;
@@ -96,10 +111,13 @@ impl<'a> StrTab<'a> {
         // NOTE: Zero length strings are allowed. The `Null` header has an empty string for its name.
         match str::from_utf8(str_bytes) {
             Ok(result) => Ok(Cow::Borrowed(result)),
-            Err(utf8_error) => Err(Error::Malformed(format!(
-                "Invalid string found in strtab at offset {}, {}",
-                current_offset, utf8_error
-            ))),
+            Err(utf8_error) => Err(Error::Malformed(
+                Details::new(format!(
+                    "Invalid string found in strtab at offset {}, {}",
+                    current_offset, utf8_error
+                ))
+                .structure("elf::StrTab"),
+            )),
         }
     }
 
@@ -119,13 +137,165 @@ impl<'a> StrTab<'a> {
         if self.strings.contains_key(&offset) {
             Ok(self.strings.get(&offset).unwrap())
         } else {
-            let parsed_result = Cow::Owned(self.parse_at_offset(offset)?.into_owned());
-            self.strings.insert(offset, parsed_result);
+            let parsed_result = self.parse_at_offset(offset)?.into_owned();
+            let lowest_offset = self.offsets.entry(parsed_result.clone()).or_insert(offset);
+            *lowest_offset = (*lowest_offset).min(offset);
+            self.strings.insert(offset, Cow::Owned(parsed_result));
             Ok(self.strings.get(&offset).unwrap())
         }
     }
 }
 
+// Reverse lookups
+impl<'a> StrTab<'a> {
+    /// Gets the offset of `value` within the table
+    ///
+    /// Strings which were merged into the tail of another string (e.g. `bar` stored within `foobar`) are still found, they
+    /// just aren't in the reverse index so they require a scan of the table bytes.
+    pub fn get_offset(&self, value: &str) -> Option<u32> {
+        if let Some(offset) = self.offsets.get(value) {
+            return Some(*offset);
+        }
+
+        let needle = value.as_bytes();
+
+        (0..self.bytes.len())
+            .filter(|start| start + needle.len() < self.bytes.len())
+            .find(|start| {
+                self.bytes[start + needle.len()] == self.delimeter
+                    && self.bytes[*start..start + needle.len()] == *needle
+            })
+            .and_then(|start| u32::try_from(start).ok())
+    }
+
+    pub fn contains(&self, value: &str) -> bool {
+        self.get_offset(value).is_some()
+    }
+
+    pub fn delimeter(&self) -> u8 {
+        self.delimeter
+    }
+
+    /// The raw bytes of the table, this is what gets written to the file
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// Builds a string table from a set of strings, merging any string that is the suffix of another string into the tail of
+/// that string. This is the same optimization `ld` does for `SHF_MERGE | SHF_STRINGS` sections.
+///
+/// Offsets are only known once the table has been built, use `StrTab::get_offset` on the result of `build` to get them.
+pub struct StrTabBuilder {
+    delimeter: u8,
+    strings: Vec<String>,
+    indices: HashMap<String, usize>,
+}
+
+impl StrTabBuilder {
+    pub fn new(delimeter: u8) -> Self {
+        Self {
+            delimeter,
+            strings: Vec::new(),
+            indices: HashMap::new(),
+        }
+    }
+
+    /// Adds `value` to the table, adding the same string more than once is a no-op
+    pub fn insert(&mut self, value: &str) -> Result<()> {
+        if value.as_bytes().contains(&self.delimeter) {
+            return Err(Error::InvalidArguments(
+                Details::new(format!(
+                    "String `{}` contains the strtab delimeter `0x{:x}`",
+                    value.escape_debug(),
+                    self.delimeter
+                ))
+                .structure("elf::StrTabBuilder"),
+            ));
+        }
+
+        if !self.indices.contains_key(value) {
+            self.indices.insert(value.to_owned(), self.strings.len());
+            self.strings.push(value.to_owned());
+        }
+
+        Ok(())
+    }
+
+    pub fn contains(&self, value: &str) -> bool {
+        self.indices.contains_key(value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Lays out the table and returns it as a `StrTab`
+    ///
+    /// The table always starts with a single delimeter so that offset `0` is the empty string, as required by ELF.
+    pub fn build(self) -> Result<StrTab<'static>> {
+        // Sorting by the reversed bytes puts every string directly after the longest string it is a suffix of
+        let mut sorted: Vec<&String> = self
+            .strings
+            .iter()
+            .filter(|value| !value.is_empty())
+            .collect();
+        sorted.sort_unstable_by(|lhs, rhs| lhs.bytes().rev().cmp(rhs.bytes().rev()).reverse());
+
+        let mut bytes: Vec<u8> = vec![self.delimeter];
+        let mut strings: HashMap<u32, Cow<'static, str>> = HashMap::new();
+        let mut offsets: HashMap<String, u32> = HashMap::new();
+        let mut previous: Option<(&String, usize)> = None;
+
+        offsets.insert(String::new(), 0);
+
+        for value in sorted {
+            let (offset, merged) = match previous {
+                Some((previous_value, previous_offset))
+                    if previous_value.ends_with(value.as_str()) =>
+                {
+                    (previous_offset + previous_value.len() - value.len(), true)
+                }
+                _ => {
+                    let offset = bytes.len();
+                    bytes.extend_from_slice(value.as_bytes());
+                    bytes.push(self.delimeter);
+                    previous = Some((value, offset));
+                    (offset, false)
+                }
+            };
+
+            let offset = u32::try_from(offset).map_err(|_| {
+                Error::TooManyArrayItems(
+                    Details::new(format!(
+                        "String table is larger than the max strtab size of `{}` bytes",
+                        u32::MAX
+                    ))
+                    .structure("elf::StrTabBuilder"),
+                )
+            })?;
+
+            // Only strings that start right after a delimeter are cached, same as `parse`
+            if !merged {
+                strings.insert(offset, Cow::Owned(value.clone()));
+            }
+
+            offsets.insert(value.clone(), offset);
+        }
+
+        Ok(StrTab {
+            bytes: Cow::Owned(bytes),
+            delimeter: self.delimeter,
+            strings,
+            offsets,
+        })
+    }
+}
+
 // Iterator stuff
 impl<'a> StrTab<'a> {
     pub fn iter(&self) -> hash_map::Iter<u32, Cow<'a, str>> {
@@ -163,3 +333,41 @@ impl<'a> IntoIterator for &'a mut StrTab<'a> {
         self.iter_mut()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_merges_suffixes() {
+        let mut builder = StrTabBuilder::new(0);
+        builder.insert("bar").unwrap();
+        builder.insert("foobar").unwrap();
+        builder.insert("obar").unwrap();
+        builder.insert("baz").unwrap();
+        builder.insert("").unwrap();
+
+        let strtab = builder.build().unwrap();
+
+        assert_eq!(
+            strtab.as_bytes().len(),
+            1 + "foobar\0".len() + "baz\0".len()
+        );
+        assert_eq!(strtab.get_offset(""), Some(0));
+
+        for value in ["bar", "foobar", "obar", "baz"] {
+            let offset = strtab.get_offset(value).unwrap();
+            assert_eq!(strtab.get_at_offset(offset).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn reverse_lookup_of_parsed_table() {
+        let strtab = StrTab::parse(Cow::Borrowed(b"\0.text\0.rela.text\0"), 0).unwrap();
+
+        assert_eq!(strtab.get_offset(".text"), Some(1));
+        assert_eq!(strtab.get_offset(".rela.text"), Some(7));
+        assert_eq!(strtab.get_offset("text"), Some(2));
+        assert_eq!(strtab.get_offset("data"), None);
+    }
+}
@@ -0,0 +1,249 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::elf::{ElfClass, StrTab};
+use crate::stringable_consts_blocks::option_stringable_consts_block;
+use crate::{Details, Error};
+use bitflags::bitflags;
+use scroll::{Endian, Pread};
+
+type Result<T> = std::result::Result<T, Error>;
+
+// Capability tags
+option_stringable_consts_block! {
+    const stringable: u64 {
+        /// Ends a capability group
+        pub CA_SUNW_NULL = 0;
+        /// First word of the machine specific hardware capabilities, e.g. `AV_386_*`
+        pub CA_SUNW_HW_1 = 1;
+        /// Software capabilities (`SF1_SUNW_*`)
+        pub CA_SUNW_SF_1 = 2;
+        /// Second word of the hardware capabilities
+        pub CA_SUNW_HW_2 = 3;
+        /// Platform name, offset into the string table
+        pub CA_SUNW_PLAT = 4;
+        /// Machine name, offset into the string table
+        pub CA_SUNW_MACH = 5;
+        /// Name of a symbol capability group, offset into the string table
+        pub CA_SUNW_ID = 6;
+        /// Third word of the hardware capabilities
+        pub CA_SUNW_HW_3 = 7;
+    }
+
+    const ignore: u64 {}
+
+    pub fn ca_to_str(value: u64) -> Option<&'static str>;
+}
+
+bitflags! {
+    /// `CA_SUNW_SF_1` values
+    #[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct SoftwareCapabilities: u64 {
+        /// The frame pointer usage is known
+        const SF1_SUNW_FPKNWN = 0x001;
+        /// The frame pointer is used
+        const SF1_SUNW_FPUSED = 0x002;
+        /// Requires a 32-bit address space
+        const SF1_SUNW_ADDR32 = 0x004;
+    }
+}
+
+/// An `Elf32_Cap` or `Elf64_Cap`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cap {
+    /// `CA_SUNW_*`
+    pub c_tag: u64,
+    /// A bitmask or a string table offset depending on `c_tag`
+    pub c_val: u64,
+}
+
+/// Capabilities up to a `CA_SUNW_NULL`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CapabilityGroup {
+    /// Index of the first `Cap` of the group within the section, `SHT_SUNW_CAPINFO` refers to groups by it
+    pub index: usize,
+    pub entries: Vec<Cap>,
+    pub hw_1: Option<u64>,
+    pub hw_2: Option<u64>,
+    pub hw_3: Option<u64>,
+    pub sf_1: Option<SoftwareCapabilities>,
+    /// Every `CA_SUNW_PLAT`, the object can run on any of them
+    pub platforms: Vec<String>,
+    /// Every `CA_SUNW_MACH`, the object can run on any of them
+    pub machines: Vec<String>,
+    /// `CA_SUNW_ID`, only used by symbol capability groups
+    pub id: Option<String>,
+}
+
+/// The capability groups of a `SHT_SUNW_CAP` section
+///
+/// The first group holds the object capabilities unless the section starts with `CA_SUNW_NULL`, the groups after it
+/// are symbol capabilities.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    pub groups: Vec<CapabilityGroup>,
+}
+
+impl Capabilities {
+    /// Parses a `SHT_SUNW_CAP` section, `strtab` is the section's `sh_link` and is needed for the string capabilities
+    pub fn parse(
+        bytes: &[u8],
+        endianness: Endian,
+        class: ElfClass,
+        strtab: Option<&StrTab>,
+    ) -> Result<Capabilities> {
+        let entry_size = match class {
+            ElfClass::Elf32 => 8,
+            ElfClass::Elf64 => 16,
+        };
+
+        let mut groups: Vec<CapabilityGroup> = Vec::new();
+        let mut group = CapabilityGroup::default();
+
+        for (index, entry) in bytes.chunks_exact(entry_size).enumerate() {
+            let cap = match class {
+                ElfClass::Elf32 => Cap {
+                    c_tag: u64::from(entry.pread_with::<u32>(0, endianness)?),
+                    c_val: u64::from(entry.pread_with::<u32>(4, endianness)?),
+                },
+                ElfClass::Elf64 => Cap {
+                    c_tag: entry.pread_with(0, endianness)?,
+                    c_val: entry.pread_with(8, endianness)?,
+                },
+            };
+
+            if cap.c_tag == CA_SUNW_NULL {
+                if !group.entries.is_empty() {
+                    groups.push(std::mem::take(&mut group));
+                }
+                group.index = index + 1;
+                continue;
+            }
+
+            match cap.c_tag {
+                CA_SUNW_HW_1 => group.hw_1 = Some(cap.c_val),
+                CA_SUNW_HW_2 => group.hw_2 = Some(cap.c_val),
+                CA_SUNW_HW_3 => group.hw_3 = Some(cap.c_val),
+                CA_SUNW_SF_1 => {
+                    group.sf_1 = Some(SoftwareCapabilities::from_bits_retain(cap.c_val))
+                }
+                CA_SUNW_PLAT => group.platforms.push(cap_string(strtab, &cap, index)?),
+                CA_SUNW_MACH => group.machines.push(cap_string(strtab, &cap, index)?),
+                CA_SUNW_ID => group.id = Some(cap_string(strtab, &cap, index)?),
+                _ => {}
+            }
+
+            group.entries.push(cap);
+        }
+
+        if !group.entries.is_empty() {
+            groups.push(group);
+        }
+
+        Ok(Capabilities { groups })
+    }
+
+    /// The capabilities the whole object requires
+    pub fn object_capabilities(&self) -> Option<&CapabilityGroup> {
+        self.groups.first().filter(|group| group.index == 0)
+    }
+
+    /// The groups of symbols with capability specific implementations
+    pub fn symbol_capabilities(&self) -> &[CapabilityGroup] {
+        match self.object_capabilities() {
+            Some(_) => &self.groups[1..],
+            None => &self.groups,
+        }
+    }
+
+    /// Returns the group starting at the `Cap` index `index`
+    pub fn group_at(&self, index: usize) -> Option<&CapabilityGroup> {
+        self.groups.iter().find(|group| group.index == index)
+    }
+}
+
+fn cap_string(strtab: Option<&StrTab>, cap: &Cap, index: usize) -> Result<String> {
+    let strtab = strtab.ok_or_else(|| {
+        Error::InvalidArguments(
+            Details::new(format!(
+                "Capability `{}` is a string but no string table was given",
+                ca_to_str(cap.c_tag).unwrap_or("CA_SUNW_UNKNOWN")
+            ))
+            .structure("elf::sunw::Cap")
+            .field("c_val"),
+        )
+    })?;
+    let offset = u32::try_from(cap.c_val).map_err(|_| {
+        Error::Malformed(
+            Details::new(format!(
+                "Capability string offset `{}` is out of range",
+                cap.c_val
+            ))
+            .structure("elf::sunw::Cap")
+            .field("c_val")
+            .offset(index as u64),
+        )
+    })?;
+
+    Ok(strtab.get_at_offset(offset)?.into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    #[test]
+    fn parse_capability_groups() {
+        let strtab = StrTab::parse(Cow::Borrowed(b"\0i86pc\0amd64\0sse4\0"), 0).unwrap();
+        let entries: [(u64, u64); 8] = [
+            (CA_SUNW_HW_1, 0x0080_0000),
+            (CA_SUNW_SF_1, 0x3),
+            (CA_SUNW_PLAT, 1),
+            (CA_SUNW_MACH, 7),
+            (CA_SUNW_NULL, 0),
+            (CA_SUNW_ID, 13),
+            (CA_SUNW_HW_1, 0x0100_0000),
+            (CA_SUNW_NULL, 0),
+        ];
+        let bytes = entries
+            .iter()
+            .flat_map(|(tag, value)| [tag.to_le_bytes(), value.to_le_bytes()])
+            .flatten()
+            .collect::<Vec<u8>>();
+
+        let capabilities =
+            Capabilities::parse(&bytes, scroll::LE, ElfClass::Elf64, Some(&strtab)).unwrap();
+
+        let object = capabilities.object_capabilities().unwrap();
+        assert_eq!(object.hw_1, Some(0x0080_0000));
+        assert_eq!(
+            object.sf_1,
+            Some(SoftwareCapabilities::SF1_SUNW_FPKNWN | SoftwareCapabilities::SF1_SUNW_FPUSED)
+        );
+        assert_eq!(
+            (object.platforms.as_slice(), object.machines.as_slice()),
+            (
+                ["i86pc".to_string()].as_slice(),
+                ["amd64".to_string()].as_slice()
+            )
+        );
+
+        let symbol = &capabilities.symbol_capabilities()[0];
+        assert_eq!(symbol.index, 5);
+        assert_eq!(symbol.id.as_deref(), Some("sse4"));
+        assert_eq!(capabilities.group_at(5), Some(symbol));
+    }
+}
@@ -0,0 +1,138 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::elf::sunw::SHT_SUNW_LDYNSYM;
+use crate::elf::{Reader, SectionHeader, Sym, SHT_DYNSYM};
+use crate::{Details, Error};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// `.SUNW_ldynsym` followed by `.dynsym`, the table `DT_SUNW_SYMTAB` and `DT_SUNW_SYMSZ` describe
+///
+/// `.SUNW_ldynsym` keeps local function and file symbols loaded so stack traces of stripped objects still have names.
+/// Both tables share `.dynstr`, and `SHT_SUNW_SYMSORT` indices refer to this merged table.
+#[derive(Clone, Default)]
+pub struct LocalDynamicSymbols {
+    /// `.SUNW_ldynsym` then `.dynsym`, each with its own null symbol
+    pub symbols: Vec<Sym>,
+    /// Index of the first `.dynsym` symbol in `symbols`
+    pub dynsym_start: usize,
+    /// Section index of the string table both tables use
+    pub strtab_index: u32,
+}
+
+impl LocalDynamicSymbols {
+    pub fn merge(ldynsym: Vec<Sym>, dynsym: Vec<Sym>, strtab_index: u32) -> Self {
+        let dynsym_start = ldynsym.len();
+        let mut symbols = ldynsym;
+        symbols.extend(dynsym);
+
+        LocalDynamicSymbols {
+            symbols,
+            dynsym_start,
+            strtab_index,
+        }
+    }
+
+    /// Reads and merges `.SUNW_ldynsym` and `.dynsym`, returns `None` if there is no `.dynsym`
+    ///
+    /// Objects without a `.SUNW_ldynsym` give a table with only `.dynsym`.
+    pub fn read<'a, TReader: Reader<'a>>(
+        reader: &mut TReader,
+        section_headers: &[SectionHeader],
+    ) -> Result<Option<LocalDynamicSymbols>> {
+        let Some(dynsym_header) = section_headers
+            .iter()
+            .find(|section_header| section_header.sh_type == SHT_DYNSYM)
+        else {
+            return Ok(None);
+        };
+        let dynsym = reader.read_sym_table_section(dynsym_header)?;
+
+        let ldynsym = match section_headers
+            .iter()
+            .find(|section_header| section_header.sh_type == SHT_SUNW_LDYNSYM)
+        {
+            Some(ldynsym_header) => {
+                if ldynsym_header.sh_link != dynsym_header.sh_link {
+                    return Err(Error::Malformed(
+                        Details::new(format!(
+                            "`.SUNW_ldynsym` links to string table `{}` but `.dynsym` links to `{}`",
+                            ldynsym_header.sh_link, dynsym_header.sh_link
+                        ))
+                        .structure("elf::SectionHeader")
+                        .field("sh_link")
+                        .offset(ldynsym_header.sh_offset),
+                    ));
+                }
+                reader.read_sym_table_section(ldynsym_header)?
+            }
+            None => Vec::new(),
+        };
+
+        Ok(Some(Self::merge(ldynsym, dynsym, dynsym_header.sh_link)))
+    }
+
+    /// The `.SUNW_ldynsym` symbols
+    pub fn locals(&self) -> &[Sym] {
+        &self.symbols[..self.dynsym_start]
+    }
+
+    /// The `.dynsym` symbols
+    pub fn dynsym(&self) -> &[Sym] {
+        &self.symbols[self.dynsym_start..]
+    }
+
+    /// Converts an index into the merged table to a `.dynsym` index, `None` for `.SUNW_ldynsym` symbols
+    pub fn dynsym_index(&self, index: usize) -> Option<usize> {
+        index
+            .checked_sub(self.dynsym_start)
+            .filter(|&index| index < self.symbols.len() - self.dynsym_start)
+    }
+
+    /// Converts a `.dynsym` index (e.g. a relocation's `r_sym`) to an index into the merged table
+    pub fn merged_index(&self, dynsym_index: usize) -> usize {
+        self.dynsym_start + dynsym_index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sym(st_name: u32) -> Sym {
+        Sym {
+            st_name,
+            st_info: 0,
+            st_other: 0,
+            st_shndx: 0,
+            st_value: 0,
+            st_size: 0,
+        }
+    }
+
+    #[test]
+    fn merge_ldynsym() {
+        let merged =
+            LocalDynamicSymbols::merge(vec![sym(0), sym(1), sym(2)], vec![sym(0), sym(3)], 4);
+
+        assert_eq!(merged.locals().len(), 3);
+        assert_eq!(merged.dynsym()[1].st_name, 3);
+        assert_eq!(merged.dynsym_index(4), Some(1));
+        assert_eq!(merged.dynsym_index(2), None);
+        assert_eq!(merged.dynsym_index(5), None);
+        assert_eq!(merged.merged_index(1), 4);
+    }
+}
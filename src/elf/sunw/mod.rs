@@ -16,6 +16,15 @@
 
 use crate::stringable_consts_blocks::option_stringable_consts_block;
 
+mod cap;
+pub use cap::*;
+mod ldynsym;
+pub use ldynsym::*;
+mod moves;
+pub use moves::*;
+mod syminfo;
+pub use syminfo::*;
+
 // Segment types
 option_stringable_consts_block! {
     const stringable: u32 {
@@ -30,6 +39,46 @@ option_stringable_consts_block! {
     pub fn pt_to_str(value: u32) -> Option<&'static str>;
 }
 
+// Section types
+option_stringable_consts_block! {
+    const stringable: u32 {
+        pub SHT_SUNW_ANCILLARY = 0x6fffffee;
+        /// Capability chain, links symbol capability families to their lead symbol
+        pub SHT_SUNW_CAPCHAIN = 0x6fffffef;
+        /// Capability information for every symbol
+        pub SHT_SUNW_CAPINFO = 0x6ffffff0;
+        /// Symbol table indices sorted by address
+        pub SHT_SUNW_SYMSORT = 0x6ffffff1;
+        /// TLS symbol table indices sorted by offset
+        pub SHT_SUNW_TLSSORT = 0x6ffffff2;
+        /// Local symbols that are kept in the dynamic symbol table, placed right before `.dynsym`
+        pub SHT_SUNW_LDYNSYM = 0x6ffffff3;
+        /// DTrace object format
+        pub SHT_SUNW_DOF = 0x6ffffff4;
+        /// Hardware and software capabilities
+        pub SHT_SUNW_CAP = 0x6ffffff5;
+        pub SHT_SUNW_SIGNATURE = 0x6ffffff6;
+        pub SHT_SUNW_ANNOTATE = 0x6ffffff7;
+        pub SHT_SUNW_DEBUGSTR = 0x6ffffff8;
+        pub SHT_SUNW_DEBUG = 0x6ffffff9;
+        /// Partially initialized data
+        pub SHT_SUNW_MOVE = 0x6ffffffa;
+        pub SHT_SUNW_COMDAT = 0x6ffffffb;
+        /// Additional symbol information
+        pub SHT_SUNW_SYMINFO = 0x6ffffffc;
+        /// Same as `SHT_GNU_VERDEF`
+        pub SHT_SUNW_VERDEF = 0x6ffffffd;
+        /// Same as `SHT_GNU_VERNEED`
+        pub SHT_SUNW_VERNEED = 0x6ffffffe;
+        /// Same as `SHT_GNU_VERSYM`
+        pub SHT_SUNW_VERSYM = 0x6fffffff;
+    }
+
+    const ignore: u32 {}
+
+    pub fn sht_to_str(value: u32) -> Option<&'static str>;
+}
+
 // Section flags
 /// Solaris equivalent of SHF_GNU_RETAIN
 pub const SHF_SUNW_NODISCARD: u32 = 0x00100000;
@@ -37,6 +86,41 @@ pub const SHF_SUNW_NODISCARD: u32 = 0x00100000;
 // Dynamic tags
 option_stringable_consts_block! {
     const stringable: u64 {
+        /// Same as `DT_AUXILIARY` for symbols with `SYMINFO_FLG_AUXILIARY`
+        pub DT_SUNW_AUXILIARY = 0x6000000d;
+        pub DT_SUNW_RTLDINF = 0x6000000e;
+        /// Same as `DT_FILTER` for symbols with `SYMINFO_FLG_FILTER`
+        pub DT_SUNW_FILTER = 0x6000000f;
+        /// Address of the `SHT_SUNW_CAP` section
+        pub DT_SUNW_CAP = 0x60000010;
+        /// Address of `.SUNW_ldynsym`, which `.dynsym` immediately follows
+        pub DT_SUNW_SYMTAB = 0x60000011;
+        /// Size of `.SUNW_ldynsym` and `.dynsym` together
+        pub DT_SUNW_SYMSZ = 0x60000012;
+        pub DT_SUNW_SORTENT = 0x60000013;
+        pub DT_SUNW_SYMSORT = 0x60000014;
+        pub DT_SUNW_SYMSORTSZ = 0x60000015;
+        pub DT_SUNW_TLSSORT = 0x60000016;
+        pub DT_SUNW_TLSSORTSZ = 0x60000017;
+        pub DT_SUNW_CAPINFO = 0x60000018;
+        pub DT_SUNW_STRPAD = 0x60000019;
+        pub DT_SUNW_CAPCHAIN = 0x6000001a;
+        /// `EM_*` of the machine the object was built for
+        pub DT_SUNW_LDMACH = 0x6000001b;
+        pub DT_SUNW_CAPCHAINENT = 0x6000001d;
+        pub DT_SUNW_CAPCHAINSZ = 0x6000001f;
+        /// Size of a `SHT_SUNW_MOVE` entry
+        pub DT_MOVEENT = 0x6ffffdfa;
+        /// Size of `SHT_SUNW_MOVE`
+        pub DT_MOVESZ = 0x6ffffdfb;
+        /// Size of a `SHT_SUNW_SYMINFO` entry
+        pub DT_SYMINENT = 0x6ffffdff;
+        /// Size of `SHT_SUNW_SYMINFO`
+        pub DT_SYMINSZ = 0x6ffffdfe;
+        /// Address of `SHT_SUNW_MOVE`
+        pub DT_MOVETAB = 0x6ffffefe;
+        /// Address of `SHT_SUNW_SYMINFO`
+        pub DT_SYMINFO = 0x6ffffeff;
         /// Shared object to load before self
         pub DT_AUXILIARY = 0x7FFFFFFD;
         /// Same as DT_NEEDED
@@ -0,0 +1,140 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::elf::ElfClass;
+use crate::{Details, Error};
+use scroll::{Endian, Pread};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// An `Elf32_Move` or `Elf64_Move` of `SHT_SUNW_MOVE`
+///
+/// Move entries initialize parts of otherwise uninitialized (`.bss`) symbols at load time, so partially initialized
+/// arrays don't take up space in the file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Move {
+    /// Value to write
+    pub m_value: u64,
+    /// Symbol index and size of `m_value`, see `m_sym` and `m_size`
+    pub m_info: u64,
+    /// Offset of the first write within the symbol
+    pub m_poffset: u64,
+    /// Number of times `m_value` is written
+    pub m_repeat: u16,
+    /// Number of `m_size` units skipped between writes
+    pub m_stride: u16,
+}
+
+impl Move {
+    /// Parses every entry of a `SHT_SUNW_MOVE` section
+    ///
+    /// `entry_size` is `sh_entsize` (or `DT_MOVEENT`), the padding of `Elf32_Move` differs between processors.
+    pub fn parse_all(
+        bytes: &[u8],
+        entry_size: usize,
+        endianness: Endian,
+        class: ElfClass,
+    ) -> Result<Vec<Move>> {
+        let minimum_size = match class {
+            ElfClass::Elf32 => 20,
+            ElfClass::Elf64 => 28,
+        };
+
+        if entry_size < minimum_size {
+            return Err(Error::Malformed(
+                Details::new(format!(
+                    "`SHT_SUNW_MOVE` has an entry size of `{}`, expected at least `{}`",
+                    entry_size, minimum_size
+                ))
+                .structure("elf::sunw::Move")
+                .field("sh_entsize"),
+            ));
+        }
+
+        bytes
+            .chunks_exact(entry_size)
+            .map(|entry| {
+                Ok(match class {
+                    ElfClass::Elf32 => Move {
+                        m_value: entry.pread_with(0, endianness)?,
+                        m_info: u64::from(entry.pread_with::<u32>(8, endianness)?),
+                        m_poffset: u64::from(entry.pread_with::<u32>(12, endianness)?),
+                        m_repeat: entry.pread_with(16, endianness)?,
+                        m_stride: entry.pread_with(18, endianness)?,
+                    },
+                    ElfClass::Elf64 => Move {
+                        m_value: entry.pread_with(0, endianness)?,
+                        m_info: entry.pread_with(8, endianness)?,
+                        m_poffset: entry.pread_with(16, endianness)?,
+                        m_repeat: entry.pread_with(24, endianness)?,
+                        m_stride: entry.pread_with(26, endianness)?,
+                    },
+                })
+            })
+            .collect()
+    }
+
+    /// Index of the symbol being initialized
+    pub fn m_sym(&self) -> u64 {
+        self.m_info >> 8
+    }
+
+    /// Size in bytes of `m_value`, one of `1`, `2`, `4` or `8`
+    pub fn m_size(&self) -> u8 {
+        self.m_info as u8
+    }
+
+    /// Offsets within the symbol written by this entry, stopping before any offset that would overflow
+    pub fn offsets(&self) -> impl Iterator<Item = u64> + '_ {
+        let step = u64::from(self.m_size()) * (u64::from(self.m_stride) + 1);
+
+        (0..u64::from(self.m_repeat)).map_while(move |index| {
+            index
+                .checked_mul(step)
+                .and_then(|offset| self.m_poffset.checked_add(offset))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_moves() {
+        let bytes = [
+            0x2au64.to_le_bytes().as_slice(),
+            &((3u64 << 8) | 4).to_le_bytes(),
+            &8u64.to_le_bytes(),
+            &3u16.to_le_bytes(),
+            &1u16.to_le_bytes(),
+            &[0; 4],
+        ]
+        .concat();
+        let moves = Move::parse_all(&bytes, 32, scroll::LE, ElfClass::Elf64).unwrap();
+
+        assert_eq!((moves[0].m_sym(), moves[0].m_size()), (3, 4));
+        assert_eq!(moves[0].offsets().collect::<Vec<_>>(), vec![8, 16, 24]);
+
+        let overflowing = Move {
+            m_poffset: u64::MAX - 8,
+            ..moves[0]
+        };
+        assert_eq!(
+            overflowing.offsets().collect::<Vec<_>>(),
+            vec![u64::MAX - 8, u64::MAX]
+        );
+    }
+}
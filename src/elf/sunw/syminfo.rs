@@ -0,0 +1,194 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::elf::{Dyn, StrTab, DT_NEEDED};
+use crate::{Details, Error};
+use bitflags::bitflags;
+use scroll::{Endian, Pread};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Size of an `Elf32_Syminfo` or `Elf64_Syminfo`, both are two halves
+pub const SYMINFO_SIZE: usize = 4;
+
+/// `si_boundto` values with a special meaning, anything lower is an index into `.dynamic`
+pub const SYMINFO_BT_SELF: u16 = 0xffff;
+pub const SYMINFO_BT_PARENT: u16 = 0xfffe;
+pub const SYMINFO_BT_NONE: u16 = 0xfffd;
+pub const SYMINFO_BT_EXTERN: u16 = 0xfffc;
+/// Lowest special `si_boundto` value
+pub const SYMINFO_BT_LOWRESERVE: u16 = 0xff00;
+
+bitflags! {
+    /// `si_flags` values
+    #[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct SyminfoFlags: u16 {
+        /// Bound directly to the object in `si_boundto`
+        const SYMINFO_FLG_DIRECT = 0x0001;
+        /// Filtered by the object in `si_boundto`
+        const SYMINFO_FLG_FILTER = 0x0002;
+        /// The symbol is a copy relocation
+        const SYMINFO_FLG_COPY = 0x0004;
+        /// The object in `si_boundto` is lazily loaded
+        const SYMINFO_FLG_LAZYLOAD = 0x0008;
+        /// References to the symbol bind directly to this definition
+        const SYMINFO_FLG_DIRECTBIND = 0x0010;
+        /// External references may not bind directly to the symbol
+        const SYMINFO_FLG_NOEXTDIRECT = 0x0020;
+        /// Auxiliary filtered by the object in `si_boundto`
+        const SYMINFO_FLG_AUXILIARY = 0x0040;
+        /// The symbol is an interposer
+        const SYMINFO_FLG_INTERPOSE = 0x0080;
+        /// The symbol has capability specific implementations
+        const SYMINFO_FLG_CAP = 0x0100;
+        /// Bindings to the symbol are deferred
+        const SYMINFO_FLG_DEFERRED = 0x0200;
+    }
+}
+
+/// Where a symbol is bound to, decoded from `si_boundto`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyminfoBoundTo {
+    /// `SYMINFO_BT_SELF`
+    SelfObject,
+    /// `SYMINFO_BT_PARENT`, the object that loaded this one
+    Parent,
+    /// `SYMINFO_BT_NONE`
+    None,
+    /// `SYMINFO_BT_EXTERN`, the symbol is defined externally
+    Extern,
+    /// Index of the `DT_NEEDED` entry of the object in `.dynamic`
+    Dynamic(u16),
+    Unknown(u16),
+}
+
+/// An `Elf32_Syminfo` or `Elf64_Syminfo`, `SHT_SUNW_SYMINFO` has one per `.dynsym` symbol
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Syminfo {
+    pub si_boundto: u16,
+    pub si_flags: SyminfoFlags,
+}
+
+impl Syminfo {
+    /// Parses every entry of a `SHT_SUNW_SYMINFO` section, entry `n` describes `.dynsym` symbol `n`
+    pub fn parse_all(bytes: &[u8], endianness: Endian) -> Result<Vec<Syminfo>> {
+        if bytes.len() % SYMINFO_SIZE != 0 {
+            return Err(Error::Malformed(
+                Details::new(format!(
+                    "`SHT_SUNW_SYMINFO` has a size of `{}`, which isn't a multiple of `{}`",
+                    bytes.len(),
+                    SYMINFO_SIZE
+                ))
+                .structure("elf::sunw::Syminfo"),
+            ));
+        }
+
+        bytes
+            .chunks_exact(SYMINFO_SIZE)
+            .map(|entry| {
+                Ok(Syminfo {
+                    si_boundto: entry.pread_with(0, endianness)?,
+                    si_flags: SyminfoFlags::from_bits_retain(entry.pread_with(2, endianness)?),
+                })
+            })
+            .collect()
+    }
+
+    pub fn bound_to(&self) -> SyminfoBoundTo {
+        match self.si_boundto {
+            SYMINFO_BT_SELF => SyminfoBoundTo::SelfObject,
+            SYMINFO_BT_PARENT => SyminfoBoundTo::Parent,
+            SYMINFO_BT_NONE => SyminfoBoundTo::None,
+            SYMINFO_BT_EXTERN => SyminfoBoundTo::Extern,
+            index if index < SYMINFO_BT_LOWRESERVE => SyminfoBoundTo::Dynamic(index),
+            unknown => SyminfoBoundTo::Unknown(unknown),
+        }
+    }
+
+    /// Name of the object the symbol is bound to, for `SyminfoBoundTo::Dynamic`
+    ///
+    /// `dynamic` is `.dynamic` and `dynstr` its string table.
+    pub fn bound_to_object(&self, dynamic: &[Dyn], dynstr: &StrTab) -> Result<Option<String>> {
+        let SyminfoBoundTo::Dynamic(index) = self.bound_to() else {
+            return Ok(None);
+        };
+
+        match dynamic.get(index as usize) {
+            Some(entry) if entry.d_tag == DT_NEEDED => {
+                let offset = u32::try_from(entry.d_val).map_err(|_| {
+                    Error::Malformed(
+                        Details::new(format!(
+                            "`DT_NEEDED` string offset `{}` is out of range",
+                            entry.d_val
+                        ))
+                        .structure("elf::Dyn")
+                        .field("d_val"),
+                    )
+                })?;
+                Ok(Some(dynstr.get_at_offset(offset)?.into_owned()))
+            }
+            _ => Err(Error::Malformed(
+                Details::new(format!(
+                    "`si_boundto` of `{}` doesn't refer to a `DT_NEEDED` entry",
+                    index
+                ))
+                .structure("elf::sunw::Syminfo")
+                .field("si_boundto"),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    #[test]
+    fn parse_syminfo() {
+        let bytes = [
+            [0x00, 0x00, 0x00, 0x00],
+            [0x01, 0x00, 0x09, 0x00],
+            [0xff, 0xff, 0x10, 0x00],
+        ]
+        .concat();
+        let syminfo = Syminfo::parse_all(&bytes, scroll::LE).unwrap();
+
+        assert_eq!(syminfo.len(), 3);
+        assert_eq!(syminfo[1].bound_to(), SyminfoBoundTo::Dynamic(1));
+        assert_eq!(
+            syminfo[1].si_flags,
+            SyminfoFlags::SYMINFO_FLG_DIRECT | SyminfoFlags::SYMINFO_FLG_LAZYLOAD
+        );
+        assert_eq!(syminfo[2].bound_to(), SyminfoBoundTo::SelfObject);
+
+        let dynstr = StrTab::parse(Cow::Borrowed(b"\0libc.so.1\0libm.so.2\0"), 0).unwrap();
+        let dynamic = [
+            Dyn {
+                d_tag: DT_NEEDED,
+                d_val: 1,
+            },
+            Dyn {
+                d_tag: DT_NEEDED,
+                d_val: 11,
+            },
+        ];
+        assert_eq!(
+            syminfo[1].bound_to_object(&dynamic, &dynstr).unwrap(),
+            Some("libm.so.2".to_string())
+        );
+        assert_eq!(syminfo[2].bound_to_object(&dynamic, &dynstr).unwrap(), None);
+    }
+}
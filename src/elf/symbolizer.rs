@@ -0,0 +1,501 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::elf::dwarf::DebugLine;
+use crate::elf::eh_frame::EhFrame;
+use crate::elf::PltAnalysis;
+use crate::elf::{
+    StrTab, Sym, EM_ARM, SHN_COMMON, SHN_UNDEF, STB_GLOBAL, STB_WEAK, STT_FUNC, STT_OBJECT,
+};
+use crate::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Where a `Symbolizer` entry came from, earlier variants are preferred when several sized (or several unsized) entries
+/// start at the same address
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SymbolSource {
+    SymTab,
+    DynSym,
+    /// Synthetic `name@plt` symbols from `PltAnalysis`
+    Plt,
+    /// Function bounds from an FDE, these don't have names
+    EhFrame,
+    /// Function bounds from a `.debug_line` sequence, these don't have names
+    DebugLine,
+    /// Function bounds added with `SymbolizerBuilder::add_function_bounds`
+    Other,
+}
+
+struct SymbolizerEntry {
+    name: Option<String>,
+    address: u64,
+    size: u64,
+    st_type: u8,
+    st_bind: u8,
+    source: SymbolSource,
+    is_thumb: bool,
+}
+
+impl SymbolizerEntry {
+    /// Lower is better, used to pick one entry out of aliases, sized entries always win over unsized ones
+    fn priority(&self) -> (bool, SymbolSource, u8) {
+        let bind_priority = match self.st_bind {
+            STB_GLOBAL => 0,
+            STB_WEAK => 1,
+            _ => 2,
+        };
+
+        (self.size == 0, self.source, bind_priority)
+    }
+}
+
+/// The result of `Symbolizer::lookup`
+#[derive(Clone, Debug, PartialEq)]
+pub struct SymbolizedAddress<'a> {
+    /// `None` for function bounds that didn't come from a symbol table
+    pub name: Option<&'a str>,
+    /// Start of the symbol, with the Thumb bit cleared
+    pub symbol_address: u64,
+    /// `0` if the symbol is sized by the symbol after it
+    pub symbol_size: u64,
+    /// Offset of the looked up address from `symbol_address`
+    pub offset: u64,
+    pub st_type: u8,
+    pub source: SymbolSource,
+    /// The symbol is a Thumb function, bit 0 of its `st_value` was set
+    pub is_thumb: bool,
+}
+
+/// Collects symbols and function bounds, see `Symbolizer`
+pub struct SymbolizerBuilder {
+    e_machine: u16,
+    entries: Vec<SymbolizerEntry>,
+}
+
+impl SymbolizerBuilder {
+    /// `e_machine` is needed to know if bit 0 of function addresses is the ARM Thumb bit
+    pub fn new(e_machine: u16) -> Self {
+        SymbolizerBuilder {
+            e_machine,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Adds every defined `STT_FUNC`, `STT_GNU_IFUNC` and `STT_OBJECT` symbol of `.symtab` or `.dynsym`
+    ///
+    /// `SHN_ABS` symbols are kept, their value is already an address. Undefined and common symbols don't have an address
+    /// and are skipped.
+    pub fn add_symbols(
+        &mut self,
+        symbols: &[Sym],
+        strtab: &StrTab,
+        source: SymbolSource,
+    ) -> Result<()> {
+        for symbol in symbols {
+            let st_type = symbol.st_type();
+            let is_function = st_type == STT_FUNC
+                || st_type == crate::elf::gnu::STT_GNU_IFUNC
+                || (self.e_machine == EM_ARM && st_type == crate::elf::arm::STT_ARM_TFUNC);
+
+            if !(is_function || st_type == STT_OBJECT)
+                || u32::from(symbol.st_shndx) == SHN_UNDEF
+                || u32::from(symbol.st_shndx) == SHN_COMMON
+            {
+                continue;
+            }
+
+            let is_thumb = is_function && self.e_machine == EM_ARM && symbol.st_value & 1 != 0;
+            let name = strtab.get_at_offset(symbol.st_name)?;
+
+            self.entries.push(SymbolizerEntry {
+                name: Some(name.into_owned()),
+                address: if is_thumb {
+                    symbol.st_value & !1
+                } else {
+                    symbol.st_value
+                },
+                size: symbol.st_size,
+                st_type,
+                st_bind: symbol.st_bind(),
+                source,
+                is_thumb,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Adds a `name@plt` symbol for every PLT stub
+    pub fn add_plt(&mut self, plt: &PltAnalysis) {
+        for entry in &plt.entries {
+            self.entries.push(SymbolizerEntry {
+                name: Some(entry.plt_name()),
+                address: entry.address,
+                size: entry.size,
+                st_type: STT_FUNC,
+                st_bind: STB_GLOBAL,
+                source: SymbolSource::Plt,
+                is_thumb: false,
+            });
+        }
+    }
+
+    /// Adds nameless function bounds, used when the symbol tables are stripped
+    pub fn add_function_bounds(&mut self, address: u64, size: u64) {
+        self.push_bounds(address, size, SymbolSource::Other);
+    }
+
+    /// Adds the range of every FDE as nameless function bounds
+    pub fn add_eh_frame(&mut self, eh_frame: &EhFrame) {
+        for fde in &eh_frame.fdes {
+            self.push_bounds(fde.pc_begin, fde.pc_range, SymbolSource::EhFrame);
+        }
+    }
+
+    /// Adds the range of every `.debug_line` sequence as nameless function bounds
+    ///
+    /// Sequences are only per function when the code was compiled with `-ffunction-sections` (or equivalent), otherwise
+    /// they cover a whole compilation unit.
+    pub fn add_debug_line(&mut self, debug_line: &DebugLine) {
+        for program in &debug_line.programs {
+            let mut sequence_start: Option<u64> = None;
+
+            for row in &program.rows {
+                match (sequence_start, row.end_sequence) {
+                    (None, false) => sequence_start = Some(row.address),
+                    (Some(start), true) => {
+                        self.push_bounds(
+                            start,
+                            row.address.saturating_sub(start),
+                            SymbolSource::DebugLine,
+                        );
+                        sequence_start = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn push_bounds(&mut self, address: u64, size: u64, source: SymbolSource) {
+        let is_thumb = self.e_machine == EM_ARM && address & 1 != 0;
+
+        self.entries.push(SymbolizerEntry {
+            name: None,
+            address: address & !u64::from(is_thumb),
+            size,
+            st_type: STT_FUNC,
+            st_bind: STB_GLOBAL,
+            source,
+            is_thumb,
+        });
+    }
+
+    pub fn build(mut self) -> Symbolizer {
+        self.entries.sort_by(|left, right| {
+            left.address
+                .cmp(&right.address)
+                .then_with(|| left.priority().cmp(&right.priority()))
+        });
+        // Aliases and the same symbol from both `.symtab` and `.dynsym` collapse into the best entry
+        self.entries.dedup_by_key(|entry| entry.address);
+
+        // Flattens the (possibly overlapping) entries into disjoint ranges, each owned by the entry starting closest
+        // before it. `active` is ordered by start address, entries that ended are only dropped once they're on top.
+        let mut ranges: Vec<SymbolizerRange> = Vec::new();
+        let mut active: Vec<usize> = Vec::new();
+        let mut cursor: u64 = 0;
+        let lasts: Vec<u64> = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(
+                |(index, entry)| match (entry.size, self.entries.get(index + 1)) {
+                    (0, Some(next)) => next.address - 1,
+                    (0, None) => entry.address,
+                    (size, _) => entry.address.saturating_add(size - 1),
+                },
+            )
+            .collect();
+
+        for index in 0..=self.entries.len() {
+            let next_start = self.entries.get(index).map(|entry| entry.address);
+
+            while let Some(&top) = active.last() {
+                if lasts[top] < cursor {
+                    active.pop();
+                    continue;
+                }
+
+                let last = match next_start {
+                    Some(next_start) if next_start <= cursor => break,
+                    Some(next_start) => lasts[top].min(next_start - 1),
+                    None => lasts[top],
+                };
+                ranges.push(SymbolizerRange {
+                    start: cursor,
+                    last,
+                    entry: top,
+                });
+
+                match last.checked_add(1) {
+                    Some(next_cursor) => cursor = next_cursor,
+                    None => break,
+                }
+            }
+
+            if let Some(next_start) = next_start {
+                cursor = next_start;
+                active.push(index);
+            }
+        }
+
+        Symbolizer {
+            entries: self.entries,
+            ranges,
+        }
+    }
+}
+
+/// Addresses `start..=last` resolve to `Symbolizer::entries[entry]`
+struct SymbolizerRange {
+    start: u64,
+    last: u64,
+    entry: usize,
+}
+
+/// Answers "which symbol contains this address" with a binary search
+///
+/// Overlapping symbols are resolved to the one starting closest to the looked up address, falling back to the closest
+/// sized symbol enclosing it.
+pub struct Symbolizer {
+    /// Sorted by address, one entry per address
+    entries: Vec<SymbolizerEntry>,
+    /// Sorted, disjoint ranges covering every address that resolves to an entry
+    ranges: Vec<SymbolizerRange>,
+}
+
+impl Symbolizer {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Finds the symbol containing `address` and the offset of `address` within it
+    ///
+    /// Symbols with a `st_size` of `0` are assumed to extend up to the next symbol, the last symbol only matches its
+    /// own address.
+    pub fn lookup(&self, address: u64) -> Option<SymbolizedAddress<'_>> {
+        let range = &self.ranges[self
+            .ranges
+            .partition_point(|range| range.start <= address)
+            .checked_sub(1)?];
+
+        if address > range.last {
+            return None;
+        }
+
+        let entry = &self.entries[range.entry];
+        let offset = address - entry.address;
+
+        Some(SymbolizedAddress {
+            name: entry.name.as_deref(),
+            symbol_address: entry.address,
+            symbol_size: entry.size,
+            offset,
+            st_type: entry.st_type,
+            source: entry.source,
+            is_thumb: entry.is_thumb,
+        })
+    }
+
+    /// Finds every symbol overlapping `start..end`, in address order
+    pub fn lookup_range(&self, start: u64, end: u64) -> Vec<SymbolizedAddress<'_>> {
+        let mut result: Vec<SymbolizedAddress<'_>> = Vec::new();
+
+        if start >= end {
+            return result;
+        }
+
+        if let Some(first) = self.lookup(start) {
+            result.push(first);
+        }
+
+        let first_after_start = self.entries.partition_point(|entry| entry.address <= start);
+
+        for entry in self.entries[first_after_start..]
+            .iter()
+            .take_while(|entry| entry.address < end)
+        {
+            if let Some(symbolized) = self.lookup(entry.address) {
+                result.push(symbolized);
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    fn sym(st_name: u32, st_info: u8, st_shndx: u16, st_value: u64, st_size: u64) -> Sym {
+        Sym {
+            st_name,
+            st_info,
+            st_other: 0,
+            st_shndx,
+            st_value,
+            st_size,
+        }
+    }
+
+    #[test]
+    fn lookup_merged_symbols() {
+        let strtab = StrTab::parse(
+            Cow::Borrowed(b"\0thumb_func\0data\0marker\0local_alias\0"),
+            0,
+        )
+        .unwrap();
+        let global_func = (STB_GLOBAL << 4) | STT_FUNC;
+        let symtab = vec![
+            sym(0, 0, 0, 0, 0),
+            sym(1, global_func, 1, 0x1001, 0x10),
+            sym(12, (STB_GLOBAL << 4) | STT_OBJECT, 2, 0x2000, 8),
+            sym(24, STT_FUNC, 1, 0x1001, 0x10),
+            sym(17, STT_OBJECT, crate::elf::SHN_ABS as u16, 0x3000, 0),
+        ];
+        let dynsym = vec![sym(1, global_func, 1, 0x1001, 0x10)];
+
+        let mut builder = SymbolizerBuilder::new(EM_ARM);
+        builder
+            .add_symbols(&symtab, &strtab, SymbolSource::SymTab)
+            .unwrap();
+        builder
+            .add_symbols(&dynsym, &strtab, SymbolSource::DynSym)
+            .unwrap();
+        builder.add_function_bounds(0x4000, 0x20);
+        let symbolizer = builder.build();
+
+        assert_eq!(symbolizer.len(), 4);
+
+        let thumb = symbolizer.lookup(0x1008).unwrap();
+        assert_eq!(thumb.name, Some("thumb_func"));
+        assert_eq!((thumb.symbol_address, thumb.offset), (0x1000, 8));
+        assert!(thumb.is_thumb);
+        assert!(symbolizer.lookup(0x1010).is_none());
+
+        assert_eq!(symbolizer.lookup(0x2004).unwrap().name, Some("data"));
+        assert_eq!(symbolizer.lookup(0x3000).unwrap().name, Some("marker"));
+        // Zero sized symbols extend up to the next entry
+        assert_eq!(symbolizer.lookup(0x3fff).unwrap().name, Some("marker"));
+
+        let stripped = symbolizer.lookup(0x4010).unwrap();
+        assert_eq!(
+            (stripped.name, stripped.source),
+            (None, SymbolSource::Other)
+        );
+        assert_eq!(symbolizer.lookup_range(0x1000, 0x3001).len(), 3);
+    }
+
+    #[test]
+    fn lookup_enclosing_symbols() {
+        let strtab = StrTab::parse(Cow::Borrowed(b"\0outer\0inner\0alias\0sized\0"), 0).unwrap();
+        let global_func = (STB_GLOBAL << 4) | STT_FUNC;
+        let symtab = vec![
+            sym(1, global_func, 1, 0x1000, 0x100),
+            sym(7, STT_FUNC, 1, 0x1010, 4),
+            sym(13, global_func, 1, 0x2000, 0),
+        ];
+        let dynsym = vec![sym(19, global_func, 1, 0x2000, 8)];
+
+        let mut builder = SymbolizerBuilder::new(crate::elf::EM_X86_64);
+        builder
+            .add_symbols(&symtab, &strtab, SymbolSource::SymTab)
+            .unwrap();
+        builder
+            .add_symbols(&dynsym, &strtab, SymbolSource::DynSym)
+            .unwrap();
+        let symbolizer = builder.build();
+
+        assert_eq!(symbolizer.lookup(0x1012).unwrap().name, Some("inner"));
+
+        let outer = symbolizer.lookup(0x1020).unwrap();
+        assert_eq!((outer.name, outer.offset), (Some("outer"), 0x20));
+        assert!(symbolizer.lookup(0x1100).is_none());
+
+        // The sized `.dynsym` entry wins over the unsized `.symtab` alias
+        let sized = symbolizer.lookup(0x2004).unwrap();
+        assert_eq!((sized.name, sized.symbol_size), (Some("sized"), 8));
+        assert!(symbolizer.lookup(0x2008).is_none());
+    }
+
+    #[test]
+    fn lookup_overlapping_bounds() {
+        // (address, size), overlapping, nested and unsized entries after one large function
+        let bounds = [
+            (0x1000, 0x1000),
+            (0x1100, 0),
+            (0x1200, 0x300),
+            (0x1280, 4),
+            (0x1400, 0x200),
+            (0x1450, 0),
+            (0x1800, 0x10),
+            (0x3000, 0),
+            (u64::MAX - 1, 0),
+        ];
+
+        let mut builder = SymbolizerBuilder::new(crate::elf::EM_X86_64);
+        for (address, size) in bounds {
+            builder.add_function_bounds(address, size);
+        }
+        let symbolizer = builder.build();
+
+        // The closest entry starting before the address that still covers it
+        let expected = |address: u64| {
+            bounds
+                .iter()
+                .enumerate()
+                .filter(|(index, (start, size))| {
+                    let last = match (size, bounds.get(index + 1)) {
+                        (0, Some((next, _))) => next - 1,
+                        (0, None) => *start,
+                        (size, _) => start + size - 1,
+                    };
+                    *start <= address && address <= last
+                })
+                .map(|(_, (start, _))| *start)
+                .last()
+        };
+
+        for address in (0xff0..0x3010).chain([u64::MAX - 2, u64::MAX - 1, u64::MAX]) {
+            assert_eq!(
+                symbolizer
+                    .lookup(address)
+                    .map(|symbolized| symbolized.symbol_address),
+                expected(address),
+                "{:#x}",
+                address
+            );
+        }
+    }
+}
@@ -0,0 +1,812 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Whole-file structural checks, the readers only check what they read is in bounds
+
+use crate::elf::gnu::{
+    DT_GNU_HASH, DT_VERDEF, DT_VERNEED, DT_VERSYM, PT_GNU_RELRO, PT_GNU_STACK, SHT_GNU_HASH,
+    SHT_GNU_VERDEF, SHT_GNU_VERNEED, SHT_GNU_VERSYM,
+};
+use crate::elf::{
+    dt_to_str, sht_to_str, Dyn, Header, PFlags, ProgramHeader, Reader, SHFlags, SectionHeader, Sym,
+    DT_FINI, DT_FINI_ARRAY, DT_HASH, DT_INIT, DT_INIT_ARRAY, DT_JMPREL, DT_NULL, DT_PLTGOT,
+    DT_PREINIT_ARRAY, DT_REL, DT_RELA, DT_RELR, DT_STRTAB, DT_SYMTAB, ET_DYN, ET_EXEC, PT_DYNAMIC,
+    PT_LOAD, SHN_LORESERVE, SHN_UNDEF, SHT_DYNAMIC, SHT_DYNSYM, SHT_GROUP, SHT_HASH, SHT_NOBITS,
+    SHT_NULL, SHT_REL, SHT_RELA, SHT_STRTAB, SHT_SYMTAB, SHT_SYMTAB_SHNDX,
+};
+use crate::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Unusual but harmless
+    Info,
+    /// Valid but likely a mistake or a hardening problem
+    Warning,
+    /// Violates the ELF specification, loaders and tools may reject or misread the file
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FindingKind {
+    /// Two `PT_LOAD` segments map the same virtual addresses
+    OverlappingLoadSegments { first: usize, second: usize },
+    /// `p_align` isn't a power of two or `p_vaddr` and `p_offset` aren't congruent modulo `p_align`
+    UnalignedLoadSegment { index: usize },
+    /// A `SHF_ALLOC` section isn't covered by any `PT_LOAD` segment
+    SectionOutsideSegments { index: usize },
+    /// `sh_link` is out of range or points at a section of the wrong type
+    InvalidSectionLink { index: usize, sh_link: u32 },
+    /// `sh_info` doesn't fit the section type, e.g. a relocation section not naming the section it relocates
+    InvalidSectionInfo { index: usize, sh_info: u32 },
+    /// `st_shndx` is neither reserved nor a valid section index
+    SymbolSectionIndexOutOfRange {
+        section_index: usize,
+        symbol_index: usize,
+        st_shndx: u16,
+    },
+    /// A `DT_*` entry holds an address no `PT_LOAD` segment maps
+    UnmappedDynamicAddress { d_tag: u64, address: u64 },
+    /// A `PT_LOAD` segment is both writable and executable
+    WritableExecutableSegment { index: usize },
+    /// There is no `PT_GNU_STACK`, most loaders then default to an executable stack
+    MissingGnuStack,
+    /// `PT_GNU_STACK` asks for an executable stack
+    ExecutableStack { index: usize },
+    /// A dynamically linked file without `PT_GNU_RELRO`
+    MissingGnuRelro,
+    /// The symbol or dynamic table of a section couldn't be read, the checks using it were skipped
+    UnreadableSection { index: usize },
+    /// The dynamic table of a `PT_DYNAMIC` segment couldn't be read, the checks using it were skipped
+    UnreadableSegment { index: usize },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub kind: FindingKind,
+    pub message: String,
+}
+
+/// Everything `validate_parsed` looks at, for callers that already parsed the file
+pub struct ValidationInput<'a> {
+    pub header: &'a Header,
+    pub program_headers: &'a [ProgramHeader],
+    pub section_headers: &'a [SectionHeader],
+    /// Symbol tables along with the index of the section they were read from
+    pub symbol_tables: Vec<(usize, &'a [Sym])>,
+    /// The `PT_DYNAMIC` (or `SHT_DYNAMIC`) entries, empty for static files
+    pub dynamic: &'a [Dyn],
+}
+
+/// Reads the headers, symbol tables and dynamic table of a file and checks them, see `validate_parsed`
+///
+/// Only failing to read the headers is an error, tables that can't be read are reported as findings first.
+pub fn validate<'a, TReader: Reader<'a>>(reader: &mut TReader) -> Result<Vec<Finding>> {
+    let header = reader.read_header()?;
+    let program_headers =
+        reader.read_program_headers(header.e_phoff, header.e_phentsize, header.e_phnum)?;
+    let section_headers =
+        reader.read_section_headers(header.e_shoff, header.e_shentsize, header.e_shnum)?;
+
+    let mut findings: Vec<Finding> = Vec::new();
+    let mut symbol_tables: Vec<(usize, Vec<Sym>)> = Vec::new();
+
+    for (index, section_header) in section_headers.iter().enumerate() {
+        if section_header.sh_type == SHT_SYMTAB || section_header.sh_type == SHT_DYNSYM {
+            // `read_sym_table_section` only accepts `SHT_SYMTAB`
+            match reader.read_sym_table(section_header.sh_offset, section_header.sh_size) {
+                Ok(symbols) => symbol_tables.push((index, symbols)),
+                Err(error) => push(
+                    &mut findings,
+                    Severity::Error,
+                    FindingKind::UnreadableSection { index },
+                    format!(
+                        "`{}` section `{}` couldn't be read, {}",
+                        sht_to_str(section_header.sh_type),
+                        index,
+                        error
+                    ),
+                ),
+            }
+        }
+    }
+
+    let dynamic = match program_headers
+        .iter()
+        .enumerate()
+        .find(|(_, program_header)| program_header.p_type == PT_DYNAMIC)
+    {
+        Some((index, program_header)) => match reader.read_dynamic_program(program_header) {
+            Ok(dynamic) => dynamic,
+            Err(error) => {
+                push(
+                    &mut findings,
+                    Severity::Error,
+                    FindingKind::UnreadableSegment { index },
+                    format!(
+                        "`PT_DYNAMIC` segment `{}` couldn't be read, {}",
+                        index, error
+                    ),
+                );
+                Vec::new()
+            }
+        },
+        None => match section_headers
+            .iter()
+            .enumerate()
+            .find(|(_, section_header)| section_header.sh_type == SHT_DYNAMIC)
+        {
+            Some((index, section_header)) => match reader.read_dynamic_section(section_header) {
+                Ok(dynamic) => dynamic,
+                Err(error) => {
+                    push(
+                        &mut findings,
+                        Severity::Error,
+                        FindingKind::UnreadableSection { index },
+                        format!(
+                            "`SHT_DYNAMIC` section `{}` couldn't be read, {}",
+                            index, error
+                        ),
+                    );
+                    Vec::new()
+                }
+            },
+            None => Vec::new(),
+        },
+    };
+
+    findings.extend(validate_parsed(&ValidationInput {
+        header: &header,
+        program_headers: &program_headers,
+        section_headers: &section_headers,
+        symbol_tables: symbol_tables
+            .iter()
+            .map(|(index, symbols)| (*index, symbols.as_slice()))
+            .collect(),
+        dynamic: &dynamic,
+    }));
+
+    Ok(findings)
+}
+
+/// Checks the file as a whole, findings are returned in the order the checks run
+pub fn validate_parsed(input: &ValidationInput) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = Vec::new();
+
+    check_load_segments(input, &mut findings);
+    check_section_segments(input, &mut findings);
+    check_section_links(input, &mut findings);
+    check_symbols(input, &mut findings);
+    check_dynamic(input, &mut findings);
+    check_hardening(input, &mut findings);
+
+    findings
+}
+
+fn push(findings: &mut Vec<Finding>, severity: Severity, kind: FindingKind, message: String) {
+    findings.push(Finding {
+        severity,
+        kind,
+        message,
+    });
+}
+
+fn load_segments<'a>(
+    input: &'a ValidationInput,
+) -> impl Iterator<Item = (usize, &'a ProgramHeader)> + 'a {
+    input
+        .program_headers
+        .iter()
+        .enumerate()
+        .filter(|(_, program_header)| program_header.p_type == PT_LOAD)
+}
+
+fn is_mapped(input: &ValidationInput, address: u64, size: u64) -> bool {
+    load_segments(input).any(|(_, program_header)| {
+        address >= program_header.p_vaddr
+            && address.saturating_add(size)
+                <= program_header
+                    .p_vaddr
+                    .saturating_add(program_header.p_memsz)
+    })
+}
+
+fn check_load_segments(input: &ValidationInput, findings: &mut Vec<Finding>) {
+    let mut segments: Vec<(usize, &ProgramHeader)> = load_segments(input).collect();
+
+    for (index, program_header) in &segments {
+        let p_align = program_header.p_align;
+
+        if p_align > 1
+            && (!p_align.is_power_of_two()
+                || program_header.p_vaddr % p_align != program_header.p_offset % p_align)
+        {
+            push(
+                findings,
+                Severity::Error,
+                FindingKind::UnalignedLoadSegment { index: *index },
+                format!(
+                    "`PT_LOAD` segment `{}` has `p_vaddr` of `0x{:x}`, `p_offset` of `0x{:x}` and `p_align` of `0x{:x}`",
+                    index, program_header.p_vaddr, program_header.p_offset, p_align
+                ),
+            );
+        }
+    }
+
+    segments.sort_by_key(|(_, program_header)| program_header.p_vaddr);
+
+    for pair in segments.windows(2) {
+        let (first, first_header) = pair[0];
+        let (second, second_header) = pair[1];
+        let first_end = first_header.p_vaddr.saturating_add(first_header.p_memsz);
+
+        if first_header.p_memsz != 0
+            && second_header.p_memsz != 0
+            && second_header.p_vaddr < first_end
+        {
+            push(
+                findings,
+                Severity::Error,
+                FindingKind::OverlappingLoadSegments { first, second },
+                format!(
+                    "`PT_LOAD` segments `{}` (`0x{:x}..0x{:x}`) and `{}` (starting at `0x{:x}`) overlap",
+                    first, first_header.p_vaddr, first_end, second, second_header.p_vaddr
+                ),
+            );
+        }
+    }
+}
+
+fn check_section_segments(input: &ValidationInput, findings: &mut Vec<Finding>) {
+    // Relocatable files don't have segments
+    if input.program_headers.is_empty() {
+        return;
+    }
+
+    for (index, section_header) in input.section_headers.iter().enumerate() {
+        // `.tbss` only exists in the TLS template, it takes no space in the segment it's attached to
+        let is_tbss =
+            section_header.sh_flags.contains(SHFlags::TLS) && section_header.sh_type == SHT_NOBITS;
+
+        if !section_header.sh_flags.contains(SHFlags::ALLOC)
+            || section_header.sh_size == 0
+            || is_tbss
+        {
+            continue;
+        }
+
+        if !is_mapped(input, section_header.sh_addr, section_header.sh_size) {
+            push(
+                findings,
+                Severity::Warning,
+                FindingKind::SectionOutsideSegments { index },
+                format!(
+                    "`SHF_ALLOC` section `{}` at `0x{:x}` with a size of `0x{:x}` isn't inside any `PT_LOAD` segment",
+                    index, section_header.sh_addr, section_header.sh_size
+                ),
+            );
+        }
+    }
+}
+
+/// The section types `sh_link` may point at for sections of type `sh_type`, `None` if `sh_link` isn't a section index
+fn expected_link_types(sh_type: u32) -> Option<&'static [u32]> {
+    match sh_type {
+        SHT_SYMTAB | SHT_DYNSYM | SHT_DYNAMIC | SHT_GNU_VERDEF | SHT_GNU_VERNEED => {
+            Some(&[SHT_STRTAB])
+        }
+        SHT_HASH | SHT_GNU_HASH | SHT_GNU_VERSYM => Some(&[SHT_DYNSYM, SHT_SYMTAB]),
+        SHT_REL | SHT_RELA => Some(&[SHT_SYMTAB, SHT_DYNSYM]),
+        SHT_GROUP | SHT_SYMTAB_SHNDX => Some(&[SHT_SYMTAB]),
+        _ => None,
+    }
+}
+
+fn check_section_links(input: &ValidationInput, findings: &mut Vec<Finding>) {
+    let section_count = input.section_headers.len();
+
+    for (index, section_header) in input.section_headers.iter().enumerate() {
+        if let Some(expected_types) = expected_link_types(section_header.sh_type) {
+            let sh_link = section_header.sh_link;
+            // Dynamic relocations without symbols (e.g. only `R_*_RELATIVE`) may leave `sh_link` as `0`
+            let optional = matches!(section_header.sh_type, SHT_REL | SHT_RELA) && sh_link == 0;
+
+            let problem = match input.section_headers.get(sh_link as usize) {
+                _ if optional => None,
+                None => Some(format!("out of range for `{}` sections", section_count)),
+                Some(linked) if !expected_types.contains(&linked.sh_type) => Some(format!(
+                    "pointing at a `{}` section, expected `{}`",
+                    sht_to_str(linked.sh_type),
+                    expected_types
+                        .iter()
+                        .map(|sh_type| sht_to_str(*sh_type))
+                        .collect::<Vec<&str>>()
+                        .join("` or `")
+                )),
+                Some(_) => None,
+            };
+
+            if let Some(problem) = problem {
+                push(
+                    findings,
+                    Severity::Error,
+                    FindingKind::InvalidSectionLink { index, sh_link },
+                    format!(
+                        "`{}` section `{}` has an `sh_link` of `{}` {}",
+                        sht_to_str(section_header.sh_type),
+                        index,
+                        sh_link,
+                        problem
+                    ),
+                );
+            }
+        }
+
+        let sh_info = section_header.sh_info;
+        let problem = match section_header.sh_type {
+            // Dynamic relocations apply to the whole image and leave `sh_info` as `0`
+            SHT_REL | SHT_RELA
+                if sh_info != 0 || section_header.sh_flags.contains(SHFlags::INFO_LINK) =>
+            {
+                match input.section_headers.get(sh_info as usize) {
+                    None => Some(format!("out of range for `{}` sections", section_count)),
+                    Some(_) if sh_info as usize == index => {
+                        Some("pointing at itself, expected the relocated section".to_string())
+                    }
+                    Some(target) if target.sh_type == SHT_NULL => Some(format!(
+                        "pointing at a `{}` section, expected the relocated section",
+                        sht_to_str(target.sh_type)
+                    )),
+                    Some(_) => None,
+                }
+            }
+            // `sh_info` is one past the last local symbol
+            SHT_SYMTAB | SHT_DYNSYM => match symbol_count(input, index) {
+                Some(count) if sh_info as usize > count => {
+                    Some(format!("greater than its `{}` symbols", count))
+                }
+                _ => None,
+            },
+            // `sh_info` is the signature symbol in the `sh_link` symbol table
+            SHT_GROUP => match symbol_count(input, section_header.sh_link as usize) {
+                Some(count) if sh_info as usize >= count => Some(format!(
+                    "out of range for the `{}` symbols of section `{}`",
+                    count, section_header.sh_link
+                )),
+                _ => None,
+            },
+            _ if section_header.sh_flags.contains(SHFlags::INFO_LINK)
+                && sh_info as usize >= section_count =>
+            {
+                Some(format!(
+                    "out of range for `{}` sections with `SHF_INFO_LINK`",
+                    section_count
+                ))
+            }
+            _ => None,
+        };
+
+        if let Some(problem) = problem {
+            push(
+                findings,
+                Severity::Error,
+                FindingKind::InvalidSectionInfo { index, sh_info },
+                format!(
+                    "`{}` section `{}` has an `sh_info` of `{}` {}",
+                    sht_to_str(section_header.sh_type),
+                    index,
+                    sh_info,
+                    problem
+                ),
+            );
+        }
+    }
+}
+
+/// The number of symbols in the symbol table section at `index`, from the table itself if it was read
+fn symbol_count(input: &ValidationInput, index: usize) -> Option<usize> {
+    if let Some((_, symbols)) = input
+        .symbol_tables
+        .iter()
+        .find(|(section_index, _)| *section_index == index)
+    {
+        return Some(symbols.len());
+    }
+
+    let section_header = input.section_headers.get(index)?;
+
+    if !matches!(section_header.sh_type, SHT_SYMTAB | SHT_DYNSYM) || section_header.sh_entsize == 0
+    {
+        return None;
+    }
+
+    usize::try_from(section_header.sh_size / section_header.sh_entsize).ok()
+}
+
+fn check_symbols(input: &ValidationInput, findings: &mut Vec<Finding>) {
+    let section_count = input.section_headers.len();
+
+    for (section_index, symbols) in &input.symbol_tables {
+        for (symbol_index, symbol) in symbols.iter().enumerate() {
+            let st_shndx = u32::from(symbol.st_shndx);
+
+            if st_shndx != SHN_UNDEF
+                && st_shndx < SHN_LORESERVE
+                && st_shndx as usize >= section_count
+            {
+                push(
+                    findings,
+                    Severity::Error,
+                    FindingKind::SymbolSectionIndexOutOfRange {
+                        section_index: *section_index,
+                        symbol_index,
+                        st_shndx: symbol.st_shndx,
+                    },
+                    format!(
+                        "Symbol `{}` of section `{}` has an `st_shndx` of `{}` but there are only `{}` sections",
+                        symbol_index, section_index, symbol.st_shndx, section_count
+                    ),
+                );
+            }
+        }
+    }
+}
+
+fn check_dynamic(input: &ValidationInput, findings: &mut Vec<Finding>) {
+    for entry in input.dynamic {
+        if entry.d_tag == DT_NULL {
+            break;
+        }
+
+        let is_address = matches!(
+            entry.d_tag,
+            DT_PLTGOT
+                | DT_HASH
+                | DT_STRTAB
+                | DT_SYMTAB
+                | DT_RELA
+                | DT_INIT
+                | DT_FINI
+                | DT_REL
+                | DT_JMPREL
+                | DT_INIT_ARRAY
+                | DT_FINI_ARRAY
+                | DT_PREINIT_ARRAY
+                | DT_RELR
+                | DT_GNU_HASH
+                | DT_VERSYM
+                | DT_VERDEF
+                | DT_VERNEED
+        );
+
+        if is_address && entry.d_val != 0 && !is_mapped(input, entry.d_val, 0) {
+            push(
+                findings,
+                Severity::Error,
+                FindingKind::UnmappedDynamicAddress {
+                    d_tag: entry.d_tag,
+                    address: entry.d_val,
+                },
+                format!(
+                    "`{}` has an address of `0x{:x}` which isn't mapped by any `PT_LOAD` segment",
+                    dt_to_str(entry.d_tag),
+                    entry.d_val
+                ),
+            );
+        }
+    }
+}
+
+fn check_hardening(input: &ValidationInput, findings: &mut Vec<Finding>) {
+    if input.header.e_type != ET_EXEC && input.header.e_type != ET_DYN {
+        return;
+    }
+
+    for (index, program_header) in load_segments(input) {
+        if program_header
+            .p_flags
+            .contains(PFlags::WRITE | PFlags::EXECUTE)
+        {
+            push(
+                findings,
+                Severity::Warning,
+                FindingKind::WritableExecutableSegment { index },
+                format!(
+                    "`PT_LOAD` segment `{}` is both writable and executable",
+                    index
+                ),
+            );
+        }
+    }
+
+    match input
+        .program_headers
+        .iter()
+        .position(|program_header| program_header.p_type == PT_GNU_STACK)
+    {
+        Some(index)
+            if input.program_headers[index]
+                .p_flags
+                .contains(PFlags::EXECUTE) =>
+        {
+            push(
+                findings,
+                Severity::Warning,
+                FindingKind::ExecutableStack { index },
+                format!(
+                    "`PT_GNU_STACK` segment `{}` makes the stack executable",
+                    index
+                ),
+            )
+        }
+        Some(_) => {}
+        None => push(
+            findings,
+            Severity::Warning,
+            FindingKind::MissingGnuStack,
+            "No `PT_GNU_STACK` segment, the stack may default to executable".to_owned(),
+        ),
+    }
+
+    let is_dynamic = input
+        .program_headers
+        .iter()
+        .any(|program_header| program_header.p_type == PT_DYNAMIC);
+    let has_relro = input
+        .program_headers
+        .iter()
+        .any(|program_header| program_header.p_type == PT_GNU_RELRO);
+
+    if is_dynamic && !has_relro {
+        push(
+            findings,
+            Severity::Info,
+            FindingKind::MissingGnuRelro,
+            "Dynamically linked file without a `PT_GNU_RELRO` segment".to_owned(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf::{ET_REL, SHT_PROGBITS};
+
+    fn load(p_flags: PFlags, p_offset: u64, p_vaddr: u64, p_memsz: u64) -> ProgramHeader {
+        ProgramHeader {
+            p_type: PT_LOAD,
+            p_flags,
+            p_offset,
+            p_vaddr,
+            p_paddr: p_vaddr,
+            p_filesz: p_memsz,
+            p_memsz,
+            p_align: 0x1000,
+        }
+    }
+
+    fn section(
+        sh_type: u32,
+        sh_flags: SHFlags,
+        sh_addr: u64,
+        sh_size: u64,
+        sh_link: u32,
+    ) -> SectionHeader {
+        SectionHeader {
+            sh_name: 0,
+            sh_type,
+            sh_flags,
+            sh_addr,
+            sh_offset: sh_addr,
+            sh_size,
+            sh_link,
+            sh_info: 0,
+            sh_addralign: 1,
+            sh_entsize: 0,
+        }
+    }
+
+    #[test]
+    fn report_findings() {
+        let header = Header {
+            e_ident: [0; 16],
+            e_type: ET_DYN,
+            e_machine: 0,
+            e_version: 1,
+            e_entry: 0,
+            e_phoff: 0,
+            e_shoff: 0,
+            e_flags: 0,
+            e_ehsize: 0,
+            e_phentsize: 0,
+            e_phnum: 2,
+            e_shentsize: 0,
+            e_shnum: 3,
+            e_shstrndx: 0,
+        };
+        let program_headers = vec![
+            load(PFlags::READ | PFlags::EXECUTE, 0, 0, 0x2000),
+            load(
+                PFlags::READ | PFlags::WRITE | PFlags::EXECUTE,
+                0x1800,
+                0x1800,
+                0x1000,
+            ),
+        ];
+        let section_headers = vec![
+            section(SHT_NULL, SHFlags::empty(), 0, 0, 0),
+            // `.dynsym` linked to itself rather than a string table
+            section(SHT_DYNSYM, SHFlags::ALLOC, 0x100, 0x30, 1),
+            section(SHT_PROGBITS, SHFlags::ALLOC, 0x5000, 0x10, 0),
+        ];
+        let symbols = vec![Sym {
+            st_name: 0,
+            st_info: 0,
+            st_other: 0,
+            st_shndx: 7,
+            st_value: 0,
+            st_size: 0,
+        }];
+        let dynamic = vec![
+            Dyn {
+                d_tag: DT_STRTAB,
+                d_val: 0x9000,
+            },
+            Dyn {
+                d_tag: DT_NULL,
+                d_val: 0,
+            },
+        ];
+
+        let findings = validate_parsed(&ValidationInput {
+            header: &header,
+            program_headers: &program_headers,
+            section_headers: &section_headers,
+            symbol_tables: vec![(1, &symbols)],
+            dynamic: &dynamic,
+        });
+        let kinds: Vec<&FindingKind> = findings.iter().map(|finding| &finding.kind).collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                &FindingKind::OverlappingLoadSegments {
+                    first: 0,
+                    second: 1
+                },
+                &FindingKind::SectionOutsideSegments { index: 2 },
+                &FindingKind::InvalidSectionLink {
+                    index: 1,
+                    sh_link: 1
+                },
+                &FindingKind::SymbolSectionIndexOutOfRange {
+                    section_index: 1,
+                    symbol_index: 0,
+                    st_shndx: 7
+                },
+                &FindingKind::UnmappedDynamicAddress {
+                    d_tag: DT_STRTAB,
+                    address: 0x9000
+                },
+                &FindingKind::WritableExecutableSegment { index: 1 },
+                &FindingKind::MissingGnuStack,
+            ]
+        );
+        assert_eq!(findings[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn report_invalid_section_info() {
+        let header = Header {
+            e_ident: [0; 16],
+            e_type: ET_REL,
+            e_machine: 0,
+            e_version: 1,
+            e_entry: 0,
+            e_phoff: 0,
+            e_shoff: 0,
+            e_flags: 0,
+            e_ehsize: 0,
+            e_phentsize: 0,
+            e_phnum: 0,
+            e_shentsize: 0,
+            e_shnum: 7,
+            e_shstrndx: 0,
+        };
+        let mut section_headers = vec![
+            section(SHT_NULL, SHFlags::empty(), 0, 0, 0),
+            section(SHT_PROGBITS, SHFlags::ALLOC, 0, 0x10, 0),
+            // `.symtab` with 2 symbols claiming 3 locals
+            section(SHT_SYMTAB, SHFlags::empty(), 0, 0x30, 3),
+            section(SHT_STRTAB, SHFlags::empty(), 0, 0x10, 0),
+            // `.rela.text` relocating itself
+            section(SHT_RELA, SHFlags::INFO_LINK, 0, 0x18, 2),
+            // `.rela.data` relocating a section that doesn't exist
+            section(SHT_RELA, SHFlags::INFO_LINK, 0, 0x18, 2),
+            // A group signature past the end of `.symtab`
+            section(SHT_GROUP, SHFlags::empty(), 0, 0x8, 2),
+        ];
+        section_headers[2].sh_entsize = 0x18;
+        section_headers[2].sh_info = 3;
+        section_headers[4].sh_info = 4;
+        section_headers[5].sh_info = 9;
+        section_headers[6].sh_info = 2;
+
+        let findings = validate_parsed(&ValidationInput {
+            header: &header,
+            program_headers: &[],
+            section_headers: &section_headers,
+            symbol_tables: Vec::new(),
+            dynamic: &[],
+        });
+        let kinds: Vec<&FindingKind> = findings
+            .iter()
+            .map(|finding| &finding.kind)
+            .filter(|kind| matches!(kind, FindingKind::InvalidSectionInfo { .. }))
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                &FindingKind::InvalidSectionInfo {
+                    index: 2,
+                    sh_info: 3
+                },
+                &FindingKind::InvalidSectionInfo {
+                    index: 4,
+                    sh_info: 4
+                },
+                &FindingKind::InvalidSectionInfo {
+                    index: 5,
+                    sh_info: 9
+                },
+                &FindingKind::InvalidSectionInfo {
+                    index: 6,
+                    sh_info: 2
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn report_unreadable_tables() {
+        let mut elf = vec![0u8; 0xc0];
+        elf[..8].copy_from_slice(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0]);
+        // `e_type`, `e_machine` and `e_version`
+        elf[16..24].copy_from_slice(&[1, 0, 0x3e, 0, 1, 0, 0, 0]);
+        // `e_shoff`
+        elf[40] = 0x40;
+        // `e_ehsize`, `e_phentsize`, `e_phnum`, `e_shentsize` and `e_shnum`
+        elf[52..62].copy_from_slice(&[0x40, 0, 0x38, 0, 0, 0, 0x40, 0, 2, 0]);
+        // `SHT_SYMTAB` at offset `0x1000`, past the end of the file
+        elf[0x84] = SHT_SYMTAB as u8;
+        elf[0x99] = 0x10;
+        elf[0xa0] = 0x18;
+        elf[0xb8] = 0x18;
+
+        let mut cursor = std::io::Cursor::new(elf);
+        let mut reader = crate::elf::elf64::IoReader::new(&mut cursor, scroll::LE).unwrap();
+        let findings = validate(&mut reader).unwrap();
+
+        assert_eq!(
+            findings[0].kind,
+            FindingKind::UnreadableSection { index: 1 }
+        );
+        assert_eq!(findings[0].severity, Severity::Error);
+    }
+}
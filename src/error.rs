@@ -19,17 +19,192 @@ use std::error;
 use std::fmt;
 use std::io;
 
+/// What was being parsed when a `Malformed`, `InvalidArguments`, `TooManyArrayItems`, `InvalidMagicNumber` or
+/// `InvalidVersionNumber` error happened
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Details {
+    pub message: String,
+    /// The structure being parsed, e.g. `dex::ClassDataItem` or `elf::Note`
+    pub structure: Option<&'static str>,
+    /// The field of `structure` that had a bad value
+    pub field: Option<&'static str>,
+    /// Byte offset of `structure` from the start of the file
+    pub offset: Option<u64>,
+}
+
+impl Details {
+    pub fn new(message: impl Into<String>) -> Self {
+        Details {
+            message: message.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn structure(mut self, structure: &'static str) -> Self {
+        self.structure = Some(structure);
+        self
+    }
+
+    pub fn field(mut self, field: &'static str) -> Self {
+        self.field = Some(field);
+        self
+    }
+
+    pub fn offset(mut self, offset: u64) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+}
+
+impl From<String> for Details {
+    fn from(value: String) -> Self {
+        Details::new(value)
+    }
+}
+
+impl From<&str> for Details {
+    fn from(value: &str) -> Self {
+        Details::new(value)
+    }
+}
+
+impl fmt::Display for Details {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+
+        match (self.structure, self.field) {
+            (Some(structure), Some(field)) => write!(f, " (`{}.{}`", structure, field)?,
+            (Some(structure), None) => write!(f, " (`{}`", structure)?,
+            (None, Some(field)) => write!(f, " (`{}`", field)?,
+            (None, None) => {
+                if let Some(offset) = self.offset {
+                    write!(f, " (at offset `0x{:x}`)", offset)?;
+                }
+
+                return Ok(());
+            }
+        }
+
+        match self.offset {
+            Some(offset) => write!(f, " at offset `0x{:x}`)", offset),
+            None => write!(f, ")"),
+        }
+    }
+}
+
+/// One step of the path to a nested structure, e.g. `class_defs[12]`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContextFrame {
+    /// Name of the list or field that was being parsed, e.g. `class_defs` or `code_item`
+    pub name: &'static str,
+    /// Index into `name` if it is a list
+    pub index: Option<usize>,
+    /// Byte offset of the item from the start of the file, if known
+    pub offset: Option<u64>,
+}
+
+impl ContextFrame {
+    pub fn new(name: &'static str) -> Self {
+        ContextFrame {
+            name,
+            index: None,
+            offset: None,
+        }
+    }
+
+    pub fn index(mut self, index: usize) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    pub fn offset(mut self, offset: u64) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+}
+
+impl fmt::Display for ContextFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+
+        if let Some(index) = self.index {
+            write!(f, "[{}]", index)?;
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub enum Error {
     IOError(io::Error),
     ScrollError(scroll::Error),
     MUTF8Error(mutf8::Error),
 
-    InvalidMagicNumber(Vec<u8>),
-    InvalidVersionNumber(String),
-    Malformed(String),
-    InvalidArguments(String),
-    TooManyArrayItems(String),
+    InvalidMagicNumber(Details),
+    InvalidVersionNumber(Details),
+    Malformed(Details),
+    InvalidArguments(Details),
+    TooManyArrayItems(Details),
+
+    /// The error happened while parsing the nested structure described by the `ContextFrame`
+    Context(ContextFrame, Box<Error>),
+}
+
+impl Error {
+    /// Wraps this error in another `ContextFrame`, making it the outermost frame
+    pub fn context(self, frame: ContextFrame) -> Self {
+        Error::Context(frame, Box::new(self))
+    }
+
+    /// Every `ContextFrame` wrapping the error, outermost first
+    ///
+    /// E.g. `class_defs[12] → class_data → direct_methods[3] → code_item`.
+    pub fn context_chain(&self) -> Vec<&ContextFrame> {
+        let mut result: Vec<&ContextFrame> = Vec::new();
+        let mut current = self;
+
+        while let Error::Context(frame, inner) = current {
+            result.push(frame);
+            current = inner;
+        }
+
+        result
+    }
+
+    /// The error without any `Context` wrapping it
+    pub fn root_cause(&self) -> &Error {
+        let mut current = self;
+
+        while let Error::Context(_, inner) = current {
+            current = inner;
+        }
+
+        current
+    }
+
+    pub fn details(&self) -> Option<&Details> {
+        match self.root_cause() {
+            Error::Malformed(details)
+            | Error::InvalidArguments(details)
+            | Error::TooManyArrayItems(details)
+            | Error::InvalidMagicNumber(details)
+            | Error::InvalidVersionNumber(details) => Some(details),
+            _ => None,
+        }
+    }
+
+    /// The most precise offset known for the error, from its `Details` or the innermost `ContextFrame` with one
+    pub fn offset(&self) -> Option<u64> {
+        self.details()
+            .and_then(|details| details.offset)
+            .or_else(|| {
+                self.context_chain()
+                    .iter()
+                    .rev()
+                    .find_map(|frame| frame.offset)
+            })
+    }
 }
 
 impl error::Error for Error {
@@ -38,6 +213,7 @@ impl error::Error for Error {
             Error::IOError(io_error) => Some(io_error),
             Error::ScrollError(scroll_error) => Some(scroll_error),
             Error::MUTF8Error(mutf8_error) => Some(mutf8_error),
+            Error::Context(_, inner) => Some(inner.as_ref()),
             _ => None,
         }
     }
@@ -49,17 +225,37 @@ impl fmt::Display for Error {
             Error::IOError(io_error) => write!(f, "{}", io_error),
             Error::ScrollError(scroll_error) => write!(f, "{}", scroll_error),
             Error::MUTF8Error(mutf8_error) => write!(f, "{}", mutf8_error),
-            Error::InvalidMagicNumber(value) => write!(f, "Invalid magic number: 0x{:x?}", *value),
-            Error::InvalidVersionNumber(expected_version) => {
-                write!(f, "Invalid version number: expected {}", expected_version)
+            Error::InvalidMagicNumber(details) => write!(f, "Invalid magic number: {}", details),
+            Error::InvalidVersionNumber(details) => {
+                write!(f, "Invalid version number: {}", details)
+            }
+            Error::Malformed(details) => write!(f, "Malformed: {}", details),
+            Error::InvalidArguments(details) => write!(f, "Invalid arguments: {}", details),
+            Error::TooManyArrayItems(details) => write!(f, "Too many items: {}", details),
+            Error::Context(frame, inner) => {
+                write!(f, "{}", frame)?;
+
+                if matches!(inner.as_ref(), Error::Context(..)) {
+                    write!(f, " → {}", inner)
+                } else {
+                    write!(f, ": {}", inner)
+                }
             }
-            Error::Malformed(message) => write!(f, "Malformed: {}", message),
-            Error::InvalidArguments(message) => write!(f, "Invalid arguments: {}", message),
-            Error::TooManyArrayItems(message) => write!(f, "Too many items: {}", message),
         }
     }
 }
 
+/// Adds a `ContextFrame` to the error of a `Result`
+pub(crate) trait ResultExt<T> {
+    fn context(self, frame: impl FnOnce() -> ContextFrame) -> Result<T, Error>;
+}
+
+impl<T, E: Into<Error>> ResultExt<T> for Result<T, E> {
+    fn context(self, frame: impl FnOnce() -> ContextFrame) -> Result<T, Error> {
+        self.map_err(|error| error.into().context(frame()))
+    }
+}
+
 impl From<io::Error> for Error {
     fn from(value: io::Error) -> Self {
         Self::IOError(value)
@@ -77,3 +273,36 @@ impl From<mutf8::Error> for Error {
         Self::MUTF8Error(value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_context_chain() {
+        let result: Result<(), Error> = Err(Error::Malformed(
+            Details::new("Invalid `tries_size`")
+                .structure("dex::CodeItem")
+                .field("tries_size")
+                .offset(0x1234),
+        ));
+        let error = result
+            .context(|| ContextFrame::new("code_item"))
+            .context(|| ContextFrame::new("direct_methods").index(3))
+            .context(|| ContextFrame::new("class_data").offset(0x1000))
+            .context(|| ContextFrame::new("class_defs").index(12))
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "class_defs[12] → class_data → direct_methods[3] → code_item: Malformed: Invalid `tries_size` (`dex::CodeItem.tries_size` at offset `0x1234`)"
+        );
+        assert_eq!(error.context_chain().len(), 4);
+        assert!(matches!(error.root_cause(), Error::Malformed(_)));
+        assert_eq!(
+            error.details().and_then(|details| details.structure),
+            Some("dex::CodeItem")
+        );
+        assert_eq!(error.offset(), Some(0x1234));
+    }
+}
@@ -16,6 +16,10 @@
 
 mod error;
 pub use error::*;
+mod limits;
+pub use limits::*;
+mod detect;
+pub use detect::*;
 
 pub mod leb128;
 pub mod mutf8;
@@ -26,6 +30,7 @@ pub mod vdex;
 
 pub(crate) mod compact_offset_table;
 pub(crate) mod stringable_consts_blocks;
+pub(crate) mod utils;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,209 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::{Details, Error};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Resource limits honoured by the ELF, Dex and VDex readers
+///
+/// Sizes and counts read from a file are checked against these before anything is allocated, so a hostile file can't
+/// make a reader allocate more than `max_allocation` bytes at once or recurse deeper than `max_recursion_depth`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// Largest buffer, in bytes, a reader will allocate for a single read (section bytes, Dex data section, etc.)
+    pub max_allocation: u64,
+    /// Largest number of items a reader will read into a single list (symbols, string ids, etc.)
+    pub max_items: u64,
+    /// Deepest nesting of recursive structures, e.g. Dex encoded arrays and annotations inside each other
+    pub max_recursion_depth: u32,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_allocation: 1 << 30,
+            max_items: 1 << 24,
+            max_recursion_depth: 64,
+        }
+    }
+}
+
+impl Limits {
+    /// No limits other than what fits in `usize`, only use this for trusted inputs
+    pub const UNLIMITED: Limits = Limits {
+        max_allocation: u64::MAX,
+        max_items: u64::MAX,
+        max_recursion_depth: u32::MAX,
+    };
+
+    /// Checks a buffer of `size` bytes can be allocated, `what` describes the buffer for the error message
+    pub(crate) fn check_allocation(&self, size: u64, what: &str) -> Result<usize> {
+        if size > self.max_allocation {
+            return Err(Error::TooManyArrayItems(
+                Details::new(format!(
+                    "Attempted to allocate `{}` bytes for {}, the limit is `{}` bytes",
+                    size, what, self.max_allocation
+                ))
+                .structure("Limits")
+                .field("max_allocation"),
+            ));
+        }
+
+        usize::try_from(size).map_err(|_| {
+            Error::TooManyArrayItems(
+                Details::new(format!(
+                    "Attempted to allocate `{}` bytes for {}, `usize` can only hold `{}`",
+                    size,
+                    what,
+                    usize::MAX
+                ))
+                .structure("Limits"),
+            )
+        })
+    }
+
+    /// Checks a list of `count` items of type `T` can be allocated, `what` describes the list for the error message
+    pub(crate) fn check_items<T>(&self, count: u64, what: &str) -> Result<usize> {
+        if count > self.max_items {
+            return Err(Error::TooManyArrayItems(
+                Details::new(format!(
+                    "Attempted to read `{}` items for {}, the limit is `{}` items",
+                    count, what, self.max_items
+                ))
+                .structure("Limits")
+                .field("max_items"),
+            ));
+        }
+
+        self.check_allocation(count.saturating_mul(std::mem::size_of::<T>() as u64), what)?;
+
+        usize::try_from(count).map_err(|_| {
+            Error::TooManyArrayItems(
+                Details::new(format!(
+                    "Attempted to read `{}` items for {}, `usize` can only hold `{}`",
+                    count,
+                    what,
+                    usize::MAX
+                ))
+                .structure("Limits"),
+            )
+        })
+    }
+
+    /// Creates a `Vec` with room for `count` items after checking them with `check_items`
+    pub(crate) fn vec_with_capacity<T>(&self, count: u64, what: &str) -> Result<Vec<T>> {
+        Ok(Vec::with_capacity(self.check_items::<T>(count, what)?))
+    }
+
+    /// Checks a recursive read at `depth` (starting at `1`) isn't too deep
+    pub(crate) fn check_recursion(&self, depth: u32, what: &str) -> Result<()> {
+        if depth > self.max_recursion_depth {
+            Err(Error::Malformed(
+                Details::new(format!(
+                    "{} are nested more than `{}` levels deep",
+                    what, self.max_recursion_depth
+                ))
+                .structure("Limits")
+                .field("max_recursion_depth"),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf::{ProgramHeader, SHFlags, SectionHeader, SHT_HASH, SHT_NOTE, SHT_PROGBITS};
+    use std::io::{BufReader, Cursor};
+
+    fn section_header(sh_type: u32, sh_offset: u64, sh_size: u64) -> SectionHeader {
+        SectionHeader {
+            sh_name: 0,
+            sh_type,
+            sh_flags: SHFlags::empty(),
+            sh_addr: 0,
+            sh_offset,
+            sh_size,
+            sh_link: 0,
+            sh_info: 0,
+            sh_addralign: 1,
+            sh_entsize: 0,
+        }
+    }
+
+    #[test]
+    fn reject_hostile_inputs() {
+        let mut bytes = vec![0u8; 64];
+        // Nameless note claiming a descriptor far larger than its section
+        bytes[8..20].copy_from_slice(&[0, 0, 0, 0, 0xf0, 0xff, 0xff, 0xff, 1, 0, 0, 0]);
+        // Hash table claiming `u32::MAX` buckets and chains
+        bytes[24..32].copy_from_slice(&[0xff; 8]);
+
+        let mut cursor = Cursor::new(bytes);
+        let mut reader = crate::elf::elf64::IoReader::with_limits(
+            &mut cursor,
+            scroll::LE,
+            Limits {
+                max_allocation: 16,
+                ..Limits::default()
+            },
+        )
+        .unwrap();
+
+        assert!(matches!(
+            reader.read_note_section(&section_header(SHT_NOTE, 8, 16)),
+            Err(Error::Malformed(_))
+        ));
+        assert!(matches!(
+            reader.read_hash_section(&section_header(SHT_HASH, 24, 16)),
+            Err(Error::Malformed(_))
+        ));
+        assert!(matches!(
+            reader.get_section_bytes(&section_header(SHT_PROGBITS, 0, 32)),
+            Err(Error::TooManyArrayItems(_))
+        ));
+        // A program at offset `0` used to trip an assert
+        let program_header = ProgramHeader {
+            p_type: crate::elf::PT_LOAD,
+            p_flags: crate::elf::PFlags::empty(),
+            p_offset: 0,
+            p_vaddr: 0,
+            p_paddr: 0,
+            p_filesz: 8,
+            p_memsz: 8,
+            p_align: 1,
+        };
+        assert_eq!(reader.get_program_bytes(&program_header).unwrap().len(), 8);
+
+        // Encoded arrays nested inside each other 100 times, each is a size of `1` followed by a `VALUE_ARRAY`
+        let mut dex_bytes = vec![0u8];
+        for _ in 0..100 {
+            dex_bytes.extend_from_slice(&[1, 0x1c]);
+        }
+        dex_bytes.push(0);
+
+        let mut buf_reader = BufReader::new(Cursor::new(dex_bytes));
+        let mut dex_reader = crate::dex::IoReader::new(&mut buf_reader, 0).unwrap();
+
+        assert!(matches!(
+            dex_reader.read_encoded_array_item_at(1),
+            Err(Error::Malformed(_))
+        ));
+    }
+}
@@ -0,0 +1,57 @@
+/*
+ * Copyright 2023 Ellie Reiselt
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Readers shared by the byte oriented formats parsed from a section's bytes
+
+use crate::{Details, Error};
+use std::io::{Cursor, Read};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Reads a nul terminated string from the cursor, invalid UTF-8 is replaced with `U+FFFD`
+///
+/// Used for DWARF strings, `.eh_frame` augmentation strings and build attributes.
+pub(crate) fn read_cstring(cursor: &mut Cursor<&[u8]>) -> Result<String> {
+    let bytes: &[u8] = cursor.get_ref();
+    let start = (cursor.position() as usize).min(bytes.len());
+
+    match bytes[start..].iter().position(|byte| *byte == 0) {
+        Some(length) => {
+            cursor.set_position((start + length + 1) as u64);
+            Ok(String::from_utf8_lossy(&bytes[start..start + length]).into_owned())
+        }
+        None => Err(Error::Malformed(Details::new(format!(
+            "String at offset `{}` is missing its nul terminator",
+            start
+        )))),
+    }
+}
+
+/// Reads `length` bytes, making sure `length` is within the cursor's bounds before allocating
+pub(crate) fn read_block(cursor: &mut Cursor<&[u8]>, length: u64) -> Result<Vec<u8>> {
+    let remaining = (cursor.get_ref().len() as u64).saturating_sub(cursor.position());
+
+    if length > remaining {
+        return Err(Error::Malformed(Details::new(format!(
+            "Block of `{}` bytes is larger than the `{}` remaining bytes",
+            length, remaining
+        ))));
+    }
+
+    let mut result: Vec<u8> = vec![0; length as usize];
+    cursor.read_exact(&mut result)?;
+    Ok(result)
+}
@@ -16,8 +16,12 @@
 
 use crate::dex::Dex;
 use crate::dex::Header as DexHeader;
+use crate::error::ResultExt;
 use crate::leb128;
+use crate::ContextFrame;
+use crate::Details;
 use crate::Error;
+use crate::Limits;
 use scroll::Endian;
 use scroll::IOread;
 use std::borrow::Cow;
@@ -56,16 +60,22 @@ pub struct IoReader<'a, TRead: IOread<Endian> + Seek> {
     pub reader: &'a mut BufReader<TRead>,
     pub endianness: Endian,
     pub stream_len: u64,
+    pub limits: Limits,
 }
 
 impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
     pub fn new(reader: &'a mut BufReader<TRead>) -> Result<Self> {
+        Self::with_limits(reader, Limits::default())
+    }
+
+    pub fn with_limits(reader: &'a mut BufReader<TRead>, limits: Limits) -> Result<Self> {
         let stream_len = reader.seek(SeekFrom::End(0))?;
         reader.seek(SeekFrom::Current(0))?;
         Ok(Self {
             reader,
             endianness: Endian::Little,
             stream_len,
+            limits,
         })
     }
 
@@ -75,9 +85,25 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
         let result = self.reader.ioread_with::<Header>(self.endianness)?;
 
         if result.magic != MAGIC {
-            Err(Error::InvalidMagicNumber(result.magic.to_vec()))
+            Err(Error::InvalidMagicNumber(
+                Details::new(format!(
+                    "Expected `{:02x?}` for the VDex magic number, found `{:02x?}`",
+                    MAGIC, result.magic
+                ))
+                .structure("vdex::vdex006::Header")
+                .field("magic")
+                .offset(0),
+            ))
         } else if result.version != VERSION {
-            Err(Error::InvalidVersionNumber(format!("{:x?}", VERSION)))
+            Err(Error::InvalidVersionNumber(
+                Details::new(format!(
+                    "Expected `{:02x?}` for the VDex version, found `{:02x?}`",
+                    VERSION, result.version
+                ))
+                .structure("vdex::vdex006::Header")
+                .field("version")
+                .offset(4),
+            ))
         } else {
             Ok(result)
         }
@@ -89,7 +115,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
             let offset = std::mem::size_of::<Header>() as u64;
             self.reader.seek(SeekFrom::Start(offset))?;
 
-            let mut result: Vec<u32> = Vec::with_capacity(size);
+            let mut result: Vec<u32> = self
+                .limits
+                .vec_with_capacity(size as u64, "dex checksums")?;
 
             for _ in 0..size {
                 result.push(self.reader.ioread_with::<u32>(self.endianness)?);
@@ -110,17 +138,24 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
             self.reader.seek(SeekFrom::Start(offset))?;
 
-            let mut result: Vec<Cow<'static, [u8]>> = Vec::with_capacity(size);
+            let mut result: Vec<Cow<'static, [u8]>> =
+                self.limits.vec_with_capacity(size as u64, "dex sections")?;
 
-            for _ in 0..size {
+            for dex_index in 0..size {
                 let dex_index_offset = self.reader.seek(SeekFrom::Current(0))?;
-                let dex_header = DexHeader::read_from_buf_reader(self.reader)?;
+                let dex_header = DexHeader::read_from_buf_reader(self.reader).context(|| {
+                    ContextFrame::new("dex_files")
+                        .index(dex_index)
+                        .offset(dex_index_offset)
+                })?;
 
                 // Jump back to before the header so we can get the bytes for the whole section...
                 self.reader.seek(SeekFrom::Start(dex_index_offset))?;
 
-                let mut bytes: Vec<u8> = Vec::with_capacity(dex_header.file_size as usize);
-                bytes.resize(dex_header.file_size as usize, 0);
+                let bytes_len = self
+                    .limits
+                    .check_allocation(u64::from(dex_header.file_size), "a dex section")?;
+                let mut bytes: Vec<u8> = vec![0; bytes_len];
 
                 self.reader.read_exact(&mut bytes)?;
 
@@ -142,8 +177,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
             self.reader.seek(SeekFrom::Start(offset))?;
 
-            let mut result: Vec<DexFileDeps> =
-                Vec::with_capacity(header.number_of_dex_files as usize);
+            let mut result: Vec<DexFileDeps> = self
+                .limits
+                .vec_with_capacity(u64::from(header.number_of_dex_files), "verifier deps")?;
 
             for _ in 0..header.number_of_dex_files {
                 let strings = self.read_verifier_deps_strings()?;
@@ -177,7 +213,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
     fn read_verifier_deps_strings(&mut self) -> Result<Vec<CString>> {
         let count: u32 = leb128::decode_uleb128(self.reader)?;
-        let mut result: Vec<CString> = Vec::with_capacity(count as usize);
+        let mut result: Vec<CString> = self
+            .limits
+            .vec_with_capacity(u64::from(count), "verifier deps strings")?;
 
         for _ in 0..count {
             let mut raw_string: Vec<u8> = Vec::new();
@@ -187,10 +225,15 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
             // NOTE: Unlike in Dex, VDex version 006 stores strings as nul-terminated C strings... this shouldn't fail.
             let string = match CString::from_vec_with_nul(raw_string) {
                 Ok(string) => string,
-                Err(error) => return Err(Error::Malformed(format!(
-                    "VDex file (version 006) contained a malformed string in its `verifier_deps` section, raw error: {}", 
-                    error
-                ))),
+                Err(error) => return Err(Error::Malformed(
+                    Details::new(format!(
+                        "VDex file (version 006) contained a malformed string in its `verifier_deps` section, raw error: {}", 
+                        error
+                    ))
+                    .structure("vdex::vdex006::DexFileDeps")
+                    .field("strings")
+                    .offset(self.reader.stream_position()? - error.as_bytes().len() as u64),
+                )),
             };
 
             result.push(string);
@@ -201,7 +244,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
     fn read_verifier_deps_type_asignability_set(&mut self) -> Result<Vec<TypeAssignability>> {
         let count: u32 = leb128::decode_uleb128(self.reader)?;
-        let mut result: Vec<TypeAssignability> = Vec::with_capacity(count as usize);
+        let mut result: Vec<TypeAssignability> = self
+            .limits
+            .vec_with_capacity(u64::from(count), "type assignabilities")?;
 
         for _ in 0..count {
             let destination_index: u32 = leb128::decode_uleb128(self.reader)?;
@@ -217,7 +262,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
     fn read_verifier_deps_class_resolution_set(&mut self) -> Result<Vec<ClassResolution>> {
         let count: u32 = leb128::decode_uleb128(self.reader)?;
-        let mut result: Vec<ClassResolution> = Vec::with_capacity(count as usize);
+        let mut result: Vec<ClassResolution> = self
+            .limits
+            .vec_with_capacity(u64::from(count), "class resolutions")?;
 
         for _ in 0..count {
             let type_index: u16 =
@@ -235,7 +282,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
     fn read_verifier_deps_field_resolution_set(&mut self) -> Result<Vec<FieldResolution>> {
         let count: u32 = leb128::decode_uleb128(self.reader)?;
-        let mut result: Vec<FieldResolution> = Vec::with_capacity(count as usize);
+        let mut result: Vec<FieldResolution> = self
+            .limits
+            .vec_with_capacity(u64::from(count), "field resolutions")?;
 
         for _ in 0..count {
             let field_index: u32 = leb128::decode_uleb128(self.reader)?;
@@ -254,7 +303,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
     fn read_verifier_deps_method_resolution_set(&mut self) -> Result<Vec<MethodResolution>> {
         let count: u32 = leb128::decode_uleb128(self.reader)?;
-        let mut result: Vec<MethodResolution> = Vec::with_capacity(count as usize);
+        let mut result: Vec<MethodResolution> = self
+            .limits
+            .vec_with_capacity(u64::from(count), "method resolutions")?;
 
         for _ in 0..count {
             let method_index: u32 = leb128::decode_uleb128(self.reader)?;
@@ -273,7 +324,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
     fn read_verifier_deps_unverified_classes(&mut self) -> Result<Vec<u16>> {
         let count: u32 = leb128::decode_uleb128(self.reader)?;
-        let mut result: Vec<u16> = Vec::with_capacity(count as usize);
+        let mut result: Vec<u16> = self
+            .limits
+            .vec_with_capacity(u64::from(count), "unverified classes")?;
 
         for _ in 0..count {
             // NOTE: You _probably_ could do `decode_uleb128::<u16>` but I'm too nervous to do that.
@@ -283,9 +336,15 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
             let value: u16 = match u16::try_from(value) {
                 Ok(value) => value,
                 Err(_) => {
-                    return Err(Error::Malformed(format!(
-                        "VDex verifier deps had invalid unverified `class_index`"
-                    )))
+                    return Err(Error::Malformed(
+                        Details::new("VDex verifier deps had invalid unverified `class_index`")
+                            .structure("vdex::vdex006::DexFileDeps")
+                            .field("unverified_classes")
+                            .offset(
+                                self.reader.stream_position()?
+                                    - leb128::encode_uleb128(value).len() as u64,
+                            ),
+                    ))
                 }
             };
             result.push(value);
@@ -326,9 +385,11 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
                             if let Some(_) = &method.code {
                                 let quickening_size =
                                     self.reader.ioread_with::<u32>(self.endianness)?;
-                                let mut quickening_info: Vec<u8> =
-                                    Vec::with_capacity(quickening_size as usize);
-                                quickening_info.resize(quickening_size as usize, 0);
+                                let quickening_info_len = self.limits.check_allocation(
+                                    u64::from(quickening_size),
+                                    "quickening info",
+                                )?;
+                                let mut quickening_info: Vec<u8> = vec![0; quickening_info_len];
                                 self.reader.read_exact(&mut quickening_info)?;
 
                                 direct_methods.push(Some(CodeItemQuickening {
@@ -343,9 +404,11 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
                             if let Some(_) = &method.code {
                                 let quickening_size =
                                     self.reader.ioread_with::<u32>(self.endianness)?;
-                                let mut quickening_info: Vec<u8> =
-                                    Vec::with_capacity(quickening_size as usize);
-                                quickening_info.resize(quickening_size as usize, 0);
+                                let quickening_info_len = self.limits.check_allocation(
+                                    u64::from(quickening_size),
+                                    "quickening info",
+                                )?;
+                                let mut quickening_info: Vec<u8> = vec![0; quickening_info_len];
                                 self.reader.read_exact(&mut quickening_info)?;
 
                                 virtual_methods.push(Some(CodeItemQuickening {
@@ -16,8 +16,12 @@
 
 use crate::dex::Dex;
 use crate::dex::Header as DexHeader;
+use crate::error::ResultExt;
 use crate::leb128;
+use crate::ContextFrame;
+use crate::Details;
 use crate::Error;
+use crate::Limits;
 use scroll::Endian;
 use scroll::IOread;
 use std::borrow::Cow;
@@ -61,16 +65,22 @@ pub struct IoReader<'a, TRead: IOread<Endian> + Seek> {
     pub reader: &'a mut BufReader<TRead>,
     pub endianness: Endian,
     pub stream_len: u64,
+    pub limits: Limits,
 }
 
 impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
     pub fn new(reader: &'a mut BufReader<TRead>) -> Result<Self> {
+        Self::with_limits(reader, Limits::default())
+    }
+
+    pub fn with_limits(reader: &'a mut BufReader<TRead>, limits: Limits) -> Result<Self> {
         let stream_len = reader.seek(SeekFrom::End(0))?;
         reader.seek(SeekFrom::Current(0))?;
         Ok(Self {
             reader,
             endianness: Endian::Little,
             stream_len,
+            limits,
         })
     }
 
@@ -80,9 +90,25 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
         let result = self.reader.ioread_with::<Header>(self.endianness)?;
 
         if result.magic != MAGIC {
-            Err(Error::InvalidMagicNumber(result.magic.to_vec()))
+            Err(Error::InvalidMagicNumber(
+                Details::new(format!(
+                    "Expected `{:02x?}` for the VDex magic number, found `{:02x?}`",
+                    MAGIC, result.magic
+                ))
+                .structure("vdex::vdex010::Header")
+                .field("magic")
+                .offset(0),
+            ))
         } else if result.version != VERSION {
-            Err(Error::InvalidVersionNumber(format!("{:x?}", VERSION)))
+            Err(Error::InvalidVersionNumber(
+                Details::new(format!(
+                    "Expected `{:02x?}` for the VDex version, found `{:02x?}`",
+                    VERSION, result.version
+                ))
+                .structure("vdex::vdex010::Header")
+                .field("version")
+                .offset(4),
+            ))
         } else {
             Ok(result)
         }
@@ -94,7 +120,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
             let offset = std::mem::size_of::<Header>() as u64;
             self.reader.seek(SeekFrom::Start(offset))?;
 
-            let mut result: Vec<u32> = Vec::with_capacity(size);
+            let mut result: Vec<u32> = self
+                .limits
+                .vec_with_capacity(size as u64, "dex checksums")?;
 
             for _ in 0..size {
                 result.push(self.reader.ioread_with::<u32>(self.endianness)?);
@@ -115,17 +143,24 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
             self.reader.seek(SeekFrom::Start(offset))?;
 
-            let mut result: Vec<Cow<'static, [u8]>> = Vec::with_capacity(size);
+            let mut result: Vec<Cow<'static, [u8]>> =
+                self.limits.vec_with_capacity(size as u64, "dex sections")?;
 
-            for _ in 0..size {
+            for dex_index in 0..size {
                 let dex_index_offset = self.reader.seek(SeekFrom::Current(0))?;
-                let dex_header = DexHeader::read_from_buf_reader(self.reader)?;
+                let dex_header = DexHeader::read_from_buf_reader(self.reader).context(|| {
+                    ContextFrame::new("dex_files")
+                        .index(dex_index)
+                        .offset(dex_index_offset)
+                })?;
 
                 // Jump back to before the header so we can get the bytes for the whole section...
                 self.reader.seek(SeekFrom::Start(dex_index_offset))?;
 
-                let mut bytes: Vec<u8> = Vec::with_capacity(dex_header.file_size as usize);
-                bytes.resize(dex_header.file_size as usize, 0);
+                let bytes_len = self
+                    .limits
+                    .check_allocation(u64::from(dex_header.file_size), "a dex section")?;
+                let mut bytes: Vec<u8> = vec![0; bytes_len];
 
                 self.reader.read_exact(&mut bytes)?;
 
@@ -147,8 +182,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
             self.reader.seek(SeekFrom::Start(offset))?;
 
-            let mut result: Vec<DexFileDeps> =
-                Vec::with_capacity(header.number_of_dex_files as usize);
+            let mut result: Vec<DexFileDeps> = self
+                .limits
+                .vec_with_capacity(u64::from(header.number_of_dex_files), "verifier deps")?;
 
             for _ in 0..header.number_of_dex_files {
                 let strings = self.read_verifier_deps_strings()?;
@@ -178,7 +214,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
     fn read_verifier_deps_strings(&mut self) -> Result<Vec<CString>> {
         let count: u32 = leb128::decode_uleb128(self.reader)?;
-        let mut result: Vec<CString> = Vec::with_capacity(count as usize);
+        let mut result: Vec<CString> = self
+            .limits
+            .vec_with_capacity(u64::from(count), "verifier deps strings")?;
 
         for _ in 0..count {
             let mut raw_string: Vec<u8> = Vec::new();
@@ -188,10 +226,15 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
             // NOTE: Unlike in Dex, VDex version 006 stores strings as nul-terminated C strings... this shouldn't fail.
             let string = match CString::from_vec_with_nul(raw_string) {
                 Ok(string) => string,
-                Err(error) => return Err(Error::Malformed(format!(
-                    "VDex file (version 006) contained a malformed string in its `verifier_deps` section, raw error: {}", 
-                    error
-                ))),
+                Err(error) => return Err(Error::Malformed(
+                    Details::new(format!(
+                        "VDex file (version 006) contained a malformed string in its `verifier_deps` section, raw error: {}", 
+                        error
+                    ))
+                    .structure("vdex::vdex010::DexFileDeps")
+                    .field("strings")
+                    .offset(self.reader.stream_position()? - error.as_bytes().len() as u64),
+                )),
             };
 
             result.push(string);
@@ -202,7 +245,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
     fn read_verifier_deps_type_asignability_set(&mut self) -> Result<Vec<TypeAssignability>> {
         let count: u32 = leb128::decode_uleb128(self.reader)?;
-        let mut result: Vec<TypeAssignability> = Vec::with_capacity(count as usize);
+        let mut result: Vec<TypeAssignability> = self
+            .limits
+            .vec_with_capacity(u64::from(count), "type assignabilities")?;
 
         for _ in 0..count {
             let destination_index: u32 = leb128::decode_uleb128(self.reader)?;
@@ -218,7 +263,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
     fn read_verifier_deps_class_resolution_set(&mut self) -> Result<Vec<ClassResolution>> {
         let count: u32 = leb128::decode_uleb128(self.reader)?;
-        let mut result: Vec<ClassResolution> = Vec::with_capacity(count as usize);
+        let mut result: Vec<ClassResolution> = self
+            .limits
+            .vec_with_capacity(u64::from(count), "class resolutions")?;
 
         for _ in 0..count {
             let type_index: u16 =
@@ -236,7 +283,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
     fn read_verifier_deps_field_resolution_set(&mut self) -> Result<Vec<FieldResolution>> {
         let count: u32 = leb128::decode_uleb128(self.reader)?;
-        let mut result: Vec<FieldResolution> = Vec::with_capacity(count as usize);
+        let mut result: Vec<FieldResolution> = self
+            .limits
+            .vec_with_capacity(u64::from(count), "field resolutions")?;
 
         for _ in 0..count {
             let field_index: u32 = leb128::decode_uleb128(self.reader)?;
@@ -255,7 +304,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
     fn read_verifier_deps_method_resolution_set(&mut self) -> Result<Vec<MethodResolution>> {
         let count: u32 = leb128::decode_uleb128(self.reader)?;
-        let mut result: Vec<MethodResolution> = Vec::with_capacity(count as usize);
+        let mut result: Vec<MethodResolution> = self
+            .limits
+            .vec_with_capacity(u64::from(count), "method resolutions")?;
 
         for _ in 0..count {
             let method_index: u32 = leb128::decode_uleb128(self.reader)?;
@@ -274,7 +325,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
     fn read_verifier_deps_unverified_classes(&mut self) -> Result<Vec<u16>> {
         let count: u32 = leb128::decode_uleb128(self.reader)?;
-        let mut result: Vec<u16> = Vec::with_capacity(count as usize);
+        let mut result: Vec<u16> = self
+            .limits
+            .vec_with_capacity(u64::from(count), "unverified classes")?;
 
         for _ in 0..count {
             // NOTE: You _probably_ could do `decode_uleb128::<u16>` but I'm too nervous to do that.
@@ -284,9 +337,15 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
             let value: u16 = match u16::try_from(value) {
                 Ok(value) => value,
                 Err(_) => {
-                    return Err(Error::Malformed(format!(
-                        "VDex verifier deps had invalid unverified `class_index`"
-                    )))
+                    return Err(Error::Malformed(
+                        Details::new("VDex verifier deps had invalid unverified `class_index`")
+                            .structure("vdex::vdex010::DexFileDeps")
+                            .field("unverified_classes")
+                            .offset(
+                                self.reader.stream_position()?
+                                    - leb128::encode_uleb128(value).len() as u64,
+                            ),
+                    ))
                 }
             };
             result.push(value);
@@ -311,15 +370,30 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
             let section_end_offset = section_start_offset + u64::from(header.quickening_info_size);
 
             let dex_file_indexes_start_offset = section_end_offset
-                - (u64::from(header.number_of_dex_files) * (std::mem::size_of::<u32>() as u64));
+                .checked_sub(
+                    u64::from(header.number_of_dex_files) * (std::mem::size_of::<u32>() as u64),
+                )
+                .ok_or_else(|| {
+                    Error::Malformed(
+                        Details::new(format!(
+                            "VDex quickening info of `{}` bytes is too small for `{}` dex files",
+                            header.quickening_info_size, header.number_of_dex_files
+                        ))
+                        .structure("vdex::vdex010::Header")
+                        .field("quickening_info_size")
+                        .offset(20),
+                    )
+                })?;
             let _dex_file_indexes_end_offset = section_end_offset;
 
             // Start at the dex file offsets list
             self.reader
                 .seek(SeekFrom::Start(dex_file_indexes_start_offset))?;
 
-            let mut dex_file_sizes_offsets: Vec<(u64, u64)> =
-                Vec::with_capacity(header.number_of_dex_files as usize);
+            let mut dex_file_sizes_offsets: Vec<(u64, u64)> = self.limits.vec_with_capacity(
+                u64::from(header.number_of_dex_files),
+                "quickening info offsets",
+            )?;
 
             for dex_file_index in 0..header.number_of_dex_files {
                 let quickening_offset_start = section_start_offset
@@ -337,8 +411,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
                 dex_file_sizes_offsets.push((quickening_offset_start, quickening_offset_end));
             }
 
-            let mut result: Vec<QuickeningInfo> =
-                Vec::with_capacity(header.number_of_dex_files as usize);
+            let mut result: Vec<QuickeningInfo> = self
+                .limits
+                .vec_with_capacity(u64::from(header.number_of_dex_files), "quickening info")?;
 
             for dex_file_index in 0..header.number_of_dex_files {
                 let (quickening_offsets_start, quickening_offsets_end) =
@@ -347,18 +422,22 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
                 self.reader
                     .seek(SeekFrom::Start(quickening_offsets_start))?;
 
-                let end_index = (quickening_offsets_end - quickening_offsets_start)
+                let end_index = quickening_offsets_end
+                    .checked_sub(quickening_offsets_start)
+                    .ok_or_else(|| {
+                        Error::Malformed(
+                            Details::new(format!(
+                                "Quickening info offsets of dex file `{}` end at `{}` before they start at `{}`",
+                                dex_file_index, quickening_offsets_end, quickening_offsets_start
+                            ))
+                            .structure("vdex::vdex010::QuickeningInfo")
+                            .offset(quickening_offsets_start),
+                        )
+                    })?
                     / (std::mem::size_of::<u64>() as u64);
-                let end_index = match usize::try_from(end_index) {
-                    Ok(value) => value,
-                    Err(_) => {
-                        return Err(Error::TooManyArrayItems(format!(
-                            "Quickening info had `{}` code_items, `usize` can only hold `{}`",
-                            end_index,
-                            usize::MAX
-                        )))
-                    }
-                };
+                let end_index = self
+                    .limits
+                    .check_items::<(u64, u64)>(end_index, "quickening info code items")?;
 
                 let mut code_item_quickening_offsets: Vec<(u64, u64)> =
                     Vec::with_capacity(end_index);
@@ -382,8 +461,10 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
                     ))?;
 
                     let length: u32 = self.reader.ioread_with(self.endianness)?;
-                    let mut info: Vec<u8> = Vec::with_capacity(length as usize);
-                    info.resize(length as usize, 0);
+                    let info_len = self
+                        .limits
+                        .check_allocation(u64::from(length), "quickening info")?;
+                    let mut info: Vec<u8> = vec![0; info_len];
 
                     self.reader.read_exact(&mut info)?;
 
@@ -17,8 +17,12 @@
 use crate::compact_offset_table::CompactOffsetTableReader;
 use crate::dex::CDex;
 use crate::dex::IoReader as DexReader;
+use crate::error::ResultExt;
 use crate::leb128;
+use crate::ContextFrame;
+use crate::Details;
 use crate::Error;
+use crate::Limits;
 use scroll::Endian;
 use scroll::IOread;
 use std::ffi::CString;
@@ -61,16 +65,22 @@ pub struct IoReader<'a, TRead: IOread<Endian> + Seek> {
     pub reader: &'a mut BufReader<TRead>,
     pub endianness: Endian,
     pub stream_len: u64,
+    pub limits: Limits,
 }
 
 impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
     pub fn new(reader: &'a mut BufReader<TRead>) -> Result<Self> {
+        Self::with_limits(reader, Limits::default())
+    }
+
+    pub fn with_limits(reader: &'a mut BufReader<TRead>, limits: Limits) -> Result<Self> {
         let stream_len = reader.seek(SeekFrom::End(0))?;
         reader.seek(SeekFrom::Current(0))?;
         Ok(Self {
             reader,
             endianness: Endian::Little,
             stream_len,
+            limits,
         })
     }
 
@@ -82,19 +92,37 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
             .ioread_with::<VerifierDepsHeader>(self.endianness)?;
 
         if result.magic != MAGIC {
-            Err(Error::InvalidMagicNumber(result.magic.to_vec()))
+            Err(Error::InvalidMagicNumber(
+                Details::new(format!(
+                    "Expected `{:02x?}` for the VDex magic number, found `{:02x?}`",
+                    MAGIC, result.magic
+                ))
+                .structure("vdex::vdex019::VerifierDepsHeader")
+                .field("magic")
+                .offset(0),
+            ))
         } else if result.verifier_deps_version != VERIFIER_DEPS_VERSION {
-            Err(Error::InvalidVersionNumber(format!(
-                "{:x?}",
-                VERIFIER_DEPS_VERSION
-            )))
+            Err(Error::InvalidVersionNumber(
+                Details::new(format!(
+                    "Expected `{:02x?}` for the VDex verifier deps version, found `{:02x?}`",
+                    VERIFIER_DEPS_VERSION, result.verifier_deps_version
+                ))
+                .structure("vdex::vdex019::VerifierDepsHeader")
+                .field("verifier_deps_version")
+                .offset(4),
+            ))
         } else if result.dex_section_version != DEX_SECTION_VERSION
             && result.dex_section_version != DEX_SECTION_VERSION_EMPTY
         {
-            Err(Error::InvalidVersionNumber(format!(
-                "{:x?}",
-                DEX_SECTION_VERSION
-            )))
+            Err(Error::InvalidVersionNumber(
+                Details::new(format!(
+                    "Expected `{:02x?}` (or `{:02x?}` without Dex files) for the VDex dex section version, found `{:02x?}`",
+                    DEX_SECTION_VERSION, DEX_SECTION_VERSION_EMPTY, result.dex_section_version
+                ))
+                .structure("vdex::vdex019::VerifierDepsHeader")
+                .field("dex_section_version")
+                .offset(8),
+            ))
         } else {
             Ok(result)
         }
@@ -110,7 +138,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
             self.reader.seek(SeekFrom::Start(offset))?;
 
-            let mut result: Vec<u32> = Vec::with_capacity(size);
+            let mut result: Vec<u32> = self
+                .limits
+                .vec_with_capacity(size as u64, "dex checksums")?;
 
             for _ in 0..size {
                 result.push(self.reader.ioread_with::<u32>(self.endianness)?);
@@ -152,17 +182,23 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
             self.reader.seek(SeekFrom::Start(offset))?;
 
-            let mut result: Vec<(u32, CDex<'static>)> = Vec::with_capacity(size);
+            let mut result: Vec<(u32, CDex<'static>)> =
+                self.limits.vec_with_capacity(size as u64, "dex files")?;
 
-            for _ in 0..verifier_deps_header.number_of_dex_files {
+            for dex_index in 0..verifier_deps_header.number_of_dex_files {
                 let quickening_offset = self.reader.ioread_with::<u32>(self.endianness)?;
                 // TODO: We need to make sure `dex_index_offset` is aligned to 4 bytes and correct if not
                 let dex_index_offset = self.reader.seek(SeekFrom::Current(0))?;
 
                 // TODO: In the future, we need to replace this with generic code that can parse both Dex AND CDex then return Dex...
-                let mut dex_reader = DexReader::new(self.reader, dex_index_offset)?;
-
-                let cdex = dex_reader.read_cdex()?;
+                let mut dex_reader =
+                    DexReader::with_limits(self.reader, dex_index_offset, self.limits)?;
+
+                let cdex = dex_reader.read_cdex().context(|| {
+                    ContextFrame::new("dex_files")
+                        .index(dex_index as usize)
+                        .offset(dex_index_offset)
+                })?;
                 let file_size = cdex.header.header.file_size;
 
                 result.push((quickening_offset, cdex));
@@ -205,8 +241,10 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
             self.reader.seek(SeekFrom::Start(offset))?;
 
-            let mut result: Vec<DexFileDeps> =
-                Vec::with_capacity(verifier_deps_header.number_of_dex_files as usize);
+            let mut result: Vec<DexFileDeps> = self.limits.vec_with_capacity(
+                u64::from(verifier_deps_header.number_of_dex_files),
+                "verifier deps",
+            )?;
 
             for _ in 0..verifier_deps_header.number_of_dex_files {
                 let strings = self.read_verifier_deps_strings()?;
@@ -236,7 +274,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
     fn read_verifier_deps_strings(&mut self) -> Result<Vec<CString>> {
         let count: u32 = leb128::decode_uleb128(self.reader)?;
-        let mut result: Vec<CString> = Vec::with_capacity(count as usize);
+        let mut result: Vec<CString> = self
+            .limits
+            .vec_with_capacity(u64::from(count), "verifier deps strings")?;
 
         for _ in 0..count {
             let mut raw_string: Vec<u8> = Vec::new();
@@ -246,10 +286,15 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
             // NOTE: Unlike in Dex, VDex version 006 stores strings as nul-terminated C strings... this shouldn't fail.
             let string = match CString::from_vec_with_nul(raw_string) {
                 Ok(string) => string,
-                Err(error) => return Err(Error::Malformed(format!(
-                    "VDex file (version 006) contained a malformed string in its `verifier_deps` section, raw error: {}", 
-                    error
-                ))),
+                Err(error) => return Err(Error::Malformed(
+                    Details::new(format!(
+                        "VDex file (version 006) contained a malformed string in its `verifier_deps` section, raw error: {}", 
+                        error
+                    ))
+                    .structure("vdex::vdex019::DexFileDeps")
+                    .field("strings")
+                    .offset(self.reader.stream_position()? - error.as_bytes().len() as u64),
+                )),
             };
 
             result.push(string);
@@ -260,7 +305,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
     fn read_verifier_deps_type_asignability_set(&mut self) -> Result<Vec<TypeAssignability>> {
         let count: u32 = leb128::decode_uleb128(self.reader)?;
-        let mut result: Vec<TypeAssignability> = Vec::with_capacity(count as usize);
+        let mut result: Vec<TypeAssignability> = self
+            .limits
+            .vec_with_capacity(u64::from(count), "type assignabilities")?;
 
         for _ in 0..count {
             let destination_index: u32 = leb128::decode_uleb128(self.reader)?;
@@ -276,7 +323,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
     fn read_verifier_deps_class_resolution_set(&mut self) -> Result<Vec<ClassResolution>> {
         let count: u32 = leb128::decode_uleb128(self.reader)?;
-        let mut result: Vec<ClassResolution> = Vec::with_capacity(count as usize);
+        let mut result: Vec<ClassResolution> = self
+            .limits
+            .vec_with_capacity(u64::from(count), "class resolutions")?;
 
         for _ in 0..count {
             let type_index: u16 =
@@ -294,7 +343,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
     fn read_verifier_deps_field_resolution_set(&mut self) -> Result<Vec<FieldResolution>> {
         let count: u32 = leb128::decode_uleb128(self.reader)?;
-        let mut result: Vec<FieldResolution> = Vec::with_capacity(count as usize);
+        let mut result: Vec<FieldResolution> = self
+            .limits
+            .vec_with_capacity(u64::from(count), "field resolutions")?;
 
         for _ in 0..count {
             let field_index: u32 = leb128::decode_uleb128(self.reader)?;
@@ -313,7 +364,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
     fn read_verifier_deps_method_resolution_set(&mut self) -> Result<Vec<MethodResolution>> {
         let count: u32 = leb128::decode_uleb128(self.reader)?;
-        let mut result: Vec<MethodResolution> = Vec::with_capacity(count as usize);
+        let mut result: Vec<MethodResolution> = self
+            .limits
+            .vec_with_capacity(u64::from(count), "method resolutions")?;
 
         for _ in 0..count {
             let method_index: u32 = leb128::decode_uleb128(self.reader)?;
@@ -332,7 +385,9 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
     fn read_verifier_deps_unverified_classes(&mut self) -> Result<Vec<u16>> {
         let count: u32 = leb128::decode_uleb128(self.reader)?;
-        let mut result: Vec<u16> = Vec::with_capacity(count as usize);
+        let mut result: Vec<u16> = self
+            .limits
+            .vec_with_capacity(u64::from(count), "unverified classes")?;
 
         for _ in 0..count {
             // NOTE: You _probably_ could do `decode_uleb128::<u16>` but I'm too nervous to do that.
@@ -342,9 +397,15 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
             let value: u16 = match u16::try_from(value) {
                 Ok(value) => value,
                 Err(_) => {
-                    return Err(Error::Malformed(format!(
-                        "VDex verifier deps had invalid unverified `class_index`"
-                    )))
+                    return Err(Error::Malformed(
+                        Details::new("VDex verifier deps had invalid unverified `class_index`")
+                            .structure("vdex::vdex019::DexFileDeps")
+                            .field("unverified_classes")
+                            .offset(
+                                self.reader.stream_position()?
+                                    - leb128::encode_uleb128(value).len() as u64,
+                            ),
+                    ))
                 }
             };
             result.push(value);
@@ -489,8 +550,10 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
     fn get_quickening_info(&mut self, offset: u32) -> Result<Vec<u8>> {
         self.reader.seek(SeekFrom::Start(u64::from(offset - 1)))?;
         let size: u32 = leb128::decode_uleb128(self.reader)?;
-        let mut quickening_info: Vec<u8> = Vec::with_capacity(size as usize);
-        quickening_info.resize(size as usize, 0);
+        let quickening_info_len = self
+            .limits
+            .check_allocation(u64::from(size), "quickening info")?;
+        let mut quickening_info: Vec<u8> = vec![0; quickening_info_len];
         self.reader.read_exact(&mut quickening_info)?;
         Ok(quickening_info)
     }
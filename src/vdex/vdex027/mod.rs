@@ -16,8 +16,12 @@
 
 use crate::dex::CDex;
 use crate::dex::IoReader as DexReader;
+use crate::error::ResultExt;
 use crate::leb128;
+use crate::ContextFrame;
+use crate::Details;
 use crate::Error;
+use crate::Limits;
 use scroll::Endian;
 use scroll::IOread;
 use std::ffi::CString;
@@ -53,16 +57,22 @@ pub struct IoReader<'a, TRead: IOread<Endian> + Seek> {
     pub reader: &'a mut BufReader<TRead>,
     pub endianness: Endian,
     pub stream_len: u64,
+    pub limits: Limits,
 }
 
 impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
     pub fn new(reader: &'a mut BufReader<TRead>) -> Result<Self> {
+        Self::with_limits(reader, Limits::default())
+    }
+
+    pub fn with_limits(reader: &'a mut BufReader<TRead>, limits: Limits) -> Result<Self> {
         let stream_len = reader.seek(SeekFrom::End(0))?;
         reader.seek(SeekFrom::Current(0))?;
         Ok(Self {
             reader,
             endianness: Endian::Little,
             stream_len,
+            limits,
         })
     }
 
@@ -72,7 +82,15 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
         let result = self.reader.ioread_with::<VDexFileHeader>(self.endianness)?;
 
         if result.magic != MAGIC {
-            Err(Error::InvalidMagicNumber(result.magic.to_vec()))
+            Err(Error::InvalidMagicNumber(
+                Details::new(format!(
+                    "Expected `{:02x?}` for the VDex magic number, found `{:02x?}`",
+                    MAGIC, result.magic
+                ))
+                .structure("vdex::vdex027::VDexFileHeader")
+                .field("magic")
+                .offset(0),
+            ))
         } else {
             Ok(result)
         }
@@ -92,30 +110,41 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
                 .reader
                 .ioread_with::<VDexSectionHeader>(self.endianness)?)
         } else {
-            Err(Error::InvalidArguments(format!(
-                "Requested VDex section index `{}` was greater than the number of VDex sections `{}`", 
-                index,
-                header.number_of_sections
-            )))
+            Err(Error::InvalidArguments(
+                Details::new(format!(
+                    "Requested VDex section index `{}` was greater than the number of VDex sections `{}`", 
+                    index,
+                    header.number_of_sections
+                ))
+                .structure("vdex::vdex027::VDexFileHeader")
+                .field("number_of_sections"),
+            ))
         }
     }
 
     pub fn read_checksum_section(&mut self, section_header: &VDexSectionHeader) -> Result<Vec<u8>> {
         if section_header.section_kind != VDEX_SECTION_CHECKSUM {
-            Err(Error::InvalidArguments(format!(
-                "`read_checksum_section` was passed an invalid section header with kind `{}`",
-                vdex_section_to_str(section_header.section_kind)
-            )))
+            Err(Error::InvalidArguments(
+                Details::new(format!(
+                    "`read_checksum_section` was passed an invalid section header with kind `{}`",
+                    vdex_section_to_str(section_header.section_kind)
+                ))
+                .structure("vdex::vdex027::VDexSectionHeader")
+                .field("section_kind"),
+            ))
         } else if section_header.section_offset == 0 || section_header.section_size == 0 {
-            Err(Error::InvalidArguments(format!(
-                "Attempted to read an empty checksum section"
-            )))
+            Err(Error::InvalidArguments(
+                Details::new("Attempted to read an empty checksum section")
+                    .structure("vdex::vdex027::VDexSectionHeader"),
+            ))
         } else {
             self.reader
                 .seek(SeekFrom::Start(u64::from(section_header.section_offset)))?;
 
-            let mut result: Vec<u8> = Vec::with_capacity(section_header.section_size as usize);
-            result.resize(section_header.section_size as usize, 0);
+            let result_len = self
+                .limits
+                .check_allocation(u64::from(section_header.section_size), "a VDex section")?;
+            let mut result: Vec<u8> = vec![0; result_len];
 
             self.reader.read_exact(&mut result)?;
 
@@ -133,14 +162,19 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
         section_header: &VDexSectionHeader,
     ) -> Result<Vec<CDex>> {
         if section_header.section_kind != VDEX_SECTION_DEX_FILE {
-            Err(Error::InvalidArguments(format!(
-                "`read_dex_files_section` was passed an invalid section header with kind `{}`",
-                vdex_section_to_str(section_header.section_kind)
-            )))
+            Err(Error::InvalidArguments(
+                Details::new(format!(
+                    "`read_dex_files_section` was passed an invalid section header with kind `{}`",
+                    vdex_section_to_str(section_header.section_kind)
+                ))
+                .structure("vdex::vdex027::VDexSectionHeader")
+                .field("section_kind"),
+            ))
         } else if section_header.section_offset == 0 || section_header.section_size == 0 {
-            Err(Error::InvalidArguments(format!(
-                "Attempted to read an empty Dex section"
-            )))
+            Err(Error::InvalidArguments(
+                Details::new("Attempted to read an empty Dex section")
+                    .structure("vdex::vdex027::VDexSectionHeader"),
+            ))
         } else {
             self.reader
                 .seek(SeekFrom::Start(u64::from(section_header.section_offset)))?;
@@ -159,11 +193,29 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
                     // TODO: Just read as CDex for now but in the future we should support
                     //       detecting Dex vs CDex and parsing either into Dex. That's how
                     //       VDex works (or used to work? I haven't gone as deep into 027)
-                    let mut dex_reader = DexReader::new(self.reader, dex_index_offset)?;
-
-                    let cdex = dex_reader.read_cdex()?;
+                    let mut dex_reader =
+                        DexReader::with_limits(self.reader, dex_index_offset, self.limits)?;
+
+                    let cdex = dex_reader.read_cdex().context(|| {
+                        ContextFrame::new("dex_files")
+                            .index(result.len())
+                            .offset(dex_index_offset)
+                    })?;
                     let file_size = cdex.header.header.file_size;
 
+                    if file_size == 0 {
+                        // We'd keep reading the same Dex file forever otherwise
+                        return Err(Error::Malformed(
+                            Details::new(format!(
+                                "Dex file at `{}` in the VDex Dex section has a `file_size` of `0`",
+                                dex_index_offset
+                            ))
+                            .structure("dex::Header")
+                            .field("file_size")
+                            .offset(dex_index_offset + 32),
+                        ));
+                    }
+
                     result.push(cdex);
 
                     // The parser isn't guaranteed to end at the true end of the file...
@@ -183,14 +235,19 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
         section_header: &VDexSectionHeader,
     ) -> Result<Vec<DexFileDeps>> {
         if section_header.section_kind != VDEX_SECTION_VERIFIER_DEPS {
-            Err(Error::InvalidArguments(format!(
-                "`read_verifier_deps` was passed an invalid section header with kind `{}`",
-                vdex_section_to_str(section_header.section_kind)
-            )))
+            Err(Error::InvalidArguments(
+                Details::new(format!(
+                    "`read_verifier_deps` was passed an invalid section header with kind `{}`",
+                    vdex_section_to_str(section_header.section_kind)
+                ))
+                .structure("vdex::vdex027::VDexSectionHeader")
+                .field("section_kind"),
+            ))
         } else if section_header.section_offset == 0 || section_header.section_size == 0 {
-            Err(Error::InvalidArguments(format!(
-                "Attempted to read an empty verifier deps section"
-            )))
+            Err(Error::InvalidArguments(
+                Details::new("Attempted to read an empty verifier deps section")
+                    .structure("vdex::vdex027::VDexSectionHeader"),
+            ))
         } else {
             let section_start_offset = self
                 .reader
@@ -209,13 +266,17 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
             // Seek backwards so we can get the offsets again...
             self.reader.seek(SeekFrom::Current(-4))?;
 
-            let mut dex_offsets: Vec<u32> = Vec::with_capacity(num_dex_files);
+            let mut dex_offsets: Vec<u32> = self
+                .limits
+                .vec_with_capacity(num_dex_files as u64, "verifier deps offsets")?;
 
             for _ in 0..num_dex_files {
                 dex_offsets.push(self.reader.ioread_with::<u32>(self.endianness)?);
             }
 
-            let mut result: Vec<DexFileDeps> = Vec::with_capacity(num_dex_files);
+            let mut result: Vec<DexFileDeps> = self
+                .limits
+                .vec_with_capacity(num_dex_files as u64, "verifier deps")?;
 
             for dex_index in 0..num_dex_files {
                 let dex_offset = dex_offsets[dex_index];
@@ -287,8 +348,22 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
             // NOTE: We need to do `- dex_offset` as the offsets here are NOT relative to itself, they're relative
             //       to the ENTIRE verifier deps section.
             if check_offset != u32::MAX {
-                if u64::from(check_offset) - dex_offset != (current_position - offset_table_start) {
-                    num_class_defs = ((u64::from(check_offset) - dex_offset) / 4) - 1;
+                let relative_offset = u64::from(check_offset)
+                    .checked_sub(dex_offset)
+                    .filter(|relative_offset| *relative_offset >= 4)
+                    .ok_or_else(|| {
+                        Error::Malformed(
+                            Details::new(format!(
+                                "VDex file contained invalid verifier deps offset `{}` for Dex file number `{}`",
+                                check_offset, dex_index
+                            ))
+                            .structure("vdex::vdex027::DexFileDeps")
+                            .offset(current_position - 4),
+                        )
+                    })?;
+
+                if relative_offset != (current_position - offset_table_start) {
+                    num_class_defs = (relative_offset / 4) - 1;
                     current_offset_index += 1;
                 } else {
                     // If we reach this point, it means that `check_offset - dex_offset == current_position - offset_table_start`
@@ -310,7 +385,10 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
         if num_class_defs > 0 {
             // Plus one to include the final index which is the total size...
-            offsets.reserve((num_class_defs as usize + 1) - offsets.len());
+            let num_offsets = self
+                .limits
+                .check_items::<u32>(num_class_defs + 1, "verifier deps offsets")?;
+            offsets.reserve(num_offsets.saturating_sub(offsets.len()));
         } else {
             // Return early. Not really needed...
             return Ok((Vec::with_capacity(0), Vec::with_capacity(0)));
@@ -321,9 +399,12 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
             offsets.push(self.reader.ioread_with::<u32>(self.endianness)?);
         }
 
-        let mut assignable_types: Vec<Vec<TypeAssignability>> =
-            Vec::with_capacity(num_class_defs as usize);
-        let mut verified_classes: Vec<bool> = Vec::with_capacity(num_class_defs as usize);
+        let mut assignable_types: Vec<Vec<TypeAssignability>> = self
+            .limits
+            .vec_with_capacity(num_class_defs, "type assignabilities")?;
+        let mut verified_classes: Vec<bool> = self
+            .limits
+            .vec_with_capacity(num_class_defs, "class bit vector")?;
 
         // Now it just comes down to two things:
         //  - If the `offset` is not `u32::MAX`, read from `offset` to next valid offset
@@ -342,7 +423,7 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
 
                     // NOTE: The `j + 1` here isn't a bug, the value at `offsets[num_class_defs]`
                     //       is the total size of this vector...
-                    for j in i..offsets.len() {
+                    for j in i..offsets.len() - 1 {
                         if offsets[(j + 1) as usize] != u32::MAX {
                             result = offsets[(j + 1) as usize];
                             break;
@@ -353,26 +434,40 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
                 };
 
                 if next_valid_offset == 0 {
-                    return Err(Error::Malformed(format!(
-                        "VDex file contained invalid verifier deps offset for Dex file number `{}`",
-                        dex_index
-                    )));
+                    return Err(Error::Malformed(
+                        Details::new(format!(
+                            "VDex file contained invalid verifier deps offset for Dex file number `{}`",
+                            dex_index
+                        ))
+                        .structure("vdex::vdex027::DexFileDeps")
+                        .offset(offset_table_start + 4 * i as u64),
+                    ));
                 }
 
                 let check_offset = self.reader.seek(SeekFrom::Current(0))?;
 
-                if check_offset != (u64::from(current_offset) - dex_offset) + offset_table_start {
-                    return Err(Error::Malformed(format!(
-                        "VDex file contained invalid verifier deps offset for Dex file number `{}`, expected offset `{}` but found offset `{}`",
-                        dex_index,
-                        check_offset,
-                        current_offset
-                    )));
+                if check_offset
+                    != u64::from(current_offset)
+                        .wrapping_sub(dex_offset)
+                        .wrapping_add(offset_table_start)
+                {
+                    return Err(Error::Malformed(
+                        Details::new(format!(
+                            "VDex file contained invalid verifier deps offset for Dex file number `{}`, expected offset `{}` but found offset `{}`",
+                            dex_index,
+                            check_offset,
+                            current_offset
+                        ))
+                        .structure("vdex::vdex027::DexFileDeps")
+                        .offset(check_offset),
+                    ));
                 }
 
                 let mut type_assignabilities: Vec<TypeAssignability> = Vec::new();
                 let mut current_offset: u64 = check_offset;
-                let end_offset = (u64::from(next_valid_offset) - dex_offset) + offset_table_start;
+                let end_offset = u64::from(next_valid_offset)
+                    .wrapping_sub(dex_offset)
+                    .wrapping_add(offset_table_start);
 
                 while current_offset != end_offset {
                     let destination_index: u32 = leb128::decode_uleb128(self.reader)?;
@@ -386,10 +481,15 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
                     current_offset = self.reader.seek(SeekFrom::Current(0))?;
 
                     if current_offset > end_offset {
-                        return Err(Error::Malformed(format!(
-                            "VDex file contained incorrect type assignabilities list for Dex file {}",
-                            dex_index
-                        )));
+                        return Err(Error::Malformed(
+                            Details::new(format!(
+                                "VDex file contained incorrect type assignabilities list for Dex file {}",
+                                dex_index
+                            ))
+                            .structure("vdex::vdex027::DexFileDeps")
+                            .field("assignable_types")
+                            .offset(check_offset),
+                        ));
                     }
                 }
 
@@ -412,14 +512,29 @@ impl<'a, TRead: IOread<Endian> + Seek> IoReader<'a, TRead> {
             let _ = self.reader.ioread_with::<u32>(self.endianness)?;
         }
 
-        let mut result: Vec<CString> = Vec::with_capacity(string_count as usize);
+        let mut result: Vec<CString> = self
+            .limits
+            .vec_with_capacity(u64::from(string_count), "verifier deps strings")?;
 
         for _ in 0..string_count {
             let mut string_bytes: Vec<u8> = Vec::new();
             self.reader.read_until(0, &mut string_bytes)?;
-            // Unwrap because `CString::from_vec_with_nul` only returns Err if there isn't
-            // exactly 1 nul byte at the end of the vec... which should be impossible here.
-            result.push(CString::from_vec_with_nul(string_bytes).unwrap());
+            // `CString::from_vec_with_nul` only returns Err if there isn't exactly 1 nul byte at the end of the
+            // vec, which only happens when the file ends in the middle of a string.
+            let string = match CString::from_vec_with_nul(string_bytes) {
+                Ok(string) => string,
+                Err(error) => {
+                    return Err(Error::Malformed(
+                        Details::new(
+                            "VDex verifier deps string wasn't nul terminated before the end of the file",
+                        )
+                        .structure("vdex::vdex027::DexFileDeps")
+                        .field("strings")
+                        .offset(self.reader.stream_position()? - error.as_bytes().len() as u64),
+                    ))
+                }
+            };
+            result.push(string);
         }
 
         Ok(result)